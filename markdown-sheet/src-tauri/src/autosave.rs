@@ -0,0 +1,414 @@
+use crate::file_io::{
+    document_from_bytes, encode_text, mtime_ms, read_document, sha256_hex, write_atomic,
+};
+use crate::markdown_parser::{
+    apply_sparse_patch, rebuild_document, table_checksums, validate_sparse_patch,
+};
+use crate::recovery::RecoveryJournal;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 自動保存したことをフロントエンドに通知するイベント名
+pub const AUTOSAVED_EVENT: &str = "autosaved";
+/// タイマーによる自動保存に失敗したことをフロントエンドに通知するイベント名
+pub const AUTOSAVE_FAILED_EVENT: &str = "autosave-failed";
+
+/// 溜まった編集（セル数）がこれを超えたら待たずに保存する
+const MAX_PENDING_EDITS: usize = 100;
+
+/// タイマーが保存対象を確認する間隔
+const TIMER_TICK: Duration = Duration::from_millis(200);
+
+/// 自動保存の結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AutosaveEvent {
+    pub path: String,
+    /// 保存後の最終更新日時（Unix エポックからのミリ秒）
+    pub mtime_ms: i64,
+    /// 保存後のファイルの SHA-256
+    pub sha256: String,
+    /// 保存後の各テーブルのチェックサム
+    pub table_checksums: Vec<String>,
+    /// 保存はできたが、復旧用ジャーナルを更新できなかった場合の警告
+    pub warning: Option<String>,
+}
+
+/// 自動保存に失敗したファイル
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AutosaveFailure {
+    pub path: String,
+    pub message: String,
+}
+
+/// セル編集を溜めた結果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Queued {
+    /// 溜まった編集が上限を超えてすぐに書き出した場合の結果
+    pub saved: Option<AutosaveEvent>,
+    /// 編集は受け付けたが、復旧用ジャーナルに記録できなかった場合の警告
+    pub warning: Option<String>,
+}
+
+/// 書き込みの結果（保存前のハッシュも返し、明示的な保存の競合検出に使う）
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlushResult {
+    pub event: AutosaveEvent,
+    pub previous_sha256: String,
+}
+
+#[derive(Debug)]
+struct Session {
+    interval: Duration,
+    /// `"table.row.col"` → 値（同じセルへの編集は後勝ちでまとめる）
+    pending: HashMap<String, String>,
+    last_edit: Option<Instant>,
+}
+
+impl Session {
+    fn is_due(&self, now: Instant) -> bool {
+        !self.pending.is_empty()
+            && self
+                .last_edit
+                .is_some_and(|t| now.duration_since(t) >= self.interval)
+    }
+}
+
+/// ファイルごとの未保存のセル編集を保持し、一定時間編集がなければディスクに書き出す管理ステート
+///
+/// ジャーナルを設定すると、受け付けた編集を書き出すまでクラッシュ復旧用に記録する。
+#[derive(Debug, Default, Clone)]
+pub struct Autosave {
+    sessions: Arc<Mutex<HashMap<PathBuf, Session>>>,
+    journal: Option<RecoveryJournal>,
+}
+
+fn session_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// 溜まった編集をファイルに適用し、一時ファイル経由で書き込む
+fn write_patch(path: &Path, patch: HashMap<String, String>) -> Result<FlushResult, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let previous_sha256 = sha256_hex(&bytes);
+    let mut doc = document_from_bytes(&bytes);
+    let touched: BTreeSet<usize> = patch
+        .keys()
+        .filter_map(|k| k.split('.').next()?.parse().ok())
+        .collect();
+    apply_sparse_patch(&mut doc, patch)?;
+    let tables: Vec<_> = touched
+        .into_iter()
+        .filter_map(|i| doc.tables.get(i).cloned())
+        .collect();
+    let content = rebuild_document(&doc.lines, &tables);
+    let encoded = encode_text(&content, &doc.encoding, doc.has_bom)?;
+    write_atomic(path, &encoded).map_err(|e| e.to_string())?;
+    let meta = fs::metadata(path).map_err(|e| e.to_string())?;
+    Ok(FlushResult {
+        event: AutosaveEvent {
+            path: path.to_string_lossy().to_string(),
+            mtime_ms: mtime_ms(&meta),
+            sha256: sha256_hex(&encoded),
+            table_checksums: table_checksums(&doc.tables),
+            warning: None,
+        },
+        previous_sha256,
+    })
+}
+
+impl Autosave {
+    pub fn with_journal(journal: RecoveryJournal) -> Self {
+        Self {
+            sessions: Arc::default(),
+            journal: Some(journal),
+        }
+    }
+
+    /// ファイルの自動保存を有効にする（既に有効なら間隔だけ変更する）
+    pub fn enable(&self, path: &Path, interval: Duration) -> Result<(), String> {
+        if !path.is_file() {
+            return Err(format!("ファイルが存在しません: {}", path.display()));
+        }
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions
+            .entry(session_key(path))
+            .and_modify(|s| s.interval = interval)
+            .or_insert(Session {
+                interval,
+                pending: HashMap::new(),
+                last_edit: None,
+            });
+        Ok(())
+    }
+
+    /// 溜まっている編集を書き出してから自動保存を無効にする
+    pub fn disable(&self, path: &Path) -> Result<Option<AutosaveEvent>, String> {
+        let flushed = self.flush(path)?;
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.remove(&session_key(path));
+        Ok(flushed.map(|r| r.event))
+    }
+
+    /// セル編集を溜める。溜まった編集が上限を超えたらすぐに書き出す
+    ///
+    /// 現在のファイルにないセルを指す編集が含まれていれば、何も溜めずにエラーを返す。
+    pub fn queue(
+        &self,
+        path: &Path,
+        patch: HashMap<String, String>,
+        now: Instant,
+    ) -> Result<Queued, String> {
+        validate_sparse_patch(&read_document(path)?, &patch)?;
+        let mut queued = Queued::default();
+        let over_limit = {
+            let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+            let session = sessions
+                .get_mut(&session_key(path))
+                .ok_or_else(|| format!("自動保存が有効になっていません: {}", path.display()))?;
+            if let Some(journal) = &self.journal {
+                if let Err(e) = journal.append(&session_key(path), &patch) {
+                    queued.warning = Some(format!("復旧用ジャーナルに書き込めませんでした: {}", e));
+                }
+            }
+            session.pending.extend(patch);
+            session.last_edit = Some(now);
+            session.pending.len() > MAX_PENDING_EDITS
+        };
+        if over_limit {
+            queued.saved = self.flush(path)?.map(|r| r.event);
+        }
+        Ok(queued)
+    }
+
+    /// 溜まっている編集を書き出し、タイマーをリセットする（編集がなければ None）
+    pub fn flush(&self, path: &Path) -> Result<Option<FlushResult>, String> {
+        let key = session_key(path);
+        let patch = {
+            let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+            let Some(session) = sessions.get_mut(&key) else {
+                return Ok(None);
+            };
+            session.last_edit = None;
+            std::mem::take(&mut session.pending)
+        };
+        if patch.is_empty() {
+            return Ok(None);
+        }
+        match write_patch(&key, patch.clone()) {
+            Ok(mut result) => {
+                if let Err(e) = self.rewrite_journal(&key) {
+                    result.event.warning =
+                        Some(format!("復旧用ジャーナルを更新できませんでした: {}", e));
+                }
+                Ok(Some(result))
+            }
+            Err(e) => {
+                // ファイルが外部で変更されて座標が合わなくなった編集は、再試行しても
+                // 成功しないので破棄する
+                if read_document(&key).is_ok_and(|doc| validate_sparse_patch(&doc, &patch).is_err())
+                {
+                    let _ = self.rewrite_journal(&key);
+                    return Err(format!("{}（溜まっていた編集を破棄しました）", e));
+                }
+                // 書き込めなかった編集は戻しておき、次の機会に再試行する（後から来た編集を優先）
+                if let Ok(mut sessions) = self.sessions.lock() {
+                    if let Some(session) = sessions.get_mut(&key) {
+                        for (cell, value) in patch {
+                            session.pending.entry(cell).or_insert(value);
+                        }
+                        session.last_edit = Some(Instant::now());
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// ジャーナルを、まだ書き出していない編集だけを残した内容に書き直す
+    ///
+    /// 書き出しの間に届いた編集は消さずに、現在のファイルを基準に記録し直す
+    /// （`queue` と同じくセッションのロック中に行い、追記と入れ違わないようにする）。
+    fn rewrite_journal(&self, key: &Path) -> Result<(), String> {
+        let Some(journal) = &self.journal else {
+            return Ok(());
+        };
+        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        journal.clear(key)?;
+        match sessions.get(key) {
+            Some(session) => journal.append(key, &session.pending),
+            None => Ok(()),
+        }
+    }
+
+    /// 最後の編集から間隔が過ぎたファイルを書き出す
+    pub fn flush_due(&self, now: Instant) -> Vec<Result<AutosaveEvent, AutosaveFailure>> {
+        let due: Vec<PathBuf> = match self.sessions.lock() {
+            Ok(sessions) => sessions
+                .iter()
+                .filter(|(_, s)| s.is_due(now))
+                .map(|(k, _)| k.clone())
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        self.flush_paths(due)
+    }
+
+    /// すべての溜まっている編集を書き出す（ウィンドウを閉じるとき・終了時）
+    pub fn flush_all(&self) -> Vec<Result<AutosaveEvent, AutosaveFailure>> {
+        let paths: Vec<PathBuf> = match self.sessions.lock() {
+            Ok(sessions) => sessions.keys().cloned().collect(),
+            Err(_) => return Vec::new(),
+        };
+        self.flush_paths(paths)
+    }
+
+    fn flush_paths(&self, paths: Vec<PathBuf>) -> Vec<Result<AutosaveEvent, AutosaveFailure>> {
+        paths
+            .into_iter()
+            .filter_map(|path| match self.flush(&path) {
+                Ok(result) => result.map(|r| Ok(r.event)),
+                Err(message) => Some(Err(AutosaveFailure {
+                    path: path.to_string_lossy().to_string(),
+                    message,
+                })),
+            })
+            .collect()
+    }
+
+    /// 一定間隔で保存対象を確認するタイマースレッドを起動する
+    pub fn spawn_timer(
+        &self,
+        on_flushed: impl Fn(Result<AutosaveEvent, AutosaveFailure>) + Send + 'static,
+    ) {
+        let autosave = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(TIMER_TICK);
+            for result in autosave.flush_due(Instant::now()) {
+                on_flushed(result);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "# T\n| A | B |\n| --- | --- |\n| 1 | 2 |\n").unwrap();
+        (dir, path)
+    }
+
+    fn edits(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_debounce_coalesces_edits() {
+        let (_dir, path) = setup();
+        let autosave = Autosave::default();
+        autosave.enable(&path, Duration::from_millis(1000)).unwrap();
+
+        let t0 = Instant::now();
+        autosave.queue(&path, edits(&[("0.0.0", "x")]), t0).unwrap();
+        autosave
+            .queue(
+                &path,
+                edits(&[("0.0.0", "y"), ("0.0.1", "z")]),
+                t0 + Duration::from_millis(500),
+            )
+            .unwrap();
+
+        // 最後の編集から間隔が経っていなければ書き出さない
+        assert!(autosave
+            .flush_due(t0 + Duration::from_millis(1200))
+            .is_empty());
+        let events = autosave.flush_due(t0 + Duration::from_millis(1600));
+        assert_eq!(events.len(), 1);
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# T\n"));
+        assert!(content.contains("| y   | z   |"));
+        assert_eq!(
+            events[0].as_ref().unwrap().sha256,
+            sha256_hex(content.as_bytes())
+        );
+        assert!(autosave
+            .flush_due(t0 + Duration::from_millis(5000))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_disable_flushes_pending_edits() {
+        let (_dir, path) = setup();
+        let autosave = Autosave::default();
+        autosave.enable(&path, Duration::from_secs(60)).unwrap();
+        autosave
+            .queue(&path, edits(&[("0.0.1", "saved")]), Instant::now())
+            .unwrap();
+
+        let event = autosave.disable(&path).unwrap();
+        assert!(event.is_some());
+        assert!(fs::read_to_string(&path).unwrap().contains("saved"));
+        assert!(autosave
+            .queue(&path, edits(&[("0.0.0", "x")]), Instant::now())
+            .is_err());
+        assert!(autosave
+            .queue(&path, edits(&[("bad", "x")]), Instant::now())
+            .is_err());
+    }
+
+    #[test]
+    fn test_flush_keeps_journal_for_unflushed_edits() {
+        let (dir, path) = setup();
+        let journal = RecoveryJournal::new(dir.path().join("recovery"));
+        let autosave = Autosave::with_journal(journal.clone());
+        autosave.enable(&path, Duration::from_secs(60)).unwrap();
+        let queued = autosave
+            .queue(&path, edits(&[("0.0.0", "x")]), Instant::now())
+            .unwrap();
+        assert_eq!(queued, Queued::default());
+        assert_eq!(journal.candidates()[0].edits, 1);
+
+        let result = autosave.flush(&path).unwrap().unwrap();
+        assert_eq!(result.event.warning, None);
+        assert!(journal.candidates().is_empty());
+
+        // 書き出し後に届いた編集は、書き出し後のファイルを基準に記録される
+        autosave
+            .queue(&path, edits(&[("0.0.1", "y")]), Instant::now())
+            .unwrap();
+        let candidates = journal.candidates();
+        assert_eq!((candidates[0].edits, candidates[0].stale), (1, false));
+    }
+
+    #[test]
+    fn test_out_of_range_edits_are_not_retried() {
+        let (_dir, path) = setup();
+        let autosave = Autosave::default();
+        autosave.enable(&path, Duration::from_secs(60)).unwrap();
+        for key in ["1.0.0", "0.1.0", "0.0.2"] {
+            assert!(autosave
+                .queue(&path, edits(&[(key, "x")]), Instant::now())
+                .is_err());
+        }
+        assert!(autosave.flush(&path).unwrap().is_none());
+
+        // 溜めた後に外部でファイルが縮んだ場合は、エラーを返して編集を捨てる
+        autosave
+            .queue(&path, edits(&[("0.0.1", "x")]), Instant::now())
+            .unwrap();
+        fs::write(&path, "# T\n| A |\n| --- |\n| 1 |\n").unwrap();
+        assert!(autosave.flush(&path).is_err());
+        assert!(autosave.flush(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,83 @@
+use icu_collator::{Collator, CollatorOptions, Strength};
+use icu_locid::Locale;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// ロケールごとの照合器（Tauri の managed state）
+///
+/// 照合表の読み込みは重いため、初めて使うときに作ってロケール名ごとに使い回す。
+#[derive(Default)]
+pub struct Collators(Mutex<HashMap<String, Arc<Collator>>>);
+
+impl Collators {
+    /// `locale` の照合器を返す（省略時はシステムのロケール）
+    ///
+    /// 強さは二次（Secondary）にして、全角・半角や大文字・小文字の違いだけの文字列は同じとみなす。
+    pub fn get(&self, locale: Option<&str>) -> Result<Arc<Collator>, String> {
+        let name = match locale.map(str::trim).filter(|l| !l.is_empty()) {
+            Some(l) => l.to_string(),
+            None => system_locale(),
+        };
+        let mut cache = self.0.lock().map_err(|e| e.to_string())?;
+        if let Some(collator) = cache.get(&name) {
+            return Ok(collator.clone());
+        }
+        let parsed: Locale = name
+            .parse()
+            .map_err(|_| format!("ロケールが正しくありません: {}", name))?;
+        let mut options = CollatorOptions::new();
+        options.strength = Some(Strength::Secondary);
+        let collator = Collator::try_new(&(&parsed).into(), options)
+            .map(Arc::new)
+            .map_err(|e| format!("照合器を作成できません（{}）: {}", name, e))?;
+        cache.insert(name, collator.clone());
+        Ok(collator)
+    }
+
+    /// `locale` の照合器で2つの文字列を比べる関数を返す（並べ替えに渡す用）
+    pub fn comparator(
+        &self,
+        locale: Option<&str>,
+    ) -> Result<impl Fn(&str, &str) -> Ordering + 'static, String> {
+        let collator = self.get(locale)?;
+        Ok(move |a: &str, b: &str| collator.compare(a, b))
+    }
+}
+
+/// システムのロケールを BCP 47 の形で返す（取得できなければ `und`）
+///
+/// `ja_JP.UTF-8` のような POSIX 形式は `ja-JP` に直す。
+pub fn system_locale() -> String {
+    sys_locale::get_locale()
+        .map(|l| {
+            let l = l.split(['.', '@']).next().unwrap_or_default();
+            l.replace('_', "-")
+        })
+        .filter(|l| l.parse::<Locale>().is_ok())
+        .unwrap_or_else(|| "und".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_japanese_collation() {
+        let collators = Collators::default();
+        let ja = collators.get(Some("ja")).unwrap();
+        assert_eq!(ja.compare("ＡＢＣ", "abc"), Ordering::Equal);
+        assert_eq!(ja.compare("ｶﾞｷﾞ", "ガギ"), Ordering::Equal);
+
+        let mut words = vec!["漢字", "かな", "Zebra", "カナダ", "apple", "１０", "あい"];
+        words.sort_by(|a, b| ja.compare(a, b));
+        assert_eq!(
+            words,
+            vec!["１０", "apple", "Zebra", "あい", "かな", "カナダ", "漢字"]
+        );
+
+        assert!(Arc::ptr_eq(&ja, &collators.get(Some("ja")).unwrap()));
+        assert!(collators.get(Some("not a locale!")).is_err());
+        assert!(collators.get(None).is_ok());
+    }
+}
@@ -1,7 +1,44 @@
-use crate::markdown_parser::{parse_markdown, rebuild_document, MarkdownTable, ParsedDocument};
+use crate::autosave::{Autosave, AutosaveEvent, AUTOSAVED_EVENT};
+use crate::collation::Collators;
+use crate::content_search::{self, ContentMatch, SearchOptions};
+use crate::deep_link::{DeepLinks, TakenDeepLinks};
+use crate::dir_watch::{ChangeKind, DirWatchers, DIR_CHANGED_EVENT};
+use crate::doc_cache::DocumentCache;
+use crate::edit_lock::{EditLockStatus, EditLocks};
+use crate::export::{self, table_to_html};
+use crate::file_io::{
+    self, collect_markdown_files, count_tables_in_file, detect_file_encoding, file_sha256,
+    read_document, write_encoded, DocumentSnapshot, FileIndex, RecentFile, SaveError,
+};
+use crate::file_lock::FileLocks;
+use crate::file_search::{FileMatch, FileSearch};
+use crate::fill::{self, FillMode};
+use crate::import;
+use crate::inventory::{self, DuplicateTableReport, TableInventory, INVENTORY_PROGRESS_EVENT};
+use crate::links;
+use crate::markdown_parser::{
+    self, parse_markdown, rebuild_document, rebuild_document_with, Aggregation, CaseMode,
+    CleanupOptions, CleanupReport, ColumnRef, ColumnSplitter, ColumnWarning, DedupReport, KeepRow,
+    MarkdownTable, MergeError, ParseError, ParsedDocument, RepairReport, SerializeOptions,
+    SortDirection, SortKey, SortMode, SplitMode, TableContext,
+};
+use crate::recent_files::{RecentEntry, RecentFiles};
+use crate::recovery::{RecoveryCandidate, RecoveryJournal};
+use crate::replace::{self, DirectoryReplaceResult, FileReplaceResult, ReplacePlan, ReplacePlans};
+use crate::row_filter::{self, RowFilter};
+use crate::search_index::{IndexProgress, IndexStatus, SearchIndex, INDEX_PROGRESS_EVENT};
+use crate::startup::StartupFiles;
+use crate::workspace::{self, Workspace};
+use indexmap::IndexMap;
+use rayon::prelude::*;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{Emitter, Manager, State};
 
 /// ファイルツリーのエントリ
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,10 +47,86 @@ pub struct FileEntry {
     pub path: String,
     pub is_dir: bool,
     pub children: Option<Vec<FileEntry>>,
+    /// ファイルサイズ（フォルダは None）
+    pub size_bytes: Option<u64>,
+    /// 最終更新日時（Unix タイムスタンプ秒）
+    pub modified_at: Option<u64>,
+    /// ファイル内のテーブル数（`include_table_counts` 指定時のみ）
+    pub table_count: Option<usize>,
+    /// ワークスペースでピン留めされているか
+    pub pinned: bool,
+}
+
+/// ファイルツリーの並び順
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// 名前の昇順
+    #[default]
+    ByName,
+    /// 更新日時の新しい順
+    ByModified,
+    /// サイズの大きい順
+    BySize,
+    /// テーブル数の多い順
+    ByTableCount,
+}
+
+/// ファイルツリー取得時のオプション
+struct TreeOptions {
+    include_table_counts: bool,
+    sort_order: SortOrder,
+    /// ピン留めされたファイルの絶対パス
+    pinned: HashSet<PathBuf>,
+}
+
+/// 並び順に従ってエントリを並べ替える（同順位は名前順を保つ）
+fn sort_entries(entries: &mut [FileEntry], order: SortOrder) {
+    match order {
+        SortOrder::ByName => {}
+        SortOrder::ByModified => entries.sort_by_key(|e| Reverse(e.modified_at)),
+        SortOrder::BySize => entries.sort_by_key(|e| Reverse(e.size_bytes)),
+        SortOrder::ByTableCount => entries.sort_by_key(|e| Reverse(e.table_count)),
+    }
+}
+
+/// ツリーエントリ1件分を組み立てる
+fn make_entry(
+    path: &Path,
+    name: String,
+    children: Option<Vec<FileEntry>>,
+    opts: &TreeOptions,
+) -> FileEntry {
+    let is_dir = children.is_some();
+    let meta = fs::metadata(path).ok();
+    let modified_at = meta
+        .as_ref()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let size_bytes = if is_dir {
+        None
+    } else {
+        meta.as_ref().map(|m| m.len())
+    };
+    let table_count = if !is_dir && opts.include_table_counts {
+        count_tables_in_file(path).ok()
+    } else {
+        None
+    };
+    FileEntry {
+        name,
+        path: path.to_string_lossy().to_string(),
+        is_dir,
+        children,
+        size_bytes,
+        modified_at,
+        table_count,
+        pinned: opts.pinned.contains(path),
+    }
 }
 
 /// ディレクトリを再帰的に読み取り、.md ファイルとフォルダのみ返す
-fn read_dir_recursive(dir: &Path, depth: u32) -> Vec<FileEntry> {
+fn read_dir_recursive(dir: &Path, depth: u32, opts: &TreeOptions) -> Vec<FileEntry> {
     if depth > 5 {
         return Vec::new();
     }
@@ -35,61 +148,2395 @@ fn read_dir_recursive(dir: &Path, depth: u32) -> Vec<FileEntry> {
         }
 
         if path.is_dir() {
-            let children = read_dir_recursive(&path, depth + 1);
+            let children = read_dir_recursive(&path, depth + 1, opts);
             // .md を含むフォルダのみ表示
             if !children.is_empty() {
-                entries.push(FileEntry {
-                    name,
-                    path: path.to_string_lossy().to_string(),
-                    is_dir: true,
-                    children: Some(children),
-                });
+                entries.push(make_entry(&path, name, Some(children), opts));
             }
         } else if name.ends_with(".md") {
-            entries.push(FileEntry {
-                name,
-                path: path.to_string_lossy().to_string(),
-                is_dir: false,
-                children: None,
-            });
+            entries.push(make_entry(&path, name, None, opts));
         }
     }
+    sort_entries(&mut entries, opts.sort_order);
+    entries
+}
+
+/// `read_dir_recursive` の並列版（子エントリを rayon で並行に読む）
+///
+/// 子の処理が終わる順序は実行ごとに異なりうるため、最後に名前順に並べ直してから
+/// `sort_order` を適用し、`read_dir_recursive` と同じ結果にする。
+fn read_dir_parallel(dir: &Path, depth: u32, opts: &TreeOptions) -> Vec<FileEntry> {
+    if depth > 5 {
+        return Vec::new();
+    }
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let items: Vec<_> = read_dir.filter_map(|e| e.ok()).collect();
+    let mut entries: Vec<FileEntry> = items
+        .par_iter()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            // 隠しフォルダ/ファイルをスキップ
+            if name.starts_with('.') {
+                return None;
+            }
+            if path.is_dir() {
+                let children = read_dir_parallel(&path, depth + 1, opts);
+                // .md を含むフォルダのみ表示
+                (!children.is_empty()).then(|| make_entry(&path, name, Some(children), opts))
+            } else {
+                name.ends_with(".md")
+                    .then(|| make_entry(&path, name, None, opts))
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    sort_entries(&mut entries, opts.sort_order);
     entries
 }
 
+/// ツリー取得オプションを組み立てる（ピン留めはルートの設定から `dir` 以下のものを拾う）
+fn tree_options(
+    dir: &Path,
+    root: &Path,
+    include_table_counts: bool,
+    sort_order: SortOrder,
+) -> TreeOptions {
+    let abs_dir = workspace::absolute_path(dir);
+    TreeOptions {
+        include_table_counts: include_table_counts || sort_order == SortOrder::ByTableCount,
+        sort_order,
+        pinned: workspace::load_settings(root)
+            .pinned
+            .iter()
+            .filter_map(|rel| {
+                root.join(rel)
+                    .strip_prefix(&abs_dir)
+                    .ok()
+                    .map(|suffix| dir.join(suffix))
+            })
+            .collect(),
+    }
+}
+
 /// ディレクトリのファイルツリーを取得する Tauri コマンド
+///
+/// `include_table_counts` が true の場合のみ、各ファイルのテーブル数を数える
+/// （`SortOrder::ByTableCount` 指定時は並べ替えのために常に数える）。
+/// 登録済みのルート（またはその中のフォルダ）ならそのまま返し、
+/// それ以外のフォルダはワークスペースのルートとして追加する（既存のルートと重なる・入れ子になる
+/// 場合はエラー）。
+/// フォルダの走査は `spawn_blocking` で別スレッドに任せ、メインスレッドを止めない。
 #[tauri::command]
-pub fn get_file_tree(dir_path: String) -> Result<Vec<FileEntry>, String> {
+pub async fn get_file_tree(
+    workspace: State<'_, Workspace>,
+    file_search: State<'_, FileSearch>,
+    dir_path: String,
+    include_table_counts: bool,
+    sort_order: Option<SortOrder>,
+) -> Result<Vec<FileEntry>, String> {
     let path = Path::new(&dir_path);
     if !path.exists() || !path.is_dir() {
         return Err("ディレクトリが存在しません".to_string());
     }
-    Ok(read_dir_recursive(path, 0))
+    // ツリーを読み直すときはファイル名検索の一覧も読み直させる
+    file_search.invalidate();
+    let root = match workspace.root_for(&dir_path) {
+        Ok(root) => root,
+        Err(_) => {
+            workspace.add_root(path)?;
+            workspace.root_for(&dir_path)?
+        }
+    };
+    let path = path.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        let opts = tree_options(
+            &path,
+            &root,
+            include_table_counts,
+            sort_order.unwrap_or_default(),
+        );
+        read_dir_recursive(&path, 0, &opts)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// ディレクトリのファイルツリーを、子フォルダを並列に走査して取得する Tauri コマンド
+///
+/// 大きなフォルダ向け。子の走査順は一定でないが、最後に並べ直すので結果は名前順で
+/// `get_file_tree`（テーブル数なし・名前順）と同じになる。ピン留めは登録済みのルート内の
+/// フォルダでのみ反映し、ワークスペースのルートは変更しない。
+#[tauri::command]
+pub async fn get_file_tree_parallel(
+    workspace: State<'_, Workspace>,
+    dir_path: String,
+) -> Result<Vec<FileEntry>, String> {
+    let path = PathBuf::from(&dir_path);
+    if !path.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let root = workspace.root_for(&dir_path).ok();
+    tauri::async_runtime::spawn_blocking(move || {
+        let opts = match &root {
+            Some(root) => tree_options(&path, root, false, SortOrder::ByName),
+            None => TreeOptions {
+                include_table_counts: false,
+                sort_order: SortOrder::ByName,
+                pinned: HashSet::new(),
+            },
+        };
+        read_dir_parallel(&path, 0, &opts)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// ワークスペース内のファイル名をあいまい検索する Tauri コマンド（クイックオープン用）
+///
+/// 結果はスコアの高い順で、`indices` は `relative` 内の一致した文字の位置。
+#[tauri::command]
+pub fn search_file_names(
+    workspace: State<'_, Workspace>,
+    file_search: State<'_, FileSearch>,
+    query: String,
+    limit: usize,
+) -> Result<Vec<FileMatch>, String> {
+    let roots = workspace.roots();
+    if roots.is_empty() {
+        return Err("ワークスペースが開かれていません".to_string());
+    }
+    Ok(file_search.search(&roots, &query, limit))
+}
+
+/// ワークスペース内のファイルを全文検索する Tauri コマンド
+///
+/// テーブル内のヒットにはテーブル ID と行・列を付けて返し、UI から該当セルへ移動できるようにする。
+#[tauri::command]
+pub fn search_content(
+    workspace: State<'_, Workspace>,
+    index: State<'_, SearchIndex>,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<Vec<ContentMatch>, String> {
+    let roots = workspace.roots();
+    if roots.is_empty() {
+        return Err("ワークスペースが開かれていません".to_string());
+    }
+    content_search::search_content(&roots, &query, &options.unwrap_or_default(), Some(&index))
+}
+
+/// フォルダ内の .md ファイルを一括置換する計画を作る Tauri コマンド（ファイルには書き込まない）
+///
+/// 正規表現検索では置換文字列で `$1` などのキャプチャを参照できる。
+#[tauri::command]
+pub fn replace_in_folder(
+    plans: State<'_, ReplacePlans>,
+    dir_path: String,
+    find: String,
+    replace: String,
+    options: Option<SearchOptions>,
+) -> Result<ReplacePlan, String> {
+    plans.plan(
+        Path::new(&dir_path),
+        &find,
+        &replace,
+        &options.unwrap_or_default(),
+    )
+}
+
+/// 置換計画のうち選択された置換だけを適用する Tauri コマンド
+///
+/// 結果はファイルごとに返し、失敗したファイルがあっても残りのファイルには適用する。
+#[tauri::command]
+pub fn apply_replace_plan(
+    plans: State<'_, ReplacePlans>,
+    cache: State<'_, DocumentCache>,
+    plan_id: String,
+    selected_match_ids: Vec<String>,
+) -> Result<Vec<FileReplaceResult>, String> {
+    let results = plans.apply(&plan_id, &selected_match_ids)?;
+    for result in results.iter().filter(|r| r.error.is_none()) {
+        cache.invalidate(Path::new(&result.path));
+    }
+    Ok(results)
+}
+
+/// フォルダ以下のテーブルのセルを一括置換する Tauri コマンド（置換したセルと書き換えたファイルを返す）
+///
+/// `dry_run` が true ならファイルには書き込まず、置換候補だけを返す。
+#[tauri::command]
+pub fn search_replace_in_directory(
+    cache: State<'_, DocumentCache>,
+    dir_path: String,
+    find: String,
+    replace: String,
+    use_regex: bool,
+    dry_run: bool,
+) -> Result<DirectoryReplaceResult, String> {
+    let result = replace::search_replace_in_directory(
+        Path::new(&dir_path),
+        &find,
+        &replace,
+        use_regex,
+        dry_run,
+    )?;
+    for path in &result.changed_files {
+        cache.invalidate(Path::new(path));
+    }
+    Ok(result)
+}
+
+/// ワークスペースのすべてのルートの全文検索索引を作り直す Tauri コマンド
+///
+/// ファイルを1つ読むごとに `search-index-progress` イベントで進捗を通知する。
+#[tauri::command]
+pub fn build_search_index(
+    app: tauri::AppHandle,
+    workspace: State<'_, Workspace>,
+    index: State<'_, SearchIndex>,
+) -> Result<Vec<IndexStatus>, String> {
+    let roots = workspace.roots();
+    if roots.is_empty() {
+        return Err("ワークスペースが開かれていません".to_string());
+    }
+    roots
+        .iter()
+        .map(|root| {
+            let root_str = root.to_string_lossy().to_string();
+            index.build(root, |done, total| {
+                let progress = IndexProgress {
+                    root: root_str.clone(),
+                    done,
+                    total,
+                };
+                let _ = app.emit(INDEX_PROGRESS_EVENT, progress);
+            })
+        })
+        .collect()
+}
+
+/// ワークスペースの各ルートの全文検索索引の状態（ファイル数・古くなったファイル数）を返す Tauri コマンド
+#[tauri::command]
+pub fn get_index_status(
+    workspace: State<'_, Workspace>,
+    index: State<'_, SearchIndex>,
+) -> Result<Vec<IndexStatus>, String> {
+    workspace
+        .roots()
+        .iter()
+        .map(|root| index.status(root))
+        .collect()
+}
+
+/// ワークスペースのルート1つ分のファイルツリー
+#[derive(Debug, Serialize)]
+pub struct RootTree {
+    /// ルート ID（正規化した絶対パス）
+    pub root: String,
+    pub name: String,
+    pub entries: Vec<FileEntry>,
+}
+
+/// 登録済みのすべてのルートのファイルツリーを取得する Tauri コマンド
+#[tauri::command]
+pub fn get_workspace_forest(
+    workspace: State<'_, Workspace>,
+    include_table_counts: bool,
+    sort_order: Option<SortOrder>,
+) -> Vec<RootTree> {
+    let sort_order = sort_order.unwrap_or_default();
+    workspace
+        .roots()
+        .into_iter()
+        .map(|root| {
+            let opts = tree_options(&root, &root, include_table_counts, sort_order);
+            RootTree {
+                name: root
+                    .file_name()
+                    .map_or_else(String::new, |n| n.to_string_lossy().to_string()),
+                entries: read_dir_recursive(&root, 0, &opts),
+                root: root.to_string_lossy().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// ワークスペースにルートフォルダを追加する Tauri コマンド
+#[tauri::command]
+pub fn add_workspace_root(
+    workspace: State<'_, Workspace>,
+    path: String,
+) -> Result<Vec<String>, String> {
+    workspace.add_root(Path::new(&path))
+}
+
+/// ワークスペースからルートフォルダを外す Tauri コマンド
+#[tauri::command]
+pub fn remove_workspace_root(
+    workspace: State<'_, Workspace>,
+    path: String,
+) -> Result<Vec<String>, String> {
+    workspace.remove_root(Path::new(&path))
+}
+
+/// 登録済みのルートフォルダ一覧を取得する Tauri コマンド
+#[tauri::command]
+pub fn list_workspace_roots(workspace: State<'_, Workspace>) -> Vec<String> {
+    workspace
+        .roots()
+        .iter()
+        .map(|r| r.to_string_lossy().to_string())
+        .collect()
+}
+
+/// ファイルを読み込んでパースする（コマンド共通）
+fn load_document(file_path: &str) -> Result<ParsedDocument, String> {
+    read_document(Path::new(file_path))
+}
+
+/// ドキュメントから指定番号のテーブルを取り出す
+fn table_at(doc: &ParsedDocument, table_index: usize) -> Result<&MarkdownTable, String> {
+    doc.tables
+        .get(table_index)
+        .ok_or_else(|| format!("テーブルが見つかりません: {}", table_index))
+}
+
+/// 内容を読み込み時と同じ文字コードで、一時ファイル経由でファイルへ書き戻す（コマンド共通）
+///
+/// 書き込めないファイルはリネームで置き換えずにエラーにする。
+fn write_back(file_path: &str, doc: &ParsedDocument, content: &str) -> Result<(), String> {
+    let path = Path::new(file_path);
+    if let Ok(meta) = fs::metadata(path) {
+        if file_io::is_read_only(path, &meta) {
+            return Err(format!("書き込みできないファイルです: {}", file_path));
+        }
+    }
+    let bytes = file_io::encode_text(content, &doc.encoding, doc.has_bom)?;
+    file_io::write_atomic(path, &bytes).map_err(|e| e.to_string())
 }
 
 /// Markdown ファイルを読み込んでパースする Tauri コマンド
+///
+/// 楽観的排他制御用に、更新日時・ハッシュ・書き込み可否も併せて返す。
+/// 読み込みに成功したファイルは最近開いたファイルの履歴に記録する。
+/// 変更されていないファイルはキャッシュしたパース結果を返す。
 #[tauri::command]
-pub fn read_markdown_file(file_path: String) -> Result<ParsedDocument, String> {
-    let content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-    Ok(parse_markdown(&content))
+pub fn read_markdown_file(
+    recent: State<'_, RecentFiles>,
+    cache: State<'_, DocumentCache>,
+    file_path: String,
+) -> Result<DocumentSnapshot, String> {
+    let snapshot = cache.read(Path::new(&file_path))?;
+    // 履歴の保存に失敗しても読み込み自体は成功扱いにする
+    let _ = recent.add(&file_path);
+    Ok(snapshot)
 }
 
-/// 起動時のコマンドライン引数からファイルパスを取得する
+/// ファイル内の壊れたテーブルを寛容モードのパースで洗い出す Tauri コマンド
+///
+/// 区切り行のないブロックや列数の合わない区切り行など、通常の読み込みでは黙って
+/// テーブルとして扱われない（または書式が直される）箇所を行番号とともに返す。
 #[tauri::command]
-pub fn get_initial_file() -> Option<String> {
-    std::env::args().nth(1).filter(|a| {
-        let lower = a.to_lowercase();
-        lower.ends_with(".md") || lower.ends_with(".markdown")
-    })
+pub fn validate_markdown_file(file_path: String) -> Result<Vec<ParseError>, String> {
+    let bytes = fs::read(&file_path).map_err(|e| e.to_string())?;
+    let (content, _, _) = file_io::decode_bytes(&bytes);
+    Ok(markdown_parser::parse_markdown_lenient(&content).1)
+}
+
+/// 起動時にコマンドライン引数・OS のファイル関連付けで渡されたファイルを取り出す Tauri コマンド
+///
+/// フロントエンドの準備ができた時点で1度呼ぶ。以降に届いたファイルは
+/// `open-file-request` イベントで通知される。
+#[tauri::command]
+pub fn take_startup_files(startup: State<'_, StartupFiles>) -> Vec<String> {
+    startup.take()
+}
+
+/// 起動時など、フロントエンドの準備前に届いたディープリンクを取り出す Tauri コマンド
+///
+/// フロントエンドの準備ができた時点と、フォルダを開いた時点で呼ぶ。ワークスペースが開かれる前は
+/// リンクを保持したまま `pending` で数だけ返す。以降に届いたリンクは
+/// `open-deep-link` / `deep-link-rejected` イベントで通知される。開けなかったリンクは警告のログも残す。
+#[tauri::command]
+pub fn take_deep_links(
+    links: State<'_, DeepLinks>,
+    workspace: State<'_, Workspace>,
+) -> TakenDeepLinks {
+    let taken = links.take(&workspace);
+    for rejected in &taken.rejected {
+        log::warn!(
+            "ディープリンクを拒否しました: {}: {}",
+            rejected.url,
+            rejected.message
+        );
+    }
+    taken
 }
 
 /// テーブルを更新して Markdown ファイルに書き戻す Tauri コマンド
+///
+/// 別のウィンドウ・操作がロック中のファイルには書き込まない。
+/// `expected_sha256` を渡した場合、読み込み後にディスク上のファイルが変更されていれば
+/// 書き込まずにエラーを返す。成功時は保存後のハッシュを返す。
+/// `make_writable` を指定すると、Windows では読み取り専用属性を外してから保存する。
+/// `encoding` を省略した場合は既存ファイルの文字コードを判定して同じ文字コードで書き戻す。
+/// 他のプロセスが編集ロックを保持している場合は `ignore_edit_lock` を指定しない限り保存しない。
+/// `expected_checksums`（テーブル番号 → 読み込み時のチェックサム）を渡した場合、
+/// ディスク上のそのテーブルの内容が変わっていれば書き込まずにエラーを返す。
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub fn save_markdown_file(
+    window: tauri::Window,
+    locks: State<'_, FileLocks>,
+    cache: State<'_, DocumentCache>,
+    autosave: State<'_, Autosave>,
+    journal: State<'_, RecoveryJournal>,
+    edit_locks: State<'_, EditLocks>,
     file_path: String,
     original_lines: Vec<String>,
     tables: Vec<MarkdownTable>,
-) -> Result<(), String> {
+    expected_sha256: Option<String>,
+    make_writable: Option<bool>,
+    encoding: Option<String>,
+    has_bom: Option<bool>,
+    ignore_edit_lock: Option<bool>,
+    expected_checksums: Option<HashMap<usize, String>>,
+) -> Result<String, SaveError> {
+    if locks.is_locked_by_other(&file_path, window.label()) {
+        return Err(SaveError::Locked { path: file_path });
+    }
+    let path = Path::new(&file_path);
+    // 自動保存で書き出す前のディスク上のテーブルと比べる
+    if let Some(expected) = &expected_checksums {
+        let table_indices = file_io::changed_tables(path, expected);
+        if !table_indices.is_empty() {
+            return Err(SaveError::TableConflict {
+                path: file_path,
+                table_indices,
+            });
+        }
+    }
+    if !ignore_edit_lock.unwrap_or(false) {
+        if let Some(holder) = edit_locks.held_by_other(path) {
+            return Err(SaveError::LockedByOther {
+                path: file_path,
+                holder,
+            });
+        }
+    }
+    // 自動保存待ちの編集を先に書き出し、タイマーをリセットする
+    let flushed = autosave.flush(path).map_err(|message| SaveError::Io {
+        path: file_path.clone(),
+        message,
+    })?;
+    if let Some(expected) = expected_sha256 {
+        let mut current = file_sha256(path).ok();
+        // 直前の自動保存による変更は競合とみなさない
+        if let Some(flushed) = &flushed {
+            if current.as_deref() == Some(flushed.event.sha256.as_str()) {
+                current = Some(flushed.previous_sha256.clone());
+            }
+        }
+        if path.exists() && current.as_deref() != Some(expected.as_str()) {
+            return Err(SaveError::Conflict { path: file_path });
+        }
+    }
+    let (encoding, has_bom) = match encoding {
+        Some(encoding) => (encoding, has_bom.unwrap_or(false)),
+        None => detect_file_encoding(path),
+    };
     let content = rebuild_document(&original_lines, &tables);
-    fs::write(&file_path, content).map_err(|e| e.to_string())
+    write_encoded(
+        path,
+        &content,
+        &encoding,
+        has_bom,
+        make_writable.unwrap_or(false),
+    )?;
+    edit_locks.touch(path);
+    // 保存できたので復旧用ジャーナルは不要になる（消せなくても、ファイルが変わったので
+    // 復旧候補としては古いものとして扱われる）
+    let _ = journal.clear(path);
+    cache
+        .refresh(path)
+        .map(|snapshot| snapshot.sha256)
+        .map_err(|message| SaveError::Io {
+            path: file_path,
+            message,
+        })
+}
+
+/// ファイルの自動保存を有効にする Tauri コマンド
+///
+/// 以降 `queue_autosave_edits` で送ったセル編集は、`interval_ms` の間編集がなければ
+/// バックエンドが書き出し、`autosaved` イベントで保存後の更新日時とハッシュを通知する。
+#[tauri::command]
+pub fn enable_autosave(
+    autosave: State<'_, Autosave>,
+    file_path: String,
+    interval_ms: u64,
+) -> Result<(), String> {
+    autosave.enable(Path::new(&file_path), Duration::from_millis(interval_ms))
+}
+
+/// 溜まっている編集を書き出してから自動保存を無効にする Tauri コマンド
+#[tauri::command]
+pub fn disable_autosave(
+    app: tauri::AppHandle,
+    autosave: State<'_, Autosave>,
+    file_path: String,
+) -> Result<Option<AutosaveEvent>, String> {
+    let event = autosave.disable(Path::new(&file_path))?;
+    if let Some(event) = &event {
+        let _ = app.emit(AUTOSAVED_EVENT, event);
+    }
+    Ok(event)
+}
+
+/// 自動保存するセル編集（`"table.row.col"` → 値）を送る Tauri コマンド
+///
+/// 編集は受け付けたが復旧用ジャーナルに記録できなかった場合は、その警告を返す。
+#[tauri::command]
+pub fn queue_autosave_edits(
+    app: tauri::AppHandle,
+    autosave: State<'_, Autosave>,
+    file_path: String,
+    patch: HashMap<String, String>,
+) -> Result<Option<String>, String> {
+    let queued = autosave.queue(Path::new(&file_path), patch, Instant::now())?;
+    if let Some(event) = queued.saved {
+        let _ = app.emit(AUTOSAVED_EVENT, event);
+    }
+    Ok(queued.warning)
+}
+
+/// 前回の終了時に保存されなかった編集の一覧を返す Tauri コマンド
+///
+/// 記録後にファイルが変更されたものは `stale` になり、`apply_recovery` では適用できない。
+#[tauri::command]
+pub fn get_recovery_candidates(journal: State<'_, RecoveryJournal>) -> Vec<RecoveryCandidate> {
+    journal.candidates()
+}
+
+/// 保存されなかった編集を再生したドキュメントを返す Tauri コマンド
+///
+/// ファイルには書き込まない。内容を確認してから `save_markdown_file` で保存する。
+#[tauri::command]
+pub fn apply_recovery(
+    journal: State<'_, RecoveryJournal>,
+    path: String,
+) -> Result<ParsedDocument, String> {
+    journal.recover(Path::new(&path))
+}
+
+/// 保存されなかった編集の記録を捨てる Tauri コマンド
+#[tauri::command]
+pub fn discard_recovery(journal: State<'_, RecoveryJournal>, path: String) -> Result<(), String> {
+    journal.clear(Path::new(&path))
+}
+
+/// 指定したファイルのキャッシュを捨てる Tauri コマンド（次回の読み込みでディスクから読み直す）
+#[tauri::command]
+pub fn invalidate_document_cache(cache: State<'_, DocumentCache>, path: String) {
+    cache.invalidate(Path::new(&path));
+}
+
+/// フォルダ以下の変更を監視し、`dir-changed` イベントでウィンドウに通知する Tauri コマンド
+///
+/// 変更のあったファイルのキャッシュは捨て、作成・削除ではファイル名検索の一覧も読み直させる。
+#[tauri::command]
+pub fn watch_directory(
+    window: tauri::Window,
+    watchers: State<'_, DirWatchers>,
+    dir_path: String,
+) -> Result<(), String> {
+    let app = window.app_handle().clone();
+    watchers.watch(Path::new(&dir_path), move |change| {
+        let path = Path::new(&change.path);
+        app.state::<DocumentCache>().invalidate(path);
+        if change.kind != ChangeKind::Modify {
+            app.state::<FileSearch>().invalidate();
+        }
+        let _ = window.emit(DIR_CHANGED_EVENT, change);
+    })
+}
+
+/// `watch_directory` で始めたフォルダの監視を止める Tauri コマンド
+#[tauri::command]
+pub fn unwatch_directory(watchers: State<'_, DirWatchers>, dir_path: String) -> Result<(), String> {
+    watchers.unwatch(Path::new(&dir_path))
+}
+
+/// ファイルの書き込みロックを取得する Tauri コマンド
+#[tauri::command]
+pub fn acquire_file_lock(
+    window: tauri::Window,
+    locks: State<'_, FileLocks>,
+    file_path: String,
+) -> Result<(), String> {
+    locks.acquire(&file_path, window.label())
+}
+
+/// ファイルの書き込みロックを解放する Tauri コマンド
+#[tauri::command]
+pub fn release_file_lock(
+    window: tauri::Window,
+    locks: State<'_, FileLocks>,
+    file_path: String,
+) -> Result<(), String> {
+    locks.release(&file_path, window.label())
+}
+
+/// 編集を始めるファイルの編集ロック（`<file>.lock`）を取得する Tauri コマンド
+///
+/// 他のプロセスが編集中なら取得せずに保持者の情報を返す。古くなったロックは自動で破棄し、
+/// `force` を指定すると他のプロセスのロックも奪う。
+#[tauri::command]
+pub fn acquire_edit_lock(
+    window: tauri::Window,
+    edit_locks: State<'_, EditLocks>,
+    path: String,
+    force: Option<bool>,
+) -> Result<EditLockStatus, String> {
+    edit_locks.acquire(Path::new(&path), window.label(), force.unwrap_or(false))
+}
+
+/// ファイルの編集ロックを解放する Tauri コマンド
+#[tauri::command]
+pub fn release_edit_lock(edit_locks: State<'_, EditLocks>, path: String) -> Result<(), String> {
+    edit_locks.release(Path::new(&path))
+}
+
+/// テーブルの列数不整合や空ヘッダーを修復して書き戻す Tauri コマンド
+///
+/// `widen_overflow` が true の場合、ヘッダーより列数の多い行に合わせて列を追加する。
+/// false（既定）の場合は余分なセルを切り捨てる。
+#[tauri::command]
+pub fn repair_table(
+    file_path: String,
+    table_index: usize,
+    widen_overflow: Option<bool>,
+) -> Result<RepairReport, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let report =
+        markdown_parser::repair_table(&doc.lines, &mut table, widen_overflow.unwrap_or(false));
+    if !report.fixes.is_empty() {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(report)
+}
+
+/// 指定列の大文字・小文字を揃えて書き戻す Tauri コマンド（変更したセル数を返す）
+#[tauri::command]
+pub fn normalize_case(
+    file_path: String,
+    table_index: usize,
+    col_index: usize,
+    mode: CaseMode,
+) -> Result<usize, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let changed = markdown_parser::normalize_case(&mut table, col_index, mode);
+    if changed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(changed)
+}
+
+/// テーブルのヘッダーとセルの前後の空白を取り除いて書き戻す Tauri コマンド（変更したセル数を返す）
+#[tauri::command]
+pub fn trim_table(file_path: String, table_index: usize) -> Result<usize, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let changed = markdown_parser::trim_table_cells(&mut table);
+    if changed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(changed)
+}
+
+/// テーブルを整形して保存するときの各列の幅（表示幅）を返す Tauri コマンド
+#[tauri::command]
+pub fn column_widths(file_path: String, table_index: usize) -> Result<Vec<usize>, String> {
+    let doc = load_document(&file_path)?;
+    Ok(markdown_parser::column_widths(table_at(&doc, table_index)?))
+}
+
+/// 長すぎるセルを `max_len` 文字に縮めて書き戻す Tauri コマンド（変更したセル数を返す）
+#[tauri::command]
+pub fn truncate_table_cells(
+    file_path: String,
+    table_index: usize,
+    max_len: usize,
+    ellipsis: String,
+) -> Result<usize, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let changed = markdown_parser::truncate_long_cells(&mut table, max_len, &ellipsis)?;
+    if changed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(changed)
+}
+
+/// 直前の行と同じ行を取り除いて書き戻す Tauri コマンド（削除した行数を返す）
+#[tauri::command]
+pub fn dedupe_consecutive_rows(file_path: String, table_index: usize) -> Result<usize, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let removed = table.dedupe_consecutive();
+    if removed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(removed)
+}
+
+/// 指定列でテーブルの行を並べ替えて書き戻す Tauri コマンド（並べ替えたテーブルを返す）
+///
+/// `table_id` はテーブル番号または安定 ID。ファイルを読み直してから並べ替え、対象のテーブルだけを書き換える。
+#[tauri::command]
+pub fn sort_table_rows(
+    collators: State<'_, Collators>,
+    file_path: String,
+    table_id: String,
+    column: usize,
+    order: Option<SortDirection>,
+    mode: Option<SortMode>,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let ((), table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            markdown_parser::sort_table_rows(
+                table,
+                column,
+                order.unwrap_or_default(),
+                mode.unwrap_or_default(),
+                &|locale| Ok(Box::new(collators.comparator(locale)?)),
+            )
+        },
+    )?;
+    Ok(table)
+}
+
+/// 空の新しいテーブルを指定行の位置に挿入して書き戻す Tauri コマンド
+///
+/// `alignments` が足りない列は `none` になる。
+#[tauri::command]
+pub fn insert_new_table(
+    file_path: String,
+    at_line: usize,
+    headers: Vec<String>,
+    alignments: Vec<String>,
+) -> Result<(), String> {
+    let mut doc = load_document(&file_path)?;
+    let table = MarkdownTable {
+        heading: None,
+        headers,
+        alignments,
+        rows: Vec::new(),
+        start_line: 0,
+        end_line: 0,
+        has_summary_row: false,
+        verbatim: Vec::new(),
+        cell_annotations: Vec::new(),
+    };
+    markdown_parser::insert_table_at_line(&mut doc, at_line, table)?;
+    let content = doc.lines.join("\n");
+    write_back(&file_path, &doc, &content)
+}
+
+/// キーと値の組から2列のテーブルを作り、指定行の位置に挿入して書き戻す Tauri コマンド
+///
+/// 行は `pairs` の順に並べる。同じキーが複数ある場合や、パイプ・改行を含む値はエラー。
+#[tauri::command]
+pub fn create_table_from_kv_pairs(
+    file_path: String,
+    at_line: usize,
+    pairs: Vec<(String, String)>,
+    key_header: String,
+    value_header: String,
+) -> Result<(), String> {
+    markdown_parser::check_cell_text(&key_header, "列名")?;
+    markdown_parser::check_cell_text(&value_header, "列名")?;
+    let mut map = IndexMap::new();
+    for (key, value) in pairs {
+        markdown_parser::check_cell_text(&key, "キー")?;
+        markdown_parser::check_cell_text(&value, "値")?;
+        if map.contains_key(&key) {
+            return Err(format!("キーが重複しています: {}", key));
+        }
+        map.insert(key, value);
+    }
+    let table = markdown_parser::table_from_key_value_map(&map, &key_header, &value_header);
+    let mut doc = load_document(&file_path)?;
+    markdown_parser::insert_table_at_line(&mut doc, at_line, table)?;
+    let content = doc.lines.join("\n");
+    write_back(&file_path, &doc, &content)
+}
+
+/// Excel などから貼り付けた TSV をテーブルにしてファイルの末尾に追加する Tauri コマンド
+///
+/// 追加したテーブル（行番号を含む）を返す。
+#[tauri::command]
+pub fn paste_as_table(file_path: String, text: String) -> Result<MarkdownTable, String> {
+    let table = import::excel_paste_to_table(&text)?;
+    let doc = load_document(&file_path)?;
+    let mut content = doc.lines.join("\n").trim_end().to_string();
+    if !content.is_empty() {
+        content.push_str("\n\n");
+    }
+    content.push_str(&markdown_parser::serialize_table(&table));
+    write_back(&file_path, &doc, &content)?;
+    parse_markdown(&content)
+        .tables
+        .pop()
+        .ok_or_else(|| "追加したテーブルを読み込めませんでした".to_string())
+}
+
+/// 複数キーでの並べ替えの結果
+#[derive(Debug, Serialize)]
+pub struct SortResult {
+    /// 並べ替え後の各行の元の行番号
+    pub order: Vec<usize>,
+    pub table: MarkdownTable,
+}
+
+/// 複数のキー（列・向き・比べ方）でテーブルの行を並べ替える Tauri コマンド
+///
+/// `preview` が true なら書き戻さずに、並べ替え後の行の順序とテーブルだけを返す。
+#[tauri::command]
+pub fn sort_table_rows_multi(
+    collators: State<'_, Collators>,
+    file_path: String,
+    table_id: String,
+    keys: Vec<SortKey>,
+    preview: Option<bool>,
+) -> Result<SortResult, String> {
+    let doc = load_document(&file_path)?;
+    let index = markdown_parser::resolve_table_ref(&doc.tables, &table_id)?;
+    let mut table = doc.tables[index].clone();
+    let order = markdown_parser::sort_table_rows_multi(&mut table, &keys, &|locale| {
+        Ok(Box::new(collators.comparator(locale)?))
+    })?;
+    if !preview.unwrap_or(false) {
+        write_back(
+            &file_path,
+            &doc,
+            &rebuild_document(&doc.lines, std::slice::from_ref(&table)),
+        )?;
+    }
+    Ok(SortResult { order, table })
+}
+
+/// 条件に合う行の番号だけを返す Tauri コマンド（行のコピーは返さない）
+///
+/// `table_id` はテーブル番号または安定 ID。
+#[tauri::command]
+pub fn filter_rows(
+    file_path: String,
+    table_id: String,
+    predicate: RowFilter,
+) -> Result<Vec<usize>, String> {
+    let doc = load_document(&file_path)?;
+    let index = markdown_parser::resolve_table_ref(&doc.tables, &table_id)?;
+    row_filter::filter_rows(&doc.tables[index], &predicate)
+}
+
+/// 列名の変更の結果
+#[derive(Debug, Serialize)]
+pub struct RenameColumnResult {
+    pub table: MarkdownTable,
+    pub warnings: Vec<ColumnWarning>,
+}
+
+/// 列のヘッダーだけを書き換えて書き戻す Tauri コマンド
+///
+/// `column` は列番号または現在の列名。列名が他の列と重なる場合も変更し、`warnings` で知らせる。
+/// ワークスペース設定のテーブル表示状態（並べ替えキー・列幅）に古い列名があれば新しい列名に直す。
+#[tauri::command]
+pub fn rename_column(
+    workspace: State<'_, Workspace>,
+    file_path: String,
+    table_id: String,
+    column: ColumnRef,
+    new_name: String,
+) -> Result<RenameColumnResult, String> {
+    let ((old_name, warnings), table) = edit_single_table(&file_path, &table_id, None, |table| {
+        let col = column.resolve(table)?;
+        let old_name = table.headers[col].clone();
+        let warnings = markdown_parser::rename_column(table, col, &new_name)?;
+        Ok((old_name, warnings))
+    })?;
+    if let Ok(root) = workspace.root_for(&file_path) {
+        // 列名の変更では見出しが変わらないため、書き戻したファイルから安定 ID を求められる
+        let doc = load_document(&file_path)?;
+        let index = markdown_parser::resolve_table_ref(&doc.tables, &table_id)?;
+        let id = &markdown_parser::table_ids(&doc.tables)[index];
+        workspace::rename_column_references(&root, &file_path, id, &old_name, &new_name)?;
+    }
+    Ok(RenameColumnResult { table, warnings })
+}
+
+/// 1つのテーブルをファイルから読み直して編集し、そのテーブルだけを書き戻す（コマンド共通）
+///
+/// `table_id` はテーブル番号または安定 ID。`expected_checksum` を渡した場合、ディスク上の
+/// テーブルが読み込み時のチェックサムから変わっていれば編集せずにエラーを返す。
+/// 毎回ファイルを読み直すので、続けて呼べば複数の編集を順に適用できる。
+/// 返すテーブルの `start_line` / `end_line` は書き戻した内容での位置。
+fn edit_single_table<T>(
+    file_path: &str,
+    table_id: &str,
+    expected_checksum: Option<&str>,
+    edit: impl FnOnce(&mut MarkdownTable) -> Result<T, String>,
+) -> Result<(T, MarkdownTable), String> {
+    let doc = load_document(file_path)?;
+    let index = markdown_parser::resolve_table_ref(&doc.tables, table_id)?;
+    let mut table = doc.tables[index].clone();
+    if let Some(expected) = expected_checksum {
+        if format!("{:016x}", table.checksum()) != expected {
+            return Err(format!(
+                "テーブルが読み込み後に変更されています: {}",
+                table_id
+            ));
+        }
+    }
+    let value = edit(&mut table)?;
+    // 編集で `end_line` が変わっていても、置き換えるのは編集前のテーブルの行範囲
+    let placed = MarkdownTable {
+        start_line: doc.tables[index].start_line,
+        end_line: doc.tables[index].end_line,
+        ..table.clone()
+    };
+    let content = rebuild_document(&doc.lines, std::slice::from_ref(&placed));
+    write_back(file_path, &doc, &content)?;
+    // 行数が変わると後ろのテーブルもずれるので、書き戻した内容で行番号を振り直す
+    let mut edited = ParsedDocument {
+        lines: content.lines().map(String::from).collect(),
+        ..doc
+    };
+    edited.tables[index] = table;
+    markdown_parser::reindex_table_lines(&mut edited);
+    Ok((value, edited.tables.swap_remove(index)))
+}
+
+/// `cleanup_table` コマンドの結果
+#[derive(Debug, Serialize)]
+pub struct CleanupResult {
+    #[serde(flatten)]
+    pub report: CleanupReport,
+    /// 整理後のテーブル（`dry_run` では書き戻していない）
+    pub table: MarkdownTable,
+}
+
+/// 空の行・空の列・セルの前後の空白を整理して書き戻す Tauri コマンド
+///
+/// `dry_run` を true にすると書き込まずに結果だけを返す（プレビュー用）。
+#[tauri::command]
+pub fn cleanup_table(
+    file_path: String,
+    table_id: String,
+    options: CleanupOptions,
+    dry_run: Option<bool>,
+    expected_checksum: Option<String>,
+) -> Result<CleanupResult, String> {
+    let (report, table) = if dry_run.unwrap_or(false) {
+        let doc = load_document(&file_path)?;
+        let index = markdown_parser::resolve_table_ref(&doc.tables, &table_id)?;
+        let mut table = doc.tables[index].clone();
+        let report = markdown_parser::cleanup_table(&mut table, options)?;
+        (report, table)
+    } else {
+        edit_single_table(
+            &file_path,
+            &table_id,
+            expected_checksum.as_deref(),
+            |table| markdown_parser::cleanup_table(table, options),
+        )?
+    };
+    Ok(CleanupResult { report, table })
+}
+
+/// 行の挿入・削除の結果
+#[derive(Debug, Serialize)]
+pub struct RowEditResult {
+    /// 挿入した行番号（削除では None）
+    pub inserted_at: Option<usize>,
+    /// 編集後のテーブルの終了行番号
+    pub end_line: usize,
+    pub table: MarkdownTable,
+}
+
+/// テーブルに行を挿入して書き戻す Tauri コマンド
+///
+/// `values` は列数に合わせて空のセルで埋める・切り詰める（省略時は空の行）。`at_index` を省略すると
+/// 末尾（集計行の手前）に追加する。`strict_types` を指定すると、列の値から推定した型
+/// （数値・真偽値・日付）に合わない値を拒否する。
+#[tauri::command]
+pub fn insert_row(
+    file_path: String,
+    table_id: String,
+    at_index: Option<usize>,
+    values: Option<Vec<String>>,
+    strict_types: Option<bool>,
+    expected_checksum: Option<String>,
+) -> Result<RowEditResult, String> {
+    let (at, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            markdown_parser::insert_row(
+                table,
+                at_index.unwrap_or(usize::MAX),
+                values.unwrap_or_default(),
+                strict_types.unwrap_or(false),
+            )
+        },
+    )?;
+    Ok(RowEditResult {
+        inserted_at: Some(at),
+        end_line: table.end_line,
+        table,
+    })
+}
+
+/// 重複した行を削除して書き戻す Tauri コマンド
+///
+/// `key_columns` を省略すると行全体を比べる。元に戻せるよう、削除した行を返す。
+#[tauri::command]
+pub fn deduplicate_rows(
+    file_path: String,
+    table_id: String,
+    key_columns: Option<Vec<ColumnRef>>,
+    keep: Option<KeepRow>,
+    case_insensitive: Option<bool>,
+    expected_checksum: Option<String>,
+) -> Result<DedupReport, String> {
+    let (report, _) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            markdown_parser::deduplicate_rows(
+                table,
+                key_columns.as_deref(),
+                keep.unwrap_or_default(),
+                case_insensitive.unwrap_or(false),
+            )
+        },
+    )?;
+    Ok(report)
+}
+
+/// テーブルの行をまとめて削除して書き戻す Tauri コマンド
+///
+/// 範囲外の行番号があれば何も削除せずにエラーを返す。
+#[tauri::command]
+pub fn delete_rows(
+    file_path: String,
+    table_id: String,
+    indices: Vec<usize>,
+    expected_checksum: Option<String>,
+) -> Result<RowEditResult, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| markdown_parser::delete_rows(table, &indices),
+    )?;
+    Ok(RowEditResult {
+        inserted_at: None,
+        end_line: table.end_line,
+        table,
+    })
+}
+
+/// テーブルに列を追加して書き戻す Tauri コマンド（更新後のテーブルを返す）
+///
+/// `alignment` の既定は `none`、`default_value` の既定は空文字列。
+#[tauri::command]
+pub fn insert_column(
+    file_path: String,
+    table_id: String,
+    at_index: usize,
+    header: String,
+    alignment: Option<String>,
+    default_value: Option<String>,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            markdown_parser::insert_column(
+                table,
+                at_index,
+                &header,
+                alignment.as_deref().unwrap_or("none"),
+                default_value.as_deref().unwrap_or(""),
+            )
+        },
+    )?;
+    Ok(table)
+}
+
+/// テーブルの列を削除して書き戻す Tauri コマンド（更新後のテーブルを返す）
+///
+/// 最後の1列は削除できない。
+#[tauri::command]
+pub fn delete_column(
+    file_path: String,
+    table_id: String,
+    index: usize,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| markdown_parser::delete_column(table, index),
+    )?;
+    Ok(table)
+}
+
+/// 列を1つ別の位置へ移して書き戻す Tauri コマンド（更新後のテーブルを返す）
+#[tauri::command]
+pub fn move_column(
+    file_path: String,
+    table_id: String,
+    from_index: usize,
+    to_index: usize,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| markdown_parser::move_column(table, from_index, to_index),
+    )?;
+    Ok(table)
+}
+
+/// ヘッダーを先頭のデータ行に下ろして書き戻す Tauri コマンド（更新後のテーブルを返す）
+///
+/// `new_headers` を省略すると `Column 1`, `Column 2`, ... を新しいヘッダーにする。
+#[tauri::command]
+pub fn demote_header(
+    file_path: String,
+    table_id: String,
+    new_headers: Option<Vec<String>>,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| table.demote_header(new_headers.unwrap_or_default()),
+    )?;
+    Ok(table)
+}
+
+/// `fill_cells` コマンドの結果
+#[derive(Debug, Serialize)]
+pub struct FillResult {
+    pub table: MarkdownTable,
+    /// 連番の規則が見つからず値をコピーした場合の警告
+    pub warning: Option<String>,
+}
+
+/// 列の指定範囲を下へのコピーまたは連番で埋めて書き戻す Tauri コマンド
+///
+/// `start_row` と `end_row` はどちらも範囲に含む。
+#[tauri::command]
+pub fn fill_cells(
+    file_path: String,
+    table_id: String,
+    column: ColumnRef,
+    start_row: usize,
+    end_row: usize,
+    mode: FillMode,
+    expected_checksum: Option<String>,
+) -> Result<FillResult, String> {
+    let (warning, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            let col = column.resolve(table)?;
+            fill::fill_cells(table, col, start_row, end_row, mode)
+        },
+    )?;
+    Ok(FillResult { table, warning })
+}
+
+/// `split_column` コマンドの結果
+#[derive(Debug, Serialize)]
+pub struct SplitColumnResult {
+    pub table: MarkdownTable,
+    /// 分けられず新しい列を空にした行の番号
+    pub unmatched_rows: Vec<usize>,
+}
+
+/// 列のセルを区切り文字または正規表現で分け、すぐ後ろに新しい列として追加して書き戻す Tauri コマンド
+///
+/// `keep_original` が false なら元の列を削除する。
+#[tauri::command]
+pub fn split_column(
+    file_path: String,
+    table_id: String,
+    column: ColumnRef,
+    delimiter_or_regex: ColumnSplitter,
+    new_headers: Vec<String>,
+    keep_original: bool,
+    expected_checksum: Option<String>,
+) -> Result<SplitColumnResult, String> {
+    let (unmatched_rows, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            let col = column.resolve(table)?;
+            markdown_parser::split_column_by(
+                table,
+                col,
+                &delimiter_or_regex,
+                new_headers,
+                keep_original,
+            )
+        },
+    )?;
+    Ok(SplitColumnResult {
+        table,
+        unmatched_rows,
+    })
+}
+
+/// 複数の列を1つの列にまとめて書き戻す Tauri コマンド（更新後のテーブルを返す）
+#[tauri::command]
+pub fn join_columns(
+    file_path: String,
+    table_id: String,
+    cols: Vec<usize>,
+    separator: String,
+    new_header: String,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| table.join_columns(cols, &separator, new_header),
+    )?;
+    Ok(table)
+}
+
+/// データ行をヘッダーに上げて書き戻す Tauri コマンド（更新後のテーブルを返す）
+#[tauri::command]
+pub fn promote_row_to_header(
+    file_path: String,
+    table_id: String,
+    row: usize,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| table.promote_row_to_header(row),
+    )?;
+    Ok(table)
+}
+
+/// テーブルの行と列を入れ替えて書き戻す Tauri コマンド（更新後のテーブルを返す）
+///
+/// `first_column_as_header` が true なら1列目を新しいヘッダーにし、false なら `Col 1`, ... を
+/// ヘッダーにする。`infer_alignments` を指定すると数値の列を右寄せにする（省略時はすべて `none`）。
+#[tauri::command]
+pub fn transpose_table(
+    file_path: String,
+    table_id: String,
+    first_column_as_header: bool,
+    infer_alignments: Option<bool>,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| {
+            markdown_parser::transpose_table(
+                table,
+                first_column_as_header,
+                infer_alignments.unwrap_or(false),
+            );
+            Ok(())
+        },
+    )?;
+    Ok(table)
+}
+
+/// 結合元のテーブルの行を結合先のテーブルに追加し、結合元を取り除いて書き戻す Tauri コマンド
+///
+/// 列は列名で対応させ、`column_mapping`（結合元の列名 → 結合先の列名）で読み替えられる。
+/// 対応表なしで列名が一致しない場合は `unmatched_columns` エラーで対応しない列名を返す。
+/// 結合後のテーブル（書き戻した後の行番号）を返す。
+#[tauri::command]
+pub fn merge_tables(
+    file_path: String,
+    source_table_id: String,
+    target_table_id: String,
+    column_mapping: Option<Vec<(String, String)>>,
+) -> Result<MarkdownTable, MergeError> {
+    let doc = load_document(&file_path)?;
+    let source = markdown_parser::resolve_table_ref(&doc.tables, &source_table_id)?;
+    let target = markdown_parser::resolve_table_ref(&doc.tables, &target_table_id)?;
+    let (content, _) =
+        markdown_parser::merge_tables(&doc, source, target, column_mapping.as_deref())?;
+    write_back(&file_path, &doc, &content)?;
+    let merged = load_document(&file_path)?;
+    let index = if source < target { target - 1 } else { target };
+    Ok(table_at(&merged, index)?.clone())
+}
+
+/// 列をまとめて並べ替えて書き戻す Tauri コマンド（更新後のテーブルを返す）
+///
+/// `order[i]` は新しい i 列目に来る元の列番号。すべての列をちょうど1回ずつ指定する。
+#[tauri::command]
+pub fn reorder_columns(
+    file_path: String,
+    table_id: String,
+    order: Vec<usize>,
+    expected_checksum: Option<String>,
+) -> Result<MarkdownTable, String> {
+    let (_, table) = edit_single_table(
+        &file_path,
+        &table_id,
+        expected_checksum.as_deref(),
+        |table| markdown_parser::reorder_columns(table, &order),
+    )?;
+    Ok(table)
+}
+
+/// 指定列の値ごとにテーブルを分割して返す Tauri コマンド
+///
+/// `replace_inline` が true なら、元のテーブルを分割後のテーブル群（空行区切り）に置き換えて書き戻す。
+#[tauri::command]
+pub fn split_table_by_column(
+    file_path: String,
+    table_index: usize,
+    col_index: usize,
+    replace_inline: bool,
+) -> Result<Vec<MarkdownTable>, String> {
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?;
+    if col_index >= table.headers.len() {
+        return Err(format!("列番号が範囲外です: {}", col_index));
+    }
+    let parts = markdown_parser::split_table_by_column_value(table, col_index);
+    if replace_inline && !parts.is_empty() {
+        let text: Vec<String> = parts.iter().map(markdown_parser::serialize_table).collect();
+        let content = markdown_parser::replace_table_text(&doc.lines, table, &text.join("\n"));
+        write_back(&file_path, &doc, &content)?;
+    }
+    Ok(parts)
+}
+
+/// テーブルを行の範囲または列の値で分割して書き戻す Tauri コマンド（読み直したドキュメントを返す）
+///
+/// 新しいテーブルは元のテーブルの直後に並べ、`with_headings` が true（既定）なら各テーブルの前に
+/// 範囲または値の `###` 見出しを付ける。すべての行が移った場合は元のテーブルを取り除く。
+#[tauri::command]
+pub fn split_table(
+    file_path: String,
+    table_id: String,
+    mode: SplitMode,
+    with_headings: Option<bool>,
+) -> Result<ParsedDocument, String> {
+    let doc = load_document(&file_path)?;
+    let index = markdown_parser::resolve_table_ref(&doc.tables, &table_id)?;
+    let table = &doc.tables[index];
+    let split = markdown_parser::split_table(table, &mode)?;
+    let text = markdown_parser::render_table_split(&split, with_headings.unwrap_or(true));
+    let content = markdown_parser::replace_table_text(&doc.lines, table, &text);
+    write_back(&file_path, &doc, &content)?;
+    load_document(&file_path)
+}
+
+/// 正規表現をコンパイルする（不正なパターンはエラーメッセージにする）
+fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("正規表現が不正です: {}", e))
+}
+
+/// 正規表現に一致するデータセルの `(row, col)` を返す Tauri コマンド
+#[tauri::command]
+pub fn regex_search_table(
+    file_path: String,
+    table_index: usize,
+    pattern: String,
+) -> Result<Vec<(usize, usize)>, String> {
+    let re = compile_regex(&pattern)?;
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?;
+    Ok(markdown_parser::find_cells(table, |cell| re.is_match(cell)))
+}
+
+/// 正規表現でセルを置換して書き戻す Tauri コマンド（変更したセル数を返す）
+///
+/// `replacement` では `$1` や `${name}` でキャプチャを参照できる。
+/// `exclude_headers` を true にするとヘッダーは置換しない。置換後の値にパイプや改行を含むセルが
+/// あれば何も書き込まずにエラーを返す。
+#[tauri::command]
+pub fn regex_replace_table(
+    file_path: String,
+    table_index: usize,
+    pattern: String,
+    replacement: String,
+    exclude_headers: Option<bool>,
+) -> Result<usize, String> {
+    let re = compile_regex(&pattern)?;
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    let changed =
+        markdown_parser::replace_cells(&mut table, !exclude_headers.unwrap_or(false), |cell| {
+            re.replace_all(cell, replacement.as_str())
+        })?;
+    if changed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[table]))?;
+    }
+    Ok(changed)
+}
+
+/// テーブルを HTML に変換して返す Tauri コマンド
+///
+/// `allow_inline_html` を true にすると、セル内のインライン Markdown と安全な HTML を描画する。
+/// `cell_annotations` を true にすると、セル末尾の `<!-- 注釈 -->` を `data-annotation` 属性にする。
+#[tauri::command]
+pub fn export_table_html(
+    file_path: String,
+    table_index: usize,
+    allow_inline_html: Option<bool>,
+    cell_annotations: Option<bool>,
+) -> Result<String, String> {
+    let mut doc = load_document(&file_path)?;
+    if cell_annotations.unwrap_or(false) {
+        markdown_parser::extract_cell_annotations(&mut doc);
+    }
+    let table = table_at(&doc, table_index)?;
+    Ok(table_to_html(table, allow_inline_html.unwrap_or(false)))
+}
+
+/// テーブルを Pandoc の pipe table（見出しをキャプションにする）に変換して返す Tauri コマンド
+#[tauri::command]
+pub fn export_table_pandoc(file_path: String, table_index: usize) -> Result<String, String> {
+    let doc = load_document(&file_path)?;
+    Ok(markdown_parser::table_to_pandoc_md(table_at(
+        &doc,
+        table_index,
+    )?))
+}
+
+/// データ行が1行のテーブルを Markdown の定義リストに変換して返す Tauri コマンド
+#[tauri::command]
+pub fn convert_table_to_definition_list(
+    file_path: String,
+    table_index: usize,
+) -> Result<String, String> {
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?;
+    export::table_to_definition_list(table).ok_or_else(|| {
+        format!(
+            "定義リストに変換できるのはデータ行が1行のテーブルのみです（{}行）",
+            table.rows.len()
+        )
+    })
+}
+
+/// テーブルの前後の行とテーブルのソースを返す Tauri コマンド（プレビュー用）
+#[tauri::command]
+pub fn table_context(
+    file_path: String,
+    table_index: usize,
+    before: usize,
+    after: usize,
+) -> Result<TableContext, String> {
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?;
+    Ok(markdown_parser::table_context(
+        &doc.lines, table, before, after,
+    ))
+}
+
+/// テーブルが属する見出しの階層を `(レベル, 見出し)` の配列で返す Tauri コマンド（パンくず表示用）
+#[tauri::command]
+pub fn get_table_heading_path(
+    file_path: String,
+    table_index: usize,
+) -> Result<Vec<(u8, String)>, String> {
+    let doc = load_document(&file_path)?;
+    table_at(&doc, table_index)?;
+    Ok(markdown_parser::heading_path_for_table(&doc, table_index))
+}
+
+/// ファイル内のテーブル数を返す Tauri コマンド（ドキュメント全体を送らずに済む軽量版）
+#[tauri::command]
+pub fn table_count(file_path: String) -> Result<usize, String> {
+    count_tables_in_file(Path::new(&file_path))
+}
+
+/// 1つのテーブルを指定の書式で整形し直して書き戻す Tauri コマンド
+///
+/// 内容は変えず、対象テーブル以外の行には手を付けない。
+#[tauri::command]
+pub fn format_table(
+    file_path: String,
+    table_index: usize,
+    opts: SerializeOptions,
+) -> Result<(), String> {
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?.clone();
+    write_back(
+        &file_path,
+        &doc,
+        &rebuild_document_with(&doc.lines, &[table], &opts),
+    )
+}
+
+/// テーブルから指定した列だけを取り出した新しいテーブルを返す Tauri コマンド（ファイルは変更しない）
+#[tauri::command]
+pub fn project_table_columns(
+    file_path: String,
+    table_index: usize,
+    col_names: Vec<String>,
+) -> Result<MarkdownTable, String> {
+    let doc = load_document(&file_path)?;
+    let names: Vec<&str> = col_names.iter().map(|s| s.as_str()).collect();
+    markdown_parser::select_columns(table_at(&doc, table_index)?, &names)
+}
+
+/// `group_col` の値ごとに `value_col` を集計したテーブルを返す Tauri コマンド（ファイルは変更しない）
+#[tauri::command]
+pub fn pivot(
+    file_path: String,
+    table_index: usize,
+    group_col: usize,
+    value_col: usize,
+    agg: Aggregation,
+) -> Result<MarkdownTable, String> {
+    let doc = load_document(&file_path)?;
+    markdown_parser::pivot_table(table_at(&doc, table_index)?, group_col, value_col, agg)
+}
+
+/// 指定列の値が似ている行の組を探す Tauri コマンド（ファイルは変更しない）
+#[tauri::command]
+pub fn fuzzy_deduplicate(
+    file_path: String,
+    table_index: usize,
+    col_index: usize,
+    similarity_threshold: f64,
+) -> Result<Vec<(usize, usize, f64)>, String> {
+    let doc = load_document(&file_path)?;
+    let mut table = table_at(&doc, table_index)?.clone();
+    if col_index >= table.headers.len() {
+        return Err(format!("列が見つかりません: {}", col_index));
+    }
+    Ok(markdown_parser::fuzzy_deduplicate(
+        &mut table,
+        col_index,
+        similarity_threshold,
+    ))
+}
+
+/// ファイルを最近開いたファイルの履歴に追加する Tauri コマンド
+#[tauri::command]
+pub fn add_recent_file(recent: State<'_, RecentFiles>, path: String) -> Result<(), String> {
+    recent.add(&path)
+}
+
+/// 最近開いたファイルを新しい順に取得する Tauri コマンド
+#[tauri::command]
+pub fn get_recent_files(recent: State<'_, RecentFiles>, limit: usize) -> Vec<RecentEntry> {
+    recent.get(limit)
+}
+
+/// 最近開いたファイルの履歴から1件削除する Tauri コマンド
+#[tauri::command]
+pub fn remove_recent_file(recent: State<'_, RecentFiles>, path: String) -> Result<(), String> {
+    recent.remove(&path)
+}
+
+/// 最近開いたファイルの履歴を消去する Tauri コマンド
+#[tauri::command]
+pub fn clear_recent_files(recent: State<'_, RecentFiles>) -> Result<(), String> {
+    recent.clear()
+}
+
+/// ドキュメント内のすべてのテーブルを同じ書式で整形し直して書き戻す Tauri コマンド
+///
+/// テーブル以外の行はそのまま残す。
+#[tauri::command]
+pub fn format_document(file_path: String, opts: SerializeOptions) -> Result<(), String> {
+    let doc = load_document(&file_path)?;
+    write_back(
+        &file_path,
+        &doc,
+        &rebuild_document_with(&doc.lines, &doc.tables, &opts),
+    )
+}
+
+/// データ行のないテーブルをファイルから削除する Tauri コマンド（削除数を返す）
+#[tauri::command]
+pub fn cleanup_empty_tables(file_path: String) -> Result<usize, String> {
+    let mut doc = load_document(&file_path)?;
+    let removed = markdown_parser::remove_empty_tables(&mut doc);
+    if removed > 0 {
+        write_back(&file_path, &doc, &rebuild_document(&doc.lines, &[]))?;
+    }
+    Ok(removed)
+}
+
+/// タグ列のタグごとに、それを含む行番号の一覧を返す Tauri コマンド
+///
+/// 使われている行が多いタグから順に並べる（同数ならタグ名の順）。
+#[tauri::command]
+pub fn get_all_tags(
+    file_path: String,
+    table_index: usize,
+    tag_column_name: String,
+) -> Result<Vec<(String, Vec<usize>)>, String> {
+    let doc = load_document(&file_path)?;
+    let table = table_at(&doc, table_index)?;
+    let mut tags: Vec<(String, Vec<usize>)> =
+        markdown_parser::extract_tags(table, &tag_column_name)
+            .into_iter()
+            .collect();
+    tags.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    Ok(tags)
+}
+
+/// フォルダ以下のすべてのファイルで、指定した名前の列の数値を集計する Tauri コマンド
+#[tauri::command]
+pub fn aggregate_column_across_files(
+    dir_path: String,
+    column_name: String,
+    agg: Aggregation,
+) -> Result<f64, String> {
+    inventory::aggregate_column_across_files(Path::new(&dir_path), &column_name, agg)
+}
+
+/// `| Key | Value |` 形式の2列のテーブルをキーから値へのマップとして返す Tauri コマンド
+#[tauri::command]
+pub fn table_to_key_value_map(
+    file_path: String,
+    table_index: usize,
+) -> Result<HashMap<String, String>, String> {
+    let doc = load_document(&file_path)?;
+    markdown_parser::table_to_key_value_map(table_at(&doc, table_index)?)
+}
+
+/// 指定した列名のヘッダーを持つテーブルを含むファイルを探す Tauri コマンド
+#[tauri::command]
+pub fn find_files_with_column(
+    dir_path: String,
+    column_name: String,
+    case_sensitive: bool,
+) -> Result<Vec<String>, String> {
+    let dir = Path::new(&dir_path);
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let target = column_name.trim();
+    let has_column = |table: &MarkdownTable| {
+        if case_sensitive {
+            table.column_index_of(target).is_some()
+        } else {
+            table.column_index_of_ci(target).is_some()
+        }
+    };
+
+    Ok(collect_markdown_files(dir)
+        .into_iter()
+        .filter(|path| {
+            fs::read_to_string(path)
+                .is_ok_and(|content| parse_markdown(&content).tables.iter().any(has_column))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// ファイルをワークスペースにピン留めする Tauri コマンド（ファイルを含むルートの設定に保存）
+#[tauri::command]
+pub fn pin_file(workspace: State<'_, Workspace>, path: String) -> Result<Vec<String>, String> {
+    workspace::pin(&workspace.root_for(&path)?, &path)
+}
+
+/// ピン留めを外す Tauri コマンド
+#[tauri::command]
+pub fn unpin_file(workspace: State<'_, Workspace>, path: String) -> Result<Vec<String>, String> {
+    workspace::unpin(&workspace.root_for(&path)?, &path)
+}
+
+/// ピン留めしたファイルを表示順に取得する Tauri コマンド
+///
+/// `root` を省略した場合は、登録済みのすべてのルートのピン留めをルート順に返す。
+#[tauri::command]
+pub fn get_pinned_files(
+    workspace: State<'_, Workspace>,
+    root: Option<String>,
+) -> Result<Vec<String>, String> {
+    match root {
+        Some(id) => Ok(workspace::pinned_files(&workspace.resolve_root(Some(&id))?)),
+        None => Ok(workspace
+            .roots()
+            .iter()
+            .flat_map(|r| workspace::pinned_files(r))
+            .collect()),
+    }
+}
+
+/// ピン留めの表示順を並べ替える Tauri コマンド（同じルート内のファイルのみ指定できる）
+#[tauri::command]
+pub fn reorder_pins(
+    workspace: State<'_, Workspace>,
+    paths: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let Some(first) = paths.first() else {
+        return Ok(Vec::new());
+    };
+    let root = workspace.root_for(first)?;
+    for path in &paths {
+        if workspace.root_for(path)? != root {
+            return Err("異なるルートのファイルは一緒に並べ替えられません".to_string());
+        }
+    }
+    workspace::reorder_pins(&root, &paths)
+}
+
+/// ディレクトリ以下の .md ファイルを更新日時の新しい順に取得する Tauri コマンド
+#[tauri::command]
+pub fn recent_files(dir_path: String, limit: usize) -> Result<Vec<RecentFile>, String> {
+    file_io::recently_modified_files(Path::new(&dir_path), limit)
+}
+
+/// 複数のファイルを順に結合して `dest_path` に書き出す Tauri コマンド（結合結果のテーブル数を返す）
+///
+/// `separator` を省略した場合は水平線（`\n\n---\n\n`）で区切る。出力は UTF-8 で書き込む。
+#[tauri::command]
+pub fn concatenate_files(
+    file_paths: Vec<String>,
+    dest_path: String,
+    separator: Option<String>,
+) -> Result<usize, String> {
+    if file_paths.is_empty() {
+        return Err("結合するファイルが指定されていません".to_string());
+    }
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    let separator = separator.unwrap_or_else(|| "\n\n---\n\n".to_string());
+    let content = file_io::concatenate_files(&paths, &separator)?;
+    write_encoded(Path::new(&dest_path), &content, "UTF-8", false, false)
+        .map_err(|e| e.to_string())?;
+    Ok(markdown_parser::count_tables(&content))
+}
+
+/// 見出しをファイル名として使える文字列にする（使えない文字は `_` に置き換える）
+fn sanitize_file_stem(heading: &str) -> String {
+    let stem: String = heading
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    stem.trim().trim_matches('.').trim().to_string()
+}
+
+/// フォルダ内のすべての CSV ファイルをテーブルにして1つの Markdown ファイルに書き出す Tauri コマンド
+///
+/// 各テーブルの見出しは CSV のファイル名（拡張子なし）。解釈できない CSV は飛ばし、
+/// その警告（`ファイル名: 理由`）を返す。
+#[tauri::command]
+pub fn import_csv_directory(dir_path: String, out_path: String) -> Result<Vec<String>, String> {
+    let (content, warnings) = import::csv_directory_to_markdown(Path::new(&dir_path))?;
+    write_encoded(Path::new(&out_path), &content, "UTF-8", false, false)
+        .map_err(|e| e.to_string())?;
+    Ok(warnings)
+}
+
+/// テーブルのセルにある相対リンクのうち、リンク先が存在しないものを返す Tauri コマンド
+#[tauri::command]
+pub fn check_cell_links(file_path: String) -> Result<Vec<links::BrokenLink>, String> {
+    links::check_cell_links(Path::new(&file_path))
+}
+
+/// Markdown ファイルの名前を変更する Tauri コマンド（リンクを書き換えたファイルのパスを返す）
+///
+/// `update_links` を指定すると、`root`（省略時は元のファイルのフォルダ）以下のファイルにある
+/// 元のファイルへの相対リンクを新しいパスに書き直す。
+#[tauri::command]
+pub fn rename_markdown_file(
+    cache: State<'_, DocumentCache>,
+    file_search: State<'_, FileSearch>,
+    old_path: String,
+    new_path: String,
+    update_links: Option<bool>,
+    root: Option<String>,
+) -> Result<Vec<String>, String> {
+    let (old, new) = (Path::new(&old_path), Path::new(&new_path));
+    links::rename_markdown_file(old, new)?;
+    cache.invalidate(old);
+    file_search.invalidate();
+    if !update_links.unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+    let root = match &root {
+        Some(root) => PathBuf::from(root),
+        None => old.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let updated = links::update_links_after_rename(&root, old, new)?;
+    for path in &updated {
+        cache.invalidate(Path::new(path));
+    }
+    Ok(updated)
+}
+
+/// ファイル内のすべてのテーブルをそれぞれ CSV ファイルとして `out_dir` に書き出す Tauri コマンド
+///
+/// ファイル名は見出しがあればその見出し、なければ `<元のファイル名>-<テーブル番号>` にし、
+/// 重複する場合は `-2` などを付ける。`out_dir` がなければ作成し、同名のファイルは上書きする。
+/// 書き出したファイルのパスを返す。
+#[tauri::command]
+pub fn export_all_tables_csv(file_path: String, out_dir: String) -> Result<Vec<String>, String> {
+    let doc = load_document(&file_path)?;
+    let source_stem = Path::new(&file_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let out_dir = Path::new(&out_dir);
+    fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+    let mut used = HashSet::new();
+    let mut written = Vec::new();
+    for (index, table) in doc.tables.iter().enumerate() {
+        let mut stem = table
+            .heading
+            .as_deref()
+            .map(sanitize_file_stem)
+            .unwrap_or_default();
+        if stem.is_empty() {
+            stem = format!("{}-{}", source_stem, index);
+        }
+        let mut name = format!("{}.csv", stem);
+        let mut n = 2;
+        while !used.insert(name.to_lowercase()) {
+            name = format!("{}-{}.csv", stem, n);
+            n += 1;
+        }
+        let dest = out_dir.join(name);
+        write_encoded(&dest, &export::table_to_csv(table), "UTF-8", false, false)
+            .map_err(|e| e.to_string())?;
+        written.push(dest.to_string_lossy().to_string());
+    }
+    Ok(written)
+}
+
+/// 指定レベルの見出しごとにファイルを分割し、`output_dir` に書き出す Tauri コマンド
+///
+/// ファイル名は見出しテキスト（最初の見出しより前の部分は元のファイル名）から作り、
+/// 重複する場合は `-2` などを付ける。既存のファイルは上書きせずエラーにする。
+/// 書き出したファイルのパスを返す。
+#[tauri::command]
+pub fn split_file_at_heading(
+    file_path: String,
+    heading_level: u8,
+    output_dir: String,
+) -> Result<Vec<String>, String> {
+    if !(1..=6).contains(&heading_level) {
+        return Err(format!(
+            "見出しレベルは1〜6で指定してください: {}",
+            heading_level
+        ));
+    }
+    let doc = load_document(&file_path)?;
+    let source_stem = Path::new(&file_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let out_dir = Path::new(&output_dir);
+    let mut used = HashSet::new();
+    let mut outputs = Vec::new();
+    for (heading, part) in markdown_parser::split_document_at_heading(&doc, heading_level) {
+        let mut stem = sanitize_file_stem(if heading.is_empty() {
+            &source_stem
+        } else {
+            &heading
+        });
+        if stem.is_empty() {
+            stem = "section".to_string();
+        }
+        let mut name = format!("{}.md", stem);
+        let mut n = 2;
+        while !used.insert(name.to_lowercase()) {
+            name = format!("{}-{}.md", stem, n);
+            n += 1;
+        }
+        let dest = out_dir.join(name);
+        if dest.exists() {
+            return Err(format!(
+                "出力先のファイルが既に存在します: {}",
+                dest.display()
+            ));
+        }
+        outputs.push((dest, part));
+    }
+    fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+    outputs
+        .into_iter()
+        .map(|(dest, part)| {
+            let content = format!("{}\n", part.lines.join("\n"));
+            write_encoded(&dest, &content, &part.encoding, part.has_bom, false)
+                .map_err(|e| e.to_string())?;
+            Ok(dest.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// ディレクトリ以下のファイルの見出しとテーブル見出しのインデックスを作る Tauri コマンド
+#[tauri::command]
+pub fn index_directory(dir_path: String) -> Result<Vec<FileIndex>, String> {
+    file_io::index_directory(Path::new(&dir_path))
+}
+
+/// ディレクトリ以下のすべてのテーブルを1つの JSON 配列としてエクスポートする Tauri コマンド
+///
+/// 読み込めなかったファイルは `errors` に入れて残りを続ける。
+#[tauri::command]
+pub fn export_directory_tables_json(
+    dir_path: String,
+) -> Result<export::DirectoryTablesJson, String> {
+    export::directory_tables_json(Path::new(&dir_path))
+}
+
+/// フォルダ内のすべてのテーブルの概要（見出し・列名・行数など）を返す Tauri コマンド
+///
+/// `header` を指定するとその列名を持つテーブルだけを返す。読み込めなかったファイルは
+/// `errors` に入れて残りを続ける。ファイルが多いときは `table-inventory-progress` で進捗を通知する。
+#[tauri::command]
+pub fn list_all_tables(
+    app: tauri::AppHandle,
+    dir_path: String,
+    header: Option<String>,
+) -> Result<TableInventory, String> {
+    inventory::list_all_tables(Path::new(&dir_path), header.as_deref(), |progress| {
+        let _ = app.emit(INVENTORY_PROGRESS_EVENT, progress);
+    })
+}
+
+/// フォルダ以下で、コピーされて内容が少しずれたテーブルの組を探す Tauri コマンド
+///
+/// ヘッダーが同じで行の 90% 以上が一致するテーブルを重複とみなす。
+#[tauri::command]
+pub async fn find_duplicate_tables_in_directory(
+    dir_path: String,
+) -> Result<Vec<DuplicateTableReport>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        inventory::find_duplicate_tables(Path::new(&dir_path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// ワークスペース設定を取得する Tauri コマンド（設定ファイルが壊れている場合は既定値と警告を返す）
+///
+/// `root` を省略した場合は主ワークスペースの設定を返す。
+#[tauri::command]
+pub fn get_workspace_settings(
+    workspace: State<'_, Workspace>,
+    root: Option<String>,
+) -> Result<workspace::LoadedSettings, String> {
+    Ok(workspace::load_settings_checked(
+        &workspace.resolve_root(root.as_deref())?,
+    ))
+}
+
+/// 部分的な設定オブジェクトをマージしてワークスペース設定を更新する Tauri コマンド
+#[tauri::command]
+pub fn update_workspace_settings(
+    workspace: State<'_, Workspace>,
+    file_search: State<'_, FileSearch>,
+    patch: serde_json::Value,
+    root: Option<String>,
+) -> Result<workspace::WorkspaceSettings, String> {
+    let settings = workspace::update_settings(&workspace.resolve_root(root.as_deref())?, &patch)?;
+    // 除外パターンが変わりうるので検索用のファイル一覧を読み直させる
+    file_search.invalidate();
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn tree(dir: &Path, include_table_counts: bool, sort_order: SortOrder) -> Vec<FileEntry> {
+        let opts = TreeOptions {
+            include_table_counts: include_table_counts || sort_order == SortOrder::ByTableCount,
+            sort_order,
+            pinned: HashSet::new(),
+        };
+        read_dir_recursive(dir, 0, &opts)
+    }
+
+    #[test]
+    fn test_file_tree_parallel_matches_sequential() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["b", "a/deep", "empty", ".hidden", "c"] {
+            fs::create_dir_all(dir.path().join(sub)).unwrap();
+        }
+        for file in [
+            "z.md",
+            "a.md",
+            "b/x.md",
+            "a/deep/y.md",
+            "a/note.txt",
+            ".hidden/h.md",
+        ] {
+            fs::write(dir.path().join(file), "| A |\n| --- |\n").unwrap();
+        }
+        for i in 0..20 {
+            fs::write(dir.path().join(format!("c/{:02}.md", i)), "").unwrap();
+        }
+        let opts = TreeOptions {
+            include_table_counts: false,
+            sort_order: SortOrder::ByName,
+            pinned: HashSet::new(),
+        };
+        let sequential = read_dir_recursive(dir.path(), 0, &opts);
+        let parallel = read_dir_parallel(dir.path(), 0, &opts);
+        assert_eq!(
+            serde_json::to_value(&parallel).unwrap(),
+            serde_json::to_value(&sequential).unwrap()
+        );
+        let names: Vec<&str> = parallel.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "a.md", "b", "c", "z.md"]);
+    }
+
+    #[test]
+    fn test_file_tree_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        fs::write(&a, "| A |\n| --- |\n| 1 |\n").unwrap();
+        fs::write(&b, "no tables").unwrap();
+        set_mtime(&a, 1_700_000_000);
+
+        let entries = tree(dir.path(), true, SortOrder::ByName);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "a.md");
+        assert_eq!(entries[0].size_bytes, Some(20));
+        assert_eq!(entries[0].modified_at, Some(1_700_000_000));
+        assert_eq!(entries[0].table_count, Some(1));
+        assert_eq!(entries[1].size_bytes, Some(9));
+        assert_eq!(entries[1].table_count, Some(0));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(entries[1].modified_at.unwrap() <= now);
+
+        let entries = tree(dir.path(), false, SortOrder::ByName);
+        assert_eq!(entries[0].table_count, None);
+    }
+
+    fn set_mtime(path: &Path, secs: u64) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_file_tree_sort_orders() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = "| A |\n| --- |\n| 1 |\n\n";
+        for (name, secs, tables) in [("a.md", 300, 1), ("b.md", 100, 3), ("c.md", 200, 2)] {
+            let path = dir.path().join(name);
+            fs::write(&path, table.repeat(tables)).unwrap();
+            set_mtime(&path, secs);
+        }
+        let names = |order| {
+            tree(dir.path(), false, order)
+                .into_iter()
+                .map(|e| e.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(SortOrder::ByName), vec!["a.md", "b.md", "c.md"]);
+        assert_eq!(names(SortOrder::ByModified), vec!["a.md", "c.md", "b.md"]);
+        assert_eq!(names(SortOrder::BySize), vec!["b.md", "c.md", "a.md"]);
+        assert_eq!(names(SortOrder::ByTableCount), vec!["b.md", "c.md", "a.md"]);
+    }
+
+    #[test]
+    fn test_regex_search_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(
+            &path,
+            "| ID | Note |\n| --- | --- |\n| a-1 | x |\n| b | a-22 |\n",
+        )
+        .unwrap();
+        let file = path.to_string_lossy().to_string();
+        assert_eq!(
+            regex_search_table(file.clone(), 0, r"^a-\d+$".to_string()).unwrap(),
+            vec![(0, 0), (1, 1)]
+        );
+        let err = regex_search_table(file, 0, "(".to_string()).unwrap_err();
+        assert!(err.starts_with("正規表現が不正です"));
+    }
+
+    #[test]
+    fn test_regex_replace_table_with_captures() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(
+            &path,
+            "| 2024-01-02 | Date |\n| --- | --- |\n| 2023-12-31 | x |\n| none | 2022-05-06 |\n",
+        )
+        .unwrap();
+        let file = path.to_string_lossy().to_string();
+        let pattern = r"(\d{4})-(\d{2})-(\d{2})".to_string();
+        let changed = regex_replace_table(
+            file.clone(),
+            0,
+            pattern.clone(),
+            "$3/$2/$1".into(),
+            Some(true),
+        )
+        .unwrap();
+        assert_eq!(changed, 2);
+        let doc = load_document(&file).unwrap();
+        assert_eq!(doc.tables[0].headers[0], "2024-01-02");
+        assert_eq!(doc.tables[0].rows[0][0], "31/12/2023");
+        assert_eq!(doc.tables[0].rows[1][1], "06/05/2022");
+        assert_eq!(
+            regex_replace_table(file.clone(), 0, pattern, "$3/$2/$1".into(), None).unwrap(),
+            1
+        );
+
+        // パイプや改行になる置換はセルを壊すため、何も書き込まずにエラーにする
+        let before = fs::read_to_string(&path).unwrap();
+        for replacement in ["$1|$2", "$1\n"] {
+            let pattern = r"(\d{2})/(\d{2})".to_string();
+            let err = regex_replace_table(file.clone(), 0, pattern, replacement.into(), None)
+                .unwrap_err();
+            assert!(err.contains("置換後の値"));
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_split_file_at_heading() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.md");
+        fs::write(
+            &path,
+            "# Q1\n| A |\n| --- |\n| 1 |\n# Q2: 見込み\ntext\n# Q1\nagain\n",
+        )
+        .unwrap();
+        let out = dir.path().join("parts");
+        let written = split_file_at_heading(
+            path.to_string_lossy().to_string(),
+            1,
+            out.to_string_lossy().to_string(),
+        )
+        .unwrap();
+        let names: Vec<String> = written
+            .iter()
+            .map(|p| {
+                Path::new(p)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(names, vec!["Q1.md", "Q2_ 見込み.md", "Q1-2.md"]);
+        assert_eq!(
+            fs::read_to_string(out.join("Q1.md")).unwrap(),
+            "# Q1\n| A |\n| --- |\n| 1 |\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("Q2_ 見込み.md")).unwrap(),
+            "# Q2: 見込み\ntext\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("Q1-2.md")).unwrap(),
+            "# Q1\nagain\n"
+        );
+        // 既存のファイルは上書きしない
+        assert!(split_file_at_heading(
+            path.to_string_lossy().to_string(),
+            1,
+            out.to_string_lossy().to_string(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_format_table_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        let md =
+            "# T\n\n|  Name   |Age|\n|:-----|------:|\n|   Alice | 30 |\n\n| X |\n|---|\n| y |";
+        fs::write(&path, md).unwrap();
+        let opts = SerializeOptions {
+            style: markdown_parser::TableStyle::Compact,
+            ..SerializeOptions::default()
+        };
+        format_table(path.to_string_lossy().to_string(), 0, opts).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# T\n\n| Name | Age |\n| :-- | --: |\n| Alice | 30 |\n\n| X |\n|---|\n| y |"
+        );
+    }
+
+    #[test]
+    fn test_single_table_edits_keep_following_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        let file_path = path.to_string_lossy().to_string();
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n| 2 |\n\nafter\n").unwrap();
+
+        let result = insert_row(file_path.clone(), "0".into(), None, None, None, None).unwrap();
+        assert_eq!(result.end_line, 4);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "| A   |\n| ----|\n| 1   |\n| 2   |\n|     |\n\nafter"
+        );
+        delete_rows(file_path.clone(), "0".into(), vec![0, 2], None).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "| A   |\n| ----|\n| 2   |\n\nafter"
+        );
+
+        // ファイル末尾で終わるテーブル
+        fs::write(&path, "| A |\n| --- |\n| 1 |").unwrap();
+        insert_row(
+            file_path.clone(),
+            "0".into(),
+            None,
+            Some(vec!["2".into()]),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "| A   |\n| ----|\n| 1   |\n| 2   |"
+        );
+        delete_rows(file_path.clone(), "0".into(), vec![0], None).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "| A   |\n| ----|\n| 2   |"
+        );
+    }
+
+    #[test]
+    fn test_validate_markdown_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A | B |\n| --- |\n| 1 | 2 |\n\n| C |\n| --- |\n").unwrap();
+        let errors = validate_markdown_file(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 0);
+    }
+
+    #[test]
+    fn test_format_document_all_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        let md = "# Doc\n\n|a|b|\n|-|-|\n|1|2|\n\nSome *text* here.\n\n| a   | b   |\n| --- | --- |\n| 1   | 2   |\n\n  | a |  b |\n  |:--|--:|\n  | 1 | 2 |\n";
+        fs::write(&path, md).unwrap();
+        let opts = SerializeOptions {
+            style: markdown_parser::TableStyle::Compact,
+            ..SerializeOptions::default()
+        };
+        format_document(path.to_string_lossy().to_string(), opts).unwrap();
+
+        let out = fs::read_to_string(&path).unwrap();
+        let plain = "| a | b |\n| --- | --- |\n| 1 | 2 |\n";
+        let aligned = "| a | b |\n| :-- | --: |\n| 1 | 2 |\n";
+        assert_eq!(
+            out,
+            format!(
+                "# Doc\n\n{plain}\nSome *text* here.\n\n{plain}\n{}",
+                aligned.trim_end()
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_files_with_column() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(
+            dir.path().join("a.md"),
+            "| Task | Priority |\n|---|---|\n| x | 1 |\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("b.md"), "| Name |\n|---|\n| y |\n").unwrap();
+        fs::write(dir.path().join("sub/c.md"), "| priority |\n|---|\n| 2 |\n").unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let names = |found: Vec<String>| {
+            found
+                .iter()
+                .map(|p| {
+                    Path::new(p)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let found = find_files_with_column(root.clone(), "PRIORITY".to_string(), false).unwrap();
+        assert_eq!(names(found), vec!["a.md", "c.md"]);
+        let found = find_files_with_column(root.clone(), "Priority".to_string(), true).unwrap();
+        assert_eq!(names(found), vec!["a.md"]);
+        let found = find_files_with_column(root, "Owner".to_string(), false).unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_export_all_tables_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("data.md");
+        fs::write(
+            &src,
+            "| A | B |\n| --- | --- |\n| 1 | x, y |\n\n## 売上/2024\n\n| C |\n| --- |\n| 2 |\n",
+        )
+        .unwrap();
+        let out = dir.path().join("csv");
+        let written = export_all_tables_csv(
+            src.to_string_lossy().to_string(),
+            out.to_string_lossy().to_string(),
+        )
+        .unwrap();
+        assert_eq!(written.len(), 2);
+        assert!(written[0].ends_with("data-0.csv"));
+        assert!(written[1].ends_with("売上_2024.csv"));
+        assert_eq!(fs::read_to_string(&written[0]).unwrap(), "A,B\n1,\"x, y\"");
+        assert_eq!(fs::read_to_string(&written[1]).unwrap(), "C\n2");
+    }
 }
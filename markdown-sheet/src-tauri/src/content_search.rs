@@ -0,0 +1,309 @@
+use crate::file_io::read_document;
+use crate::markdown_parser::{table_ids, MarkdownTable};
+use crate::search_index::SearchIndex;
+use crate::workspace;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 既定の最大件数
+const DEFAULT_MAX_RESULTS: usize = 500;
+/// 検索文字列の最大長（文字数）
+const MAX_QUERY_CHARS: usize = 1000;
+/// コンパイル後の正規表現の最大サイズ
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+/// スニペットの前後に残す文字数
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// 全文検索のオプション
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// 大文字・小文字を区別する
+    pub case_sensitive: bool,
+    /// 単語全体に一致するものだけ
+    pub whole_word: bool,
+    /// `query` を正規表現として扱う
+    pub regex: bool,
+    /// テーブルのセルだけを検索する
+    pub tables_only: bool,
+    /// 最大件数（省略時は 500）
+    pub max_results: Option<usize>,
+}
+
+/// テーブル内のヒット位置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CellLocation {
+    pub table_index: usize,
+    /// `table_ids` と同じ安定 ID
+    pub table_id: String,
+    /// データ行の番号（ヘッダーなら None）
+    pub row: Option<usize>,
+    pub col: usize,
+}
+
+/// 全文検索のヒット1件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContentMatch {
+    pub path: String,
+    /// 行番号（0 始まり、`start_line` と同じ数え方）
+    pub line: usize,
+    /// ヒット箇所の前後を切り出したテキスト（テーブル内ならセルの値）
+    pub snippet: String,
+    /// テーブル内のヒットならそのセル
+    pub cell: Option<CellLocation>,
+}
+
+/// 検索条件を正規表現にまとめる（長すぎる・複雑すぎるパターンはエラー）
+pub fn build_matcher(query: &str, options: &SearchOptions) -> Result<Regex, String> {
+    if query.is_empty() {
+        return Err("検索文字列が空です".to_string());
+    }
+    if query.chars().count() > MAX_QUERY_CHARS {
+        return Err(format!(
+            "検索文字列が長すぎます（{}文字まで）",
+            MAX_QUERY_CHARS
+        ));
+    }
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let pattern = if options.whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => "検索パターンが複雑すぎます".to_string(),
+            e => format!("正規表現が不正です: {}", e),
+        })
+}
+
+/// ヒット箇所の前後だけを切り出す（文字単位で切るので多バイト文字でも安全）
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let before: Vec<char> = text[..start].chars().collect();
+    let after: Vec<char> = text[end..].chars().collect();
+    let mut out = String::new();
+    if before.len() > SNIPPET_CONTEXT_CHARS {
+        out.push('…');
+    }
+    out.extend(&before[before.len().saturating_sub(SNIPPET_CONTEXT_CHARS)..]);
+    out.push_str(&text[start..end]);
+    out.extend(after.iter().take(SNIPPET_CONTEXT_CHARS));
+    if after.len() > SNIPPET_CONTEXT_CHARS {
+        out.push('…');
+    }
+    out.trim().to_string()
+}
+
+/// 行がテーブルのどこに当たるか（ヘッダーは None、区切り行は対象外）
+pub(crate) fn table_row_at(
+    tables: &[MarkdownTable],
+    line: usize,
+) -> Option<(usize, Option<Option<usize>>)> {
+    let ti = tables
+        .iter()
+        .position(|t| t.start_line <= line && line <= t.end_line)?;
+    let offset = line - tables[ti].start_line;
+    let row = match offset {
+        0 => Some(None),
+        1 => None,
+        n => Some(Some(n - 2)),
+    };
+    Some((ti, row))
+}
+
+/// 1つのファイルを検索する（`only` を指定したらその行だけを照合する）
+fn search_file(
+    path: &Path,
+    re: &Regex,
+    tables_only: bool,
+    only: Option<&HashSet<usize>>,
+) -> Vec<ContentMatch> {
+    let Ok(doc) = read_document(path) else {
+        return Vec::new();
+    };
+    let ids = table_ids(&doc.tables);
+    let path_str = path.to_string_lossy().to_string();
+    let mut matches = Vec::new();
+    for (line_no, line) in doc.lines.iter().enumerate() {
+        if only.is_some_and(|lines| !lines.contains(&line_no)) {
+            continue;
+        }
+        match table_row_at(&doc.tables, line_no) {
+            // テーブル内はセル単位で照合し、セルの位置を返す
+            Some((ti, row)) => {
+                let Some(row) = row else {
+                    continue;
+                };
+                let table = &doc.tables[ti];
+                let cells = match row {
+                    None => &table.headers,
+                    Some(r) => &table.rows[r],
+                };
+                for (col, cell) in cells.iter().enumerate() {
+                    if let Some(m) = re.find(cell) {
+                        matches.push(ContentMatch {
+                            path: path_str.clone(),
+                            line: line_no,
+                            snippet: snippet(cell, m.start(), m.end()),
+                            cell: Some(CellLocation {
+                                table_index: ti,
+                                table_id: ids[ti].clone(),
+                                row,
+                                col,
+                            }),
+                        });
+                    }
+                }
+            }
+            None if tables_only => {}
+            None => {
+                if let Some(m) = re.find(line) {
+                    matches.push(ContentMatch {
+                        path: path_str.clone(),
+                        line: line_no,
+                        snippet: snippet(line, m.start(), m.end()),
+                        cell: None,
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// ワークスペースのルート以下の .md ファイルを並列に全文検索する
+///
+/// 除外パターンに一致するファイルは対象外。ファイル・行の順に並べて最大件数で打ち切る
+/// （件数に達した後に残っているファイルは読まない）。
+/// 索引が作成済みのルートでは、索引で絞り込んだ行だけを照合する（正規表現検索は常に全件を走査）。
+pub fn search_content(
+    roots: &[PathBuf],
+    query: &str,
+    options: &SearchOptions,
+    index: Option<&SearchIndex>,
+) -> Result<Vec<ContentMatch>, String> {
+    let re = build_matcher(query, options)?;
+    let max = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let mut files: Vec<(PathBuf, Option<HashSet<usize>>)> = Vec::new();
+    for root in roots {
+        let candidates = match index {
+            Some(index) if !options.regex => index.candidates(root, query, options.tables_only)?,
+            _ => None,
+        };
+        match candidates {
+            Some(candidates) => files.extend(
+                candidates
+                    .into_iter()
+                    .map(|(path, lines)| (path, Some(lines))),
+            ),
+            None => files.extend(
+                workspace::markdown_files(root)
+                    .into_iter()
+                    .map(|(path, _)| (path, None)),
+            ),
+        }
+    }
+    let found = AtomicUsize::new(0);
+    let mut per_file: Vec<(usize, Vec<ContentMatch>)> = files
+        .par_iter()
+        .enumerate()
+        .filter_map(|(i, (path, only))| {
+            if found.load(Ordering::Relaxed) >= max {
+                return None;
+            }
+            let matches = search_file(path, &re, options.tables_only, only.as_ref());
+            found.fetch_add(matches.len(), Ordering::Relaxed);
+            (!matches.is_empty()).then_some((i, matches))
+        })
+        .collect();
+    per_file.sort_by_key(|(i, _)| *i);
+    let mut matches: Vec<ContentMatch> = per_file.into_iter().flat_map(|(_, m)| m).collect();
+    matches.truncate(max);
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("billing")).unwrap();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::write(
+            root.join("billing/2024.md"),
+            "# Invoices\n\nSee INVOICE-2024-117 below.\n\n| No | Note |\n| --- | --- |\n| invoice-2024-116 | ok |\n| invoice-2024-117 | 未払い |\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes.md"), "invoice-2024-1170 is different\n").unwrap();
+        fs::write(root.join("archive/old.md"), "invoice-2024-117\n").unwrap();
+        workspace::update_settings(root, &serde_json::json!({ "ignore": ["archive"] })).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_search_content_in_and_out_of_tables() {
+        let dir = fixture();
+        let roots = vec![dir.path().to_path_buf()];
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let matches = search_content(&roots, "invoice-2024-117", &options, None).unwrap();
+        assert_eq!(matches.len(), 2);
+        // 索引があれば索引で絞り込んでも同じ結果になる
+        let index = SearchIndex::default();
+        index.build(dir.path(), |_, _| {}).unwrap();
+        assert_eq!(
+            search_content(&roots, "invoice-2024-117", &options, Some(&index)).unwrap(),
+            matches
+        );
+        assert!(matches[0].path.ends_with("2024.md"));
+        assert_eq!(matches[0].line, 2);
+        assert_eq!(matches[0].snippet, "See INVOICE-2024-117 below.");
+        assert_eq!(matches[0].cell, None);
+        assert_eq!(matches[1].line, 7);
+        assert_eq!(
+            matches[1].cell,
+            Some(CellLocation {
+                table_index: 0,
+                table_id: "invoices".to_string(),
+                row: Some(1),
+                col: 0,
+            })
+        );
+
+        let options = SearchOptions {
+            case_sensitive: true,
+            tables_only: true,
+            ..SearchOptions::default()
+        };
+        let matches = search_content(&roots, "invoice-2024-117", &options, None).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].cell.is_some());
+
+        let options = SearchOptions {
+            regex: true,
+            max_results: Some(2),
+            ..SearchOptions::default()
+        };
+        let matches = search_content(&roots, r"invoice-\d{4}-11\d", &options, None).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(search_content(&roots, "(", &options, None).is_err());
+        assert!(search_content(&roots, &"a".repeat(MAX_QUERY_CHARS + 1), &options, None).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::file_io::read_document;
+use crate::markdown_parser::resolve_table_ref;
+use crate::startup::validate_open_path;
+use crate::workspace::Workspace;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use url::Url;
+
+/// ディープリンクの URL スキーム
+pub const SCHEME: &str = "markdown-sheet";
+/// フロントエンドにディープリンクを通知するイベント名
+pub const DEEP_LINK_EVENT: &str = "open-deep-link";
+/// 開けなかったディープリンクをフロントエンドに通知するイベント名
+pub const DEEP_LINK_REJECTED_EVENT: &str = "deep-link-rejected";
+
+/// ディープリンクで指定されたテーブル（番号または安定 ID）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TableRef {
+    Index { index: usize },
+    Id { id: String },
+}
+
+/// `markdown-sheet://open?path=...&table=...` をパースした結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeepLink {
+    pub path: String,
+    pub table: Option<TableRef>,
+}
+
+/// フロントエンドに送る、検証済みのディープリンク
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeepLinkPayload {
+    /// 正規化した絶対パス
+    pub path: String,
+    /// 解決したテーブル番号（指定がない・見つからない場合は None）
+    pub table_index: Option<usize>,
+    /// 指定されたテーブルが見つからなかった理由（ファイルだけ開く）
+    pub warning: Option<String>,
+}
+
+/// 開けなかったディープリンク
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedDeepLink {
+    pub url: String,
+    pub message: String,
+}
+
+/// `take_deep_links` の結果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TakenDeepLinks {
+    pub links: Vec<DeepLinkPayload>,
+    pub rejected: Vec<RejectedDeepLink>,
+    /// ワークスペースが開かれていないため、まだ保持しているリンクの数
+    pub pending: usize,
+}
+
+#[derive(Debug, Default)]
+struct PendingLinks {
+    urls: Vec<String>,
+    /// フロントエンドが `take_deep_links` を呼んだか
+    ready: bool,
+}
+
+/// 起動直後など、フロントエンドがイベントを受け取れるようになる前やワークスペースが開かれる前に
+/// 届いたディープリンクを保持する管理ステート
+#[derive(Debug, Default)]
+pub struct DeepLinks(Mutex<PendingLinks>);
+
+impl DeepLinks {
+    /// ディープリンクを受け取る
+    ///
+    /// フロントエンドの準備前、またはワークスペースのルートがまだない場合は保持して false を返し、
+    /// それ以外は true を返す（呼び出し側で解決してイベントで通知する）。
+    pub fn receive(&self, url: &str, workspace: &Workspace) -> bool {
+        let Ok(mut pending) = self.0.lock() else {
+            return false;
+        };
+        let deliver = pending.ready && !workspace.roots().is_empty();
+        if !deliver {
+            pending.urls.push(url.to_string());
+        }
+        deliver
+    }
+
+    /// 保持しているディープリンクを取り出して解決する。以降に届いたリンクはイベントで通知する
+    ///
+    /// ワークスペースのルートがまだなければ解決せずに保持し続け、その数を `pending` で返す
+    /// （フォルダを開いたあとにもう一度呼ぶ）。
+    pub fn take(&self, workspace: &Workspace) -> TakenDeepLinks {
+        let urls = match self.0.lock() {
+            Ok(mut pending) => {
+                pending.ready = true;
+                if workspace.roots().is_empty() {
+                    return TakenDeepLinks {
+                        pending: pending.urls.len(),
+                        ..TakenDeepLinks::default()
+                    };
+                }
+                std::mem::take(&mut pending.urls)
+            }
+            Err(_) => Vec::new(),
+        };
+        let mut taken = TakenDeepLinks::default();
+        for url in urls {
+            match resolve_deep_link(&url, workspace) {
+                Ok(payload) => taken.links.push(payload),
+                Err(message) => taken.rejected.push(RejectedDeepLink { url, message }),
+            }
+        }
+        taken
+    }
+}
+
+/// クエリ文字列の値をパーセントデコードする（`+` は空白にせずそのまま扱う）
+fn decode_component(value: &str) -> Result<String, String> {
+    percent_decode_str(value)
+        .decode_utf8()
+        .map(|s| s.to_string())
+        .map_err(|_| "URL のエンコーディングが不正です".to_string())
+}
+
+/// ディープリンク URL をパースする（パスの存在やサンドボックスは検証しない）
+pub fn parse_deep_link(url: &str) -> Result<DeepLink, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("URL を解釈できません: {}", e))?;
+    if parsed.scheme() != SCHEME {
+        return Err(format!("対応していないスキームです: {}", parsed.scheme()));
+    }
+    if parsed.host_str() != Some("open") || !matches!(parsed.path(), "" | "/") {
+        return Err(format!("対応していないリンクです: {}", url));
+    }
+
+    let mut path = None;
+    let mut table = None;
+    for pair in parsed.query().unwrap_or("").split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            "path" => path = Some(decode_component(value)?),
+            "table" => {
+                let value = decode_component(value)?;
+                table = Some(match value.parse::<usize>() {
+                    Ok(index) => TableRef::Index { index },
+                    Err(_) if !value.is_empty() => TableRef::Id { id: value },
+                    Err(_) => return Err("テーブルの指定が空です".to_string()),
+                });
+            }
+            _ => {}
+        }
+    }
+    let path = path
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "path が指定されていません".to_string())?;
+    Ok(DeepLink { path, table })
+}
+
+/// ディープリンクを検証し、フロントエンドに送る内容に変換する
+///
+/// 存在する Markdown ファイルのうち、登録済みのワークスペースのルート内にあるものだけを
+/// 受け付ける（ワークスペースが開かれていなければすべて拒否する）。
+pub fn resolve_deep_link(url: &str, workspace: &Workspace) -> Result<DeepLinkPayload, String> {
+    let link = parse_deep_link(url)?;
+    let path = validate_open_path(Path::new(&link.path))
+        .ok_or_else(|| format!("開けないファイルです: {}", link.path))?;
+    if workspace.roots().is_empty() {
+        return Err(
+            "ワークスペースが開かれていないため、ディープリンクのファイルを開けません".to_string(),
+        );
+    }
+    workspace.root_for(&path)?;
+    let mut payload = DeepLinkPayload {
+        path,
+        table_index: None,
+        warning: None,
+    };
+    if let Some(table) = &link.table {
+        let reference = match table {
+            TableRef::Index { index } => index.to_string(),
+            TableRef::Id { id } => id.clone(),
+        };
+        let doc = read_document(Path::new(&payload.path))?;
+        match resolve_table_ref(&doc.tables, &reference) {
+            Ok(index) => payload.table_index = Some(index),
+            Err(e) => payload.warning = Some(e),
+        }
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deep_link_with_encoded_japanese_path() {
+        let link = parse_deep_link(
+            "markdown-sheet://open?path=%2Fnotes%2F%E8%A8%88%E7%94%BB%20A%2Bb.md&table=2",
+        )
+        .unwrap();
+        assert_eq!(link.path, "/notes/計画 A+b.md");
+        assert_eq!(link.table, Some(TableRef::Index { index: 2 }));
+
+        let link =
+            parse_deep_link("markdown-sheet://open?table=%E4%BA%88%E5%AE%9A-2&path=/notes/plan.md")
+                .unwrap();
+        assert_eq!(
+            link.table,
+            Some(TableRef::Id {
+                id: "予定-2".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_deep_link_rejects_malformed() {
+        assert!(parse_deep_link("https://open?path=/a.md").is_err());
+        assert!(parse_deep_link("markdown-sheet://delete?path=/a.md").is_err());
+        assert!(parse_deep_link("markdown-sheet://open?table=1").is_err());
+        assert!(parse_deep_link("markdown-sheet://open?path=%FF.md").is_err());
+        assert!(parse_deep_link("not a url").is_err());
+    }
+
+    #[test]
+    fn test_resolve_deep_link_sandbox() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let inside_file = root.path().join("plan.md");
+        let outside_file = outside.path().join("x.md");
+        std::fs::write(&inside_file, "## Tasks\n| A |\n| --- |\n").unwrap();
+        std::fs::write(&outside_file, "").unwrap();
+        let link = |p: &Path, table: &str| {
+            format!(
+                "markdown-sheet://open?path={}&table={}",
+                p.to_string_lossy(),
+                table
+            )
+        };
+
+        // ワークスペースが開かれていなければ拒否する
+        let ws = Workspace::default();
+        assert!(resolve_deep_link(&link(&inside_file, "0"), &ws).is_err());
+
+        ws.add_root(root.path()).unwrap();
+        let payload = resolve_deep_link(&link(&inside_file, "tasks"), &ws).unwrap();
+        assert_eq!(payload.table_index, Some(0));
+        assert_eq!(payload.warning, None);
+        let payload = resolve_deep_link(&link(&inside_file, "9"), &ws).unwrap();
+        assert_eq!(payload.table_index, None);
+        assert!(payload.warning.is_some());
+        assert!(resolve_deep_link(&link(&outside_file, "0"), &ws).is_err());
+    }
+
+    #[test]
+    fn test_cold_start_links_are_held_until_taken() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("plan.md");
+        std::fs::write(&file, "| A |\n| --- |\n").unwrap();
+        let url = format!("markdown-sheet://open?path={}", file.to_string_lossy());
+        let ws = Workspace::default();
+
+        let links = DeepLinks::default();
+        assert!(!links.receive(&url, &ws));
+        assert!(!links.receive("markdown-sheet://open?path=/nope.md", &ws));
+        // フォルダを開く前は解決せずに保持し続ける
+        let taken = links.take(&ws);
+        assert_eq!((taken.links.len(), taken.pending), (0, 2));
+        assert!(!links.receive(&url, &ws));
+
+        ws.add_root(root.path()).unwrap();
+        let taken = links.take(&ws);
+        assert_eq!((taken.links.len(), taken.pending), (2, 0));
+        assert_eq!(taken.rejected.len(), 1);
+        // 準備後に届いたリンクは保持せず、呼び出し側で通知する
+        assert!(links.receive(&url, &ws));
+        assert_eq!(links.take(&ws), TakenDeepLinks::default());
+    }
+}
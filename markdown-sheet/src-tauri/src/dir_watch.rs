@@ -0,0 +1,166 @@
+use indexmap::IndexMap;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// フォルダ内の変更をフロントエンドに通知するイベント名
+pub const DIR_CHANGED_EVENT: &str = "dir-changed";
+
+/// 最初の変更からこの時間に届いた変更をまとめて通知する
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// 変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Create,
+    Delete,
+    Modify,
+}
+
+/// `dir-changed` イベントの内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirChange {
+    pub kind: ChangeKind,
+    pub path: String,
+}
+
+/// notify のイベントを変更の種類に直す（アクセスなど通知しないものは None）
+fn change_kind(kind: &EventKind) -> Option<ChangeKind> {
+    match kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            Some(ChangeKind::Create)
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            Some(ChangeKind::Delete)
+        }
+        EventKind::Modify(_) => Some(ChangeKind::Modify),
+        _ => None,
+    }
+}
+
+/// 同じパスへの変更を1つにまとめる（作成直後の変更は作成のまま、それ以外は後勝ち）
+fn merge(pending: &mut IndexMap<PathBuf, ChangeKind>, path: PathBuf, kind: ChangeKind) {
+    let entry = pending.entry(path).or_insert(kind);
+    if !(*entry == ChangeKind::Create && kind == ChangeKind::Modify) {
+        *entry = kind;
+    }
+}
+
+/// 監視中のフォルダ（Tauri の managed state）
+///
+/// 監視を止めると notify の監視と通知用のスレッドが終わる。
+#[derive(Default)]
+pub struct DirWatchers(Mutex<HashMap<PathBuf, RecommendedWatcher>>);
+
+fn watch_key(dir: &Path) -> PathBuf {
+    fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())
+}
+
+impl DirWatchers {
+    /// `dir` 以下を再帰的に監視し、変更を `on_change` に渡す
+    ///
+    /// 変更は `DEBOUNCE` の間まとめ、同じパスへの変更は1件にする。監視中のフォルダを
+    /// もう一度指定した場合は監視し直す。
+    pub fn watch(
+        &self,
+        dir: &Path,
+        on_change: impl Fn(DirChange) + Send + 'static,
+    ) -> Result<(), String> {
+        if !dir.is_dir() {
+            return Err(format!("ディレクトリが存在しません: {}", dir.display()));
+        }
+        let (tx, rx) = mpsc::channel::<Event>();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = tx.send(event);
+            }
+        })
+        .map_err(|e| format!("フォルダを監視できません: {}", e))?;
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| format!("フォルダを監視できません: {}", e))?;
+
+        // 監視を止めると送信側が落ちて recv がエラーになり、スレッドが終わる
+        thread::spawn(move || {
+            while let Ok(first) = rx.recv() {
+                let mut pending = IndexMap::new();
+                let mut add = |event: Event| {
+                    if let Some(kind) = change_kind(&event.kind) {
+                        for path in event.paths {
+                            merge(&mut pending, path, kind);
+                        }
+                    }
+                };
+                add(first);
+                let deadline = Instant::now() + DEBOUNCE;
+                loop {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    match rx.recv_timeout(left) {
+                        Ok(event) => add(event),
+                        Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                            break
+                        }
+                    }
+                }
+                for (path, kind) in pending {
+                    on_change(DirChange {
+                        kind,
+                        path: path.to_string_lossy().to_string(),
+                    });
+                }
+            }
+        });
+
+        let mut watchers = self.0.lock().map_err(|e| e.to_string())?;
+        watchers.insert(watch_key(dir), watcher);
+        Ok(())
+    }
+
+    /// フォルダの監視を止める（監視していなければエラー）
+    pub fn unwatch(&self, dir: &Path) -> Result<(), String> {
+        let mut watchers = self.0.lock().map_err(|e| e.to_string())?;
+        watchers
+            .remove(&watch_key(dir))
+            .map(drop)
+            .ok_or_else(|| format!("監視していないフォルダです: {}", dir.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_directory_reports_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let watchers = DirWatchers::default();
+        let (tx, rx) = mpsc::channel();
+        watchers
+            .watch(dir.path(), move |change| {
+                let _ = tx.send(change);
+            })
+            .unwrap();
+
+        let file = dir.path().join("new.md");
+        fs::write(&file, "| A |\n| --- |\n").unwrap();
+        let change = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(change.kind, ChangeKind::Create);
+        assert!(change.path.ends_with("new.md"));
+        // 作成直後の書き込みは作成の通知にまとめる
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        watchers.unwatch(dir.path()).unwrap();
+        assert!(watchers.unwatch(dir.path()).is_err());
+        fs::write(dir.path().join("late.md"), "x").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(watchers.watch(&dir.path().join("missing"), |_| {}).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::file_io::{is_read_only, mtime_ms, sha256_hex, snapshot_from_bytes, DocumentSnapshot};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// キャッシュに保持する行データの合計サイズの既定上限（バイト）
+const DEFAULT_CAPACITY_BYTES: usize = 64 * 1024 * 1024;
+
+/// 更新日時がキャッシュ時刻にこれより近いファイルは、同じ更新日時のまま再度書き換えられた
+/// 可能性がある（更新日時の精度が粗いファイルシステム対策）ため、ハッシュでも確認する
+const RACY_WINDOW_MS: i64 = 2000;
+
+#[derive(Debug)]
+struct CacheEntry {
+    snapshot: DocumentSnapshot,
+    /// ファイルサイズ（更新日時と併せて変更の検出に使う）
+    len: u64,
+    /// キャッシュした時刻（Unix エポックからのミリ秒）
+    cached_at_ms: i64,
+    /// 行データの合計バイト数
+    bytes: usize,
+    /// 最後に使われた順序（LRU 用）
+    last_used: u64,
+}
+
+impl CacheEntry {
+    /// 更新日時が同じでも中身が変わっている可能性があるか
+    fn is_racy(&self) -> bool {
+        self.snapshot.mtime_ms + RACY_WINDOW_MS >= self.cached_at_ms
+    }
+}
+
+#[derive(Debug)]
+struct CacheInner {
+    entries: HashMap<PathBuf, CacheEntry>,
+    capacity_bytes: usize,
+    total_bytes: usize,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// キャッシュの利用状況
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub total_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// 正規化したパスをキーに、パース済みのドキュメントを保持する管理ステート
+///
+/// 更新日時とサイズが変わっていなければディスクを読まずにキャッシュを返す。
+/// 行データの合計バイト数が上限を超えたら、最も長く使われていないものから捨てる。
+#[derive(Debug)]
+pub struct DocumentCache(Mutex<CacheInner>);
+
+impl Default for DocumentCache {
+    fn default() -> Self {
+        DocumentCache::with_capacity(DEFAULT_CAPACITY_BYTES)
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+fn cache_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn line_bytes(snapshot: &DocumentSnapshot) -> usize {
+    snapshot.document.lines.iter().map(|l| l.len()).sum()
+}
+
+impl CacheInner {
+    fn remove(&mut self, key: &Path) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.bytes;
+        }
+    }
+
+    /// キャッシュを返し、使用順序を更新する
+    fn hit(&mut self, key: &Path) -> Option<DocumentSnapshot> {
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = tick;
+        self.hits += 1;
+        Some(entry.snapshot.clone())
+    }
+
+    fn insert(&mut self, key: PathBuf, snapshot: DocumentSnapshot, len: u64) {
+        self.remove(&key);
+        let bytes = line_bytes(&snapshot);
+        if bytes > self.capacity_bytes {
+            return;
+        }
+        while self.total_bytes + bytes > self.capacity_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.tick += 1;
+        self.total_bytes += bytes;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                snapshot,
+                len,
+                cached_at_ms: now_ms(),
+                bytes,
+                last_used: self.tick,
+            },
+        );
+    }
+}
+
+impl DocumentCache {
+    /// 行データの合計バイト数の上限を指定して作成する
+    pub fn with_capacity(capacity_bytes: usize) -> Self {
+        DocumentCache(Mutex::new(CacheInner {
+            entries: HashMap::new(),
+            capacity_bytes,
+            total_bytes: 0,
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }))
+    }
+
+    /// ファイルを読み込む。変更されていなければキャッシュしたパース結果を返す
+    ///
+    /// 更新日時とサイズが一致すれば再読み込みしない。ただし更新日時がキャッシュ時刻に
+    /// 近い場合は同じ更新日時のまま書き換えられた可能性があるため、ハッシュも比較する。
+    pub fn read(&self, path: &Path) -> Result<DocumentSnapshot, String> {
+        let key = cache_key(path);
+        let meta = fs::metadata(path).map_err(|e| e.to_string())?;
+        let mtime = mtime_ms(&meta);
+
+        let mut verify_hash = None;
+        {
+            let mut inner = self.0.lock().map_err(|e| e.to_string())?;
+            let fresh = inner
+                .entries
+                .get(&key)
+                .filter(|e| e.snapshot.mtime_ms == mtime && e.len == meta.len());
+            match fresh {
+                Some(entry) if entry.is_racy() => verify_hash = Some(entry.snapshot.sha256.clone()),
+                Some(_) => {
+                    if let Some(mut snapshot) = inner.hit(&key) {
+                        snapshot.read_only = is_read_only(path, &meta);
+                        return Ok(snapshot);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        let mut inner = self.0.lock().map_err(|e| e.to_string())?;
+        if verify_hash.is_some_and(|sha| sha == sha256_hex(&bytes)) {
+            if let Some(mut snapshot) = inner.hit(&key) {
+                snapshot.read_only = is_read_only(path, &meta);
+                return Ok(snapshot);
+            }
+        }
+        inner.misses += 1;
+        let snapshot = snapshot_from_bytes(path, &bytes, &meta);
+        inner.insert(key, snapshot.clone(), meta.len());
+        Ok(snapshot)
+    }
+
+    /// キャッシュを捨ててディスクから読み直す（保存直後などに使う）
+    pub fn refresh(&self, path: &Path) -> Result<DocumentSnapshot, String> {
+        self.invalidate(path);
+        self.read(path)
+    }
+
+    /// 指定したファイルのキャッシュを捨てる
+    pub fn invalidate(&self, path: &Path) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.remove(&cache_key(path));
+        }
+    }
+
+    /// キャッシュの利用状況を返す
+    pub fn stats(&self) -> CacheStats {
+        match self.0.lock() {
+            Ok(inner) => CacheStats {
+                entries: inner.entries.len(),
+                total_bytes: inner.total_bytes,
+                hits: inner.hits,
+                misses: inner.misses,
+            },
+            Err(_) => CacheStats {
+                entries: 0,
+                total_bytes: 0,
+                hits: 0,
+                misses: 0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn set_mtime(path: &Path, secs_ago: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(secs_ago))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_hit_and_miss_after_external_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n").unwrap();
+        set_mtime(&path, 60);
+
+        let cache = DocumentCache::default();
+        cache.read(&path).unwrap();
+        cache.read(&path).unwrap();
+        assert_eq!(cache.stats().misses, 1);
+        assert_eq!(cache.stats().hits, 1);
+
+        fs::write(&path, "| A |\n| --- |\n| 2 |\n").unwrap();
+        let snapshot = cache.read(&path).unwrap();
+        assert_eq!(snapshot.document.tables[0].rows[0][0], "2");
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[test]
+    fn test_same_mtime_change_is_detected_by_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "x1").unwrap();
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let cache = DocumentCache::default();
+        cache.read(&path).unwrap();
+        // 更新日時とサイズを変えずに中身だけ書き換える
+        fs::write(&path, "x2").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(mtime).unwrap();
+
+        assert_eq!(cache.read(&path).unwrap().document.lines, vec!["x2"]);
+        assert_eq!(cache.read(&path).unwrap().document.lines, vec!["x2"]);
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_lru_eviction_by_line_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = ["a.md", "b.md", "c.md"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                fs::write(&path, "0123456789").unwrap();
+                set_mtime(&path, 60);
+                path
+            })
+            .collect();
+
+        let cache = DocumentCache::with_capacity(25);
+        cache.read(&paths[0]).unwrap();
+        cache.read(&paths[1]).unwrap();
+        cache.read(&paths[0]).unwrap();
+        cache.read(&paths[2]).unwrap();
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.total_bytes, 20);
+
+        // b が最も使われていないので追い出されている
+        cache.read(&paths[0]).unwrap();
+        cache.read(&paths[1]).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+
+        cache.invalidate(&paths[1]);
+        assert_eq!(cache.stats().entries, 1);
+    }
+}
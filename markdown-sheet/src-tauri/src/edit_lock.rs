@@ -0,0 +1,316 @@
+use crate::file_io::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// これより古いロックは保持者が落ちたものとみなして破棄できる（保存のたびに更新される）
+const STALE_AFTER: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// 編集ロックのサイドカーファイル（`<file>.lock`）の内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+    /// 取得（または最後に更新）した日時（Unix エポックからのミリ秒）
+    pub acquired_at_ms: i64,
+}
+
+/// 編集ロックの取得結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EditLockStatus {
+    Acquired,
+    /// 別のプロセス（別のマシンを含む）が編集中
+    HeldByOther {
+        holder: LockInfo,
+    },
+}
+
+/// 他のアプリ・他のマシンとの同時編集を検出するための、サイドカーファイルによる勧告ロック
+///
+/// このプロセスが取得したロックをウィンドウのラベルごとに覚えておき、ウィンドウを閉じたときに解放する。
+#[derive(Debug)]
+pub struct EditLocks {
+    pid: u32,
+    hostname: String,
+    held: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl Default for EditLocks {
+    fn default() -> Self {
+        Self::with_identity(std::process::id(), hostname())
+    }
+}
+
+/// このマシンのホスト名（取得できなければ空文字列）
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// ロック対象ファイルのサイドカーファイルのパス
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// プロセスが存在しないことが確実か（確認できない環境では false）
+fn is_dead_process(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        !Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        false
+    }
+}
+
+fn read_lock(sidecar: &Path) -> Option<LockInfo> {
+    serde_json::from_str(&fs::read_to_string(sidecar).ok()?).ok()
+}
+
+impl EditLocks {
+    pub(crate) fn with_identity(pid: u32, hostname: String) -> Self {
+        Self {
+            pid,
+            hostname,
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_mine(&self, info: &LockInfo) -> bool {
+        info.pid == self.pid && info.hostname == self.hostname
+    }
+
+    /// 保持者が落ちたとみなせるロックか（古すぎる、または同じマシンでプロセスが終了している）
+    fn is_stale(&self, info: &LockInfo) -> bool {
+        let age_ms = now_ms().saturating_sub(info.acquired_at_ms);
+        age_ms > STALE_AFTER.as_millis() as i64
+            || (!self.hostname.is_empty()
+                && info.hostname == self.hostname
+                && is_dead_process(info.pid))
+    }
+
+    fn my_lock(&self) -> LockInfo {
+        LockInfo {
+            pid: self.pid,
+            hostname: self.hostname.clone(),
+            acquired_at_ms: now_ms(),
+        }
+    }
+
+    /// 編集ロックを取得する
+    ///
+    /// 古くなったロックは破棄して取得する。`force` を指定すると他のプロセスのロックも奪う。
+    pub fn acquire(&self, path: &Path, owner: &str, force: bool) -> Result<EditLockStatus, String> {
+        let sidecar = lock_path(path);
+        let body = serde_json::to_vec(&self.my_lock()).map_err(|e| e.to_string())?;
+        let mut acquired = false;
+        for _ in 0..2 {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&sidecar)
+            {
+                Ok(mut file) => {
+                    file.write_all(&body).map_err(|e| e.to_string())?;
+                    acquired = true;
+                    break;
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    match read_lock(&sidecar) {
+                        Some(info) if self.is_mine(&info) => {
+                            write_atomic(&sidecar, &body).map_err(|e| e.to_string())?;
+                            acquired = true;
+                            break;
+                        }
+                        Some(info) if !force && !self.is_stale(&info) => {
+                            return Ok(EditLockStatus::HeldByOther { holder: info });
+                        }
+                        // 古い・壊れたロックは消してから取り直す
+                        _ => match fs::remove_file(&sidecar) {
+                            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                                return Err(e.to_string())
+                            }
+                            _ => continue,
+                        },
+                    }
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        if !acquired {
+            return Err(format!(
+                "編集ロックを取得できませんでした: {}",
+                path.display()
+            ));
+        }
+        let mut held = self.held.lock().map_err(|e| e.to_string())?;
+        held.insert(canonical(path), owner.to_string());
+        Ok(EditLockStatus::Acquired)
+    }
+
+    /// このプロセスが保持している編集ロックを解放する（他のプロセスのロックには触れない）
+    pub fn release(&self, path: &Path) -> Result<(), String> {
+        let key = canonical(path);
+        if let Ok(mut held) = self.held.lock() {
+            held.remove(&key);
+        }
+        let sidecar = lock_path(&key);
+        match read_lock(&sidecar) {
+            Some(info) if self.is_mine(&info) => match fs::remove_file(&sidecar) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// 指定のウィンドウが取得した編集ロックをすべて解放する
+    pub fn release_all(&self, owner: &str) {
+        let paths: Vec<PathBuf> = match self.held.lock() {
+            Ok(held) => held
+                .iter()
+                .filter(|(_, o)| o.as_str() == owner)
+                .map(|(p, _)| p.clone())
+                .collect(),
+            Err(_) => return,
+        };
+        for path in paths {
+            let _ = self.release(&path);
+        }
+    }
+
+    /// このプロセスが保持しているすべての編集ロックを解放する（終了時）
+    pub fn release_everything(&self) {
+        let paths: Vec<PathBuf> = match self.held.lock() {
+            Ok(held) => held.keys().cloned().collect(),
+            Err(_) => return,
+        };
+        for path in paths {
+            let _ = self.release(&path);
+        }
+    }
+
+    /// 他のプロセスが有効な編集ロックを保持していれば、その内容を返す
+    pub fn held_by_other(&self, path: &Path) -> Option<LockInfo> {
+        read_lock(&lock_path(path)).filter(|info| !self.is_mine(info) && !self.is_stale(info))
+    }
+
+    /// 保存に合わせて自分のロックの日時を更新し、長い編集中に古いロックとみなされないようにする
+    pub fn touch(&self, path: &Path) {
+        let sidecar = lock_path(path);
+        if read_lock(&sidecar).is_some_and(|info| self.is_mine(&info)) {
+            if let Ok(body) = serde_json::to_vec(&self.my_lock()) {
+                let _ = write_atomic(&sidecar, &body);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "# A\n").unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_acquire_conflict_and_release() {
+        let (_dir, path) = setup();
+        let mine = EditLocks::with_identity(std::process::id(), "host-a".to_string());
+        let other = EditLocks::with_identity(std::process::id(), "host-b".to_string());
+
+        assert_eq!(
+            mine.acquire(&path, "main", false).unwrap(),
+            EditLockStatus::Acquired
+        );
+        assert!(lock_path(&path).exists());
+        // 同じプロセスからの再取得は成功する
+        assert_eq!(
+            mine.acquire(&path, "main", false).unwrap(),
+            EditLockStatus::Acquired
+        );
+        assert!(mine.held_by_other(&path).is_none());
+
+        let status = other.acquire(&path, "main", false).unwrap();
+        assert!(
+            matches!(status, EditLockStatus::HeldByOther { holder } if holder.hostname == "host-a")
+        );
+        assert!(other.held_by_other(&path).is_some());
+        // 他のプロセスのロックは解放できない
+        other.release(&path).unwrap();
+        assert!(lock_path(&path).exists());
+
+        mine.release_all("main");
+        assert!(!lock_path(&path).exists());
+        assert_eq!(
+            other.acquire(&path, "main", false).unwrap(),
+            EditLockStatus::Acquired
+        );
+    }
+
+    #[test]
+    fn test_stale_lock_is_broken() {
+        let (_dir, path) = setup();
+        let locks = EditLocks::with_identity(std::process::id(), "host-a".to_string());
+        let old = LockInfo {
+            pid: 1,
+            hostname: "host-b".to_string(),
+            acquired_at_ms: now_ms() - STALE_AFTER.as_millis() as i64 - 1000,
+        };
+        fs::write(lock_path(&path), serde_json::to_vec(&old).unwrap()).unwrap();
+        assert!(locks.held_by_other(&path).is_none());
+        assert_eq!(
+            locks.acquire(&path, "main", false).unwrap(),
+            EditLockStatus::Acquired
+        );
+
+        // 新しいロックでも force を指定すれば奪える
+        let fresh = LockInfo {
+            acquired_at_ms: now_ms(),
+            ..old
+        };
+        fs::write(lock_path(&path), serde_json::to_vec(&fresh).unwrap()).unwrap();
+        assert!(locks.held_by_other(&path).is_some());
+        assert_eq!(
+            locks.acquire(&path, "main", true).unwrap(),
+            EditLockStatus::Acquired
+        );
+        assert!(locks.held_by_other(&path).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_dead_process_lock_is_stale() {
+        let (_dir, path) = setup();
+        let locks = EditLocks::with_identity(std::process::id(), "host-a".to_string());
+        let dead = LockInfo {
+            pid: u32::MAX - 1,
+            hostname: "host-a".to_string(),
+            acquired_at_ms: now_ms(),
+        };
+        fs::write(lock_path(&path), serde_json::to_vec(&dead).unwrap()).unwrap();
+        assert!(locks.held_by_other(&path).is_none());
+    }
+}
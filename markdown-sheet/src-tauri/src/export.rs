@@ -0,0 +1,381 @@
+use crate::file_io::{collect_markdown_files, read_document};
+use crate::inventory::FileError;
+use crate::markdown_parser::{get_cell, quote_cell, CellQuoting, MarkdownTable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::path::Path;
+
+/// インライン HTML として通すタグ（属性なしのもののみ許可）
+const SAFE_INLINE_TAGS: &[&str] = &[
+    "b", "i", "em", "strong", "code", "u", "s", "del", "sub", "sup", "mark", "kbd", "br",
+];
+
+/// HTML の特殊文字をエスケープする
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// 先頭が許可済みのインラインタグ（`<b>` `</b>` `<br/>` など）ならその長さを返す
+fn safe_tag_len(text: &str) -> Option<usize> {
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    let inner = inner.strip_prefix('/').unwrap_or(inner);
+    let inner = inner.strip_suffix('/').unwrap_or(inner).trim_end();
+    let name = inner.to_ascii_lowercase();
+    SAFE_INLINE_TAGS.contains(&name.as_str()).then_some(end + 1)
+}
+
+/// リンク先として安全な URL か（スクリプト実行系のスキームを拒否）
+///
+/// ブラウザは URL 中のタブ・改行や前後の制御文字を無視するため、それらを除いてから判定する。
+fn is_safe_url(url: &str) -> bool {
+    let lower: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_control() && *c != ' ')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    !["javascript:", "vbscript:", "data:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+}
+
+/// セル内のインライン Markdown（太字・斜体・コード・リンク）と安全なインライン HTML を描画する
+///
+/// 対応していない記法や許可されていないタグはエスケープして文字列として出力する。
+pub fn render_inline(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some(ch) = rest.chars().next() {
+        // コードスパン: 中身は常にエスケープ
+        if let Some(r) = rest.strip_prefix('`') {
+            if let Some(end) = r.find('`') {
+                out.push_str(&format!("<code>{}</code>", escape_html(&r[..end])));
+                rest = &r[end + 1..];
+                continue;
+            }
+        }
+        // 太字
+        if let Some(r) = rest.strip_prefix("**") {
+            if let Some(end) = r.find("**").filter(|&e| e > 0) {
+                out.push_str(&format!("<strong>{}</strong>", render_inline(&r[..end])));
+                rest = &r[end + 2..];
+                continue;
+            }
+        }
+        // 斜体
+        if ch == '*' || ch == '_' {
+            let r = &rest[1..];
+            if let Some(end) = r.find(ch).filter(|&e| e > 0) {
+                out.push_str(&format!("<em>{}</em>", render_inline(&r[..end])));
+                rest = &r[end + 1..];
+                continue;
+            }
+        }
+        // リンク [text](url)
+        if let Some(r) = rest.strip_prefix('[') {
+            if let Some(mid) = r.find("](") {
+                if let Some(close) = r[mid + 2..].find(')') {
+                    let label = render_inline(&r[..mid]);
+                    let url = &r[mid + 2..mid + 2 + close];
+                    if is_safe_url(url) {
+                        out.push_str(&format!("<a href=\"{}\">{}</a>", escape_html(url), label));
+                    } else {
+                        out.push_str(&label);
+                    }
+                    rest = &r[mid + 2 + close + 1..];
+                    continue;
+                }
+            }
+        }
+        // 許可済みのインライン HTML タグ
+        if ch == '<' {
+            if let Some(len) = safe_tag_len(rest) {
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        out.push_str(&escape_html(&rest[..ch.len_utf8()]));
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    out
+}
+
+/// アライメントを style 属性に変換する
+fn align_attr(align: Option<&String>) -> &'static str {
+    match align.map(|s| s.as_str()) {
+        Some("left") => " style=\"text-align: left\"",
+        Some("center") => " style=\"text-align: center\"",
+        Some("right") => " style=\"text-align: right\"",
+        _ => "",
+    }
+}
+
+/// テーブルを HTML の `<table>` に変換する
+///
+/// `allow_inline_html` が false（既定）の場合はセル内容をすべてエスケープする。
+/// true の場合はインライン Markdown と安全なインライン HTML を描画する。
+/// セルの注釈（`cell_annotations`）は `data-annotation` 属性に出力する。
+pub fn table_to_html(table: &MarkdownTable, allow_inline_html: bool) -> String {
+    let cell = |text: &str| {
+        if allow_inline_html {
+            render_inline(text)
+        } else {
+            escape_html(text)
+        }
+    };
+
+    let mut out = String::from("<table>\n<thead>\n<tr>");
+    for (ci, header) in table.headers.iter().enumerate() {
+        out.push_str(&format!(
+            "<th{}>{}</th>",
+            align_attr(table.alignments.get(ci)),
+            cell(header)
+        ));
+    }
+    out.push_str("</tr>\n</thead>\n<tbody>\n");
+    for ri in 0..table.rows.len() {
+        out.push_str("<tr>");
+        for ci in 0..table.headers.len() {
+            let value = get_cell(table, ri, ci).unwrap_or("");
+            let annotation = table
+                .annotation(ri, ci)
+                .map(|note| format!(" data-annotation=\"{}\"", escape_html(note)))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "<td{}{}>{}</td>",
+                align_attr(table.alignments.get(ci)),
+                annotation,
+                cell(value)
+            ));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+    out
+}
+
+/// セル内の改行表現
+const BR_TAGS: &[&str] = &["<br>", "<br/>", "<br />"];
+
+/// セル内容を定義リストの用語・定義として出力できる形に整える
+///
+/// 行頭の記号はエスケープし、`<br>` は `separator` に置き換える。
+fn definition_text(cell: &str, separator: &str) -> String {
+    let mut text = quote_cell(cell.trim(), CellQuoting::Backslash).into_owned();
+    for tag in BR_TAGS {
+        text = text.replace(tag, separator);
+    }
+    text
+}
+
+/// データ行が1行だけのテーブルを Markdown の定義リスト（`用語\n: 定義`）に変換する
+///
+/// 行が1行でない場合は `None` を返す。ヘッダーが空の列は `列N` を用語にする。
+/// セル内の `<br>` は定義では継続行に、用語では空白に置き換える。
+pub fn table_to_definition_list(table: &MarkdownTable) -> Option<String> {
+    if table.rows.len() != 1 {
+        return None;
+    }
+    let items: Vec<String> = table
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(ci, header)| {
+            let term = match definition_text(header, " ") {
+                t if t.is_empty() => format!("列{}", ci + 1),
+                t => t,
+            };
+            let value = get_cell(table, 0, ci).unwrap_or("");
+            format!("{}\n: {}\n", term, definition_text(value, "\n  "))
+        })
+        .collect();
+    Some(items.join("\n"))
+}
+
+/// CSV の1セル分（カンマ・引用符・改行を含む値だけを引用符で囲む）
+fn csv_field(cell: &str) -> Cow<'_, str> {
+    if cell.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", cell.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+/// テーブルを CSV テキストに変換する（1行目はヘッダー、行の区切りは `\n`）
+pub fn table_to_csv(table: &MarkdownTable) -> String {
+    let line = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .map(|c| csv_field(c))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    std::iter::once(line(&table.headers))
+        .chain(table.rows.iter().map(|row| line(row)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ディレクトリ一括エクスポートの1テーブル分
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryTable {
+    pub file: String,
+    pub table_index: usize,
+    pub heading: Option<String>,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// ディレクトリ一括エクスポートの結果
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryTablesJson {
+    /// すべてのテーブルを並べた JSON 配列
+    pub json: String,
+    /// 読み込めずに飛ばしたファイル
+    pub errors: Vec<FileError>,
+}
+
+/// ディレクトリ以下のすべての .md ファイルからテーブルを集める
+///
+/// 読み込めないファイルは飛ばし、`FileError` として併せて返す。
+pub fn collect_directory_tables(dir: &Path) -> (Vec<DirectoryTable>, Vec<FileError>) {
+    let mut out = Vec::new();
+    let mut errors = Vec::new();
+    for path in collect_markdown_files(dir) {
+        let doc = match read_document(&path) {
+            Ok(doc) => doc,
+            Err(error) => {
+                errors.push(FileError {
+                    path: path.to_string_lossy().to_string(),
+                    error,
+                });
+                continue;
+            }
+        };
+        let file = path.to_string_lossy().to_string();
+        out.extend(
+            doc.tables
+                .into_iter()
+                .enumerate()
+                .map(|(table_index, t)| DirectoryTable {
+                    file: file.clone(),
+                    table_index,
+                    heading: t.heading,
+                    headers: t.headers,
+                    rows: t.rows,
+                }),
+        );
+    }
+    (out, errors)
+}
+
+/// ディレクトリ以下のすべてのテーブルを1つの JSON 配列に変換する
+pub fn directory_tables_json(dir: &Path) -> Result<DirectoryTablesJson, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let (tables, errors) = collect_directory_tables(dir);
+    let json = serde_json::to_string_pretty(&tables).map_err(|e| e.to_string())?;
+    Ok(DirectoryTablesJson { json, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown;
+
+    fn sample_table() -> MarkdownTable {
+        let md = "| Name |\n| :---: |\n| **bold** <i>x</i> |\n";
+        parse_markdown(md).tables.remove(0)
+    }
+
+    #[test]
+    fn test_table_to_csv_quotes_special_cells() {
+        let md = "| Name | Note |\n| --- | --- |\n| a | x, y |\n| b | say \"hi\" |\n";
+        let csv = table_to_csv(&parse_markdown(md).tables[0]);
+        assert_eq!(csv, "Name,Note\na,\"x, y\"\nb,\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_table_to_html_escapes_by_default() {
+        let html = table_to_html(&sample_table(), false);
+        assert!(
+            html.contains("<td style=\"text-align: center\">**bold** &lt;i&gt;x&lt;/i&gt;</td>")
+        );
+    }
+
+    #[test]
+    fn test_table_to_html_renders_inline() {
+        let html = table_to_html(&sample_table(), true);
+        assert!(html.contains("<strong>bold</strong> <i>x</i>"));
+        assert_eq!(
+            render_inline("<script>x</script> [a](javascript:void)"),
+            "&lt;script&gt;x&lt;/script&gt; a"
+        );
+        assert!(!is_safe_url("java\tscript:alert(1)"));
+        assert!(!is_safe_url(" \u{1}java\r\nscript:alert(1)"));
+        assert!(is_safe_url("https://example.com/a"));
+    }
+
+    #[test]
+    fn test_table_to_definition_list() {
+        let multi = parse_markdown("| A |\n| --- |\n| 1 |\n| 2 |\n")
+            .tables
+            .remove(0);
+        assert_eq!(table_to_definition_list(&multi), None);
+
+        let md = "| Name | Note | |\n| --- | --- | --- |\n| Alice | - a<br>- b | x |\n";
+        let single = parse_markdown(md).tables.remove(0);
+        assert_eq!(
+            table_to_definition_list(&single).unwrap(),
+            "Name\n: Alice\n\nNote\n: \\- a\n  - b\n\n列3\n: x\n"
+        );
+    }
+
+    #[test]
+    fn test_directory_tables_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("sub").join("b.md");
+        std::fs::write(&a, "# A\n| X |\n| --- |\n| 1 |\n").unwrap();
+        std::fs::write(&b, "| Y | Z |\n| --- | --- |\n| 2 | 3 |\n").unwrap();
+
+        let exported = directory_tables_json(dir.path()).unwrap();
+        assert!(exported.errors.is_empty());
+        let json: serde_json::Value = serde_json::from_str(&exported.json).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "file": a.to_string_lossy(),
+                    "table_index": 0,
+                    "heading": "A",
+                    "headers": ["X"],
+                    "rows": [["1"]],
+                },
+                {
+                    "file": b.to_string_lossy(),
+                    "table_index": 0,
+                    "heading": null,
+                    "headers": ["Y", "Z"],
+                    "rows": [["2", "3"]],
+                },
+            ])
+        );
+    }
+}
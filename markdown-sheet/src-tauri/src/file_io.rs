@@ -0,0 +1,685 @@
+use crate::edit_lock::LockInfo;
+use crate::markdown_parser::{
+    count_tables, outline, parse_markdown, table_checksums, ParsedDocument,
+};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// 読み込み時点のファイル状態（バージョントークン）付きのドキュメント
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSnapshot {
+    /// 読み込んだファイルのパス
+    pub path: String,
+    /// パース結果
+    pub document: ParsedDocument,
+    /// 最終更新日時（Unix エポックからのミリ秒）
+    pub mtime_ms: i64,
+    /// ディスク上の生バイト列の SHA-256（16進小文字）
+    pub sha256: String,
+    /// 書き込み不可のファイルか
+    pub read_only: bool,
+    /// 各テーブルの内容のチェックサム（`MarkdownTable::checksum` の16進表記）
+    pub table_checksums: Vec<String>,
+}
+
+/// 保存時のエラー（フロントエンドで種類ごとに表示を分けられるよう区別する）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SaveError {
+    /// 書き込み権限がない
+    PermissionDenied { path: String },
+    /// 読み取り専用のファイルシステム上にある
+    ReadOnlyFilesystem { path: String },
+    /// 別の操作がファイルをロックしている
+    Locked { path: String },
+    /// 他のプロセス（別のマシンを含む）が編集ロックを保持している
+    LockedByOther { path: String, holder: LockInfo },
+    /// 読み込み後にディスク上のファイルが変更された
+    Conflict { path: String },
+    /// 読み込み後にディスク上のテーブルの内容が変更された
+    TableConflict {
+        path: String,
+        table_indices: Vec<usize>,
+    },
+    /// その他の I/O エラー
+    Io { path: String, message: String },
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::PermissionDenied { path } => {
+                write!(f, "ファイルへの書き込み権限がありません: {}", path)
+            }
+            SaveError::ReadOnlyFilesystem { path } => {
+                write!(f, "読み取り専用のファイルシステムです: {}", path)
+            }
+            SaveError::Locked { path } => {
+                write!(f, "ファイルは別の操作によってロックされています: {}", path)
+            }
+            SaveError::LockedByOther { path, holder } => write!(
+                f,
+                "ファイルは別のプロセスが編集中です（{} の pid {}）: {}",
+                holder.hostname, holder.pid, path
+            ),
+            SaveError::Conflict { path } => {
+                write!(f, "ファイルがディスク上で変更されています: {}", path)
+            }
+            SaveError::TableConflict {
+                path,
+                table_indices,
+            } => write!(
+                f,
+                "テーブルがディスク上で変更されています（テーブル {:?}）: {}",
+                table_indices, path
+            ),
+            SaveError::Io { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl SaveError {
+    /// I/O エラーを保存エラーに変換する
+    pub fn from_io(path: &str, err: io::Error) -> Self {
+        let path = path.to_string();
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => SaveError::PermissionDenied { path },
+            io::ErrorKind::ReadOnlyFilesystem => SaveError::ReadOnlyFilesystem { path },
+            _ => SaveError::Io {
+                path,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// バイト列の SHA-256 を16進文字列で返す
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// メタデータから最終更新日時をミリ秒で取得する（取得できなければ 0）
+pub fn mtime_ms(meta: &fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// BOM のない UTF-16 を NUL バイトの偏りから推定する
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(1024) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+    if odd_zeros * 3 > pairs && even_zeros * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 3 > pairs && odd_zeros * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// バイト列の文字コードを判定して文字列にデコードする
+///
+/// BOM → UTF-8 として妥当か → BOM なし UTF-16 の推定 → chardetng による推定の順に判定する。
+/// 戻り値は (デコード結果, 文字コード, BOM の有無)。
+pub fn decode_bytes(bytes: &[u8]) -> (String, &'static Encoding, bool) {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return (text.into_owned(), encoding, true);
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), UTF_8, false);
+    }
+    let encoding = sniff_utf16(bytes).unwrap_or_else(|| {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    });
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding, false)
+}
+
+/// 文字列を指定の文字コードでエンコードする
+///
+/// encoding_rs は UTF-16 へのエンコードに対応していないため個別に処理する。
+/// 表現できない文字が含まれる場合は文字化けを防ぐためエラーにする。
+pub fn encode_text(text: &str, encoding_name: &str, bom: bool) -> Result<Vec<u8>, String> {
+    let encoding = Encoding::for_label(encoding_name.as_bytes())
+        .ok_or_else(|| format!("未対応の文字コードです: {}", encoding_name))?;
+    let mut out = Vec::new();
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let le = encoding == UTF_16LE;
+        if bom {
+            out.extend_from_slice(if le { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+        }
+        for unit in text.encode_utf16() {
+            out.extend_from_slice(&if le {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            });
+        }
+        return Ok(out);
+    }
+    if bom && encoding == UTF_8 {
+        out.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (bytes, _, had_errors) = encoding.encode(text);
+    if had_errors {
+        return Err(format!(
+            "{} で表現できない文字が含まれています",
+            encoding.name()
+        ));
+    }
+    out.extend_from_slice(&bytes);
+    Ok(out)
+}
+
+/// バイト列をデコードしてパースし、文字コード情報を記録する
+pub fn document_from_bytes(bytes: &[u8]) -> ParsedDocument {
+    let (content, encoding, has_bom) = decode_bytes(bytes);
+    let mut document = parse_markdown(&content);
+    document.encoding = encoding.name().to_string();
+    document.has_bom = has_bom;
+    document
+}
+
+/// ファイルを読み込んでパースする（文字コードは自動判定）
+pub fn read_document(path: &Path) -> Result<ParsedDocument, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    Ok(document_from_bytes(&bytes))
+}
+
+/// 既存ファイルの文字コードと BOM の有無を判定する（読めなければ UTF-8）
+pub fn detect_file_encoding(path: &Path) -> (String, bool) {
+    match fs::read(path) {
+        Ok(bytes) => {
+            let (_, encoding, bom) = decode_bytes(&bytes);
+            (encoding.name().to_string(), bom)
+        }
+        Err(_) => (UTF_8.name().to_string(), false),
+    }
+}
+
+/// ファイル単位の見出しインデックス
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileIndex {
+    pub path: String,
+    /// ファイル内の見出し（出現順）
+    pub headings: Vec<String>,
+    /// 各テーブル直前の見出し（テーブル順）
+    pub table_headings: Vec<Option<String>>,
+    /// 読み込めなかった場合のエラー（見出しは空になる）
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// ディレクトリ以下の .md ファイルの見出しとテーブル見出しを集める（行データは持たない）
+///
+/// 読み込めないファイルも飛ばさず、見出しを空にして `error` に理由を入れて返す。
+pub fn index_directory(dir: &Path) -> Result<Vec<FileIndex>, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    Ok(collect_markdown_files(dir)
+        .into_iter()
+        .map(|path| {
+            let path_str = path.to_string_lossy().to_string();
+            match fs::read(&path) {
+                Ok(bytes) => {
+                    let (content, _, _) = decode_bytes(&bytes);
+                    let outline = outline(&content);
+                    FileIndex {
+                        path: path_str,
+                        headings: outline.headings,
+                        table_headings: outline.table_headings,
+                        error: None,
+                    }
+                }
+                Err(e) => FileIndex {
+                    path: path_str,
+                    headings: Vec::new(),
+                    table_headings: Vec::new(),
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+/// 更新日時付きのファイル
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: String,
+    /// 最終更新日時（Unix タイムスタンプ秒）
+    pub modified: u64,
+}
+
+/// ディレクトリ以下の .md ファイルを更新日時の新しい順に最大 `limit` 件返す
+///
+/// 更新日時を取得できないファイルは除外する。
+pub fn recently_modified_files(dir: &Path, limit: usize) -> Result<Vec<RecentFile>, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let mut files: Vec<RecentFile> = collect_markdown_files(dir)
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()?
+                .duration_since(UNIX_EPOCH)
+                .ok()?
+                .as_secs();
+            Some(RecentFile {
+                path: path.to_string_lossy().to_string(),
+                modified,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    files.truncate(limit);
+    Ok(files)
+}
+
+/// 複数のファイルの内容を順に `separator` でつなげる（文字コードはファイルごとに判定する）
+///
+/// 各ファイル末尾の改行は取り除いてからつなげ、結果は改行1つで終える。
+pub fn concatenate_files(paths: &[PathBuf], separator: &str) -> Result<String, String> {
+    let mut parts = Vec::with_capacity(paths.len());
+    for path in paths {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (text, _, _) = decode_bytes(&bytes);
+        parts.push(text.trim_end_matches(['\r', '\n']).to_string());
+    }
+    let mut content = parts.join(separator);
+    content.push('\n');
+    Ok(content)
+}
+
+/// ディスク上のテーブルが期待するチェックサム（テーブル番号 → 16進文字列）のままか確かめ、
+/// 変わっていたテーブル番号を返す（ファイルが読めない場合は空）
+pub fn changed_tables(path: &Path, expected: &HashMap<usize, String>) -> Vec<usize> {
+    let Ok(doc) = read_document(path) else {
+        return Vec::new();
+    };
+    let current = table_checksums(&doc.tables);
+    let mut changed: Vec<usize> = expected
+        .iter()
+        .filter(|(i, checksum)| current.get(**i) != Some(*checksum))
+        .map(|(i, _)| *i)
+        .collect();
+    changed.sort_unstable();
+    changed
+}
+
+/// ディスク上のファイルの現在のハッシュを取得する
+pub fn file_sha256(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    Ok(sha256_hex(&bytes))
+}
+
+/// ファイルが書き込み不可かどうかを判定する
+///
+/// 読み取り専用属性に加え、実際に書き込みモードで開けるかも確認する
+/// （所有者違いのパーミッションや読み取り専用マウントを検出するため）。
+pub fn is_read_only(path: &Path, meta: &fs::Metadata) -> bool {
+    meta.permissions().readonly() || fs::OpenOptions::new().append(true).open(path).is_err()
+}
+
+/// Windows の読み取り専用属性を外す
+#[cfg(windows)]
+#[allow(clippy::permissions_set_readonly_false)]
+fn clear_readonly(path: &Path) -> io::Result<()> {
+    let mut perms = fs::metadata(path)?.permissions();
+    if perms.readonly() {
+        perms.set_readonly(false);
+        fs::set_permissions(path, perms)?;
+    }
+    Ok(())
+}
+
+/// ファイルに書き込む。失敗時は原因ごとに区別した `SaveError` を返す
+///
+/// `make_writable` が true の場合、Windows では書き込み前に読み取り専用属性を外す。
+pub fn write_file(path: &Path, content: &str, make_writable: bool) -> Result<(), SaveError> {
+    write_encoded(path, content, UTF_8.name(), false, make_writable)
+}
+
+/// 指定の文字コードでエンコードしてファイルに書き込む
+pub fn write_encoded(
+    path: &Path,
+    content: &str,
+    encoding: &str,
+    bom: bool,
+    make_writable: bool,
+) -> Result<(), SaveError> {
+    let path_str = path.to_string_lossy();
+    let bytes = encode_text(content, encoding, bom).map_err(|message| SaveError::Io {
+        path: path_str.to_string(),
+        message,
+    })?;
+    #[cfg(windows)]
+    if make_writable && path.exists() {
+        clear_readonly(path).map_err(|e| SaveError::from_io(&path_str, e))?;
+    }
+    #[cfg(not(windows))]
+    let _ = make_writable;
+    fs::write(path, bytes).map_err(|e| SaveError::from_io(&path_str, e))
+}
+
+/// 一時ファイルに書いてからリネームすることで、途中までしか書かれていない状態を残さずに書き込む
+///
+/// 同じディレクトリに一時ファイルを作るため、リネームは同一ファイルシステム内で完結する。
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let name = path
+        .file_name()
+        .map_or_else(|| "file".to_string(), |n| n.to_string_lossy().to_string());
+    let tmp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// ディレクトリ以下の .md ファイルを再帰的に列挙する（ファイルツリーと同じく隠しファイルは除外）
+pub fn collect_markdown_files(dir: &Path) -> Vec<PathBuf> {
+    fn walk(dir: &Path, depth: u32, out: &mut Vec<PathBuf>) {
+        if depth > 5 {
+            return;
+        }
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut items: Vec<_> = read_dir.filter_map(|e| e.ok()).collect();
+        items.sort_by_key(|e| e.file_name());
+        for entry in items {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, depth + 1, out);
+            } else if name.ends_with(".md") {
+                out.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, 0, &mut files);
+    files
+}
+
+/// ファイル内のテーブル数を軽量スキャンで数える（行データは組み立てない。文字コードは自動判定）
+pub fn count_tables_in_file(path: &Path) -> Result<usize, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let (content, _, _) = decode_bytes(&bytes);
+    Ok(count_tables(&content))
+}
+
+/// ファイルを読み込み、パース結果とバージョントークンをまとめて返す
+pub fn read_snapshot(path: &Path) -> Result<DocumentSnapshot, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let meta = fs::metadata(path).map_err(|e| e.to_string())?;
+    Ok(snapshot_from_bytes(path, &bytes, &meta))
+}
+
+/// 読み込み済みのバイト列とメタデータからスナップショットを作る
+pub fn snapshot_from_bytes(path: &Path, bytes: &[u8], meta: &fs::Metadata) -> DocumentSnapshot {
+    let document = document_from_bytes(bytes);
+    DocumentSnapshot {
+        path: path.to_string_lossy().to_string(),
+        table_checksums: table_checksums(&document.tables),
+        document,
+        mtime_ms: mtime_ms(meta),
+        // ハッシュはデコード前の生バイト列で計算する
+        sha256: sha256_hex(bytes),
+        read_only: is_read_only(path, meta),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_stable_and_changes_with_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n").unwrap();
+
+        let first = read_snapshot(&path).unwrap();
+        let second = read_snapshot(&path).unwrap();
+        assert_eq!(first.sha256, second.sha256);
+        assert_eq!(first.sha256.len(), 64);
+        assert_eq!(first.document.tables.len(), 1);
+
+        fs::write(&path, "| A |\n| --- |\n| 2 |\n").unwrap();
+        let third = read_snapshot(&path).unwrap();
+        assert_ne!(first.sha256, third.sha256);
+    }
+
+    #[test]
+    fn test_changed_tables_by_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n\n| B |\n| --- |\n| 2 |\n").unwrap();
+        let snapshot = read_snapshot(&path).unwrap();
+        let expected: HashMap<usize, String> =
+            snapshot.table_checksums.into_iter().enumerate().collect();
+        assert!(changed_tables(&path, &expected).is_empty());
+
+        // 書式だけの変更は競合にしない
+        fs::write(&path, "|A|\n|---|\n|1|\n\n| B |\n| --- |\n| 2 |\n").unwrap();
+        assert!(changed_tables(&path, &expected).is_empty());
+
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n\n| B |\n| --- |\n| 9 |\n").unwrap();
+        assert_eq!(changed_tables(&path, &expected), vec![1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_file_detected_and_save_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ro.md");
+        fs::write(&path, "# ro\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+
+        assert!(read_snapshot(&path).unwrap().read_only);
+
+        // root 権限ではパーミッションに関係なく書き込めるため、その場合は判定を省略する
+        if fs::OpenOptions::new().append(true).open(&path).is_err() {
+            let err = write_file(&path, "changed", false).unwrap_err();
+            assert_eq!(
+                err,
+                SaveError::PermissionDenied {
+                    path: path.to_string_lossy().to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_utf16le_file_and_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("utf16.md");
+        let text = "| Name | Note |\n| --- | --- |\n| café | 日本語 |\n";
+        let bytes = encode_text(text, "UTF-16LE", true).unwrap();
+        assert_eq!(&bytes[..4], &[0xFF, 0xFE, b'|', 0]);
+        fs::write(&path, &bytes).unwrap();
+
+        let doc = read_document(&path).unwrap();
+        assert_eq!(doc.encoding, "UTF-16LE");
+        assert!(doc.has_bom);
+        assert_eq!(doc.tables[0].rows[0], vec!["café", "日本語"]);
+
+        write_encoded(&path, text, &doc.encoding, doc.has_bom, false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_read_windows_1252_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("latin.md");
+        let text = "# Café\n\nLe garçon a mangé une crème brûlée très délicieuse à côté de la fenêtre.\n\n| Mot | Définition |\n| --- | --- |\n| naïve | élève déçu |\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        fs::write(&path, &bytes).unwrap();
+
+        let doc = read_document(&path).unwrap();
+        assert_eq!(doc.encoding, "windows-1252");
+        assert!(!doc.has_bom);
+        assert_eq!(doc.tables[0].rows[0], vec!["naïve", "élève déçu"]);
+        assert_eq!(
+            encode_text(text, &doc.encoding, false).unwrap(),
+            bytes.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_count_tables_in_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("three.md");
+        let text = "# A\n| A |\n| --- |\n| 1 |\n\n| B | C |\n|---|---|\n\ntext | with pipe\n\n## D\n| D |\n| :-: |\n| ü |\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(count_tables_in_file(&path).unwrap(), 3);
+        assert!(count_tables_in_file(&dir.path().join("missing.md")).is_err());
+    }
+
+    #[test]
+    fn test_save_error_mapping() {
+        let err = SaveError::from_io("a.md", io::Error::from(io::ErrorKind::ReadOnlyFilesystem));
+        assert_eq!(
+            err,
+            SaveError::ReadOnlyFilesystem {
+                path: "a.md".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_index_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("sub").join("b.md");
+        fs::write(&a, "# Plan\n## Tasks\n| A |\n| --- |\n| 1 |\n").unwrap();
+        fs::write(&b, "| X |\n| --- |\n\n# Notes\n").unwrap();
+        fs::write(dir.path().join("ignored.txt"), "# No").unwrap();
+        // 読み込めないファイル（リンク切れのシンボリックリンク）
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("broken.md"))
+            .unwrap();
+
+        let mut index = index_directory(dir.path()).unwrap();
+        #[cfg(unix)]
+        {
+            let pos = index.iter().position(|f| f.error.is_some()).unwrap();
+            let broken = index.remove(pos);
+            assert!(broken.path.ends_with("broken.md"));
+            assert!(broken.headings.is_empty());
+        }
+        assert_eq!(
+            index,
+            vec![
+                FileIndex {
+                    path: a.to_string_lossy().to_string(),
+                    headings: vec!["Plan".to_string(), "Tasks".to_string()],
+                    table_headings: vec![Some("Tasks".to_string())],
+                    error: None,
+                },
+                FileIndex {
+                    path: b.to_string_lossy().to_string(),
+                    headings: vec!["Notes".to_string()],
+                    table_headings: vec![None],
+                    error: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concatenate_files_keeps_table_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        fs::write(&a, "# A\n\n| X |\n| --- |\n| a |\n").unwrap();
+        fs::write(
+            &b,
+            "# B\n\n| Y |\n| --- |\n| b1 |\n\n| Z |\n| --- |\n| b2 |\n",
+        )
+        .unwrap();
+
+        let content = concatenate_files(&[b.clone(), a.clone()], "\n\n---\n\n").unwrap();
+        let doc = parse_markdown(&content);
+        let headers: Vec<&str> = doc.tables.iter().map(|t| t.headers[0].as_str()).collect();
+        assert_eq!(headers, vec!["Y", "Z", "X"]);
+        assert!(content.contains("| b2 |\n\n---\n\n# A\n"));
+        assert!(concatenate_files(&[dir.path().join("missing.md")], "").is_err());
+    }
+
+    #[test]
+    fn test_recently_modified_files_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, secs_ago) in [("old.md", 300), ("sub/new.md", 10), ("mid.md", 100)] {
+            let path = dir.path().join(name);
+            fs::write(&path, "").unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(secs_ago))
+                .unwrap();
+        }
+
+        let names: Vec<String> = recently_modified_files(dir.path(), 2)
+            .unwrap()
+            .into_iter()
+            .map(|f| {
+                Path::new(&f.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(names, vec!["new.md", "mid.md"]);
+        assert_eq!(recently_modified_files(dir.path(), 10).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// ファイルごとの書き込みロックを保持する管理ステート
+///
+/// キーは正規化したファイルパス、値はロックを取得したウィンドウのラベル。
+#[derive(Debug, Default)]
+pub struct FileLocks(Mutex<HashMap<String, String>>);
+
+/// ロックのキーとして使うパスを正規化する（存在しない場合はそのまま）
+pub fn lock_key(file_path: &str) -> String {
+    fs::canonicalize(file_path)
+        .unwrap_or_else(|_| PathBuf::from(file_path))
+        .to_string_lossy()
+        .to_string()
+}
+
+impl FileLocks {
+    /// ロックを取得する。同じ所有者による再取得は成功扱い
+    pub fn acquire(&self, file_path: &str, owner: &str) -> Result<(), String> {
+        let mut locks = self.0.lock().map_err(|e| e.to_string())?;
+        let key = lock_key(file_path);
+        match locks.get(&key) {
+            Some(current) if current != owner => Err(format!(
+                "ファイルは別の操作によってロックされています: {}",
+                file_path
+            )),
+            _ => {
+                locks.insert(key, owner.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// ロックを解放する。別の所有者が保持しているロックは解放できない
+    pub fn release(&self, file_path: &str, owner: &str) -> Result<(), String> {
+        let mut locks = self.0.lock().map_err(|e| e.to_string())?;
+        let key = lock_key(file_path);
+        match locks.get(&key) {
+            Some(current) if current != owner => {
+                Err(format!("ロックは別の操作が保持しています: {}", file_path))
+            }
+            Some(_) => {
+                locks.remove(&key);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 指定の所有者以外がロックを保持しているか
+    pub fn is_locked_by_other(&self, file_path: &str, owner: &str) -> bool {
+        let Ok(locks) = self.0.lock() else {
+            return false;
+        };
+        locks
+            .get(&lock_key(file_path))
+            .is_some_and(|current| current != owner)
+    }
+
+    /// 指定の所有者（ウィンドウ）が保持しているロックをすべて解放する
+    pub fn release_all(&self, owner: &str) {
+        if let Ok(mut locks) = self.0.lock() {
+            locks.retain(|_, current| current != owner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_conflict_between_owners() {
+        let locks = FileLocks::default();
+        locks.acquire("/tmp/a.md", "main").unwrap();
+        assert!(locks.acquire("/tmp/a.md", "main").is_ok());
+        assert!(locks.acquire("/tmp/a.md", "other").is_err());
+        assert!(locks.is_locked_by_other("/tmp/a.md", "other"));
+        assert!(!locks.is_locked_by_other("/tmp/a.md", "main"));
+        assert!(locks.release("/tmp/a.md", "other").is_err());
+        locks.release("/tmp/a.md", "main").unwrap();
+        assert!(!locks.is_locked_by_other("/tmp/a.md", "other"));
+    }
+
+    #[test]
+    fn test_release_all_on_window_close() {
+        let locks = FileLocks::default();
+        locks.acquire("/tmp/a.md", "main").unwrap();
+        locks.acquire("/tmp/b.md", "main").unwrap();
+        locks.acquire("/tmp/c.md", "other").unwrap();
+        locks.release_all("main");
+        assert!(!locks.is_locked_by_other("/tmp/a.md", "other"));
+        assert!(!locks.is_locked_by_other("/tmp/b.md", "other"));
+        assert!(locks.is_locked_by_other("/tmp/c.md", "main"));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::workspace;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// 一致した1文字あたりの基本点
+const SCORE_MATCH: i64 = 16;
+/// 一致した文字の間に飛ばした文字があるときの減点（最初の1文字）
+const GAP_START: i64 = 3;
+/// 飛ばした文字が2文字目以降のときの1文字あたりの減点
+const GAP_EXTENSION: i64 = 1;
+/// `/` の直後（フォルダ名・ファイル名の先頭）
+const BONUS_PATH_SEPARATOR: i64 = 9;
+/// 先頭や `-` `_` `.` 空白の直後
+const BONUS_BOUNDARY: i64 = 8;
+/// camelCase の大文字
+const BONUS_CAMEL: i64 = 7;
+/// 連続して一致したときの最低ボーナス
+const BONUS_CONSECUTIVE: i64 = 4;
+/// クエリの1文字目のボーナスは倍にする
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// フォルダ名よりファイル名での一致を優先する
+const BONUS_FILE_NAME: i64 = 2;
+
+/// ファイル名検索の結果1件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileMatch {
+    pub path: String,
+    /// ワークスペースルートからの相対パス（`/` 区切り）
+    pub relative: String,
+    pub score: i64,
+    /// `relative` 内で一致した文字の位置（UTF-16 ではなく文字単位）
+    pub indices: Vec<usize>,
+}
+
+/// 検索対象のファイル（小文字化した文字列と位置ごとのボーナスを前計算しておく）
+#[derive(Debug)]
+struct Candidate {
+    path: PathBuf,
+    relative: String,
+    lower: Vec<char>,
+    bonus: Vec<i64>,
+}
+
+fn lower_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn char_bonus(prev: Option<char>, cur: char) -> i64 {
+    if matches!(cur, '/' | '-' | '_' | '.' | ' ') {
+        return 0;
+    }
+    match prev {
+        None => BONUS_BOUNDARY,
+        Some('/') => BONUS_PATH_SEPARATOR,
+        Some('-' | '_' | '.' | ' ') => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && cur.is_uppercase() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+impl Candidate {
+    fn new(path: PathBuf, relative: String) -> Self {
+        let chars: Vec<char> = relative.chars().collect();
+        let name_start = chars.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+        let bonus = chars
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| {
+                let prev = i.checked_sub(1).map(|p| chars[p]);
+                let name = if i >= name_start { BONUS_FILE_NAME } else { 0 };
+                char_bonus(prev, c) + name
+            })
+            .collect();
+        Candidate {
+            path,
+            lower: chars.iter().map(|&c| lower_char(c)).collect(),
+            relative,
+            bonus,
+        }
+    }
+}
+
+/// クエリを部分列として含むかを採点し、スコアと一致位置を返す（含まなければ None）
+///
+/// fzf と同様に、連続した一致と単語の区切りでの一致を高く評価し、間を飛ばすほど減点する。
+fn fuzzy_match(query: &[char], candidate: &Candidate) -> Option<(i64, Vec<usize>)> {
+    let text = &candidate.lower;
+    let (m, n) = (query.len(), text.len());
+    if m == 0 {
+        return Some((0, Vec::new()));
+    }
+    let mut qi = 0;
+    for &c in text {
+        if qi < m && c == query[qi] {
+            qi += 1;
+        }
+    }
+    if qi < m {
+        return None;
+    }
+
+    const NONE: i64 = i64::MIN / 2;
+    // score[i * n + j]: クエリの i 文字目を j 文字目に一致させたときの最高点
+    let mut score = vec![NONE; m * n];
+    // 連続した一致の先頭のボーナス（連続部分はこれを引き継ぐ）
+    let mut chunk = vec![0i64; m * n];
+    let mut from = vec![usize::MAX; m * n];
+    for i in 0..m {
+        // 1文字以上飛ばして j に来る場合の最高点（飛ばした分の減点込み）
+        let mut gap_best = NONE;
+        let mut gap_from = usize::MAX;
+        for j in i..n {
+            if i > 0 && j >= 2 {
+                if gap_best > NONE {
+                    gap_best -= GAP_EXTENSION;
+                }
+                let prev = score[(i - 1) * n + j - 2];
+                if prev > NONE && prev - GAP_START > gap_best {
+                    gap_best = prev - GAP_START;
+                    gap_from = j - 2;
+                }
+            }
+            if text[j] != query[i] {
+                continue;
+            }
+            let bonus = candidate.bonus[j];
+            let at = i * n + j;
+            if i == 0 {
+                score[at] = SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+                chunk[at] = bonus;
+                continue;
+            }
+            if j >= 1 && score[at - n - 1] > NONE {
+                let inherited = chunk[at - n - 1].max(bonus).max(BONUS_CONSECUTIVE);
+                score[at] = score[at - n - 1] + SCORE_MATCH + inherited;
+                chunk[at] = inherited;
+                from[at] = j - 1;
+            }
+            if gap_best > NONE && gap_best + SCORE_MATCH + bonus > score[at] {
+                score[at] = gap_best + SCORE_MATCH + bonus;
+                chunk[at] = bonus;
+                from[at] = gap_from;
+            }
+        }
+    }
+
+    let last = (m - 1) * n;
+    let (mut j, best) = (0..n)
+        .map(|j| (j, score[last + j]))
+        .filter(|&(_, s)| s > NONE)
+        .fold((0, NONE), |acc, cur| if cur.1 > acc.1 { cur } else { acc });
+    if best <= NONE {
+        return None;
+    }
+    let mut indices = vec![0; m];
+    for i in (0..m).rev() {
+        indices[i] = j;
+        j = from[i * n + j];
+    }
+    Some((best, indices))
+}
+
+/// クイックオープン用のファイル名検索（ルートごとのファイル一覧をキャッシュする管理ステート）
+///
+/// ファイルの追加・削除や除外設定の変更時には `invalidate` でキャッシュを捨てる。
+#[derive(Debug, Default)]
+pub struct FileSearch(Mutex<HashMap<PathBuf, Vec<Candidate>>>);
+
+impl FileSearch {
+    /// ルート以下のファイル名をあいまい検索し、スコアの高い順に最大 `limit` 件返す
+    ///
+    /// 大文字・小文字は区別せず、クエリ中の空白は無視する。
+    pub fn search(&self, roots: &[PathBuf], query: &str, limit: usize) -> Vec<FileMatch> {
+        let query: Vec<char> = query
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(lower_char)
+            .collect();
+        let Ok(mut cache) = self.0.lock() else {
+            return Vec::new();
+        };
+        let mut matches = Vec::new();
+        for root in roots {
+            let candidates = cache.entry(root.clone()).or_insert_with(|| {
+                workspace::markdown_files(root)
+                    .into_iter()
+                    .map(|(path, rel)| Candidate::new(path, rel))
+                    .collect()
+            });
+            matches.extend(candidates.iter().filter_map(|c| {
+                let (score, indices) = fuzzy_match(&query, c)?;
+                Some(FileMatch {
+                    path: c.path.to_string_lossy().to_string(),
+                    relative: c.relative.clone(),
+                    score,
+                    indices,
+                })
+            }));
+        }
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.relative.chars().count().cmp(&b.relative.chars().count()))
+                .then_with(|| a.relative.cmp(&b.relative))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// キャッシュしたファイル一覧を捨てる（次回の検索で読み直す）
+    pub fn invalidate(&self) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ranking_and_highlight() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        for rel in [
+            "archive/r/e/p.md",
+            "notes/report.md",
+            "reviews/empty-page.md",
+            "議事録/2024年会議.md",
+            "drafts/report-draft.md",
+        ] {
+            let path = root.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+        workspace::update_settings(&root, &serde_json::json!({ "ignore": ["drafts"] })).unwrap();
+
+        let search = FileSearch::default();
+        let results = search.search(std::slice::from_ref(&root), "REP", 10);
+        let names: Vec<&str> = results.iter().map(|m| m.relative.as_str()).collect();
+        assert_eq!(names[0], "notes/report.md");
+        assert_eq!(results[0].indices, vec![6, 7, 8]);
+        assert!(names.contains(&"reviews/empty-page.md"));
+        assert!(!names.iter().any(|n| n.starts_with("drafts/")));
+
+        // 日本語のファイル名でも文字単位で一致位置を返す
+        let results = search.search(std::slice::from_ref(&root), "会議", 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].indices, vec![9, 10]);
+
+        // キャッシュを捨てるまで新しいファイルは見えない
+        fs::write(root.join("notes/repo.md"), "").unwrap();
+        assert!(!search
+            .search(std::slice::from_ref(&root), "repo", 10)
+            .iter()
+            .any(|m| m.relative == "notes/repo.md"));
+        search.invalidate();
+        let results = search.search(std::slice::from_ref(&root), "repo", 10);
+        assert_eq!(results[0].relative, "notes/repo.md");
+    }
+}
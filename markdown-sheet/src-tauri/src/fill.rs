@@ -0,0 +1,358 @@
+use crate::markdown_parser::{get_cell, set_cell, MarkdownTable};
+use regex::Regex;
+use serde::Deserialize;
+
+/// セルの埋め方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillMode {
+    /// 範囲の先頭のセルの値をそのまま下へコピーする
+    CopyDown,
+    /// 範囲の先頭から続く値を連番として延ばす
+    Series,
+}
+
+/// 連番の1項
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// 数値と、その前に付く文字列（`item-1` の `item-`）。`width` は 0 埋めする桁数
+    Number {
+        prefix: String,
+        value: i64,
+        width: usize,
+    },
+    /// `YYYY-MM-DD` 形式の日付（1970-01-01 からの日数）
+    Day { sep: char, days: i64 },
+    /// `YYYY-MM` 形式の年月（西暦0年1月からの月数）
+    Month { sep: char, months: i64 },
+}
+
+impl Term {
+    /// 同じ種類の項か（数値なら前に付く文字列、日付なら区切りも同じ）
+    fn same_kind(&self, other: &Term) -> bool {
+        match (self, other) {
+            (Term::Number { prefix, .. }, Term::Number { prefix: p, .. }) => prefix == p,
+            (Term::Day { sep, .. }, Term::Day { sep: s, .. })
+            | (Term::Month { sep, .. }, Term::Month { sep: s, .. }) => sep == s,
+            _ => false,
+        }
+    }
+
+    fn ordinal(&self) -> i64 {
+        match self {
+            Term::Number { value, .. } => *value,
+            Term::Day { days, .. } => *days,
+            Term::Month { months, .. } => *months,
+        }
+    }
+
+    /// 同じ種類で位置が `ordinal` の項を文字列にする
+    ///
+    /// 表せない値（前に文字列が付く負の数・0〜9999 年の外の日付）は None。
+    fn render(&self, ordinal: i64) -> Option<String> {
+        match self {
+            Term::Number { prefix, .. } if !prefix.is_empty() && ordinal < 0 => None,
+            Term::Number { prefix, width, .. } => {
+                Some(format!("{}{:0width$}", prefix, ordinal, width = *width))
+            }
+            Term::Day { sep, .. } => {
+                let range = days_from_civil(0, 1, 1)..=days_from_civil(9999, 12, 31);
+                if !range.contains(&ordinal) {
+                    return None;
+                }
+                let (y, m, d) = civil_from_days(ordinal);
+                Some(format!("{:04}{}{:02}{}{:02}", y, sep, m, sep, d))
+            }
+            Term::Month { sep, .. } => (0..10000 * 12).contains(&ordinal).then(|| {
+                format!(
+                    "{:04}{}{:02}",
+                    ordinal.div_euclid(12),
+                    sep,
+                    ordinal.rem_euclid(12) + 1
+                )
+            }),
+        }
+    }
+}
+
+/// 1970-01-01 からの日数（グレゴリオ暦）
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `days_from_civil` の逆
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+/// 連番として読めるセルの形
+struct Patterns {
+    day: Regex,
+    month: Regex,
+    integer: Regex,
+    numbered: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Patterns {
+            day: Regex::new(r"^(\d{4})([-/])(\d{2})([-/])(\d{2})$").unwrap(),
+            month: Regex::new(r"^(\d{4})([-/])(\d{2})$").unwrap(),
+            integer: Regex::new(r"^-?\d{1,18}$").unwrap(),
+            numbered: Regex::new(r"^(.*?)(\d{1,18})$").unwrap(),
+        }
+    }
+
+    /// セルを連番の項として読む（読めなければ None）
+    fn parse(&self, cell: &str) -> Option<Term> {
+        let sep_of = |s: &str| s.chars().next().unwrap_or('-');
+        if let Some(c) = self.day.captures(cell) {
+            let (y, m, d): (i64, i64, i64) =
+                (c[1].parse().ok()?, c[3].parse().ok()?, c[5].parse().ok()?);
+            let days = days_from_civil(y, m, d);
+            // 存在しない日付（2月30日など）は連番にしない
+            return (c[2] == c[4] && civil_from_days(days) == (y, m, d)).then(|| Term::Day {
+                sep: sep_of(&c[2]),
+                days,
+            });
+        }
+        if let Some(c) = self.month.captures(cell) {
+            let (y, m): (i64, i64) = (c[1].parse().ok()?, c[3].parse().ok()?);
+            return (1..=12).contains(&m).then(|| Term::Month {
+                sep: sep_of(&c[2]),
+                months: y * 12 + m - 1,
+            });
+        }
+        let (prefix, digits) = if self.integer.is_match(cell) {
+            ("", cell)
+        } else {
+            let c = self.numbered.captures(cell)?;
+            (c.get(1)?.as_str(), c.get(2)?.as_str())
+        };
+        let unsigned = digits.trim_start_matches('-');
+        let width = if unsigned.len() > 1 && unsigned.starts_with('0') {
+            digits.len()
+        } else {
+            0
+        };
+        Some(Term::Number {
+            prefix: prefix.to_string(),
+            value: digits.parse().ok()?,
+            width,
+        })
+    }
+}
+
+/// 先頭から続く値から連番の最後の項と増分を求める（規則が見つからなければ None）
+///
+/// 値が1つなら増分は 1。2つ以上なら隣り合う値の差がすべて等しいときだけその差を使う。
+fn detect_series(seeds: &[&str]) -> Option<(Term, i64)> {
+    let patterns = Patterns::new();
+    let terms: Vec<Term> = seeds
+        .iter()
+        .map(|s| patterns.parse(s))
+        .collect::<Option<_>>()?;
+    let mut last = terms.last()?.clone();
+    if !terms.iter().all(|t| t.same_kind(&last)) {
+        return None;
+    }
+    // `09, 11` のように 0 埋めの桁数が途中で見えなくなっても、最も長い桁数で埋める
+    if let Term::Number { width, .. } = &mut last {
+        *width = terms
+            .iter()
+            .map(|t| match t {
+                Term::Number { width, .. } => *width,
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+    }
+    let steps: Vec<i64> = terms
+        .windows(2)
+        .map(|w| w[1].ordinal() - w[0].ordinal())
+        .collect();
+    match steps.first() {
+        None => Some((last, 1)),
+        Some(&step) if step != 0 && steps.iter().all(|&s| s == step) => Some((last, step)),
+        Some(_) => None,
+    }
+}
+
+/// 列 `col` の `start_row` から `end_row` まで（両端を含む）を埋める
+///
+/// `CopyDown` は先頭のセルの値を残りの行へコピーする。`Series` は先頭から続く空でない
+/// セルを手本に、その後ろの行を連番で埋める（整数・`001` のような 0 埋めの整数・
+/// `YYYY-MM-DD` / `YYYY-MM` の日付・`item-1` のような文字列+数値）。連番の規則が
+/// 見つからない場合は手本の最後の値を下へコピーし（手本のセルは変えない）、警告を返す。
+/// 連番が表せる範囲（`i64`・前に文字列が付く数は 0 以上・0〜9999 年）を超える場合はエラー。
+/// 範囲内の列数が足りない行は先に空のセルで埋める。集計行は埋めない。
+pub fn fill_cells(
+    table: &mut MarkdownTable,
+    col: usize,
+    start_row: usize,
+    end_row: usize,
+    mode: FillMode,
+) -> Result<Option<String>, String> {
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    if start_row > end_row {
+        return Err(format!(
+            "開始行が終了行より後ろです: {}〜{}",
+            start_row, end_row
+        ));
+    }
+    if end_row >= data_rows {
+        return Err(format!("行が範囲外です: {}（{} 行）", end_row, data_rows));
+    }
+    if col >= table.headers.len() {
+        return Err(format!("列番号が範囲外です: {}", col));
+    }
+    let width = table.headers.len();
+    for row in &mut table.rows[start_row..=end_row] {
+        if row.len() < width {
+            row.resize(width, String::new());
+        }
+    }
+    let cells: Vec<String> = (start_row..=end_row)
+        .map(|ri| get_cell(table, ri, col).unwrap_or("").trim().to_string())
+        .collect();
+    if cells[0].is_empty() {
+        return Err(format!("先頭のセル（{} 行目）が空です", start_row));
+    }
+    // 手本にする値の数（CopyDown では先頭の1つだけ）
+    let seed_count = match mode {
+        FillMode::CopyDown => 1,
+        FillMode::Series => cells.iter().take_while(|c| !c.is_empty()).count(),
+    };
+    let rest = start_row + seed_count..=end_row;
+    let copy_down = |table: &mut MarkdownTable| -> Result<(), String> {
+        for ri in rest.clone() {
+            set_cell(table, ri, col, cells[seed_count - 1].clone())?;
+        }
+        Ok(())
+    };
+    if mode == FillMode::CopyDown {
+        copy_down(table)?;
+        return Ok(None);
+    }
+    let seeds: Vec<&str> = cells[..seed_count].iter().map(String::as_str).collect();
+    let Some((last, step)) = detect_series(&seeds) else {
+        copy_down(table)?;
+        return Ok(Some(format!(
+            "連番の規則が見つからないため、最後の値をコピーしました: {}",
+            seeds.join(", ")
+        )));
+    };
+    let values = rest
+        .clone()
+        .enumerate()
+        .map(|(k, ri)| {
+            (k as i64 + 1)
+                .checked_mul(step)
+                .and_then(|offset| last.ordinal().checked_add(offset))
+                .and_then(|ordinal| last.render(ordinal))
+                .ok_or_else(|| format!("連番が表せる範囲を超えました（{} 行目）", ri))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (ri, value) in rest.zip(values) {
+        set_cell(table, ri, col, value)?;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown;
+
+    fn column(seeds: &[&str], blanks: usize) -> MarkdownTable {
+        let mut md = String::from("| Value | Note |\n| --- | --- |\n");
+        for seed in seeds {
+            md.push_str(&format!("| {} | x |\n", seed));
+        }
+        for _ in 0..blanks {
+            md.push_str("| |\n");
+        }
+        parse_markdown(&md).tables.remove(0)
+    }
+
+    fn filled(seeds: &[&str], blanks: usize, mode: FillMode) -> (Vec<String>, Option<String>) {
+        let mut table = column(seeds, blanks);
+        let end = seeds.len() + blanks - 1;
+        let warning = fill_cells(&mut table, 0, 0, end, mode).unwrap();
+        let values = table.rows.iter().map(|r| r[0].clone()).collect();
+        (values, warning)
+    }
+
+    #[test]
+    fn test_fill_cells_series() {
+        let series = |seeds: &[&str], blanks| filled(seeds, blanks, FillMode::Series).0;
+        assert_eq!(series(&["1"], 3), vec!["1", "2", "3", "4"]);
+        assert_eq!(series(&["10", "8"], 2), vec!["10", "8", "6", "4"]);
+        assert_eq!(series(&["008"], 3), vec!["008", "009", "010", "011"]);
+        assert_eq!(series(&["item-1"], 2), vec!["item-1", "item-2", "item-3"]);
+        assert_eq!(series(&["A-09", "A-11"], 1), vec!["A-09", "A-11", "A-13"]);
+        assert_eq!(
+            series(&["2024-02-28"], 2),
+            vec!["2024-02-28", "2024-02-29", "2024-03-01"]
+        );
+        assert_eq!(
+            series(&["2024/01/01", "2024/01/08"], 1),
+            vec!["2024/01/01", "2024/01/08", "2024/01/15"]
+        );
+        assert_eq!(
+            series(&["2024-11"], 2),
+            vec!["2024-11", "2024-12", "2025-01"]
+        );
+
+        // 短い行は列数まで空のセルで埋めてから書き込む
+        let mut table = column(&["1"], 2);
+        fill_cells(&mut table, 1, 0, 2, FillMode::CopyDown).unwrap();
+        assert!(table.rows.iter().all(|r| r.len() == 2 && r[1] == "x"));
+    }
+
+    #[test]
+    fn test_fill_cells_copy_down_and_fallback() {
+        let (values, warning) = filled(&["5"], 2, FillMode::CopyDown);
+        assert_eq!(values, vec!["5", "5", "5"]);
+        assert!(warning.is_none());
+
+        // 規則が見つからなければ最後の値をコピーし、警告を返す
+        let (values, warning) = filled(&["apple"], 2, FillMode::Series);
+        assert_eq!(values, vec!["apple", "apple", "apple"]);
+        assert!(warning.unwrap().contains("apple"));
+        let (values, warning) = filled(&["1", "2", "4"], 1, FillMode::Series);
+        assert_eq!(values, vec!["1", "2", "4", "4"]);
+        assert!(warning.is_some());
+        assert!(filled(&["2024-02-30"], 1, FillMode::Series).1.is_some());
+
+        let mut table = column(&["1"], 2);
+        assert!(fill_cells(&mut table, 0, 2, 1, FillMode::Series).is_err());
+        assert!(fill_cells(&mut table, 0, 0, 9, FillMode::Series).is_err());
+        assert!(fill_cells(&mut table, 0, 1, 2, FillMode::Series).is_err());
+
+        // 表せない値になる連番はセルを変えずにエラーにする
+        let mut table = column(&["item-2", "item-1"], 2);
+        assert!(fill_cells(&mut table, 0, 0, 3, FillMode::Series).is_err());
+        assert_eq!(table.rows[2][0], "");
+        let mut table = column(&["0", "999999999999999999"], 9);
+        assert!(fill_cells(&mut table, 0, 0, 10, FillMode::Series).is_err());
+        let mut table = column(&["9999-12"], 1);
+        assert!(fill_cells(&mut table, 0, 0, 1, FillMode::Series).is_err());
+    }
+}
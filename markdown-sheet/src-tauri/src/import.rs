@@ -0,0 +1,186 @@
+use crate::file_io::decode_bytes;
+use crate::markdown_parser::{serialize_table, MarkdownTable};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 区切り文字で区切られたテキストを行・セルに分ける
+///
+/// 行は `\r\n` または `\n` で区切る。`"` で始まるセルは引用符の内側の区切り文字・改行を
+/// そのまま値に含め、`""` を `"` として読む。末尾の改行は無視する。
+pub fn parse_delimited(text: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut chars = text.chars().peekable();
+    let mut at_cell_start = true;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if at_cell_start => {
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            cell.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => cell.push(c),
+                        None => return Err("引用符（\"）が閉じられていません".to_string()),
+                    }
+                }
+                at_cell_start = false;
+            }
+            c if c == delimiter => {
+                row.push(std::mem::take(&mut cell));
+                at_cell_start = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+                at_cell_start = true;
+            }
+            c => {
+                cell.push(c);
+                at_cell_start = false;
+            }
+        }
+    }
+    if !at_cell_start || !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// セルの値を Markdown テーブルのセルとして書ける形にする
+///
+/// 改行は `<br>` に、列の区切りと誤解されるパイプは `&#124;` に置き換える。
+fn to_markdown_cell(value: &str) -> String {
+    value
+        .trim()
+        .replace("\r\n", "\n")
+        .replace('\n', "<br>")
+        .replace('|', "&#124;")
+}
+
+/// Excel などからクリップボード経由で貼り付けた TSV をテーブルにする（1行目をヘッダーとする）
+pub fn excel_paste_to_table(text: &str) -> Result<MarkdownTable, String> {
+    delimited_to_table(text, '\t')
+}
+
+/// 区切り文字で区切られたテキストをテーブルにする（1行目をヘッダーとする）
+///
+/// 列数が足りない行は空のセルで埋める。位置情報（`start_line` など）は 0 のまま返す。
+pub fn delimited_to_table(text: &str, delimiter: char) -> Result<MarkdownTable, String> {
+    let rows = parse_delimited(text, delimiter)?;
+    let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut rows = rows.into_iter().map(|row| {
+        let mut cells: Vec<String> = row.iter().map(|c| to_markdown_cell(c)).collect();
+        cells.resize(cols, String::new());
+        cells
+    });
+    let headers = rows
+        .next()
+        .filter(|_| cols > 0)
+        .ok_or_else(|| "テキストが空です".to_string())?;
+    Ok(MarkdownTable {
+        heading: None,
+        alignments: vec!["none".to_string(); cols],
+        headers,
+        rows: rows.collect(),
+        start_line: 0,
+        end_line: 0,
+        has_summary_row: false,
+        verbatim: Vec::new(),
+        cell_annotations: Vec::new(),
+    })
+}
+
+/// フォルダ内のすべての CSV ファイルを、ファイル名を見出しにしたテーブルとして1つの Markdown にまとめる
+///
+/// ファイルは名前順に並べ、文字コードは自動判定する。読み込めない・解釈できないファイルは
+/// 飛ばして警告（`ファイル名: 理由`）として返す。取り込めるファイルが1つもなければエラーにする。
+pub fn csv_directory_to_markdown(dir: &Path) -> Result<(String, Vec<String>), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("ディレクトリを読み込めません: {}", e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        })
+        .collect();
+    paths.sort();
+    let mut sections = Vec::new();
+    let mut warnings = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let table = fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| delimited_to_table(&decode_bytes(&bytes).0, ','));
+        match table {
+            Ok(table) => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                sections.push(format!("## {}\n\n{}", stem, serialize_table(&table)));
+            }
+            Err(e) => warnings.push(format!("{}: {}", name, e)),
+        }
+    }
+    if sections.is_empty() {
+        return Err("取り込める CSV ファイルがありません".to_string());
+    }
+    Ok((sections.join("\n"), warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown;
+
+    #[test]
+    fn test_excel_paste_with_quoted_multiline_cell() {
+        let text = "品名\tメモ\t数量\r\nりんご\t\"1行目\r\n2行目\"\t3\r\n\"He said \"\"hi\"\"\"\ta|b\r\nみかん\t\t1,200\r\n";
+        let table = excel_paste_to_table(text).unwrap();
+        assert_eq!(table.headers, vec!["品名", "メモ", "数量"]);
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.rows[0], vec!["りんご", "1行目<br>2行目", "3"]);
+        assert_eq!(table.rows[1], vec!["He said \"hi\"", "a&#124;b", ""]);
+        assert_eq!(table.rows[2], vec!["みかん", "", "1,200"]);
+
+        let reparsed = parse_markdown(&serialize_table(&table));
+        assert_eq!(reparsed.tables[0].rows, table.rows);
+
+        assert!(excel_paste_to_table("").is_err());
+        assert!(excel_paste_to_table("a\t\"open\n").is_err());
+    }
+
+    #[test]
+    fn test_csv_directory_to_markdown() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("b_sales.csv"),
+            "月,売上\r\n1月,\"1,200\"\r\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("a_items.csv"),
+            "id,name\n1,apple\n2,\"x|y\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("broken.csv"), "a,\"open\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not,csv\n").unwrap();
+
+        let (content, warnings) = csv_directory_to_markdown(dir.path()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("broken.csv: "));
+        let doc = parse_markdown(&content);
+        assert_eq!(doc.tables.len(), 2);
+        assert_eq!(doc.tables[0].heading.as_deref(), Some("a_items"));
+        assert_eq!(doc.tables[0].rows[1], vec!["2", "x&#124;y"]);
+        assert_eq!(doc.tables[1].heading.as_deref(), Some("b_sales"));
+        assert_eq!(doc.tables[1].rows, vec![vec!["1月", "1,200"]]);
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(csv_directory_to_markdown(empty.path()).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::file_io::read_document;
+use crate::markdown_parser::{get_cell, parse_number, table_ids, Aggregation};
+use crate::workspace;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// テーブル一覧の進捗を通知するイベント名
+pub const INVENTORY_PROGRESS_EVENT: &str = "table-inventory-progress";
+/// 重複とみなす行の一致率の下限
+const DUPLICATE_THRESHOLD: f64 = 0.9;
+/// 同時に読み込むファイル数の上限
+const MAX_THREADS: usize = 4;
+/// 進捗を通知する間隔（ファイル数）。これより少ないワークスペースでは通知しない
+const PROGRESS_INTERVAL: usize = 100;
+
+/// テーブル一覧の1件（行データは含めない）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableSummary {
+    pub path: String,
+    /// `table_ids` と同じ安定 ID
+    pub table_id: String,
+    pub heading: Option<String>,
+    pub headers: Vec<String>,
+    pub row_count: usize,
+    pub col_count: usize,
+    pub start_line: usize,
+}
+
+/// 読み込めなかったファイル
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// ワークスペース内のテーブル一覧
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TableInventory {
+    pub tables: Vec<TableSummary>,
+    pub errors: Vec<FileError>,
+}
+
+/// テーブル一覧の進捗
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// フォルダ以下のすべてのテーブルの概要を集める
+///
+/// 除外パターンに一致するファイルは対象外。ファイルは最大 `MAX_THREADS` 並列で読み込み、
+/// 結果はファイルツリーと同じ順に並べる。`header` を指定すると、その列名（大文字・小文字は
+/// 区別しない）を持つテーブルだけを返す。ファイルが多いときは `on_progress` で進捗を通知する。
+pub fn list_all_tables(
+    dir: &Path,
+    header: Option<&str>,
+    on_progress: impl Fn(InventoryProgress) + Sync,
+) -> Result<TableInventory, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let files = workspace::markdown_files(dir);
+    let total = files.len();
+    let done = AtomicUsize::new(0);
+    let header = header.map(str::trim);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(MAX_THREADS)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let per_file: Vec<Result<Vec<TableSummary>, FileError>> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|(path, _)| {
+                let result = summarize_file(path, header);
+                let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if total >= PROGRESS_INTERVAL && (n.is_multiple_of(PROGRESS_INTERVAL) || n == total)
+                {
+                    on_progress(InventoryProgress { done: n, total });
+                }
+                result
+            })
+            .collect()
+    });
+    let mut inventory = TableInventory::default();
+    for result in per_file {
+        match result {
+            Ok(tables) => inventory.tables.extend(tables),
+            Err(e) => inventory.errors.push(e),
+        }
+    }
+    Ok(inventory)
+}
+
+fn summarize_file(path: &Path, header: Option<&str>) -> Result<Vec<TableSummary>, FileError> {
+    let doc = read_document(path).map_err(|error| FileError {
+        path: path.to_string_lossy().to_string(),
+        error,
+    })?;
+    let ids = table_ids(&doc.tables);
+    Ok(doc
+        .tables
+        .into_iter()
+        .zip(ids)
+        .filter(|(t, _)| header.is_none_or(|h| t.column_index_of_ci(h).is_some()))
+        .map(|(t, table_id)| TableSummary {
+            path: path.to_string_lossy().to_string(),
+            table_id,
+            heading: t.heading,
+            row_count: t.rows.len(),
+            col_count: t.headers.len(),
+            start_line: t.start_line,
+            headers: t.headers,
+        })
+        .collect())
+}
+
+/// フォルダ以下のすべてのテーブルから指定した名前の列の数値を集めて集計する
+///
+/// 除外パターンに一致するファイルと読み込めないファイルは対象外。数値として読めないセルと
+/// 集計行は飛ばし、Count は数値セルの数を返す。数値セルが1つもなければ Mean はエラーにする。
+pub fn aggregate_column_across_files(
+    dir: &Path,
+    column_name: &str,
+    agg: Aggregation,
+) -> Result<f64, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let column_name = column_name.trim();
+    let values: Vec<f64> = workspace::markdown_files(dir)
+        .iter()
+        .filter_map(|(path, _)| read_document(path).ok())
+        .flat_map(|doc| {
+            let mut values = Vec::new();
+            for table in &doc.tables {
+                let Some(col) = table.column_index_of(column_name) else {
+                    continue;
+                };
+                let data_rows = table
+                    .rows
+                    .len()
+                    .saturating_sub(usize::from(table.has_summary_row));
+                values.extend(
+                    (0..data_rows).filter_map(|ri| get_cell(table, ri, col).and_then(parse_number)),
+                );
+            }
+            values
+        })
+        .collect();
+    match agg {
+        Aggregation::Sum => Ok(values.iter().sum()),
+        Aggregation::Count => Ok(values.len() as f64),
+        Aggregation::Mean if values.is_empty() => {
+            Err(format!("列「{}」に数値のセルがありません", column_name))
+        }
+        Aggregation::Mean => Ok(values.iter().sum::<f64>() / values.len() as f64),
+    }
+}
+
+/// 別の場所にコピーされたとみなせるテーブルの組
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateTableReport {
+    /// テーブルのあるファイル（2つ以上。同じファイル内の重複では同じパスが並ぶ）
+    pub files: Vec<String>,
+    /// `files` と同じ並びのテーブル ID
+    pub table_ids: Vec<String>,
+    /// グループ内で重複と判定した組の行の一致率のうち最も低いもの（0.0〜1.0）
+    pub similarity: f64,
+}
+
+/// 重複の判定に使うテーブルの中身（セルは前後の空白を除き、列数に揃える）
+struct TableFingerprint {
+    path: String,
+    table_id: String,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// 2つのテーブルの行の一致率（一致する行の数を多い方の行数で割る。行の順序は問わない）
+fn row_similarity(a: &[Vec<String>], b: &[Vec<String>]) -> f64 {
+    let mut counts: HashMap<&Vec<String>, usize> = HashMap::new();
+    for row in a {
+        *counts.entry(row).or_default() += 1;
+    }
+    let mut common = 0;
+    for row in b {
+        if let Some(n) = counts.get_mut(row).filter(|n| **n > 0) {
+            *n -= 1;
+            common += 1;
+        }
+    }
+    common as f64 / a.len().max(b.len()) as f64
+}
+
+fn find_root(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[i] = root;
+    root
+}
+
+/// フォルダ以下で、ヘッダーが同じで行の 90% 以上が一致するテーブルの組を探す
+///
+/// ヘッダーのハッシュでまとめてから、同じまとまりの中だけで行を比べる。重複と判定した組が
+/// つながるテーブルは1つのグループとして返す。データ行のないテーブル・読み込めないファイル・
+/// 除外パターンに一致するファイルは対象外。一致率の高い順に並べる。
+pub fn find_duplicate_tables(dir: &Path) -> Result<Vec<DuplicateTableReport>, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let tables: Vec<TableFingerprint> = workspace::markdown_files(dir)
+        .par_iter()
+        .filter_map(|(path, _)| read_document(path).ok().map(|doc| (path, doc)))
+        .flat_map_iter(|(path, doc)| {
+            let ids = table_ids(&doc.tables);
+            doc.tables
+                .into_iter()
+                .zip(ids)
+                .filter(|(t, _)| !t.rows.is_empty())
+                .map(move |(t, table_id)| {
+                    let width = t.headers.len();
+                    let rows = t
+                        .rows
+                        .iter()
+                        .map(|row| {
+                            (0..width)
+                                .map(|ci| row.get(ci).map_or("", |c| c.trim()).to_string())
+                                .collect()
+                        })
+                        .collect();
+                    TableFingerprint {
+                        path: path.to_string_lossy().to_string(),
+                        table_id,
+                        headers: t.headers.iter().map(|h| h.trim().to_string()).collect(),
+                        rows,
+                    }
+                })
+        })
+        .collect();
+
+    let mut by_headers: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, table) in tables.iter().enumerate() {
+        let mut hasher = DefaultHasher::new();
+        table.headers.hash(&mut hasher);
+        by_headers.entry(hasher.finish()).or_default().push(i);
+    }
+
+    let mut parent: Vec<usize> = (0..tables.len()).collect();
+    let mut lowest: HashMap<usize, f64> = HashMap::new();
+    let mut edges = Vec::new();
+    for group in by_headers.values().filter(|g| g.len() > 1) {
+        for (n, &a) in group.iter().enumerate() {
+            for &b in &group[n + 1..] {
+                // ハッシュの衝突に備えてヘッダーそのものも比べる
+                if tables[a].headers != tables[b].headers {
+                    continue;
+                }
+                let similarity = row_similarity(&tables[a].rows, &tables[b].rows);
+                if similarity >= DUPLICATE_THRESHOLD {
+                    edges.push((a, b, similarity));
+                }
+            }
+        }
+    }
+    for &(a, b, _) in &edges {
+        let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+        parent[rb] = ra;
+    }
+    for &(a, _, similarity) in &edges {
+        let root = find_root(&mut parent, a);
+        let entry = lowest.entry(root).or_insert(similarity);
+        *entry = entry.min(similarity);
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..tables.len() {
+        let root = find_root(&mut parent, i);
+        if lowest.contains_key(&root) {
+            members.entry(root).or_default().push(i);
+        }
+    }
+    let mut reports: Vec<DuplicateTableReport> = members
+        .into_iter()
+        .map(|(root, group)| DuplicateTableReport {
+            files: group.iter().map(|&i| tables[i].path.clone()).collect(),
+            table_ids: group.iter().map(|&i| tables[i].table_id.clone()).collect(),
+            similarity: lowest[&root],
+        })
+        .collect();
+    reports.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.files.cmp(&b.files))
+    });
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_list_all_tables_with_filter_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("tasks")).unwrap();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::write(
+            root.join("tasks/a.md"),
+            "# Tasks\n\n| Task | Status |\n| --- | --- |\n| x | done |\n| y | open |\n\n## Notes\n\n| Note |\n| --- |\n",
+        )
+        .unwrap();
+        fs::write(root.join("archive/old.md"), "| Status |\n| --- |\n| x |\n").unwrap();
+        workspace::update_settings(root, &serde_json::json!({ "ignore": ["archive"] })).unwrap();
+        // 読み込めないファイル（リンク切れのシンボリックリンク）
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("missing"), root.join("broken.md")).unwrap();
+
+        let inventory = list_all_tables(root, None, |_| {}).unwrap();
+        assert_eq!(inventory.tables.len(), 2);
+        let first = &inventory.tables[0];
+        assert!(first.path.ends_with("a.md"));
+        assert_eq!(first.table_id, "tasks");
+        assert_eq!((first.row_count, first.col_count), (2, 2));
+        assert_eq!(first.start_line, 2);
+        assert_eq!(inventory.tables[1].table_id, "notes");
+        #[cfg(unix)]
+        assert!(inventory.errors[0].path.ends_with("broken.md"));
+
+        let filtered = list_all_tables(root, Some("status"), |_| {}).unwrap();
+        assert_eq!(filtered.tables.len(), 1);
+        assert_eq!(filtered.tables[0].headers, vec!["Task", "Status"]);
+        assert!(list_all_tables(&root.join("missing"), None, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_column_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sprints")).unwrap();
+        fs::write(
+            root.join("sprints/1.md"),
+            "| Story | Points |\n| --- | --- |\n| a | 3 |\n| b | 5 |\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("sprints/2.md"),
+            "| Story | Points |\n| --- | --- |\n| c | 1,000 |\n| d | TBD |\n\n| Other |\n| --- |\n| 99 |\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("3.md"),
+            "| Points | Story |\n| --- | --- |\n| 2 | e |\n",
+        )
+        .unwrap();
+
+        let agg = |a| aggregate_column_across_files(root, "Points", a).unwrap();
+        assert_eq!(agg(Aggregation::Sum), 1010.0);
+        assert_eq!(agg(Aggregation::Count), 4.0);
+        assert_eq!(agg(Aggregation::Mean), 252.5);
+        assert!(aggregate_column_across_files(root, "Missing", Aggregation::Mean).is_err());
+        assert_eq!(
+            aggregate_column_across_files(root, "Missing", Aggregation::Sum).unwrap(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_find_duplicate_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        let rows: String = (1..=10)
+            .map(|i| format!("| item{} | {} |\n", i, i))
+            .collect();
+        let table = format!("| Name | Qty |\n| --- | --- |\n{}", rows);
+        fs::write(root.join("a.md"), &table).unwrap();
+        // 10 行中 9 行が同じ（1行だけ書き換えた）コピー
+        fs::write(
+            root.join("notes/b.md"),
+            format!(
+                "# Copy\n\n{}",
+                table.replace("| item3 | 3 |", "| item3 | 30 |")
+            ),
+        )
+        .unwrap();
+        // 行の 80% しか一致しない・ヘッダーが違うテーブルは重複としない
+        let diverged = table
+            .replace("| item1 | 1 |", "| x | 1 |")
+            .replace("| item2 | 2 |", "| y | 2 |");
+        fs::write(root.join("c.md"), diverged).unwrap();
+        fs::write(root.join("d.md"), table.replace("| Name |", "| Title |")).unwrap();
+
+        let reports = find_duplicate_tables(root).unwrap();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert_eq!(report.files.len(), 2);
+        assert!(report.files.iter().any(|f| f.ends_with("a.md")));
+        assert!(report.files.iter().any(|f| f.ends_with("b.md")));
+        assert!(report.table_ids.contains(&"copy".to_string()));
+        assert!((report.similarity - 0.9).abs() < 1e-9);
+
+        assert!(find_duplicate_tables(&root.join("missing")).is_err());
+    }
+}
@@ -1,19 +1,293 @@
+pub mod autosave;
+pub mod collation;
 pub mod commands;
+pub mod content_search;
+pub mod deep_link;
+pub mod dir_watch;
+pub mod doc_cache;
+pub mod edit_lock;
+pub mod export;
+pub mod file_io;
+pub mod file_lock;
+pub mod file_search;
+pub mod fill;
+pub mod import;
+pub mod inventory;
+pub mod links;
 pub mod markdown_parser;
+pub mod recent_files;
+pub mod recovery;
+pub mod replace;
+pub mod row_filter;
+pub mod search_index;
+pub mod startup;
+pub mod workspace;
 
-use commands::{get_file_tree, get_initial_file, read_markdown_file, save_markdown_file};
+use autosave::{Autosave, AUTOSAVED_EVENT, AUTOSAVE_FAILED_EVENT};
+use collation::Collators;
+use commands::{
+    acquire_edit_lock, acquire_file_lock, add_recent_file, add_workspace_root,
+    aggregate_column_across_files, apply_recovery, apply_replace_plan, build_search_index,
+    check_cell_links, cleanup_empty_tables, cleanup_table, clear_recent_files, column_widths,
+    concatenate_files, convert_table_to_definition_list, create_table_from_kv_pairs,
+    dedupe_consecutive_rows, deduplicate_rows, delete_column, delete_rows, demote_header,
+    disable_autosave, discard_recovery, enable_autosave, export_all_tables_csv,
+    export_directory_tables_json, export_table_html, export_table_pandoc, fill_cells, filter_rows,
+    find_duplicate_tables_in_directory, find_files_with_column, format_document, format_table,
+    fuzzy_deduplicate, get_all_tags, get_file_tree, get_file_tree_parallel, get_index_status,
+    get_pinned_files, get_recent_files, get_recovery_candidates, get_table_heading_path,
+    get_workspace_forest, get_workspace_settings, import_csv_directory, index_directory,
+    insert_column, insert_new_table, insert_row, invalidate_document_cache, join_columns,
+    list_all_tables, list_workspace_roots, merge_tables, move_column, normalize_case,
+    paste_as_table, pin_file, pivot, project_table_columns, promote_row_to_header,
+    queue_autosave_edits, read_markdown_file, recent_files, regex_replace_table,
+    regex_search_table, release_edit_lock, release_file_lock, remove_recent_file,
+    remove_workspace_root, rename_column, rename_markdown_file, reorder_columns, reorder_pins,
+    repair_table, replace_in_folder, save_markdown_file, search_content, search_file_names,
+    search_replace_in_directory, sort_table_rows, sort_table_rows_multi, split_column,
+    split_file_at_heading, split_table, split_table_by_column, table_context, table_count,
+    table_to_key_value_map, take_deep_links, take_startup_files, transpose_table, trim_table,
+    truncate_table_cells, unpin_file, unwatch_directory, update_workspace_settings,
+    validate_markdown_file, watch_directory,
+};
+use deep_link::DeepLinks;
+use dir_watch::DirWatchers;
+use doc_cache::DocumentCache;
+use edit_lock::EditLocks;
+use file_lock::FileLocks;
+use file_search::FileSearch;
+use recent_files::RecentFiles;
+use recovery::RecoveryJournal;
+use replace::ReplacePlans;
+use search_index::SearchIndex;
+use startup::StartupFiles;
+use tauri::{DragDropEvent, Emitter, Manager, RunEvent, WindowEvent};
+use tauri_plugin_deep_link::DeepLinkExt;
+use workspace::Workspace;
+
+/// ディープリンクを検証してフロントエンドに通知する
+///
+/// フロントエンドの準備前・ワークスペースが開かれる前に届いたリンクは保持し、`take_deep_links` で渡す。
+/// 開けなかったリンクは警告のログも残す。
+fn handle_deep_link(app: &tauri::AppHandle, url: &str) {
+    let workspace = app.state::<Workspace>();
+    if !app.state::<DeepLinks>().receive(url, &workspace) {
+        return;
+    }
+    let _ = match deep_link::resolve_deep_link(url, &workspace) {
+        Ok(payload) => app.emit(deep_link::DEEP_LINK_EVENT, payload),
+        Err(message) => {
+            log::warn!("ディープリンクを拒否しました: {}: {}", url, message);
+            app.emit(
+                deep_link::DEEP_LINK_REJECTED_EVENT,
+                deep_link::RejectedDeepLink {
+                    url: url.to_string(),
+                    message,
+                },
+            )
+        }
+    };
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(FileLocks::default())
+        .manage(EditLocks::default())
+        .manage(Workspace::default())
+        .manage(DocumentCache::default())
+        .manage(DirWatchers::default())
+        .manage(FileSearch::default())
+        .manage(SearchIndex::default())
+        .manage(ReplacePlans::default())
+        .manage(Collators::default())
+        .manage(StartupFiles::from_args(std::env::args()))
+        .manage(DeepLinks::default())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(RecentFiles::load(data_dir.join("recent_files.json")));
+
+            let journal = RecoveryJournal::new(data_dir.join("recovery"));
+            let autosave = Autosave::with_journal(journal.clone());
+            app.manage(journal);
+            let handle = app.handle().clone();
+            autosave.spawn_timer(move |result| {
+                let _ = match result {
+                    Ok(event) => handle.emit(AUTOSAVED_EVENT, event),
+                    Err(failure) => handle.emit(AUTOSAVE_FAILED_EVENT, failure),
+                };
+            });
+            app.manage(autosave);
+
+            // markdown-sheet://open?path=...&table=... で開かれたファイルとテーブルを通知する
+            #[cfg(any(windows, target_os = "linux"))]
+            app.deep_link().register_all()?;
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    handle_deep_link(&handle, url.as_str());
+                }
+            });
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    handle_deep_link(app.handle(), url.as_str());
+                }
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            match event {
+                // ウィンドウが閉じたら、そのウィンドウが保持していたロックを解放する
+                // 自動保存待ちの編集は閉じる前に書き出す
+                WindowEvent::Destroyed => {
+                    window.state::<FileLocks>().release_all(window.label());
+                    window.state::<Autosave>().flush_all();
+                    window.state::<EditLocks>().release_all(window.label());
+                }
+                // ドロップされた Markdown はその場で読み込んで渡し、フォルダはワークスペースとして開かせる
+                WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                    let outcome = startup::classify_drop(paths);
+                    let label = window.label();
+                    if !outcome.documents.is_empty() {
+                        let _ =
+                            window.emit_to(label, startup::FILES_DROPPED_EVENT, outcome.documents);
+                    }
+                    for folder in outcome.folders {
+                        let _ = window.emit_to(label, startup::FOLDER_DROPPED_EVENT, folder);
+                    }
+                    if !outcome.unsupported.is_empty() {
+                        let _ = window.emit_to(
+                            label,
+                            startup::UNSUPPORTED_DROP_EVENT,
+                            outcome.unsupported,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_file_tree,
-            get_initial_file,
             read_markdown_file,
             save_markdown_file,
+            acquire_file_lock,
+            release_file_lock,
+            repair_table,
+            export_table_html,
+            format_table,
+            add_recent_file,
+            get_recent_files,
+            remove_recent_file,
+            clear_recent_files,
+            find_files_with_column,
+            format_document,
+            pin_file,
+            unpin_file,
+            get_pinned_files,
+            reorder_pins,
+            cleanup_empty_tables,
+            get_workspace_settings,
+            update_workspace_settings,
+            export_directory_tables_json,
+            get_workspace_forest,
+            add_workspace_root,
+            remove_workspace_root,
+            list_workspace_roots,
+            take_startup_files,
+            project_table_columns,
+            index_directory,
+            fuzzy_deduplicate,
+            normalize_case,
+            invalidate_document_cache,
+            recent_files,
+            trim_table,
+            enable_autosave,
+            disable_autosave,
+            queue_autosave_edits,
+            convert_table_to_definition_list,
+            get_recovery_candidates,
+            apply_recovery,
+            discard_recovery,
+            dedupe_consecutive_rows,
+            split_table_by_column,
+            regex_search_table,
+            acquire_edit_lock,
+            release_edit_lock,
+            concatenate_files,
+            regex_replace_table,
+            search_file_names,
+            split_file_at_heading,
+            pivot,
+            search_content,
+            build_search_index,
+            get_index_status,
+            table_context,
+            replace_in_folder,
+            apply_replace_plan,
+            insert_row,
+            list_all_tables,
+            rename_column,
+            sort_table_rows,
+            paste_as_table,
+            sort_table_rows_multi,
+            table_count,
+            insert_new_table,
+            filter_rows,
+            get_all_tags,
+            export_all_tables_csv,
+            delete_rows,
+            import_csv_directory,
+            aggregate_column_across_files,
+            insert_column,
+            delete_column,
+            rename_markdown_file,
+            move_column,
+            reorder_columns,
+            table_to_key_value_map,
+            create_table_from_kv_pairs,
+            demote_header,
+            promote_row_to_header,
+            transpose_table,
+            truncate_table_cells,
+            merge_tables,
+            column_widths,
+            get_file_tree_parallel,
+            split_table,
+            search_replace_in_directory,
+            deduplicate_rows,
+            watch_directory,
+            unwatch_directory,
+            join_columns,
+            cleanup_table,
+            find_duplicate_tables_in_directory,
+            fill_cells,
+            get_table_heading_path,
+            check_cell_links,
+            split_column,
+            export_table_pandoc,
+            validate_markdown_file,
+            take_deep_links,
         ])
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
         .expect("error while running tauri application");
+
+    app.run(|app_handle, event| {
+        // macOS ではファイルの関連付けで開かれたファイルが Opened イベントで届く
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        if let RunEvent::Opened { urls } = &event {
+            let paths = urls.iter().filter_map(|url| url.to_file_path().ok());
+            let ready = app_handle.state::<StartupFiles>().receive(paths);
+            if !ready.is_empty() {
+                let _ = app_handle.emit(startup::OPEN_FILE_EVENT, ready);
+            }
+        }
+        // 終了時は自動保存待ちの編集を同期的に書き出す
+        if let RunEvent::Exit = event {
+            app_handle.state::<Autosave>().flush_all();
+            app_handle.state::<EditLocks>().release_everything();
+        }
+    });
 }
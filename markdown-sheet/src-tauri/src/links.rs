@@ -0,0 +1,337 @@
+use crate::file_io::{decode_bytes, encode_text, read_document, write_atomic};
+use crate::markdown_parser::fenced_lines;
+use crate::workspace;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 書き直したリンク先をパーセントエンコードするときに残さない文字
+const PATH_ESCAPE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'(')
+    .add(b')')
+    .add(b'%')
+    .add(b'#');
+
+/// インラインリンク・画像 `[text](target "title")`（2つ目のグループがリンク先）
+const INLINE_LINK: &str = r"(!?\[[^\]]*\]\()(<[^>\n]*>|[^()\s]+)";
+/// URL スキーム（`https:` や `mailto:` など）
+const SCHEME: &str = r"^[A-Za-z][A-Za-z0-9+.-]*:";
+
+/// `.` と `..` を文字列上で解決する（ファイルシステムには問い合わせない）
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 絶対パスにして `.` と `..` を解決する
+fn absolute(path: &Path) -> Result<PathBuf, String> {
+    std::path::absolute(path)
+        .map(|p| normalize(&p))
+        .map_err(|e| e.to_string())
+}
+
+/// `from_dir` から `to` への `/` 区切りの相対パス
+fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+/// ファイル名の変更・移動に合わせてリンク先を書き直す
+struct Rename<'a> {
+    old: &'a Path,
+    new: &'a Path,
+    /// インラインリンク・画像 `[text](target "title")` のリンク先
+    inline_link: Regex,
+    /// 参照リンクの定義 `[id]: target`
+    reference_def: Regex,
+    /// URL スキーム（`https:` や `mailto:` など）
+    scheme: Regex,
+}
+
+impl<'a> Rename<'a> {
+    fn new(old: &'a Path, new: &'a Path) -> Self {
+        Rename {
+            old,
+            new,
+            inline_link: Regex::new(INLINE_LINK).unwrap(),
+            reference_def: Regex::new(r"^(\s{0,3}\[[^\]]+\]:\s*)(<[^>\n]*>|\S+)").unwrap(),
+            scheme: Regex::new(SCHEME).unwrap(),
+        }
+    }
+
+    /// `from_dir` を基準に書かれたリンク先を、`to_dir` から見た正しいリンク先に直す
+    ///
+    /// URL・ページ内アンカー・ルートからのパスは対象外。変更がなければ None。
+    fn rewrite(&self, target: &str, from_dir: &Path, to_dir: &Path) -> Option<String> {
+        let (angle, raw) = match target.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            Some(inner) => (true, inner),
+            None => (false, target),
+        };
+        if raw.is_empty() || raw.starts_with(['#', '/']) || self.scheme.is_match(raw) {
+            return None;
+        }
+        let (path_part, fragment) = match raw.split_once('#') {
+            Some((p, f)) => (p, Some(f)),
+            None => (raw, None),
+        };
+        let decoded = percent_decode_str(path_part).decode_utf8().ok()?;
+        let resolved = normalize(&from_dir.join(decoded.as_ref()));
+        let moved = resolved == self.old;
+        if !moved && from_dir == to_dir {
+            return None;
+        }
+        let dest = if moved { self.new } else { resolved.as_path() };
+        let mut rel = relative_path(to_dir, dest);
+        if rel == decoded {
+            return None;
+        }
+        let mut angle = angle;
+        if decoded != path_part {
+            rel = utf8_percent_encode(&rel, PATH_ESCAPE).to_string();
+        } else if rel.contains(' ') {
+            angle = true;
+        }
+        if let Some(fragment) = fragment {
+            rel = format!("{}#{}", rel, fragment);
+        }
+        Some(if angle { format!("<{}>", rel) } else { rel })
+    }
+
+    /// 文書中のリンクを書き直した全文を返す（変更がなければ None）
+    ///
+    /// コードフェンスの内側は書き換えない。改行コードは元のまま残す。
+    fn rewrite_document(&self, content: &str, from_dir: &Path, to_dir: &Path) -> Option<String> {
+        let lines: Vec<String> = content.split('\n').map(String::from).collect();
+        let fenced = fenced_lines(&lines);
+        let mut changed = false;
+        let rewritten: Vec<String> = lines
+            .iter()
+            .zip(fenced)
+            .map(|(line, fenced)| {
+                if fenced {
+                    return line.clone();
+                }
+                let mut replace = |caps: &Captures| match self.rewrite(&caps[2], from_dir, to_dir) {
+                    Some(target) => {
+                        changed = true;
+                        format!("{}{}", &caps[1], target)
+                    }
+                    None => caps[0].to_string(),
+                };
+                let line = self
+                    .inline_link
+                    .replace_all(line, &mut replace)
+                    .into_owned();
+                self.reference_def.replace(&line, &mut replace).into_owned()
+            })
+            .collect();
+        changed.then(|| rewritten.join("\n"))
+    }
+}
+
+/// Markdown ファイルの名前を変更する（移動先のフォルダは既存のものに限る）
+///
+/// 変更先に同名のファイルがある場合は上書きせずにエラーにする。
+pub fn rename_markdown_file(old: &Path, new: &Path) -> Result<(), String> {
+    if !old.is_file() {
+        return Err(format!("ファイルが存在しません: {}", old.display()));
+    }
+    if new.exists() {
+        return Err(format!(
+            "変更先のファイルが既に存在します: {}",
+            new.display()
+        ));
+    }
+    fs::rename(old, new).map_err(|e| format!("名前を変更できません: {}", e))
+}
+
+/// `root` 以下の Markdown ファイルにある、`old` へのリンクを `new` へのリンクに書き直す
+///
+/// `old` から `new` へ名前を変更した後に呼ぶ。移動したファイル自身の相対リンクも、
+/// 新しい場所から見たパスに直す。除外パターンに一致するファイルは対象外。
+/// 書き換えたファイルのパスを返す。
+pub fn update_links_after_rename(
+    root: &Path,
+    old: &Path,
+    new: &Path,
+) -> Result<Vec<String>, String> {
+    let (old, new) = (absolute(old)?, absolute(new)?);
+    let rename = Rename::new(&old, &new);
+    let mut updated = Vec::new();
+    for (path, _) in workspace::markdown_files(root) {
+        let path = absolute(&path)?;
+        let to_dir = path.parent().unwrap_or(Path::new("/"));
+        let from_dir = if path == new {
+            old.parent().unwrap_or(Path::new("/"))
+        } else {
+            to_dir
+        };
+        let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (content, encoding, has_bom) = decode_bytes(&bytes);
+        let Some(rewritten) = rename.rewrite_document(&content, from_dir, to_dir) else {
+            continue;
+        };
+        let bytes = encode_text(&rewritten, encoding.name(), has_bom)?;
+        write_atomic(&path, &bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        updated.push(path.to_string_lossy().to_string());
+    }
+    Ok(updated)
+}
+
+/// リンク先のファイルが存在しないセル内のリンク
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokenLink {
+    pub table_index: usize,
+    pub row_index: usize,
+    pub col_index: usize,
+    /// セルに書かれたリンク先
+    pub target: String,
+    /// ファイルのフォルダを基準に解決したパス
+    pub resolved: String,
+}
+
+/// テーブルのセルにある相対リンクのうち、リンク先のファイルが存在しないものを返す
+///
+/// リンク先はファイルのあるフォルダを基準に解決する。URL（`https:` など）・ページ内アンカー・
+/// ルートからのパスは対象外。`#` 以降は無視し、パーセントエンコードは戻してから確かめる。
+pub fn check_cell_links(path: &Path) -> Result<Vec<BrokenLink>, String> {
+    let doc = read_document(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let inline_link = Regex::new(INLINE_LINK).unwrap();
+    let scheme = Regex::new(SCHEME).unwrap();
+    let mut broken = Vec::new();
+    for (table_index, table) in doc.tables.iter().enumerate() {
+        for (row_index, row) in table.rows.iter().enumerate() {
+            for (col_index, cell) in row.iter().enumerate() {
+                for caps in inline_link.captures_iter(cell) {
+                    let target = &caps[2];
+                    let raw = target
+                        .strip_prefix('<')
+                        .and_then(|t| t.strip_suffix('>'))
+                        .unwrap_or(target);
+                    let file = raw.split('#').next().unwrap_or_default();
+                    if file.is_empty() || file.starts_with('/') || scheme.is_match(file) {
+                        continue;
+                    }
+                    let decoded = percent_decode_str(file)
+                        .decode_utf8()
+                        .map_or_else(|_| file.to_string(), |d| d.into_owned());
+                    let resolved = normalize(&dir.join(decoded));
+                    if !resolved.exists() {
+                        broken.push(BrokenLink {
+                            table_index,
+                            row_index,
+                            col_index,
+                            target: target.to_string(),
+                            resolved: resolved.to_string_lossy().to_string(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(broken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_updates_relative_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("docs/sub")).unwrap();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::write(
+            root.join("index.md"),
+            "[Plan](docs/plan.md#goals) and ![img](docs/img.png)\n[ref]: ./docs/plan.md\n```\n[code](docs/plan.md)\n```\n[web](https://example.com/docs/plan.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("docs/sub/note.md"),
+            "See [plan](../plan.md \"title\").\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("docs/plan.md"),
+            "[Index](../index.md) [self](#top) [note](sub/note.md)\n",
+        )
+        .unwrap();
+        fs::write(root.join("other.md"), "[x](docs/other.md)\n").unwrap();
+
+        let old = root.join("docs/plan.md");
+        let new = root.join("archive/old plan.md");
+        rename_markdown_file(&old, &new).unwrap();
+        let updated = update_links_after_rename(root, &old, &new).unwrap();
+        assert_eq!(updated.len(), 3);
+
+        let read = |p: &str| fs::read_to_string(root.join(p)).unwrap();
+        assert_eq!(
+            read("index.md"),
+            "[Plan](<archive/old plan.md#goals>) and ![img](docs/img.png)\n[ref]: <archive/old plan.md>\n```\n[code](docs/plan.md)\n```\n[web](https://example.com/docs/plan.md)\n"
+        );
+        assert_eq!(
+            read("docs/sub/note.md"),
+            "See [plan](<../../archive/old plan.md> \"title\").\n"
+        );
+        // 移動したファイル自身のリンクも新しい場所から見たパスに直す
+        assert_eq!(
+            read("archive/old plan.md"),
+            "[Index](../index.md) [self](#top) [note](../docs/sub/note.md)\n"
+        );
+        assert_eq!(read("other.md"), "[x](docs/other.md)\n");
+
+        assert!(rename_markdown_file(&old, &new).is_err());
+    }
+
+    #[test]
+    fn test_check_cell_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/spec sheet.md"), "spec").unwrap();
+        fs::write(
+            root.join("index.md"),
+            "| Doc | Link |\n| --- | --- |\n| spec | [Spec](./docs/spec%20sheet.md#top) |\n| plan | see [Plan](docs/plan.md) and [web](https://example.com/x.md) |\n| self | [top](#top) |\n",
+        )
+        .unwrap();
+
+        let broken = check_cell_links(&root.join("index.md")).unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(
+            (
+                broken[0].table_index,
+                broken[0].row_index,
+                broken[0].col_index
+            ),
+            (0, 1, 1)
+        );
+        assert_eq!(broken[0].target, "docs/plan.md");
+        assert!(broken[0].resolved.ends_with("plan.md"));
+        assert!(check_cell_links(&root.join("missing.md")).is_err());
+    }
+}
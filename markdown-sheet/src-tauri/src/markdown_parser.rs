@@ -1,4 +1,10 @@
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
 
 /// Markdown ドキュメント内の1つのテーブルを表す構造体
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +21,341 @@ pub struct MarkdownTable {
     pub start_line: usize,
     /// ドキュメント内でのテーブル終了行番号
     pub end_line: usize,
+    /// 最終行が `append_summary_row` で追加した集計行か
+    #[serde(default)]
+    pub has_summary_row: bool,
+    /// 前後の空白を保持するセルの位置（行番号, 列番号）
+    ///
+    /// 前後に空白を含むバッククォート囲みのセル（`` ` x ` ``）は囲みを外した値で読み込み、
+    /// ここに位置を記録する。出力時はこの位置のセルだけを再びバッククォートで囲む。
+    #[serde(default)]
+    pub verbatim: Vec<(usize, usize)>,
+    /// セル末尾の `<!-- 注釈 -->` コメント（`rows` と同じ並び。注釈のないセルは None）
+    ///
+    /// `parse_markdown_annotated` で読み込んだときだけコメントをセルの値から外してここに入れ、
+    /// 出力時にセルの後ろへ戻す。注釈が1つもないテーブルでは空。行・列を動かす操作は
+    /// `verbatim` と同じく位置を付け替えるが、別のテーブルを作る操作（抽出・分割など）には引き継がない。
+    #[serde(default)]
+    pub cell_annotations: Vec<Vec<Option<String>>>,
+}
+
+impl MarkdownTable {
+    /// 内容（ヘッダー・アライメント・行）の安定したハッシュ
+    ///
+    /// 行番号や見出し、セル前後の空白・列幅などの書式には依存しない。FNV-1a で計算するので
+    /// Rust のバージョンやプロセスが変わっても同じ値になる。
+    pub fn checksum(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash = OFFSET;
+        let mut feed = |bytes: &[u8]| {
+            for &b in bytes {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        let mut feed_cells = |tag: u8, cells: &[String]| {
+            feed(&[tag]);
+            feed(&(cells.len() as u64).to_le_bytes());
+            for cell in cells {
+                feed(&(cell.len() as u64).to_le_bytes());
+                feed(cell.as_bytes());
+            }
+        };
+        feed_cells(b'h', &self.headers);
+        feed_cells(b'a', &self.alignments);
+        for row in &self.rows {
+            feed_cells(b'r', row);
+        }
+        hash
+    }
+
+    /// 行・列を動かした後に `verbatim` と `cell_annotations` の位置を付け替える
+    /// （`map` が None を返した位置は外す）
+    fn remap_verbatim(&mut self, map: impl Fn(usize, usize) -> Option<(usize, usize)>) {
+        self.verbatim = self
+            .verbatim
+            .iter()
+            .filter_map(|&(r, c)| map(r, c))
+            .collect();
+        self.verbatim.sort_unstable();
+        if self.cell_annotations.is_empty() {
+            return;
+        }
+        let mut moved: Vec<Vec<Option<String>>> = Vec::new();
+        for (r, row) in std::mem::take(&mut self.cell_annotations)
+            .into_iter()
+            .enumerate()
+        {
+            for (c, note) in row.into_iter().enumerate() {
+                let (Some(note), Some((r, c))) = (note, map(r, c)) else {
+                    continue;
+                };
+                if moved.len() <= r {
+                    moved.resize(r + 1, Vec::new());
+                }
+                if moved[r].len() <= c {
+                    moved[r].resize(c + 1, None);
+                }
+                moved[r][c] = Some(note);
+            }
+        }
+        self.cell_annotations = moved;
+    }
+
+    /// データセル `(row, col)` の注釈
+    pub fn annotation(&self, row: usize, col: usize) -> Option<&str> {
+        self.cell_annotations
+            .get(row)
+            .and_then(|r| r.get(col))
+            .and_then(|a| a.as_deref())
+    }
+
+    /// 直前の行とまったく同じ行を取り除き、削除した行数を返す
+    ///
+    /// 連続していない重複行は残し、行の順序は保つ。
+    pub fn dedupe_consecutive(&mut self) -> usize {
+        let remove: Vec<bool> = (0..self.rows.len())
+            .map(|ri| ri > 0 && self.rows[ri] == self.rows[ri - 1])
+            .collect();
+        remove_rows_masked(self, &remove)
+    }
+
+    /// 列名が `header` と一致する最初の列の番号（大文字・小文字を区別する）
+    pub fn column_index_of(&self, header: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == header)
+    }
+
+    /// 列名が `header` と一致する最初の列の番号（大文字・小文字を区別しない）
+    pub fn column_index_of_ci(&self, header: &str) -> Option<usize> {
+        let header = header.to_lowercase();
+        self.headers.iter().position(|h| h.to_lowercase() == header)
+    }
+
+    /// データ行を列名でも引ける `RowView` として順に返す
+    pub fn iter_rows(&self) -> impl Iterator<Item = RowView<'_>> {
+        self.rows.iter().enumerate().map(|(index, cells)| RowView {
+            index,
+            headers: &self.headers,
+            cells,
+        })
+    }
+
+    /// ヘッダーを先頭のデータ行に下ろし、`new_headers` を新しいヘッダーにする
+    ///
+    /// ヘッダーのないデータをテーブルとして書いていた場合に使う。`new_headers` が空なら
+    /// `Column 1`, `Column 2`, ... を付ける。列数と合わない・パイプや改行を含む名前はエラー。
+    pub fn demote_header(&mut self, new_headers: Vec<String>) -> Result<(), String> {
+        let cols = self.headers.len();
+        let new_headers = if new_headers.is_empty() {
+            (1..=cols).map(|i| format!("Column {}", i)).collect()
+        } else if new_headers.len() != cols {
+            return Err(format!(
+                "列名の数が列数と一致しません（列名 {} 個、{} 列）",
+                new_headers.len(),
+                cols
+            ));
+        } else {
+            for name in &new_headers {
+                check_cell_text(name, "列名")?;
+            }
+            new_headers.iter().map(|h| h.trim().to_string()).collect()
+        };
+        let old = std::mem::replace(&mut self.headers, new_headers);
+        self.rows.insert(0, old);
+        self.remap_verbatim(|r, c| Some((r + 1, c)));
+        self.end_line += 1;
+        Ok(())
+    }
+
+    /// データ行 `row` をヘッダーに上げる（`demote_header` の逆。元のヘッダーは捨てる）
+    ///
+    /// 集計行はヘッダーにできない。列数が足りない行は空の列名で補う。
+    pub fn promote_row_to_header(&mut self, row: usize) -> Result<(), String> {
+        let len = self.rows.len();
+        if row >= len {
+            return Err(format!("行が範囲外です: {}（{} 行）", row, len));
+        }
+        if self.has_summary_row && row == len - 1 {
+            return Err("集計行はヘッダーにできません".to_string());
+        }
+        let mut headers: Vec<String> = self
+            .rows
+            .remove(row)
+            .into_iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+        headers.resize(self.headers.len(), String::new());
+        self.headers = headers;
+        self.remap_verbatim(|r, c| match r.cmp(&row) {
+            Ordering::Less => Some((r, c)),
+            Ordering::Equal => None,
+            Ordering::Greater => Some((r - 1, c)),
+        });
+        self.end_line -= 1;
+        Ok(())
+    }
+
+    /// 複数の列のセルを `separator` でつないだ1つの列にまとめる
+    ///
+    /// セルは `cols` の順に前後の空白を除いてつなぎ、空のセルは飛ばす。まとめた列は
+    /// `cols[0]` の位置に置き、アライメントも `cols[0]` のものを引き継ぐ。
+    pub fn join_columns(
+        &mut self,
+        cols: Vec<usize>,
+        separator: &str,
+        new_header: String,
+    ) -> Result<(), String> {
+        let width = self.headers.len();
+        if cols.len() < 2 {
+            return Err("まとめる列を2つ以上指定してください".to_string());
+        }
+        if let Some(&bad) = cols.iter().find(|&&c| c >= width) {
+            return Err(format!("列番号が範囲外です: {}（{} 列）", bad, width));
+        }
+        if cols.iter().collect::<HashSet<_>>().len() != cols.len() {
+            return Err("同じ列が2回指定されています".to_string());
+        }
+        check_cell_text(separator, "区切り文字")?;
+        check_cell_text(&new_header, "列名")?;
+        pad_to_headers(self);
+        let joined: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let parts: Vec<&str> = cols
+                    .iter()
+                    .map(|&c| row[c].trim())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                parts.join(separator)
+            })
+            .collect();
+        let alignment = self.alignments[cols[0]].clone();
+        // 残す列の新しい番号（まとめた列は `at` に入る）
+        let at = cols[0] - cols.iter().filter(|&&c| c < cols[0]).count();
+        let mut new_index = Vec::with_capacity(width);
+        let mut kept = 0;
+        for c in 0..width {
+            if cols.contains(&c) {
+                new_index.push(None);
+            } else {
+                new_index.push(Some(if kept >= at { kept + 1 } else { kept }));
+                kept += 1;
+            }
+        }
+        let keep = |c: &usize| !cols.contains(c);
+        let retain = |values: &mut Vec<String>| {
+            let mut c = 0;
+            values.retain(|_| {
+                c += 1;
+                keep(&(c - 1))
+            });
+        };
+        retain(&mut self.headers);
+        retain(&mut self.alignments);
+        self.headers.insert(at, new_header.trim().to_string());
+        self.alignments.insert(at, alignment);
+        for (row, value) in self.rows.iter_mut().zip(joined) {
+            retain(row);
+            row.insert(at, value);
+        }
+        self.remap_verbatim(|r, c| new_index[c].map(|c| (r, c)));
+        Ok(())
+    }
+
+    /// 列 `col` のセルを `delimiter` で分け、`new_headers` の数の列に置き換える
+    ///
+    /// [`split_column_by`] を `keep_original = false` で呼ぶのと同じで、分けられなかった行の番号を返す。
+    pub fn split_column(
+        &mut self,
+        col: usize,
+        delimiter: &str,
+        new_headers: Vec<String>,
+    ) -> Result<Vec<usize>, String> {
+        let splitter = ColumnSplitter::Delimiter {
+            delimiter: delimiter.to_string(),
+            max_splits: None,
+        };
+        split_column_by(self, col, &splitter, new_headers, false)
+    }
+}
+
+/// テーブルのデータ行1行の読み取り専用ビュー
+///
+/// シリアライズすると `{ 列名: 値 }` のマップになる（列数が足りないセルは空文字列）。
+#[derive(Debug, Clone, Copy)]
+pub struct RowView<'a> {
+    /// データ行の番号
+    pub index: usize,
+    headers: &'a [String],
+    cells: &'a [String],
+}
+
+impl<'a> RowView<'a> {
+    /// 列名（前後の空白は無視）でセルを引く。同名の列が複数あれば最初の列
+    pub fn get(&self, col_name: &str) -> Option<&'a str> {
+        let col = self
+            .headers
+            .iter()
+            .position(|h| h.trim() == col_name.trim())?;
+        self.get_by_index(col)
+    }
+
+    /// 列番号でセルを引く（ヘッダーの範囲外なら None）
+    pub fn get_by_index(&self, i: usize) -> Option<&'a str> {
+        if i >= self.headers.len() {
+            return None;
+        }
+        Some(self.cells.get(i).map_or("", |c| c.as_str()))
+    }
+}
+
+impl Serialize for RowView<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(self.headers.len()))?;
+        for (i, header) in self.headers.iter().enumerate() {
+            map.serialize_entry(header, self.get_by_index(i).unwrap_or(""))?;
+        }
+        map.end()
+    }
+}
+
+/// データ行のセルの値を返す（範囲外なら None）
+///
+/// 列数が足りない行でも、ヘッダーの範囲内なら空文字列として扱う。
+pub fn get_cell(table: &MarkdownTable, row: usize, col: usize) -> Option<&str> {
+    let cells = table.rows.get(row)?;
+    match cells.get(col) {
+        Some(cell) => Some(cell.as_str()),
+        None if col < table.headers.len() => Some(""),
+        None => None,
+    }
+}
+
+/// データ行のセルの値を書き換える（範囲外やパイプ・改行を含む値はエラー）
+///
+/// 列数が足りない行は空のセルで埋めてから書き込む。
+pub fn set_cell(
+    table: &mut MarkdownTable,
+    row: usize,
+    col: usize,
+    value: String,
+) -> Result<(), String> {
+    if col >= table.headers.len() {
+        return Err(format!("列番号が範囲外です: {}", col));
+    }
+    check_cell_text(&value, "セルの値")?;
+    let cells = table
+        .rows
+        .get_mut(row)
+        .ok_or_else(|| format!("行番号が範囲外です: {}", row))?;
+    if cells.len() <= col {
+        cells.resize(col + 1, String::new());
+    }
+    cells[col] = value;
+    Ok(())
 }
 
 /// Markdown ドキュメント全体のパース結果
@@ -24,10 +365,55 @@ pub struct ParsedDocument {
     pub lines: Vec<String>,
     /// 抽出されたテーブル群
     pub tables: Vec<MarkdownTable>,
+    /// 元ファイルの文字コード名（保存時に同じ文字コードで書き戻す）
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    /// 元ファイルに BOM が付いていたか
+    #[serde(default)]
+    pub has_bom: bool,
+    /// 脚注定義（`[^id]: text`）の ID → 本文
+    #[serde(default)]
+    pub footnotes: HashMap<String, String>,
+}
+
+fn default_encoding() -> String {
+    "UTF-8".to_string()
+}
+
+/// テーブルの内容（ヘッダー・アライメント・行・集計行・逐語セル・セル注釈）を比べる
+///
+/// 位置を表す `start_line` / `end_line` と、前の行から決まる `heading` は比べないので、
+/// シリアライズし直して位置が変わったテーブルも等しいとみなす。
+impl PartialEq for MarkdownTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.headers == other.headers
+            && self.alignments == other.alignments
+            && self.rows == other.rows
+            && self.has_summary_row == other.has_summary_row
+            && self.verbatim == other.verbatim
+            && self.cell_annotations == other.cell_annotations
+    }
+}
+
+/// ドキュメントのテーブル・脚注・文字コード・BOM の有無を比べる
+///
+/// `lines` は比べないので、テーブル以外の行や空白の違いは無視する。
+impl PartialEq for ParsedDocument {
+    fn eq(&self, other: &Self) -> bool {
+        self.tables == other.tables
+            && self.footnotes == other.footnotes
+            && self.encoding == other.encoding
+            && self.has_bom == other.has_bom
+    }
 }
 
 /// パイプ区切り行をセル値の配列にパースする
 fn parse_row(line: &str) -> Vec<String> {
+    split_row(line).into_iter().map(str::to_string).collect()
+}
+
+/// パイプ区切り行を、元の行を指すセル値の配列に分ける
+fn split_row(line: &str) -> Vec<&str> {
     let trimmed = line.trim();
     // 先頭・末尾のパイプを除去してからスプリット
     let inner = trimmed
@@ -35,7 +421,27 @@ fn parse_row(line: &str) -> Vec<String> {
         .unwrap_or(trimmed)
         .strip_suffix('|')
         .unwrap_or(trimmed);
-    inner.split('|').map(|s| s.trim().to_string()).collect()
+    inner.split('|').map(str::trim).collect()
+}
+
+/// 前後に空白を含むバッククォート囲みのセル（`` ` x ` ``）なら、囲みの内側をそのまま返す
+///
+/// 空白を含まない `` `code` `` は通常のインラインコードとして扱い、値を変えない。
+fn unwrap_verbatim(cell: &str) -> Option<&str> {
+    let n = cell.len() - cell.trim_start_matches('`').len();
+    if n == 0 || cell.len() <= n * 2 {
+        return None;
+    }
+    let inner = cell[n..].strip_suffix(&cell[..n])?;
+    (inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace))
+        .then_some(inner)
+}
+
+/// 値をバッククォートで囲む（内容のバッククォートより長いフェンスを使う）
+fn wrap_verbatim(cell: &str) -> String {
+    let longest = cell.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    format!("{fence}{cell}{fence}")
 }
 
 /// セパレーター行かどうかを判定する (例: |---|:---:|---:|)
@@ -60,6 +466,14 @@ fn is_separator_line(line: &str) -> bool {
 
 /// セパレーター行からアライメント情報を抽出する
 fn parse_alignments(line: &str) -> Vec<String> {
+    alignments_of(line)
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+/// セパレーター行の各列のアライメント（`left` / `center` / `right` / `none`）
+fn alignments_of(line: &str) -> Vec<&'static str> {
     let trimmed = line.trim();
     let inner = trimmed
         .strip_prefix('|')
@@ -73,10 +487,10 @@ fn parse_alignments(line: &str) -> Vec<String> {
             let left = c.starts_with(':');
             let right = c.ends_with(':');
             match (left, right) {
-                (true, true) => "center".to_string(),
-                (false, true) => "right".to_string(),
-                (true, false) => "left".to_string(),
-                _ => "none".to_string(),
+                (true, true) => "center",
+                (false, true) => "right",
+                (true, false) => "left",
+                _ => "none",
             }
         })
         .collect()
@@ -88,48 +502,253 @@ fn is_table_line(line: &str) -> bool {
     !trimmed.is_empty() && trimmed.contains('|')
 }
 
+/// 脚注定義行（`[^id]: text`）なら ID と本文を返す
+fn parse_footnote_definition(line: &str) -> Option<(String, String)> {
+    let rest = line.trim_start().strip_prefix("[^")?;
+    let (id, text) = rest.split_once("]:")?;
+    if id.is_empty() || id.contains(char::is_whitespace) || id.contains(']') {
+        return None;
+    }
+    Some((id.to_string(), text.trim().to_string()))
+}
+
+/// ドキュメント内の脚注定義を集める（インデントされた後続行は本文の続きとして連結する）
+fn collect_footnotes<S: AsRef<str>>(lines: &[S]) -> HashMap<String, String> {
+    let lines: Vec<&str> = lines.iter().map(AsRef::as_ref).collect();
+    let mut footnotes = HashMap::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some((id, mut text)) = parse_footnote_definition(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        i += 1;
+        while i < lines.len()
+            && (lines[i].starts_with("    ") || lines[i].starts_with('\t'))
+            && !lines[i].trim().is_empty()
+        {
+            text.push(' ');
+            text.push_str(lines[i].trim());
+            i += 1;
+        }
+        footnotes.entry(id).or_insert(text);
+    }
+    footnotes
+}
+
+impl ParsedDocument {
+    /// セル内の脚注参照（`[^id]`）を定義本文に置き換えて返す
+    ///
+    /// 定義のある参照は ` (本文)` としてインライン化し、未定義の参照はそのまま残す。
+    pub fn resolve_footnotes_in_cell(&self, cell: &str) -> String {
+        let mut out = String::with_capacity(cell.len());
+        let mut rest = cell;
+        while let Some(start) = rest.find("[^") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let resolved = after.find(']').and_then(|end| {
+                let id = &after[..end];
+                self.footnotes.get(id).map(|text| (end, text))
+            });
+            match resolved {
+                Some((end, text)) => {
+                    if !out.is_empty() && !out.ends_with(char::is_whitespace) {
+                        out.push(' ');
+                    }
+                    out.push('(');
+                    out.push_str(text);
+                    out.push(')');
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    out.push_str("[^");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// 直前の見出しが `heading` と完全に一致するテーブルを `(テーブル番号, テーブル)` で返す
+    pub fn tables_with_heading<'a>(
+        &'a self,
+        heading: &str,
+    ) -> impl Iterator<Item = (usize, &'a MarkdownTable)> {
+        let heading = heading.to_string();
+        self.tables
+            .iter()
+            .enumerate()
+            .filter(move |(_, t)| t.heading.as_deref() == Some(heading.as_str()))
+    }
+
+    /// 直前の見出しが正規表現 `pattern` に一致するテーブルを `(テーブル番号, テーブル)` で返す
+    ///
+    /// 見出しのないテーブルは対象外。パターンが不正ならエラー。
+    pub fn tables_matching_heading_pattern<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> Result<impl Iterator<Item = (usize, &'a MarkdownTable)>, String> {
+        let re = regex::Regex::new(pattern).map_err(|e| format!("正規表現が不正です: {}", e))?;
+        Ok(self
+            .tables
+            .iter()
+            .enumerate()
+            .filter(move |(_, t)| t.heading.as_deref().is_some_and(|h| re.is_match(h))))
+    }
+}
+
 /// Markdown テキスト全文をパースし、テーブル群を抽出する
 pub fn parse_markdown(content: &str) -> ParsedDocument {
-    let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
-    let mut tables: Vec<MarkdownTable> = Vec::new();
+    parse_markdown_borrowed(content).into_owned()
+}
+
+/// `parse_markdown` に加え、セル末尾の `<!-- 注釈 -->` を `cell_annotations` に取り出す
+pub fn parse_markdown_annotated(content: &str) -> ParsedDocument {
+    let mut doc = parse_markdown(content);
+    extract_cell_annotations(&mut doc);
+    doc
+}
+
+/// セル末尾の HTML コメントを外す（`(コメントを除いた値, 注釈)`。コメントがなければ None）
+fn split_annotation(cell: &str) -> Option<(&str, &str)> {
+    let body = cell.trim_end().strip_suffix("-->")?;
+    let start = body.rfind("<!--")?;
+    Some((body[..start].trim_end(), body[start + 4..].trim()))
+}
+
+/// 読み込み済みのドキュメントのデータセルから、末尾の `<!-- 注釈 -->` を `cell_annotations` に移す
+///
+/// ヘッダーは対象外。注釈が1つもないテーブルの `cell_annotations` は空のまま。
+pub fn extract_cell_annotations(doc: &mut ParsedDocument) {
+    for table in &mut doc.tables {
+        let mut annotations = vec![Vec::new(); table.rows.len()];
+        let mut found = false;
+        for (row, notes) in table.rows.iter_mut().zip(&mut annotations) {
+            for cell in row.iter_mut() {
+                let split = split_annotation(cell).map(|(v, n)| (v.to_string(), n.to_string()));
+                let note = split.map(|(value, note)| {
+                    *cell = value;
+                    note
+                });
+                found |= note.is_some();
+                notes.push(note);
+            }
+        }
+        if found {
+            table.cell_annotations = annotations;
+        }
+    }
+}
+
+/// 元のテキストを指したままのテーブル（`parse_markdown_borrowed` の結果）
+///
+/// 見出し・ヘッダー・セルは元のテキストの一部を指し、コピーしない。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BorrowedTable<'a> {
+    pub heading: Option<&'a str>,
+    pub headers: Vec<&'a str>,
+    pub alignments: Vec<&'static str>,
+    pub rows: Vec<Vec<&'a str>>,
+    /// ドキュメント内でのテーブル開始行番号（`lines[start_line..=end_line]` がテーブルの範囲）
+    pub start_line: usize,
+    pub end_line: usize,
+    /// 前後の空白を保持するセルの位置（`MarkdownTable::verbatim` と同じ）
+    pub verbatim: Vec<(usize, usize)>,
+}
+
+impl BorrowedTable<'_> {
+    /// 値をコピーして `MarkdownTable` にする
+    pub fn to_owned_table(&self) -> MarkdownTable {
+        let owned = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect();
+        MarkdownTable {
+            heading: self.heading.map(str::to_string),
+            headers: owned(&self.headers),
+            alignments: self.alignments.iter().map(|a| a.to_string()).collect(),
+            rows: self.rows.iter().map(|row| owned(row)).collect(),
+            start_line: self.start_line,
+            end_line: self.end_line,
+            has_summary_row: false,
+            verbatim: self.verbatim.clone(),
+            cell_annotations: Vec::new(),
+        }
+    }
+}
+
+/// 元のテキストを指したままのドキュメント（行もテーブルもコピーしない）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BorrowedDocument<'a> {
+    pub lines: Vec<&'a str>,
+    pub tables: Vec<BorrowedTable<'a>>,
+}
+
+impl BorrowedDocument<'_> {
+    /// 値をコピーして `ParsedDocument` にする（`parse_markdown` と同じ結果になる）
+    pub fn into_owned(self) -> ParsedDocument {
+        ParsedDocument {
+            footnotes: collect_footnotes(&self.lines),
+            tables: self
+                .tables
+                .iter()
+                .map(BorrowedTable::to_owned_table)
+                .collect(),
+            lines: self.lines.into_iter().map(str::to_string).collect(),
+            encoding: default_encoding(),
+            has_bom: false,
+        }
+    }
+}
+
+/// 行やセルをコピーせずに Markdown テキストをパースする（ライブラリ用）
+///
+/// 結果は `content` を借用し、行・見出し・セルは `content` の一部を指す。
+/// テーブルの抽出規則は `parse_markdown` と同じ。
+pub fn parse_markdown_borrowed(content: &str) -> BorrowedDocument<'_> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut tables = Vec::new();
     let mut i = 0;
     let len = lines.len();
-    let mut last_heading: Option<String> = None;
+    let mut last_heading: Option<&str> = None;
 
     while i < len {
-        let trimmed = lines[i].trim();
-
         // 見出しを追跡
-        if trimmed.starts_with('#') {
-            last_heading = Some(trimmed.trim_start_matches('#').trim().to_string());
+        if let Some(heading) = heading_text(lines[i]) {
+            last_heading = Some(heading);
             i += 1;
             continue;
         }
 
         // テーブルの開始を検出: ヘッダー行 + セパレーター行
-        if i + 1 < len && is_table_line(&lines[i]) && is_separator_line(&lines[i + 1]) {
+        if i + 1 < len && is_table_line(lines[i]) && is_separator_line(lines[i + 1]) {
             let start_line = i;
-            let headers = parse_row(&lines[i]);
-            let alignments = parse_alignments(&lines[i + 1]);
-            let mut rows: Vec<Vec<String>> = Vec::new();
+            let headers = split_row(lines[i]);
+            let alignments = alignments_of(lines[i + 1]);
+            let mut rows: Vec<Vec<&str>> = Vec::new();
+            let mut verbatim = Vec::new();
 
             let mut j = i + 2;
-            while j < len && is_table_line(&lines[j]) && !is_separator_line(&lines[j]) {
-                let mut row = parse_row(&lines[j]);
+            while j < len && is_table_line(lines[j]) && !is_separator_line(lines[j]) {
+                let mut row = split_row(lines[j]);
                 // 列数をヘッダーに合わせる
-                row.resize(headers.len(), String::new());
-                row.truncate(headers.len());
+                row.resize(headers.len(), "");
+                for (ci, cell) in row.iter_mut().enumerate() {
+                    if let Some(inner) = unwrap_verbatim(cell) {
+                        *cell = inner;
+                        verbatim.push((rows.len(), ci));
+                    }
+                }
                 rows.push(row);
                 j += 1;
             }
 
-            tables.push(MarkdownTable {
-                heading: last_heading.clone(),
+            tables.push(BorrowedTable {
+                heading: last_heading,
                 headers,
                 alignments,
                 rows,
                 start_line,
                 end_line: j - 1,
+                verbatim,
             });
 
             i = j;
@@ -139,121 +758,4190 @@ pub fn parse_markdown(content: &str) -> ParsedDocument {
         i += 1;
     }
 
-    ParsedDocument { lines, tables }
+    BorrowedDocument { lines, tables }
 }
 
-/// テーブルを Markdown テキストに変換する
-pub fn serialize_table(table: &MarkdownTable) -> String {
-    let col_count = table.headers.len();
+/// 寛容モードのパースで見つけたテーブルの問題
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseError {
+    /// 問題のある行（0 始まり。区切り行の書式の問題は区切り行、それ以外はテーブルの先頭行）
+    pub line: usize,
+    pub message: String,
+}
 
-    // 各列の最大幅を計算
-    let mut widths: Vec<usize> = table.headers.iter().map(|h| h.len().max(3)).collect();
-    for row in &table.rows {
-        for (ci, cell) in row.iter().enumerate() {
-            if ci < col_count {
-                widths[ci] = widths[ci].max(cell.len());
+/// 区切り行のセルが `:?-+:?` の形か（コロンは両端だけ、ダッシュが1つ以上）
+fn is_well_formed_separator_cell(cell: &str) -> bool {
+    let c = cell.trim();
+    let c = c.strip_prefix(':').unwrap_or(c);
+    let c = c.strip_suffix(':').unwrap_or(c);
+    !c.is_empty() && c.chars().all(|ch| ch == '-')
+}
+
+/// 壊れたテーブルを読み飛ばしながらパースし、読み飛ばした理由を返す
+///
+/// 区切り行の列数がヘッダーと違うテーブルと、`|` で始まる行が続くのに区切り行がないブロックを
+/// エラーとして記録し、テーブル一覧には含めない。区切り行に `:--:-` や `-:-` のような
+/// 書式の崩れたセル（途中のコロンなど）があるテーブルは、一覧に含めたうえで区切り行の
+/// 行番号とともに記録する（保存すると正しい書式に直るため、元の誤りを見落とさないように）。
+/// それ以外は `parse_markdown` と同じ。
+pub fn parse_markdown_lenient(content: &str) -> (ParsedDocument, Vec<ParseError>) {
+    let mut doc = parse_markdown(content);
+    let mut errors = Vec::new();
+    doc.tables.retain(|t| {
+        let separator = &doc.lines[t.start_line + 1];
+        let separator_cols = parse_alignments(separator).len();
+        if separator_cols == t.headers.len() {
+            let malformed: Vec<String> = split_row(separator)
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| !is_well_formed_separator_cell(cell))
+                .map(|(ci, cell)| format!("{} 列目 `{}`", ci + 1, cell))
+                .collect();
+            if !malformed.is_empty() {
+                errors.push(ParseError {
+                    line: t.start_line + 1,
+                    message: format!("区切り行の書式が正しくありません: {}", malformed.join(", ")),
+                });
             }
+            return true;
+        }
+        errors.push(ParseError {
+            line: t.start_line,
+            message: format!(
+                "区切り行の列数（{}）がヘッダーの列数（{}）と一致しません",
+                separator_cols,
+                t.headers.len()
+            ),
+        });
+        false
+    });
+
+    let mut in_table = vec![false; doc.lines.len()];
+    for t in &doc.tables {
+        in_table[t.start_line..=t.end_line].fill(true);
+    }
+    let mut i = 0;
+    while i < doc.lines.len() {
+        let starts_block = !in_table[i] && doc.lines[i].trim_start().starts_with('|');
+        let mut j = i;
+        while starts_block && j < doc.lines.len() && !in_table[j] && is_table_line(&doc.lines[j]) {
+            j += 1;
+        }
+        let is_block = j - i >= 2 && !is_separator_line(&doc.lines[i]);
+        if is_block && errors.iter().all(|e| e.line != i) {
+            errors.push(ParseError {
+                line: i,
+                message: "テーブルの区切り行（| --- |）がありません".to_string(),
+            });
         }
+        i = j.max(i + 1);
     }
+    errors.sort_by_key(|e| e.line);
+    (doc, errors)
+}
 
-    let mut out = String::new();
+/// 見出し行ならそのレベル（`#` の数）を返す
+///
+/// `#` の並びの直後が空白か行末の場合だけ見出しとみなす（`#tag` は見出しではない）。
+fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    let rest = &trimmed[level..];
+    (level > 0 && (rest.is_empty() || rest.starts_with([' ', '\t']))).then_some(level)
+}
 
-    // ヘッダー行
-    out.push('|');
-    for (ci, header) in table.headers.iter().enumerate() {
-        let w = widths.get(ci).copied().unwrap_or(3);
-        out.push_str(&format!(" {:<width$} |", header, width = w));
-    }
-    out.push('\n');
+/// 見出し行なら見出しのテキスト（`#` と前後の空白を除いたもの）を返す
+fn heading_text(line: &str) -> Option<&str> {
+    heading_level(line).map(|_| line.trim().trim_start_matches('#').trim())
+}
 
-    // セパレーター行
-    out.push('|');
-    for ci in 0..col_count {
-        let w = widths.get(ci).copied().unwrap_or(3);
-        let align = table
-            .alignments
-            .get(ci)
-            .map(|s| s.as_str())
-            .unwrap_or("none");
-        let sep = match align {
-            "left" => format!(":{}-|", "-".repeat(w)),
-            "right" => format!(" {}:|", "-".repeat(w)),
-            "center" => format!(":{}:|", "-".repeat(w)),
-            _ => format!(" {}-|", "-".repeat(w)),
+/// コードフェンス（```` ``` ```` / `~~~`）の内側にある行か
+pub(crate) fn fenced_lines(lines: &[String]) -> Vec<bool> {
+    let mut fence: Option<&str> = None;
+    lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+            match (fence, marker) {
+                (None, Some(m)) => {
+                    fence = Some(m);
+                    true
+                }
+                (Some(open), Some(m)) if open == m => {
+                    fence = None;
+                    true
+                }
+                (inside, _) => inside.is_some(),
+            }
+        })
+        .collect()
+}
+
+/// 各行の見出しレベル（コードフェンスの内側の行は見出しにしない）
+fn heading_levels(lines: &[String]) -> Vec<Option<usize>> {
+    let fenced = fenced_lines(lines);
+    lines
+        .iter()
+        .zip(fenced)
+        .map(|(line, fenced)| if fenced { None } else { heading_level(line) })
+        .collect()
+}
+
+/// テーブルが属する見出しの階層（パンくず）を上位から順に `(レベル, 見出しテキスト)` で返す
+///
+/// テーブルより前の見出しのうち、直前の見出しとその祖先（より浅いレベルで最も近いもの）を並べる。
+/// `#` の後に空白が続く行を見出しとし、コードフェンスの内側は数えない。テーブル番号が範囲外なら空。
+pub fn heading_path_for_table(doc: &ParsedDocument, table_index: usize) -> Vec<(u8, String)> {
+    let Some(table) = doc.tables.get(table_index) else {
+        return Vec::new();
+    };
+    let before = &doc.lines[..table.start_line.min(doc.lines.len())];
+    let mut path: Vec<(u8, String)> = Vec::new();
+    for (line, level) in before.iter().zip(heading_levels(before)) {
+        let Some(level) = level else {
+            continue;
         };
-        out.push_str(&sep);
+        let level = level.min(u8::MAX as usize) as u8;
+        while path.last().is_some_and(|(l, _)| *l >= level) {
+            path.pop();
+        }
+        path.push((level, heading_text(line).unwrap_or_default().to_string()));
     }
-    out.push('\n');
+    path
+}
 
-    // データ行
-    for row in &table.rows {
-        out.push('|');
-        for ci in 0..col_count {
-            let w = widths.get(ci).copied().unwrap_or(3);
-            let cell = row.get(ci).map(|s| s.as_str()).unwrap_or("");
-            out.push_str(&format!(" {:<width$} |", cell, width = w));
-        }
-        out.push('\n');
+/// 指定レベルの見出しごとにドキュメントを分割し、`(見出しテキスト, 部分ドキュメント)` を返す
+///
+/// 各部分は見出し行から次の同じレベルの見出しの直前まで。最初の見出しより前に
+/// 空行以外の内容があれば、見出しテキストを空文字列として先頭に含める。
+/// 文字コードと BOM の有無は元のドキュメントを引き継ぐ。
+pub fn split_document_at_heading(doc: &ParsedDocument, level: u8) -> Vec<(String, ParsedDocument)> {
+    let starts: Vec<usize> = heading_levels(&doc.lines)
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, l)| l == Some(level as usize))
+        .map(|(i, _)| i)
+        .collect();
+    let mut bounds = Vec::with_capacity(starts.len() + 1);
+    let first = starts.first().copied().unwrap_or(doc.lines.len());
+    if doc.lines[..first].iter().any(|l| !l.trim().is_empty()) {
+        bounds.push((String::new(), 0));
     }
+    for &start in &starts {
+        let heading = heading_text(&doc.lines[start]).unwrap_or_default();
+        bounds.push((heading.to_string(), start));
+    }
+    let ends: Vec<usize> = bounds
+        .iter()
+        .skip(1)
+        .map(|(_, start)| *start)
+        .chain([doc.lines.len()])
+        .collect();
+    bounds
+        .into_iter()
+        .zip(ends)
+        .map(|((heading, start), end)| {
+            let mut part = parse_markdown(&doc.lines[start..end].join("\n"));
+            part.encoding = doc.encoding.clone();
+            part.has_bom = doc.has_bom;
+            (heading, part)
+        })
+        .collect()
+}
 
-    out
+/// 各テーブルのチェックサムを16進文字列で返す（JavaScript の数値では 64 ビットを表せないため）
+pub fn table_checksums(tables: &[MarkdownTable]) -> Vec<String> {
+    tables
+        .iter()
+        .map(|t| format!("{:016x}", t.checksum()))
+        .collect()
 }
 
-/// ドキュメント全体を再構築する（テーブル部分を更新済みテーブルで置換）
-pub fn rebuild_document(original_lines: &[String], tables: &[MarkdownTable]) -> String {
-    if tables.is_empty() {
-        return original_lines.join("\n");
-    }
+/// 軽量スキャンで見つかった要素
+enum ScanItem<'a> {
+    /// 見出し（`#` を除いたテキスト）
+    Heading(&'a str),
+    /// テーブル（直前の見出しと、ヘッダー行・最終行の行番号）
+    Table {
+        heading: Option<&'a str>,
+        start: usize,
+        end: usize,
+    },
+}
 
-    let mut result = String::new();
-    let mut cursor = 0;
+/// 行データを構築せずに見出しとテーブルの位置だけを走査する
+fn scan_structure(content: &str, visit: impl FnMut(ScanItem)) {
+    let lines: Vec<&str> = content.lines().collect();
+    scan_lines(&lines, visit);
+}
 
-    for table in tables {
-        // テーブル前のテキストをそのまま出力
-        for line in &original_lines[cursor..table.start_line] {
-            result.push_str(line);
-            result.push('\n');
+/// 行の配列から見出しとテーブルの位置だけを走査する
+///
+/// 判定規則は `parse_markdown` と同じ。
+fn scan_lines<S: AsRef<str>>(lines: &[S], mut visit: impl FnMut(ScanItem)) {
+    let len = lines.len();
+    let line = |i: usize| lines[i].as_ref();
+    let mut last_heading: Option<&str> = None;
+    let mut i = 0;
+
+    while i < len {
+        if let Some(heading) = heading_text(line(i)) {
+            last_heading = Some(heading);
+            visit(ScanItem::Heading(heading));
+            i += 1;
+            continue;
         }
-        // 更新されたテーブルを出力
-        result.push_str(&serialize_table(table));
-        cursor = table.end_line + 1;
+        if i + 1 < len && is_table_line(line(i)) && is_separator_line(line(i + 1)) {
+            let mut j = i + 2;
+            while j < len && is_table_line(line(j)) && !is_separator_line(line(j)) {
+                j += 1;
+            }
+            visit(ScanItem::Table {
+                heading: last_heading,
+                start: i,
+                end: j - 1,
+            });
+            i = j;
+            continue;
+        }
+        i += 1;
     }
+}
 
-    // 最後のテーブル以降のテキスト
-    for line in &original_lines[cursor..] {
-        result.push_str(line);
-        result.push('\n');
+/// `doc.lines` を走査し直して、各テーブルの `start_line` / `end_line` を実際の位置に合わせる
+///
+/// 行を挿入・削除した後に呼ぶ。テーブルは出現順に対応付け、走査で見つかった数より
+/// 多いテーブルの位置は変更しない。
+pub fn reindex_table_lines(doc: &mut ParsedDocument) {
+    let mut spans = Vec::with_capacity(doc.tables.len());
+    scan_lines(&doc.lines, |item| {
+        if let ScanItem::Table { start, end, .. } = item {
+            spans.push((start, end));
+        }
+    });
+    for (table, (start, end)) in doc.tables.iter_mut().zip(spans) {
+        table.start_line = start;
+        table.end_line = end;
     }
+}
 
-    // 末尾の余分な改行を除去
-    if result.ends_with('\n') && !original_lines.last().map_or(false, |l| l.is_empty()) {
-        result.pop();
+/// テーブル数だけを数える（行データを構築しない軽量スキャン）
+pub fn count_tables(content: &str) -> usize {
+    let mut count = 0;
+    scan_structure(content, |item| {
+        if let ScanItem::Table { .. } = item {
+            count += 1;
+        }
+    });
+    count
+}
+
+/// ドキュメントの見出しとテーブルの見出しの一覧
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Outline {
+    /// 出現順の見出し
+    pub headings: Vec<String>,
+    /// 各テーブル直前の見出し（テーブル順）
+    pub table_headings: Vec<Option<String>>,
+}
+
+/// 見出しとテーブルの見出しだけを取り出す（行データを構築しない軽量スキャン）
+pub fn outline(content: &str) -> Outline {
+    let mut out = Outline::default();
+    scan_structure(content, |item| match item {
+        ScanItem::Heading(h) => out.headings.push(h.to_string()),
+        ScanItem::Table { heading, .. } => out.table_headings.push(heading.map(str::to_string)),
+    });
+    out
+}
+
+/// テーブルの前後の行（プレビュー用）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableContext {
+    /// テーブルの直前の行（ファイルの先頭で打ち切る）
+    pub before: Vec<String>,
+    /// テーブルのソース（改行区切り）
+    pub source: String,
+    /// テーブルの直後の行（ファイルの末尾で打ち切る）
+    pub after: Vec<String>,
+    /// `before` の最初の行の行番号
+    pub first_line: usize,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// テーブルの前 `before` 行・後 `after` 行とテーブル自体のソースを切り出す
+pub fn table_context(
+    lines: &[String],
+    table: &MarkdownTable,
+    before: usize,
+    after: usize,
+) -> TableContext {
+    let start = table.start_line.min(lines.len());
+    let end = (table.end_line + 1).clamp(start, lines.len());
+    let first_line = start.saturating_sub(before);
+    let last = end.saturating_add(after).min(lines.len());
+    TableContext {
+        before: lines[first_line..start].to_vec(),
+        source: lines[start..end].join("\n"),
+        after: lines[end..last].to_vec(),
+        first_line,
+        start_line: table.start_line,
+        end_line: table.end_line,
     }
+}
 
-    result
+/// テーブルの出力スタイル
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableStyle {
+    /// 列幅を揃えて空白で埋める
+    #[default]
+    Padded,
+    /// 列幅を揃えず最小限の記号で出力する
+    Compact,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// 再パース時に誤解されやすいセル（`---` や `#` で始まるもの、前後に空白があるもの）の出力方法
+///
+/// - `Backticks`: セル全体をバッククォートで囲む。前後に空白のあるセルは囲みの内側がそのまま
+///   読まれるため、再パースすると元の値に戻る。それ以外のセルは表示がコード書式になり、
+///   再パースしたセル値にはバッククォートが含まれたままになる。
+/// - `Backslash`: 先頭の記号をバックスラッシュでエスケープし、前後の空白は `&#32;` にする。
+///   表示は元の文字列のままで、`unquote_cell` で元の値に戻せるが、生テキストは読みにくくなる。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellQuoting {
+    /// そのまま出力する（既定）
+    #[default]
+    None,
+    /// バッククォートで囲む
+    Backticks,
+    /// 先頭の記号をエスケープする
+    Backslash,
+}
 
-    #[test]
-    fn test_parse_simple_table() {
-        let md = "# Test\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n| 3 | 4 |\n";
-        let doc = parse_markdown(md);
-        assert_eq!(doc.tables.len(), 1);
-        assert_eq!(doc.tables[0].headers, vec!["A", "B"]);
-        assert_eq!(doc.tables[0].rows.len(), 2);
+/// 行頭にあると Markdown の記法と紛らわしい記号
+const RESERVED_LEADING: &[char] = &['-', '#', '+', '>', '=', ':', '*', '|'];
+
+/// 再パース時に誤解されうるセルか
+fn is_reserved_cell(cell: &str) -> bool {
+    cell.starts_with(char::is_whitespace)
+        || cell.ends_with(char::is_whitespace)
+        || cell.starts_with(RESERVED_LEADING)
+}
+
+/// 必要な場合だけセルをクォートする
+pub fn quote_cell(cell: &str, quoting: CellQuoting) -> Cow<'_, str> {
+    if quoting == CellQuoting::None || !is_reserved_cell(cell) {
+        return Cow::Borrowed(cell);
+    }
+    match quoting {
+        CellQuoting::None => Cow::Borrowed(cell),
+        // 囲みの内側は再パース時にそのまま読まれるため、空白を足さない
+        CellQuoting::Backticks => Cow::Owned(wrap_verbatim(cell)),
+        CellQuoting::Backslash => {
+            let body = cell.trim();
+            let leading = cell.len() - cell.trim_start().len();
+            let trailing = cell.len() - cell.trim_end().len();
+            let escaped = if body.starts_with(RESERVED_LEADING) {
+                format!("\\{}", body)
+            } else {
+                body.to_string()
+            };
+            Cow::Owned(format!(
+                "{}{}{}",
+                "&#32;".repeat(leading),
+                escaped,
+                "&#32;".repeat(trailing)
+            ))
+        }
+    }
+}
+
+/// `CellQuoting::Backslash` で出力したセルを元の値に戻す
+pub fn unquote_cell(cell: &str) -> String {
+    let mut rest = cell;
+    let mut leading = 0;
+    while let Some(r) = rest.strip_prefix("&#32;") {
+        rest = r;
+        leading += 1;
+    }
+    let mut trailing = 0;
+    while let Some(r) = rest.strip_suffix("&#32;") {
+        rest = r;
+        trailing += 1;
+    }
+    let body = match rest.strip_prefix('\\') {
+        Some(r) if r.starts_with(RESERVED_LEADING) => r,
+        _ => rest,
+    };
+    format!("{}{}{}", " ".repeat(leading), body, " ".repeat(trailing))
+}
+
+/// テーブルのシリアライズオプション
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerializeOptions {
+    /// 出力スタイル
+    pub style: TableStyle,
+    /// 列の最小幅（Padded のみ有効）
+    pub min_width: usize,
+    /// セル内容の左右に入れる空白の数
+    pub padding: usize,
+    /// 集計行の値を太字（`**value**`）で出力する
+    pub bold_summary: bool,
+    /// 誤解されやすいセルのクォート方法
+    pub quote_reserved: CellQuoting,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            style: TableStyle::Padded,
+            min_width: 3,
+            padding: 1,
+            bold_summary: false,
+            quote_reserved: CellQuoting::None,
+        }
+    }
+}
+
+/// テーブルを Markdown テキストに変換する
+pub fn serialize_table(table: &MarkdownTable) -> String {
+    serialize_table_with(table, &SerializeOptions::default())
+}
+
+/// テーブルを Pandoc の pipe table に変換する（見出しがあれば `: 見出し` のキャプションを前に付ける）
+///
+/// キャプションとテーブルの間には空行を入れる。集計行は太字にする。
+pub fn table_to_pandoc_md(table: &MarkdownTable) -> String {
+    let opts = SerializeOptions {
+        bold_summary: true,
+        ..SerializeOptions::default()
+    };
+    let body = serialize_table_with(table, &opts);
+    let body = body.trim_end_matches('\n');
+    match table.heading.as_deref().map(str::trim) {
+        Some(caption) if !caption.is_empty() => format!(": {}\n\n{}\n", caption, body),
+        _ => format!("{}\n", body),
+    }
+}
+
+/// セパレーター行の1セル分を生成する（`total` はパイプ間の文字数）
+fn separator_cell(align: &str, total: usize, padding: usize) -> String {
+    let left = matches!(align, "left" | "center");
+    let right = matches!(align, "right" | "center");
+    let first = if left {
+        ":"
+    } else if padding > 0 {
+        " "
+    } else {
+        "-"
+    };
+    let last = if right { ":" } else { "-" };
+    format!("{}{}{}", first, "-".repeat(total.saturating_sub(2)), last)
+}
+
+/// 等幅フォントでの表示幅（全角文字は2、結合文字は0）。列の幅揃えに使う
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// 出力するデータセルの表記（前後の空白の保持・クォート・集計行の太字を反映する）
+fn rendered_cell(
+    table: &MarkdownTable,
+    opts: &SerializeOptions,
+    ri: usize,
+    ci: usize,
+    cell: &str,
+) -> String {
+    let keep_spaces = cell.starts_with(char::is_whitespace) || cell.ends_with(char::is_whitespace);
+    let cell = if keep_spaces && table.verbatim.contains(&(ri, ci)) {
+        Cow::Owned(wrap_verbatim(cell))
+    } else {
+        quote_cell(cell, opts.quote_reserved)
+    };
+    let summary = opts.bold_summary && table.has_summary_row && ri + 1 == table.rows.len();
+    let cell = if summary && !cell.is_empty() {
+        format!("**{}**", cell)
+    } else {
+        cell.into_owned()
+    };
+    match table.annotation(ri, ci) {
+        Some(note) if cell.is_empty() => format!("<!-- {} -->", note),
+        Some(note) => format!("{} <!-- {} -->", cell, note),
+        None => cell,
+    }
+}
+
+/// `serialize_table` が揃える各列の幅（パディングを除いた表示幅）
+///
+/// ヘッダーとセルの表示幅（`display_width`）の最大で、最小は 3。
+pub fn column_widths(table: &MarkdownTable) -> Vec<usize> {
+    column_widths_with(table, &SerializeOptions::default())
+}
+
+/// オプションに従って出力するときの各列の幅（Compact では揃えないのですべて 0）
+pub fn column_widths_with(table: &MarkdownTable, opts: &SerializeOptions) -> Vec<usize> {
+    let col_count = table.headers.len();
+    if opts.style == TableStyle::Compact {
+        return vec![0; col_count];
+    }
+    let mut widths: Vec<usize> = table
+        .headers
+        .iter()
+        .map(|h| display_width(&quote_cell(h, opts.quote_reserved)).max(opts.min_width))
+        .collect();
+    for (ri, row) in table.rows.iter().enumerate() {
+        for (ci, cell) in row.iter().enumerate().take(col_count) {
+            widths[ci] = widths[ci].max(display_width(&rendered_cell(table, opts, ri, ci, cell)));
+        }
+    }
+    widths
+}
+
+/// `text` の後ろに空白を足して表示幅を `width` にする
+fn pad_to_width(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+/// オプションに従ってテーブルを Markdown テキストに変換する
+pub fn serialize_table_with(table: &MarkdownTable, opts: &SerializeOptions) -> String {
+    let col_count = table.headers.len();
+    let pad = " ".repeat(opts.padding);
+    let widths = column_widths_with(table, opts);
+
+    let mut out = String::new();
+
+    // ヘッダー行
+    out.push('|');
+    for (ci, header) in table.headers.iter().enumerate() {
+        let w = widths.get(ci).copied().unwrap_or(0);
+        let header = quote_cell(header, opts.quote_reserved);
+        out.push_str(&format!("{pad}{}{pad}|", pad_to_width(&header, w)));
+    }
+    out.push('\n');
+
+    // セパレーター行
+    out.push('|');
+    for ci in 0..col_count {
+        let align = table
+            .alignments
+            .get(ci)
+            .map(|s| s.as_str())
+            .unwrap_or("none");
+        let sep = match opts.style {
+            TableStyle::Padded => {
+                let w = widths.get(ci).copied().unwrap_or(0);
+                separator_cell(align, w + opts.padding * 2, opts.padding)
+            }
+            TableStyle::Compact => {
+                let dashes = match align {
+                    "left" => ":--",
+                    "right" => "--:",
+                    "center" => ":-:",
+                    _ => "---",
+                };
+                format!("{pad}{}{pad}", dashes)
+            }
+        };
+        out.push_str(&sep);
+        out.push('|');
+    }
+    out.push('\n');
+
+    // データ行
+    for ri in 0..table.rows.len() {
+        out.push('|');
+        for ci in 0..col_count {
+            let w = widths.get(ci).copied().unwrap_or(0);
+            let cell = rendered_cell(table, opts, ri, ci, get_cell(table, ri, ci).unwrap_or(""));
+            out.push_str(&format!("{pad}{}{pad}|", pad_to_width(&cell, w)));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// ドキュメント全体を再構築する（テーブル部分を更新済みテーブルで置換）
+pub fn rebuild_document(original_lines: &[String], tables: &[MarkdownTable]) -> String {
+    rebuild_document_with(original_lines, tables, &SerializeOptions::default())
+}
+
+/// オプションを指定してドキュメント全体を再構築する
+///
+/// `tables` に含まれないテーブルの行は元のテキストのまま残る。
+pub fn rebuild_document_with(
+    original_lines: &[String],
+    tables: &[MarkdownTable],
+    opts: &SerializeOptions,
+) -> String {
+    rebuild_with(original_lines, tables, |table| {
+        serialize_table_with(table, opts)
+    })
+}
+
+/// 1つのテーブルの行範囲を任意のテキストに置き換えたドキュメント全体を返す
+///
+/// `replacement` は `serialize_table` の出力と同じく改行で終わる形で渡す。
+pub fn replace_table_text(
+    original_lines: &[String],
+    table: &MarkdownTable,
+    replacement: &str,
+) -> String {
+    rebuild_with(original_lines, std::slice::from_ref(table), |_| {
+        replacement.to_string()
+    })
+}
+
+/// テーブルの行範囲を `render` の出力で置き換えながらドキュメントを組み立てる
+fn rebuild_with(
+    original_lines: &[String],
+    tables: &[MarkdownTable],
+    render: impl Fn(&MarkdownTable) -> String,
+) -> String {
+    if tables.is_empty() {
+        return original_lines.join("\n");
+    }
+
+    let mut result = String::new();
+    let mut cursor = 0;
+
+    for table in tables {
+        // テーブル前のテキストをそのまま出力
+        for line in &original_lines[cursor..table.start_line] {
+            result.push_str(line);
+            result.push('\n');
+        }
+        // 更新されたテーブルを出力
+        result.push_str(&render(table));
+        cursor = table.end_line + 1;
+    }
+
+    // 最後のテーブル以降のテキスト
+    for line in &original_lines[cursor..] {
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    // 末尾の余分な改行を除去
+    if result.ends_with('\n') && !original_lines.last().is_some_and(|l| l.is_empty()) {
+        result.pop();
+    }
+
+    result
+}
+
+/// テーブル修復で行った個々の修正内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RepairFix {
+    /// 列数が足りない行を空セルで補完した
+    PaddedRow { row: usize, added: usize },
+    /// ヘッダーより列数の多い行に合わせて列を追加した
+    WidenedColumns { added: usize },
+    /// ヘッダーより多いセルを切り捨てた（列を広げない場合）
+    TruncatedRow { row: usize, removed: usize },
+    /// 空のヘッダーに `Column N` を設定した
+    FilledHeader { col: usize, name: String },
+    /// アライメント数をヘッダー数に合わせた
+    AlignedAlignments { from: usize, to: usize },
+}
+
+/// テーブル修復の結果レポート
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepairReport {
+    pub fixes: Vec<RepairFix>,
+}
+
+/// 列数の不整合などを元の行から検出してテーブルを正規化する
+///
+/// パース済みのテーブルは列数がヘッダーに揃えられているため、
+/// `lines` に残っている元の行を読み直して不整合を判定する。
+pub fn repair_table(
+    lines: &[String],
+    table: &mut MarkdownTable,
+    widen_overflow: bool,
+) -> RepairReport {
+    let mut fixes = Vec::new();
+    let raw_rows: Vec<Vec<String>> =
+        if table.end_line < lines.len() && table.start_line + 2 <= table.end_line + 1 {
+            lines[table.start_line + 2..=table.end_line]
+                .iter()
+                .map(|l| parse_row(l))
+                .collect()
+        } else {
+            table.rows.clone()
+        };
+
+    let header_count = table.headers.len();
+    let max_row_len = raw_rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let width = if widen_overflow {
+        header_count.max(max_row_len)
+    } else {
+        header_count
+    };
+    if width > header_count {
+        table.headers.resize(width, String::new());
+        fixes.push(RepairFix::WidenedColumns {
+            added: width - header_count,
+        });
+    }
+
+    let mut rows = Vec::with_capacity(raw_rows.len());
+    for (ri, mut row) in raw_rows.into_iter().enumerate() {
+        if row.len() < width {
+            fixes.push(RepairFix::PaddedRow {
+                row: ri,
+                added: width - row.len(),
+            });
+            row.resize(width, String::new());
+        } else if row.len() > width {
+            fixes.push(RepairFix::TruncatedRow {
+                row: ri,
+                removed: row.len() - width,
+            });
+            row.truncate(width);
+        }
+        rows.push(row);
+    }
+    table.rows = rows;
+
+    for (ci, header) in table.headers.iter_mut().enumerate() {
+        if header.trim().is_empty() {
+            *header = format!("Column {}", ci + 1);
+            fixes.push(RepairFix::FilledHeader {
+                col: ci,
+                name: header.clone(),
+            });
+        }
+    }
+
+    if table.alignments.len() != width {
+        fixes.push(RepairFix::AlignedAlignments {
+            from: table.alignments.len(),
+            to: width,
+        });
+        table.alignments.resize(width, "none".to_string());
+    }
+
+    RepairReport { fixes }
+}
+
+/// データ行のないテーブル（ヘッダーのみ）をドキュメントから取り除き、削除数を返す
+///
+/// 該当する行を `lines` から削除し、残ったテーブルの `start_line` / `end_line` を振り直す。
+pub fn remove_empty_tables(doc: &mut ParsedDocument) -> usize {
+    let mut removed = 0;
+    let mut i = doc.tables.len();
+    while i > 0 {
+        i -= 1;
+        if !doc.tables[i].rows.is_empty() {
+            continue;
+        }
+        let table = doc.tables.remove(i);
+        let end = table.end_line.min(doc.lines.len().saturating_sub(1));
+        doc.lines.drain(table.start_line..=end);
+        removed += 1;
+    }
+    if removed > 0 {
+        reindex_table_lines(doc);
+    }
+    removed
+}
+
+/// 新しいテーブルを `doc.lines` の `at_line` 行目の位置に挿入する
+///
+/// 前後の行が空でなければ空行を挟み、前後の段落やテーブルとつながらないようにする。
+/// 見出しは挿入位置より前の最後の見出しにし、`doc.tables` には `start_line` の順に加え、
+/// `reindex_table_lines` で各テーブルの `start_line` / `end_line` を振り直す。
+/// 既存のテーブルの途中には挿入できない。
+pub fn insert_table_at_line(
+    doc: &mut ParsedDocument,
+    at_line: usize,
+    mut table: MarkdownTable,
+) -> Result<(), String> {
+    if at_line > doc.lines.len() {
+        return Err(format!(
+            "行番号が範囲外です: {}（{} 行）",
+            at_line,
+            doc.lines.len()
+        ));
+    }
+    if table.headers.is_empty() {
+        return Err("ヘッダーを指定してください".to_string());
+    }
+    if doc
+        .tables
+        .iter()
+        .any(|t| t.start_line < at_line && at_line <= t.end_line)
+    {
+        return Err(format!("テーブルの途中には挿入できません: {}", at_line));
+    }
+    table
+        .alignments
+        .resize(table.headers.len(), "none".to_string());
+    let mut block: Vec<String> = serialize_table(&table).lines().map(String::from).collect();
+    let blank_before = at_line > 0 && !doc.lines[at_line - 1].trim().is_empty();
+    let blank_after = doc.lines.get(at_line).is_some_and(|l| !l.trim().is_empty());
+    if blank_before {
+        block.insert(0, String::new());
+    }
+    if blank_after {
+        block.push(String::new());
+    }
+    table.heading = doc.lines[..at_line]
+        .iter()
+        .rev()
+        .find_map(|l| heading_text(l))
+        .map(str::to_string);
+    let pos = doc.tables.partition_point(|t| t.start_line < at_line);
+    doc.lines.splice(at_line..at_line, block);
+    doc.tables.insert(pos, table);
+    reindex_table_lines(doc);
+    Ok(())
+}
+
+/// すべてのセルが空の行を取り除き、削除数を返す（`end_line` も詰める）
+pub fn remove_empty_rows(table: &mut MarkdownTable) -> usize {
+    let remove: Vec<bool> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().all(|cell| cell.trim().is_empty()))
+        .collect();
+    let removed = remove_rows_masked(table, &remove);
+    table.end_line = table.end_line.saturating_sub(removed);
+    removed
+}
+
+/// `cleanup_table` で行う整理（それぞれ独立に指定する）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct CleanupOptions {
+    /// すべてのセルが空の行を削除する
+    pub remove_empty_rows: bool,
+    /// ヘッダーもすべてのセルも空の列を削除する
+    pub remove_empty_columns: bool,
+    /// ヘッダーとセルの前後の空白を取り除く
+    pub trim_cells: bool,
+}
+
+/// `cleanup_table` の結果（行番号・列番号は整理前のもの）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CleanupReport {
+    pub removed_rows: Vec<usize>,
+    pub removed_columns: Vec<usize>,
+    /// 前後の空白を取り除いたセル数（ヘッダーを含む）
+    pub trimmed_cells: usize,
+}
+
+/// 空の行・空の列を削除し、セルの前後の空白を取り除く
+///
+/// 空白だけのセルは空とみなす。ヘッダーが空でもセルに値がある列は残す。すべての列が
+/// 空の場合も、テーブルが壊れないよう先頭の1列は残す。
+pub fn cleanup_table(
+    table: &mut MarkdownTable,
+    options: CleanupOptions,
+) -> Result<CleanupReport, String> {
+    let mut report = CleanupReport::default();
+    let blank = |cell: &String| cell.trim().is_empty();
+    if options.remove_empty_rows {
+        report.removed_rows = (0..table.rows.len())
+            .filter(|&ri| table.rows[ri].iter().all(blank))
+            .collect();
+        delete_rows(table, &report.removed_rows)?;
+    }
+    if options.remove_empty_columns {
+        report.removed_columns = (0..table.headers.len())
+            .filter(|&ci| {
+                blank(&table.headers[ci])
+                    && table.rows.iter().all(|row| row.get(ci).is_none_or(blank))
+            })
+            .collect();
+        if report.removed_columns.len() == table.headers.len() {
+            report.removed_columns.remove(0);
+        }
+        for &ci in report.removed_columns.iter().rev() {
+            delete_column(table, ci)?;
+        }
+    }
+    if options.trim_cells {
+        report.trimmed_cells = trim_table_cells(table);
+    }
+    Ok(report)
+}
+
+/// 列の集計方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    /// 数値セルの合計
+    Sum,
+    /// 空でないセルの数
+    Count,
+    /// 数値セルの平均
+    Mean,
+}
+
+/// セルを数値として解釈する（桁区切りのカンマは無視）
+pub fn parse_number(cell: &str) -> Option<f64> {
+    let cleaned: String = cell.trim().chars().filter(|&c| c != ',').collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    cleaned.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// 行頭にあれば読み飛ばす通貨記号
+const CURRENCY_SYMBOLS: &[char] = &['$', '¥', '￥', '€', '£'];
+
+/// 金額などの数値を読む（桁区切りのカンマと先頭の通貨記号を許す。`-$5` と `$-5` のどちらも可）
+pub(crate) fn parse_amount(cell: &str) -> Option<f64> {
+    let cell = cell.trim();
+    let (negative, rest) = match cell.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, cell),
+    };
+    let value = parse_number(rest.trim_start_matches(CURRENCY_SYMBOLS))?;
+    Some(if negative { -value } else { value })
+}
+
+/// 集計結果の数値をセル用の文字列にする（整数は小数点なし、小数は末尾の 0 を省く）
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let s = format!("{:.10}", value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 列の値を集計する（数値として読めないセルは Sum / Mean の対象外）
+pub fn aggregate_column(table: &MarkdownTable, col: usize, agg: Aggregation) -> String {
+    let cells = (0..table.rows.len()).map(|ri| get_cell(table, ri, col).unwrap_or(""));
+    match agg {
+        Aggregation::Count => cells.filter(|c| !c.trim().is_empty()).count().to_string(),
+        Aggregation::Sum => format_number(cells.filter_map(parse_number).sum()),
+        Aggregation::Mean => {
+            let values: Vec<f64> = cells.filter_map(parse_number).collect();
+            if values.is_empty() {
+                String::new()
+            } else {
+                format_number(values.iter().sum::<f64>() / values.len() as f64)
+            }
+        }
+    }
+}
+
+/// 集計行をテーブル末尾に追加する
+///
+/// `agg_specs` で指定した列に集計値、`label_col` にラベル、それ以外は空文字を入れる。
+/// 既に集計行がある場合は集計行を除いて再計算し、置き換える。
+pub fn append_summary_row(
+    table: &mut MarkdownTable,
+    agg_specs: &[(usize, Aggregation)],
+    label_col: usize,
+    label: &str,
+) {
+    if table.has_summary_row && table.rows.pop().is_some() {
+        table.end_line = table.end_line.saturating_sub(1);
+        // 置き換える集計行の `verbatim` と注釈は外す
+        let last = table.rows.len();
+        table.remap_verbatim(|r, c| (r != last).then_some((r, c)));
+    }
+    let mut row = vec![String::new(); table.headers.len()];
+    for &(col, agg) in agg_specs {
+        if col < row.len() {
+            row[col] = aggregate_column(table, col, agg);
+        }
+    }
+    if label_col < row.len() {
+        row[label_col] = label.to_string();
+    }
+    table.rows.push(row);
+    table.end_line += 1;
+    table.has_summary_row = true;
+}
+
+/// セルに書くテキストとして使えるか確かめる（`what` はエラーメッセージでの呼び名）
+///
+/// パーサーは `\|` も含めてすべてのパイプで列を区切るため、パイプや改行を含むテキストは
+/// 保存すると列がずれる。
+pub(crate) fn check_cell_text(value: &str, what: &str) -> Result<(), String> {
+    if value.contains('|') {
+        return Err(format!("{}にパイプ（|）は使えません: {}", what, value));
+    }
+    if value.contains(['\n', '\r']) {
+        return Err(format!("{}に改行は使えません", what));
+    }
+    Ok(())
+}
+
+/// 列の操作で見つかった注意点（操作自体は行う）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ColumnWarning {
+    /// 同じ名前の列が複数ある（列名での並べ替え・絞り込みでは最初の列だけが対象になる）
+    DuplicateHeader { name: String, columns: Vec<usize> },
+}
+
+/// 列のヘッダーだけを書き換える（パイプや改行を含む名前はエラー）
+///
+/// 新しい名前が他の列と重なる場合は変更したうえで `DuplicateHeader` を返す。
+pub fn rename_column(
+    table: &mut MarkdownTable,
+    col: usize,
+    new_name: &str,
+) -> Result<Vec<ColumnWarning>, String> {
+    check_cell_text(new_name, "列名")?;
+    let new_name = new_name.trim();
+    let header = table
+        .headers
+        .get_mut(col)
+        .ok_or_else(|| format!("列番号が範囲外です: {}", col))?;
+    *header = new_name.to_string();
+    let columns: Vec<usize> = (0..table.headers.len())
+        .filter(|&i| table.headers[i].trim() == new_name)
+        .collect();
+    Ok(if columns.len() > 1 && !new_name.is_empty() {
+        vec![ColumnWarning::DuplicateHeader {
+            name: new_name.to_string(),
+            columns,
+        }]
+    } else {
+        Vec::new()
+    })
+}
+
+/// 列数がヘッダーより少ない行を空のセルで埋め、アライメントの数もヘッダーに揃える
+fn pad_to_headers(table: &mut MarkdownTable) {
+    let cols = table.headers.len();
+    for row in table.rows.iter_mut().filter(|r| r.len() < cols) {
+        row.resize(cols, String::new());
+    }
+    table.alignments.resize(cols, "none".to_string());
+}
+
+/// `at` の位置に列を追加する（ヘッダー・アライメント・すべての行をまとめて更新する）
+///
+/// `alignment` は `left` / `center` / `right` / `none` のいずれか。新しい列のセルは
+/// `default_value` で埋める。追加する前に列数の足りない行を空のセルで埋める。
+pub fn insert_column(
+    table: &mut MarkdownTable,
+    at: usize,
+    header: &str,
+    alignment: &str,
+    default_value: &str,
+) -> Result<(), String> {
+    if at > table.headers.len() {
+        return Err(format!(
+            "列番号が範囲外です: {}（{} 列）",
+            at,
+            table.headers.len()
+        ));
+    }
+    if !matches!(alignment, "left" | "center" | "right" | "none") {
+        return Err(format!("アライメントが正しくありません: {}", alignment));
+    }
+    check_cell_text(header, "列名")?;
+    check_cell_text(default_value, "セルの値")?;
+    pad_to_headers(table);
+    table.headers.insert(at, header.trim().to_string());
+    table.alignments.insert(at, alignment.to_string());
+    for row in &mut table.rows {
+        row.insert(at, default_value.trim().to_string());
+    }
+    table.remap_verbatim(|r, c| Some((r, if c >= at { c + 1 } else { c })));
+    Ok(())
+}
+
+/// 列の分け方
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnSplitter {
+    /// 区切り文字で分ける（`max_splits` は分ける回数の上限）
+    Delimiter {
+        delimiter: String,
+        #[serde(default)]
+        max_splits: Option<usize>,
+    },
+    /// 正規表現のキャプチャグループごとに分ける（グループ数は新しい列の数と同じにする）
+    Regex { pattern: String },
+}
+
+/// 列 `col` のセルを分けた値を、その列のすぐ後ろに新しい列として追加する
+///
+/// 区切り文字では `max_splits` 回（省略時は列数に合わせて）まで分け、残りは最後の列にまとめる。
+/// 区切り文字を含まないセル・正規表現に一致しないセルは新しい列を空にし、その行番号を返す
+/// （空のセルは含めない）。新しい列は元の列のアライメントを引き継ぐ。`keep_original` が
+/// false なら元の列を削除する。
+pub fn split_column_by(
+    table: &mut MarkdownTable,
+    col: usize,
+    splitter: &ColumnSplitter,
+    new_headers: Vec<String>,
+    keep_original: bool,
+) -> Result<Vec<usize>, String> {
+    let width = table.headers.len();
+    if col >= width {
+        return Err(format!("列番号が範囲外です: {}（{} 列）", col, width));
+    }
+    if new_headers.is_empty() {
+        return Err("新しい列名を1つ以上指定してください".to_string());
+    }
+    for name in &new_headers {
+        check_cell_text(name, "列名")?;
+    }
+    let n = new_headers.len();
+    /// 検証・コンパイル済みの分け方
+    enum Compiled<'a> {
+        Delimiter(&'a str, usize),
+        Regex(regex::Regex),
+    }
+    let compiled = match splitter {
+        ColumnSplitter::Delimiter { delimiter, .. } if delimiter.is_empty() => {
+            return Err("区切り文字を指定してください".to_string());
+        }
+        ColumnSplitter::Delimiter {
+            delimiter,
+            max_splits,
+        } => Compiled::Delimiter(delimiter, max_splits.map_or(n, |m| (m + 1).min(n))),
+        ColumnSplitter::Regex { pattern } => {
+            let re =
+                regex::Regex::new(pattern).map_err(|e| format!("正規表現が不正です: {}", e))?;
+            if re.captures_len() - 1 != n {
+                return Err(format!(
+                    "キャプチャグループの数が列名の数と一致しません（グループ {} 個、列名 {} 個）",
+                    re.captures_len() - 1,
+                    n
+                ));
+            }
+            Compiled::Regex(re)
+        }
+    };
+    let split = |cell: &str| -> Option<Vec<String>> {
+        let mut parts: Vec<String> = match &compiled {
+            Compiled::Regex(re) => {
+                let caps = re.captures(cell)?;
+                (1..=n)
+                    .map(|i| caps.get(i).map_or("", |m| m.as_str()).to_string())
+                    .collect()
+            }
+            Compiled::Delimiter(delimiter, limit) => {
+                if !cell.contains(delimiter) {
+                    return None;
+                }
+                cell.splitn(*limit, delimiter).map(str::to_string).collect()
+            }
+        };
+        for part in &mut parts {
+            *part = part.trim().to_string();
+        }
+        parts.resize(n, String::new());
+        Some(parts)
+    };
+
+    pad_to_headers(table);
+    let mut unmatched = Vec::new();
+    for (ri, row) in table.rows.iter_mut().enumerate() {
+        let cell = row[col].trim();
+        let parts = split(cell).unwrap_or_else(|| {
+            if !cell.is_empty() {
+                unmatched.push(ri);
+            }
+            vec![String::new(); n]
+        });
+        row.splice(col + 1..col + 1, parts);
+        if !keep_original {
+            row.remove(col);
+        }
+    }
+    let alignment = table.alignments[col].clone();
+    table
+        .alignments
+        .splice(col + 1..col + 1, std::iter::repeat_n(alignment, n));
+    table.headers.splice(
+        col + 1..col + 1,
+        new_headers.iter().map(|h| h.trim().to_string()),
+    );
+    if !keep_original {
+        table.alignments.remove(col);
+        table.headers.remove(col);
+    }
+    table.remap_verbatim(|r, c| match c.cmp(&col) {
+        Ordering::Less => Some((r, c)),
+        Ordering::Equal => keep_original.then_some((r, c)),
+        Ordering::Greater if keep_original => Some((r, c + n)),
+        Ordering::Greater => Some((r, c + n - 1)),
+    });
+    Ok(unmatched)
+}
+
+/// 列を削除する（ヘッダー・アライメント・すべての行をまとめて更新する）
+///
+/// 最後の1列は削除できない。
+pub fn delete_column(table: &mut MarkdownTable, col: usize) -> Result<(), String> {
+    if col >= table.headers.len() {
+        return Err(format!(
+            "列番号が範囲外です: {}（{} 列）",
+            col,
+            table.headers.len()
+        ));
+    }
+    if table.headers.len() == 1 {
+        return Err("最後の列は削除できません".to_string());
+    }
+    pad_to_headers(table);
+    table.headers.remove(col);
+    table.alignments.remove(col);
+    for row in &mut table.rows {
+        row.remove(col);
+    }
+    table.remap_verbatim(|r, c| match c.cmp(&col) {
+        Ordering::Less => Some((r, c)),
+        Ordering::Equal => None,
+        Ordering::Greater => Some((r, c - 1)),
+    });
+    Ok(())
+}
+
+/// 列の値から推定した型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    /// 空でないセルがない（どの値でも受け付ける）
+    Empty,
+    /// すべて数値（通貨記号・桁区切り付きも可）
+    Number,
+    /// すべて `true` / `false`
+    Boolean,
+    /// すべて `YYYY-MM-DD` 形式の日付
+    Date,
+    /// それ以外
+    Text,
+}
+
+fn is_boolean(cell: &str) -> bool {
+    matches!(cell.trim().to_ascii_lowercase().as_str(), "true" | "false")
+}
+
+fn is_date(cell: &str) -> bool {
+    let b = cell.trim().as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b.iter()
+            .enumerate()
+            .all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+}
+
+impl ColumnType {
+    /// 値がこの型として受け付けられるか（空のセルは常に受け付ける）
+    pub fn accepts(self, cell: &str) -> bool {
+        cell.trim().is_empty()
+            || match self {
+                ColumnType::Empty | ColumnType::Text => true,
+                ColumnType::Number => parse_amount(cell).is_some(),
+                ColumnType::Boolean => is_boolean(cell),
+                ColumnType::Date => is_date(cell),
+            }
+    }
+}
+
+/// 列の型を推定する（空のセルと集計行は判定に使わない）
+pub fn infer_column_type(table: &MarkdownTable, col: usize) -> ColumnType {
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    let cells: Vec<&str> = (0..data_rows)
+        .filter_map(|ri| get_cell(table, ri, col))
+        .filter(|c| !c.trim().is_empty())
+        .collect();
+    if cells.is_empty() {
+        return ColumnType::Empty;
+    }
+    [ColumnType::Number, ColumnType::Boolean, ColumnType::Date]
+        .into_iter()
+        .find(|ty| cells.iter().all(|c| ty.accepts(c)))
+        .unwrap_or(ColumnType::Text)
+}
+
+/// 行を `at` の位置に挿入する（`at` が行数以上なら末尾、集計行があればその手前）
+///
+/// 値は列数に合わせて空文字列で埋めるか切り詰める。`strict_types` を指定すると、
+/// 推定した列の型に合わない値があれば挿入せずにエラーにする。
+pub fn insert_row(
+    table: &mut MarkdownTable,
+    at: usize,
+    mut values: Vec<String>,
+    strict_types: bool,
+) -> Result<usize, String> {
+    let cols = table.headers.len();
+    values.resize(cols, String::new());
+    if strict_types {
+        for (col, value) in values.iter().enumerate() {
+            let ty = infer_column_type(table, col);
+            if !ty.accepts(value) {
+                return Err(format!(
+                    "列「{}」の型（{:?}）に合わない値です: {}",
+                    table.headers[col].trim(),
+                    ty,
+                    value
+                ));
+            }
+        }
+    }
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    let at = at.min(data_rows);
+    table.rows.insert(at, values);
+    table.remap_verbatim(|r, c| Some((if r >= at { r + 1 } else { r }, c)));
+    table.end_line += 1;
+    Ok(at)
+}
+
+/// 指定した行をまとめて削除し、削除した行数を返す（`end_line` も詰める）
+///
+/// 範囲外の行番号が1つでもあれば何も削除せずにエラーにする。重複した行番号は1回として扱う。
+/// 集計行を削除した場合は `has_summary_row` を外す。
+pub fn delete_rows(table: &mut MarkdownTable, indices: &[usize]) -> Result<usize, String> {
+    let len = table.rows.len();
+    if let Some(&bad) = indices.iter().find(|&&i| i >= len) {
+        return Err(format!("行が範囲外です: {}（{} 行）", bad, len));
+    }
+    let mut remove = vec![false; len];
+    for &i in indices {
+        remove[i] = true;
+    }
+    if table.has_summary_row && remove.last() == Some(&true) {
+        table.has_summary_row = false;
+    }
+    let removed = remove_rows_masked(table, &remove);
+    table.end_line -= removed;
+    Ok(removed)
+}
+
+/// `remove[ri]` が true の行を取り除き、`verbatim` と注釈の位置も詰める（`end_line` はそのまま）
+fn remove_rows_masked(table: &mut MarkdownTable, remove: &[bool]) -> usize {
+    let mut new_index = Vec::with_capacity(remove.len());
+    let mut kept = 0;
+    for &r in remove {
+        new_index.push((!r).then_some(kept));
+        kept += usize::from(!r);
+    }
+    let mut ri = 0;
+    table.rows.retain(|_| {
+        ri += 1;
+        !remove[ri - 1]
+    });
+    table.remap_verbatim(|r, c| new_index.get(r).copied().flatten().map(|r| (r, c)));
+    remove.len() - kept
+}
+
+/// 行の並べ替えの向き
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// 行の並べ替えでのセルの比べ方
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    /// 文字列として比べる
+    Text,
+    /// 数値として比べる（数値として読めないセルは数値の後に文字列順）
+    Numeric,
+    /// 数字の並びを数値として比べる（`item2` < `item10`）
+    Natural,
+    /// 列の型が数値なら Numeric、それ以外は Natural
+    #[default]
+    Auto,
+    /// ロケールの照合順で比べる（全角・半角の違いは無視。ロケール省略時はシステムのロケール）
+    Collate(Option<String>),
+}
+
+/// 2つの文字列の比べ方（`SortMode::Collate` のロケールごとの照合など）
+pub type Comparator = Box<dyn Fn(&str, &str) -> Ordering>;
+
+/// ロケールの照合器などを解決したあとのセルの比べ方
+enum CellOrder {
+    Text,
+    Numeric,
+    Natural,
+    Collate(Comparator),
+}
+
+/// 数字の並びを数値として比べる（大文字・小文字は区別しない）
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn chunks(s: &str) -> Vec<(bool, String)> {
+        let mut out: Vec<(bool, String)> = Vec::new();
+        for c in s.chars().flat_map(char::to_lowercase) {
+            let digit = c.is_ascii_digit();
+            match out.last_mut() {
+                Some((d, chunk)) if *d == digit => chunk.push(c),
+                _ => out.push((digit, c.to_string())),
+            }
+        }
+        out
+    }
+    let (ca, cb) = (chunks(a), chunks(b));
+    for ((da, xa), (db, xb)) in ca.iter().zip(&cb) {
+        let ord = if *da && *db {
+            let (ta, tb) = (xa.trim_start_matches('0'), xb.trim_start_matches('0'));
+            ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb))
+        } else {
+            xa.cmp(xb)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    ca.len().cmp(&cb.len())
+}
+
+/// 空でない2つのセルを比べる
+fn compare_cells(a: &str, b: &str, order: &CellOrder) -> Ordering {
+    match order {
+        CellOrder::Text => a.cmp(b),
+        CellOrder::Natural => natural_cmp(a, b),
+        CellOrder::Collate(compare) => compare(a, b),
+        CellOrder::Numeric => match (parse_amount(a), parse_amount(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(b),
+        },
+    }
+}
+
+/// 列の指定（列番号または列名）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+impl ColumnRef {
+    /// 列番号に解決する（見つからなければ列名の一覧を添えたエラー）
+    pub fn resolve(&self, table: &MarkdownTable) -> Result<usize, String> {
+        let found = match self {
+            ColumnRef::Index(i) => (*i < table.headers.len()).then_some(*i),
+            ColumnRef::Name(name) => table.headers.iter().position(|h| h.trim() == name.trim()),
+        };
+        found.ok_or_else(|| {
+            let label = match self {
+                ColumnRef::Index(i) => i.to_string(),
+                ColumnRef::Name(name) => name.clone(),
+            };
+            format!(
+                "列が見つかりません: {}（列: {}）",
+                label,
+                table.headers.join(", ")
+            )
+        })
+    }
+}
+
+/// 並べ替えのキー1つ分
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub column: ColumnRef,
+    #[serde(default)]
+    pub order: SortDirection,
+    #[serde(default)]
+    pub mode: SortMode,
+}
+
+/// 複数のキーで並べ替えたときの行の順序を返す（`order[i]` は並べ替え後 i 行目の元の行番号）
+///
+/// 前のキーが同じ行だけを次のキーで比べ、すべて同じなら元の順序を保つ（安定ソート）。
+/// 各キーの空のセルは向きにかかわらず後ろに置き、集計行は最後の行のまま残す。
+/// `Collate` の比べ方は `collate` にロケール（省略時は None）を渡して取る。テーブルは変更しない。
+pub fn sorted_row_order(
+    table: &MarkdownTable,
+    keys: &[SortKey],
+    collate: &dyn Fn(Option<&str>) -> Result<Comparator, String>,
+) -> Result<Vec<usize>, String> {
+    if keys.is_empty() {
+        return Err("並べ替えのキーを指定してください".to_string());
+    }
+    let mut resolved = Vec::with_capacity(keys.len());
+    for key in keys {
+        let col = key.column.resolve(table)?;
+        let mode = match &key.mode {
+            SortMode::Text => CellOrder::Text,
+            SortMode::Numeric => CellOrder::Numeric,
+            SortMode::Natural => CellOrder::Natural,
+            SortMode::Auto if infer_column_type(table, col) == ColumnType::Number => {
+                CellOrder::Numeric
+            }
+            SortMode::Auto => CellOrder::Natural,
+            SortMode::Collate(locale) => CellOrder::Collate(collate(locale.as_deref())?),
+        };
+        resolved.push((col, key.order, mode));
+    }
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    let cell = |ri: usize, col: usize| get_cell(table, ri, col).map_or("", str::trim);
+    let mut order: Vec<usize> = (0..data_rows).collect();
+    order.sort_by(|&ra, &rb| {
+        resolved
+            .iter()
+            .map(|(col, direction, mode)| {
+                let (col, direction) = (*col, *direction);
+                let (a, b) = (cell(ra, col), cell(rb, col));
+                match (a.is_empty(), b.is_empty()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => {
+                        let ord = compare_cells(a, b, mode);
+                        match direction {
+                            SortDirection::Asc => ord,
+                            SortDirection::Desc => ord.reverse(),
+                        }
+                    }
+                }
+            })
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    order.extend(data_rows..table.rows.len());
+    Ok(order)
+}
+
+/// 複数のキーで行を安定ソートし、行の順序（元の行番号）を返す
+pub fn sort_table_rows_multi(
+    table: &mut MarkdownTable,
+    keys: &[SortKey],
+    collate: &dyn Fn(Option<&str>) -> Result<Comparator, String>,
+) -> Result<Vec<usize>, String> {
+    let order = sorted_row_order(table, keys, collate)?;
+    let mut rows: Vec<Option<Vec<String>>> = table.rows.drain(..).map(Some).collect();
+    table.rows = order.iter().filter_map(|&i| rows[i].take()).collect();
+    let mut new_index = vec![0; order.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
+    }
+    table.remap_verbatim(|r, c| new_index.get(r).map(|&r| (r, c)));
+    Ok(order)
+}
+
+/// 指定列で行を安定ソートする（値が同じ行は元の順序を保つ）
+///
+/// 空のセルは向きにかかわらず末尾に置き、集計行は最後の行のまま残す。
+pub fn sort_table_rows(
+    table: &mut MarkdownTable,
+    col: usize,
+    direction: SortDirection,
+    mode: SortMode,
+    collate: &dyn Fn(Option<&str>) -> Result<Comparator, String>,
+) -> Result<(), String> {
+    let key = SortKey {
+        column: ColumnRef::Index(col),
+        order: direction,
+        mode,
+    };
+    sort_table_rows_multi(table, &[key], collate).map(|_| ())
+}
+
+/// 行と列を入れ替える
+///
+/// `first_column_as_header` が true なら1列目が新しいヘッダーに、元のヘッダーが新しい1列目になる
+/// （2回行うと元に戻る）。false ならヘッダーも含めて入れ替えたうえで `Col 1`, `Col 2`, ... を
+/// ヘッダーにする。列数の足りない行は先に空のセルで埋めるのでデータは失われない。アライメントは
+/// `none` に戻し、`infer_alignments` が true なら数値の列だけ右寄せにする。集計行の印は外す。
+pub fn transpose_table(
+    table: &mut MarkdownTable,
+    first_column_as_header: bool,
+    infer_alignments: bool,
+) {
+    let width = table
+        .rows
+        .iter()
+        .map(Vec::len)
+        .chain([table.headers.len()])
+        .max()
+        .unwrap_or(0);
+    let mut grid: Vec<Vec<String>> = std::iter::once(std::mem::take(&mut table.headers))
+        .chain(std::mem::take(&mut table.rows))
+        .collect();
+    for row in &mut grid {
+        row.resize(width, String::new());
+    }
+    let mut transposed: Vec<Vec<String>> = (0..width)
+        .map(|c| {
+            grid.iter_mut()
+                .map(|row| std::mem::take(&mut row[c]))
+                .collect()
+        })
+        .collect();
+    if first_column_as_header && !transposed.is_empty() {
+        table.headers = transposed.remove(0);
+        table.remap_verbatim(|r, c| c.checked_sub(1).map(|c| (c, r + 1)));
+    } else {
+        table.headers = (1..=grid.len()).map(|i| format!("Col {}", i)).collect();
+        table.remap_verbatim(|r, c| Some((c, r + 1)));
+    }
+    table.rows = transposed;
+    table.has_summary_row = false;
+    table.end_line = table.start_line + 1 + table.rows.len();
+    table.alignments = (0..table.headers.len())
+        .map(|col| {
+            let numeric = infer_alignments && infer_column_type(table, col) == ColumnType::Number;
+            if numeric { "right" } else { "none" }.to_string()
+        })
+        .collect();
+}
+
+/// テーブルの結合のエラー（フロントエンドで列の対応を尋ねられるよう区別する）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MergeError {
+    /// 列名が対応しない列がある（`column_mapping` で対応を指定する）
+    UnmatchedColumns {
+        /// 結合元にだけある列名
+        source_only: Vec<String>,
+        /// 結合先にだけある列名
+        target_only: Vec<String>,
+    },
+    /// テーブルが見つからない・対応表の列名が存在しないなど
+    Invalid { message: String },
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::UnmatchedColumns {
+                source_only,
+                target_only,
+            } => write!(
+                f,
+                "列が対応しません（結合元のみ: {}、結合先のみ: {}）",
+                source_only.join(", "),
+                target_only.join(", ")
+            ),
+            MergeError::Invalid { message } => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for MergeError {
+    fn from(message: String) -> Self {
+        MergeError::Invalid { message }
+    }
+}
+
+/// `source` のデータ行を `target` の末尾（集計行の手前）に追加する
+///
+/// 列は列名で対応させる。`mapping` を省略した場合、列名の集合が一致しなければ
+/// `UnmatchedColumns` を返す。`mapping`（結合元の列名 → 結合先の列名）を指定した場合は
+/// 対応表で列名を読み替え、結合先にない列は末尾に追加する。片方にしかない列は、
+/// もう片方の行では空のセルになる。結合元の集計行は追加しない。
+pub fn append_table_rows(
+    target: &mut MarkdownTable,
+    source: &MarkdownTable,
+    mapping: Option<&[(String, String)]>,
+) -> Result<usize, MergeError> {
+    for (from, _) in mapping.unwrap_or_default() {
+        if !source.headers.iter().any(|h| h.trim() == from.trim()) {
+            return Err(format!("結合元に列がありません: {}", from).into());
+        }
+    }
+    let names: Vec<String> = source
+        .headers
+        .iter()
+        .map(|h| {
+            mapping
+                .unwrap_or_default()
+                .iter()
+                .find(|(from, _)| from.trim() == h.trim())
+                .map_or(h.trim(), |(_, to)| to.trim())
+                .to_string()
+        })
+        .collect();
+    let find = |headers: &[String], name: &str| headers.iter().position(|h| h.trim() == name);
+    if mapping.is_none() {
+        let source_only: Vec<String> = names
+            .iter()
+            .filter(|n| find(&target.headers, n).is_none())
+            .cloned()
+            .collect();
+        let target_only: Vec<String> = target
+            .headers
+            .iter()
+            .filter(|h| !names.contains(&h.trim().to_string()))
+            .cloned()
+            .collect();
+        if !source_only.is_empty() || !target_only.is_empty() {
+            return Err(MergeError::UnmatchedColumns {
+                source_only,
+                target_only,
+            });
+        }
+    }
+    for name in &names {
+        check_cell_text(name, "列名")?;
+    }
+
+    pad_to_headers(target);
+    let cols: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .map(|(ci, name)| {
+            find(&target.headers, name).unwrap_or_else(|| {
+                target.headers.push(name.clone());
+                target.alignments.push(
+                    source
+                        .alignments
+                        .get(ci)
+                        .cloned()
+                        .unwrap_or_else(|| "none".to_string()),
+                );
+                target.headers.len() - 1
+            })
+        })
+        .collect();
+    pad_to_headers(target);
+
+    let data_rows = source
+        .rows
+        .len()
+        .saturating_sub(usize::from(source.has_summary_row));
+    let at = target
+        .rows
+        .len()
+        .saturating_sub(usize::from(target.has_summary_row));
+    let width = target.headers.len();
+    let appended: Vec<Vec<String>> = source.rows[..data_rows]
+        .iter()
+        .map(|row| {
+            let mut cells = vec![String::new(); width];
+            for (ci, &to) in cols.iter().enumerate() {
+                cells[to] = row.get(ci).cloned().unwrap_or_default();
+            }
+            cells
+        })
+        .collect();
+    target.rows.splice(at..at, appended);
+    target.remap_verbatim(|r, c| Some((if r >= at { r + data_rows } else { r }, c)));
+    target.verbatim.extend(
+        source
+            .verbatim
+            .iter()
+            .filter(|&&(r, c)| r < data_rows && c < cols.len())
+            .map(|&(r, c)| (at + r, cols[c])),
+    );
+    target.verbatim.sort_unstable();
+    target.end_line += data_rows;
+    Ok(data_rows)
+}
+
+/// `source` 番目のテーブルの行を `target` 番目のテーブルに結合し、`source` を取り除いた
+/// ドキュメント全体と結合後のテーブルを返す（`append_table_rows` を参照）
+///
+/// 取り除いたテーブルの前後の空行は1つにまとめる。返すテーブルの行番号は、結合元を
+/// 取り除く前のドキュメントでの位置（`end_line` は追加した行の分だけ伸ばしたもの）。
+pub fn merge_tables(
+    doc: &ParsedDocument,
+    source: usize,
+    target: usize,
+    mapping: Option<&[(String, String)]>,
+) -> Result<(String, MarkdownTable), MergeError> {
+    if source == target {
+        return Err("同じテーブルどうしは結合できません".to_string().into());
+    }
+    let find = |index: usize| {
+        doc.tables
+            .get(index)
+            .ok_or_else(|| format!("テーブルが見つかりません: {}", index))
+    };
+    let (source_table, mut merged) = (find(source)?, find(target)?.clone());
+    append_table_rows(&mut merged, source_table, mapping)?;
+
+    let lines = &doc.lines;
+    let mut removed = source_table.clone();
+    while removed.end_line + 1 < lines.len()
+        && lines[removed.end_line + 1].trim().is_empty()
+        && (removed.start_line == 0 || lines[removed.start_line - 1].trim().is_empty())
+    {
+        removed.end_line += 1;
+    }
+    // 結合先は結合前の行範囲を置き換える
+    let placed = MarkdownTable {
+        end_line: doc.tables[target].end_line,
+        ..merged.clone()
+    };
+    let mut tables = [removed, placed];
+    tables.sort_by_key(|t| t.start_line);
+    let source_start = source_table.start_line;
+    let content = rebuild_with(lines, &tables, |t| {
+        if t.start_line == source_start {
+            String::new()
+        } else {
+            serialize_table(t)
+        }
+    });
+    Ok((content, merged))
+}
+
+/// `group_col` の値ごとに `value_col` を集計した2列のテーブルを返す
+///
+/// グループは値が最初に現れた順に並ぶ。数値として読めないセルは Sum / Mean の対象外で、
+/// 集計行（`has_summary_row`）は集計に含めない。
+pub fn pivot_table(
+    table: &MarkdownTable,
+    group_col: usize,
+    value_col: usize,
+    agg: Aggregation,
+) -> Result<MarkdownTable, String> {
+    for col in [group_col, value_col] {
+        if col >= table.headers.len() {
+            return Err(format!("列番号が範囲外です: {}", col));
+        }
+    }
+    let mut source = table.clone();
+    if source.has_summary_row {
+        source.rows.pop();
+    }
+    let rows = split_table_by_column_value(&source, group_col)
+        .into_iter()
+        .map(|group| {
+            vec![
+                group.heading.clone().unwrap_or_default(),
+                aggregate_column(&group, value_col, agg),
+            ]
+        })
+        .collect();
+    Ok(MarkdownTable {
+        heading: table.heading.clone(),
+        headers: vec![
+            table.headers[group_col].clone(),
+            table.headers[value_col].clone(),
+        ],
+        alignments: vec![
+            table
+                .alignments
+                .get(group_col)
+                .cloned()
+                .unwrap_or_else(|| "none".to_string()),
+            "right".to_string(),
+        ],
+        rows,
+        start_line: table.start_line,
+        end_line: table.end_line,
+        has_summary_row: false,
+        verbatim: Vec::new(),
+        cell_annotations: Vec::new(),
+    })
+}
+
+/// 列を並べ替える。`perm[i]` は新しい i 列目に来る元の列番号
+///
+/// ヘッダー数を超えるはみ出しセルは末尾にそのまま残す。
+fn permute_columns(table: &mut MarkdownTable, perm: &[usize]) {
+    let pick = |cells: &[String]| -> Vec<String> {
+        perm.iter()
+            .map(|&i| cells.get(i).cloned().unwrap_or_default())
+            .chain(cells.iter().skip(perm.len()).cloned())
+            .collect()
+    };
+    table.headers = pick(&table.headers);
+    if !table.alignments.is_empty() {
+        table.alignments = perm
+            .iter()
+            .map(|&i| {
+                table
+                    .alignments
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| "none".to_string())
+            })
+            .collect();
+    }
+    for row in &mut table.rows {
+        *row = pick(row);
+    }
+    table.remap_verbatim(|r, c| {
+        perm.iter()
+            .position(|&i| i == c)
+            .or((c >= perm.len()).then_some(c))
+            .map(|c| (r, c))
+    });
+}
+
+/// ヘッダー名で指定した順に列を並べ替える
+///
+/// `order` にない列は、指定した列の後ろに元の相対順のまま並ぶ。
+/// 存在しない列名や重複した指定はエラーにする。
+pub fn reorder_columns_by_names(table: &mut MarkdownTable, order: &[&str]) -> Result<(), String> {
+    let mut perm = Vec::with_capacity(table.headers.len());
+    for name in order {
+        let idx = table
+            .column_index_of(name)
+            .ok_or_else(|| format!("列が見つかりません: {}", name))?;
+        if perm.contains(&idx) {
+            return Err(format!("列が重複して指定されています: {}", name));
+        }
+        perm.push(idx);
+    }
+    for idx in 0..table.headers.len() {
+        if !perm.contains(&idx) {
+            perm.push(idx);
+        }
+    }
+    permute_columns(table, &perm);
+    Ok(())
+}
+
+/// 列番号の並びで列を並べ替える（`order[i]` は新しい i 列目に来る元の列番号）
+///
+/// `order` は 0 から列数 - 1 までをちょうど1回ずつ含む並びでなければエラーにする。
+pub fn reorder_columns(table: &mut MarkdownTable, order: &[usize]) -> Result<(), String> {
+    let cols = table.headers.len();
+    if order.len() != cols {
+        return Err(format!(
+            "列の並びの数が列数と一致しません: {}（{} 列）",
+            order.len(),
+            cols
+        ));
+    }
+    let mut seen = vec![false; cols];
+    for &i in order {
+        if i >= cols || std::mem::replace(&mut seen[i], true) {
+            return Err(format!("列の並びが正しくありません: {:?}", order));
+        }
+    }
+    permute_columns(table, order);
+    Ok(())
+}
+
+/// 列を1つ `from` から `to` の位置へ移す（ヘッダー・アライメント・すべての行のセルを一緒に動かす）
+///
+/// `from == to` なら何もしない。
+pub fn move_column(table: &mut MarkdownTable, from: usize, to: usize) -> Result<(), String> {
+    let cols = table.headers.len();
+    if let Some(bad) = [from, to].into_iter().find(|&i| i >= cols) {
+        return Err(format!("列番号が範囲外です: {}（{} 列）", bad, cols));
+    }
+    if from == to {
+        return Ok(());
+    }
+    let mut order: Vec<usize> = (0..cols).collect();
+    let col = order.remove(from);
+    order.insert(to, col);
+    permute_columns(table, &order);
+    Ok(())
+}
+
+/// 指定した列だけを指定順に持つ新しいテーブルを返す（列の射影）
+///
+/// 存在しない列名を指定した場合はエラーにする。
+pub fn select_columns(table: &MarkdownTable, col_names: &[&str]) -> Result<MarkdownTable, String> {
+    let indices = col_names
+        .iter()
+        .map(|name| {
+            table
+                .column_index_of(name)
+                .ok_or_else(|| format!("列が見つかりません: {}", name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let pick = |cells: &[String], fallback: &str| -> Vec<String> {
+        indices
+            .iter()
+            .map(|&i| {
+                cells
+                    .get(i)
+                    .map_or_else(|| fallback.to_string(), |c| c.clone())
+            })
+            .collect()
+    };
+    Ok(MarkdownTable {
+        heading: table.heading.clone(),
+        headers: pick(&table.headers, ""),
+        alignments: pick(&table.alignments, "none"),
+        rows: table.rows.iter().map(|row| pick(row, "")).collect(),
+        start_line: table.start_line,
+        end_line: table.end_line,
+        has_summary_row: table.has_summary_row,
+        verbatim: table
+            .verbatim
+            .iter()
+            .filter_map(|&(r, c)| indices.iter().position(|&i| i == c).map(|c| (r, c)))
+            .collect(),
+        cell_annotations: Vec::new(),
+    })
+}
+
+/// 見出しテキストから ID 用のスラッグを作る（英数字・かなは残し、空白と記号は `-` にまとめる）
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for ch in text.chars().flat_map(char::to_lowercase) {
+        if ch.is_alphanumeric() {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// 各テーブルの安定 ID を返す
+///
+/// 直前の見出しのスラッグを ID とし、すでに使われている ID と重なる場合は `-2`, `-3` … のうち
+/// 空いている最初のものを付ける。見出しのないテーブルは `table`。テーブル番号と区別できるよう、
+/// 数字だけのスラッグ（見出し `2` など）には `table-` を前に付ける。
+/// 行の編集や他の見出しの下へのテーブル追加では変わらない。
+pub fn table_ids(tables: &[MarkdownTable]) -> Vec<String> {
+    let mut emitted: HashSet<String> = HashSet::new();
+    tables
+        .iter()
+        .map(|t| {
+            let base = match t.heading.as_deref().map(slugify) {
+                Some(slug) if slug.parse::<usize>().is_ok() => format!("table-{}", slug),
+                Some(slug) if !slug.is_empty() => slug,
+                _ => "table".to_string(),
+            };
+            let id = (1..)
+                .map(|n| match n {
+                    1 => base.clone(),
+                    n => format!("{}-{}", base, n),
+                })
+                .find(|id| !emitted.contains(id))
+                .unwrap_or(base);
+            emitted.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
+/// テーブル番号（数字）または安定 ID の文字列からテーブル番号を解決する
+pub fn resolve_table_ref(tables: &[MarkdownTable], reference: &str) -> Result<usize, String> {
+    let reference = reference.trim();
+    if let Ok(index) = reference.parse::<usize>() {
+        return (index < tables.len())
+            .then_some(index)
+            .ok_or_else(|| format!("テーブルが見つかりません: {}", reference));
+    }
+    table_ids(tables)
+        .iter()
+        .position(|id| id == reference)
+        .ok_or_else(|| format!("テーブルが見つかりません: {}", reference))
+}
+
+/// `"table.row.col"` 形式のキーを検証し、ドキュメント内のセル座標に変換する
+fn parse_patch_key(doc: &ParsedDocument, key: &str) -> Result<(usize, usize, usize), String> {
+    let coords: Vec<&str> = key.split('.').collect();
+    let [t, r, c] = coords[..] else {
+        return Err(format!(
+            "セルの指定が不正です（\"table.row.col\" 形式で指定してください）: {}",
+            key
+        ));
+    };
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("セルの指定に数値以外が含まれています: {}", key))
+    };
+    let (t, r, c) = (parse(t)?, parse(r)?, parse(c)?);
+    let table = doc
+        .tables
+        .get(t)
+        .ok_or_else(|| format!("テーブル番号が範囲外です: {}", key))?;
+    if r >= table.rows.len() {
+        return Err(format!("行番号が範囲外です: {}", key));
+    }
+    if c >= table.headers.len() {
+        return Err(format!("列番号が範囲外です: {}", key));
+    }
+    Ok((t, r, c))
+}
+
+/// セル編集1件のキーを座標に変換し、値がセルに書ける文字列か確かめる
+fn parse_patch_entry(
+    doc: &ParsedDocument,
+    key: &str,
+    value: &str,
+) -> Result<(usize, usize, usize), String> {
+    let coords = parse_patch_key(doc, key)?;
+    check_cell_text(value, &format!("セル {} の値", key))?;
+    Ok(coords)
+}
+
+/// セル編集のキーがすべてドキュメント内の座標を指し、値にパイプや改行を含まないか確かめる（適用はしない）
+pub fn validate_sparse_patch(
+    doc: &ParsedDocument,
+    patch: &HashMap<String, String>,
+) -> Result<(), String> {
+    patch
+        .iter()
+        .try_for_each(|(key, value)| parse_patch_entry(doc, key, value).map(|_| ()))
+}
+
+/// `"table.row.col"` 形式のキーで指定したセル編集をまとめて適用する
+///
+/// 行番号はデータ行（ヘッダーを除く）の 0 始まり。すべてのキーと値を検証してから適用するため、
+/// 不正なキー・範囲外の座標・パイプや改行を含む値が1つでもあれば何も変更せずにエラーを返す。
+pub fn apply_sparse_patch(
+    doc: &mut ParsedDocument,
+    patch: HashMap<String, String>,
+) -> Result<(), String> {
+    let mut edits = Vec::with_capacity(patch.len());
+    for (key, value) in patch {
+        let (t, r, c) = parse_patch_entry(doc, &key, &value)?;
+        edits.push((t, r, c, value));
+    }
+    for (t, r, c, value) in edits {
+        set_cell(&mut doc.tables[t], r, c, value)?;
+    }
+    Ok(())
+}
+
+/// 指定列の値が似ている行の組を探す（行は削除しない）
+///
+/// 正規化した Levenshtein 距離による類似度（0.0〜1.0）が `similarity_threshold` 以上の
+/// `(row_a, row_b, similarity)` を返す（`row_a < row_b`）。空のセルは比較しない。
+pub fn fuzzy_deduplicate(
+    table: &mut MarkdownTable,
+    col_index: usize,
+    similarity_threshold: f64,
+) -> Vec<(usize, usize, f64)> {
+    let values: Vec<&str> = (0..table.rows.len())
+        .map(|ri| get_cell(table, ri, col_index).map_or("", str::trim))
+        .collect();
+    let mut pairs = Vec::new();
+    for a in 0..values.len() {
+        if values[a].is_empty() {
+            continue;
+        }
+        for b in a + 1..values.len() {
+            if values[b].is_empty() {
+                continue;
+            }
+            let similarity = strsim::normalized_levenshtein(values[a], values[b]);
+            if similarity >= similarity_threshold {
+                pairs.push((a, b, similarity));
+            }
+        }
+    }
+    pairs
+}
+
+/// 重複した行のうち残す行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepRow {
+    /// 最初に出てくる行を残す
+    #[default]
+    First,
+    /// 最後に出てくる行を残す
+    Last,
+}
+
+/// 重複として削除した行（元に戻せるよう、削除前の行番号と内容を持つ）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemovedRow {
+    pub index: usize,
+    pub cells: Vec<String>,
+}
+
+/// `deduplicate_rows` の結果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DedupReport {
+    pub removed_count: usize,
+    /// 削除前の行番号順
+    pub removed_rows: Vec<RemovedRow>,
+}
+
+/// 重複した行を削除する
+///
+/// `key_columns` が None のときは行全体、指定したときはその列だけを比べる。値は前後の空白を
+/// 除いて比べ、`case_insensitive` なら大文字・小文字も区別しない。集計行は対象外。
+pub fn deduplicate_rows(
+    table: &mut MarkdownTable,
+    key_columns: Option<&[ColumnRef]>,
+    keep: KeepRow,
+    case_insensitive: bool,
+) -> Result<DedupReport, String> {
+    let columns: Vec<usize> = match key_columns {
+        Some([]) => return Err("比べる列を指定してください".to_string()),
+        Some(refs) => refs
+            .iter()
+            .map(|c| c.resolve(table))
+            .collect::<Result<_, _>>()?,
+        None => (0..table.headers.len()).collect(),
+    };
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    let key_of = |ri: usize| -> Vec<String> {
+        columns
+            .iter()
+            .map(|&ci| {
+                let value = get_cell(table, ri, ci).unwrap_or("").trim();
+                if case_insensitive {
+                    value.to_lowercase()
+                } else {
+                    value.to_string()
+                }
+            })
+            .collect()
+    };
+    let order: Vec<usize> = match keep {
+        KeepRow::First => (0..data_rows).collect(),
+        KeepRow::Last => (0..data_rows).rev().collect(),
+    };
+    let mut seen = HashSet::new();
+    let mut duplicates: Vec<usize> = order
+        .into_iter()
+        .filter(|&ri| !seen.insert(key_of(ri)))
+        .collect();
+    duplicates.sort_unstable();
+    let removed_rows = duplicates
+        .iter()
+        .map(|&index| RemovedRow {
+            index,
+            cells: table.rows[index].clone(),
+        })
+        .collect();
+    let removed_count = delete_rows(table, &duplicates)?;
+    Ok(DedupReport {
+        removed_count,
+        removed_rows,
+    })
+}
+
+/// 大文字・小文字の変換方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseMode {
+    /// すべて小文字
+    Lowercase,
+    /// すべて大文字
+    Uppercase,
+    /// 単語（空白・ハイフン区切り）ごとに先頭を大文字、残りを小文字
+    TitleCase,
+    /// 先頭の1文字だけを大文字にし、残りは変えない
+    Capitalize,
+}
+
+/// 先頭の1文字を大文字にし、残りを `rest` で変換する
+fn upper_first(word: &str, rest: impl Fn(&str) -> String) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(rest(chars.as_str()).chars())
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// 文字列の大文字・小文字を変換する
+pub fn convert_case(text: &str, mode: CaseMode) -> String {
+    match mode {
+        CaseMode::Lowercase => text.to_lowercase(),
+        CaseMode::Uppercase => text.to_uppercase(),
+        CaseMode::Capitalize => upper_first(text, str::to_string),
+        CaseMode::TitleCase => {
+            let mut out = String::with_capacity(text.len());
+            let mut word_start = 0;
+            for (i, ch) in text.char_indices() {
+                if ch.is_whitespace() || ch == '-' {
+                    out.push_str(&upper_first(&text[word_start..i], str::to_lowercase));
+                    out.push(ch);
+                    word_start = i + ch.len_utf8();
+                }
+            }
+            out.push_str(&upper_first(&text[word_start..], str::to_lowercase));
+            out
+        }
+    }
+}
+
+/// 指定列のセルの大文字・小文字を揃え、変更したセル数を返す
+pub fn normalize_case(table: &mut MarkdownTable, col_index: usize, mode: CaseMode) -> usize {
+    let mut changed = 0;
+    for row in &mut table.rows {
+        if let Some(cell) = row.get_mut(col_index) {
+            let converted = convert_case(cell, mode);
+            if converted != *cell {
+                *cell = converted;
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// 指定列の値ごとに行を分け、値ごとのテーブルを返す
+///
+/// テーブルは値が最初に現れた順に並び、ヘッダーとアライメントは元のまま、
+/// `heading` にはその値が入る。列が範囲外なら空の Vec を返す。
+pub fn split_table_by_column_value(table: &MarkdownTable, col_index: usize) -> Vec<MarkdownTable> {
+    if col_index >= table.headers.len() {
+        return Vec::new();
+    }
+    let mut groups: Vec<MarkdownTable> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (ri, row) in table.rows.iter().enumerate() {
+        let value = get_cell(table, ri, col_index).unwrap_or("");
+        let gi = *index.entry(value).or_insert_with(|| {
+            groups.push(MarkdownTable {
+                heading: Some(value.to_string()),
+                rows: Vec::new(),
+                has_summary_row: false,
+                verbatim: Vec::new(),
+                cell_annotations: Vec::new(),
+                ..table.clone()
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[gi];
+        for &(_, c) in table.verbatim.iter().filter(|&&(r, _)| r == ri) {
+            group.verbatim.push((group.rows.len(), c));
+        }
+        group.rows.push(row.clone());
+    }
+    groups
+}
+
+/// テーブルの分割方法
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    /// 指定した範囲（データ行番号、`start..end`）の行をそれぞれ新しいテーブルに移す
+    ByRowRanges(Vec<Range<usize>>),
+    /// 指定した列の値ごとに新しいテーブルに分ける（元のテーブルは残らない）
+    ByColumnValue(ColumnRef),
+}
+
+/// テーブルを分割した結果
+#[derive(Debug, Clone)]
+pub struct TableSplit {
+    /// 元のテーブルに残った行（行がすべて移った場合は None）
+    pub remaining: Option<MarkdownTable>,
+    /// 新しいテーブル。`heading` には範囲（`行 51–100`）または値が入る
+    pub parts: Vec<MarkdownTable>,
+}
+
+/// テーブルを行の範囲または列の値で分割する（テーブルは変更しない）
+///
+/// 新しいテーブルのヘッダーとアライメントは元のまま。範囲は重なってはならず、集計行は
+/// 分割の対象外で、元のテーブルが残る場合だけ残す。範囲に含まれない行は元のテーブルに残る。
+pub fn split_table(table: &MarkdownTable, mode: &SplitMode) -> Result<TableSplit, String> {
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    let mut data = MarkdownTable {
+        has_summary_row: false,
+        ..table.clone()
+    };
+    data.rows.truncate(data_rows);
+    data.remap_verbatim(|r, c| (r < data_rows).then_some((r, c)));
+    let pick = |rows: &mut dyn Iterator<Item = usize>, heading: String| {
+        let mut part = MarkdownTable {
+            heading: Some(heading),
+            rows: Vec::new(),
+            verbatim: Vec::new(),
+            cell_annotations: Vec::new(),
+            ..data.clone()
+        };
+        for ri in rows {
+            for &(_, c) in data.verbatim.iter().filter(|&&(r, _)| r == ri) {
+                part.verbatim.push((part.rows.len(), c));
+            }
+            part.rows.push(data.rows[ri].clone());
+        }
+        part
+    };
+    match mode {
+        SplitMode::ByColumnValue(column) => {
+            let col = column.resolve(table)?;
+            let parts = split_table_by_column_value(&data, col)
+                .into_iter()
+                .map(|mut part| {
+                    if part.heading.as_deref() == Some("") {
+                        part.heading = Some("（空）".to_string());
+                    }
+                    part
+                })
+                .collect();
+            Ok(TableSplit {
+                remaining: None,
+                parts,
+            })
+        }
+        SplitMode::ByRowRanges(ranges) => {
+            if ranges.is_empty() {
+                return Err("分割する範囲を指定してください".to_string());
+            }
+            let mut moved = vec![false; data_rows];
+            for range in ranges {
+                if range.is_empty() || range.end > data_rows {
+                    return Err(format!(
+                        "行の範囲が正しくありません: {}..{}（{} 行）",
+                        range.start, range.end, data_rows
+                    ));
+                }
+                if moved[range.clone()].iter().any(|&m| m) {
+                    return Err(format!(
+                        "行の範囲が重なっています: {}..{}",
+                        range.start, range.end
+                    ));
+                }
+                moved[range.clone()].fill(true);
+            }
+            let parts = ranges
+                .iter()
+                .map(|range| {
+                    let heading = format!("行 {}–{}", range.start + 1, range.end);
+                    pick(&mut range.clone(), heading)
+                })
+                .collect();
+            let kept: Vec<usize> = (0..data_rows).filter(|&ri| !moved[ri]).collect();
+            let remaining = (!kept.is_empty()).then(|| {
+                let mut rest = pick(&mut kept.iter().copied(), String::new());
+                rest.heading = table.heading.clone();
+                if table.has_summary_row {
+                    rest.rows.push(table.rows[data_rows].clone());
+                    rest.has_summary_row = true;
+                }
+                rest.end_line = rest.start_line + 1 + rest.rows.len();
+                rest
+            });
+            Ok(TableSplit { remaining, parts })
+        }
+    }
+}
+
+/// 見出しや本文に入れる文字列の Markdown 記号をバックスラッシュでエスケープする
+pub fn escape_markdown_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if "\\`*_[]<>#|~!".contains(ch) {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// 分割結果を、元のテーブルの位置に置くテキストにする
+///
+/// 残ったテーブルの後に新しいテーブルを空行区切りで並べる。`with_headings` が true なら
+/// 各テーブルの前に範囲または値の `###` 見出しを付ける。
+pub fn render_table_split(split: &TableSplit, with_headings: bool) -> String {
+    let mut blocks: Vec<String> = split.remaining.iter().map(serialize_table).collect();
+    for part in &split.parts {
+        let table = serialize_table(part);
+        blocks.push(match (&part.heading, with_headings) {
+            (Some(heading), true) => {
+                format!("### {}\n\n{}", escape_markdown_text(heading), table)
+            }
+            _ => table,
+        });
+    }
+    blocks.join("\n")
+}
+
+/// タグ列（カンマ区切りのキーワード）から、タグごとにそれを含む行番号の一覧を作る
+///
+/// 各タグは前後の空白を除いて比べ、空のタグは無視する。同じ行に同じタグが複数あっても
+/// 行番号は1回だけ記録する。列が見つからなければ空のマップを返す。
+pub fn extract_tags(table: &MarkdownTable, col_name: &str) -> HashMap<String, Vec<usize>> {
+    let mut tags: HashMap<String, Vec<usize>> = HashMap::new();
+    let Some(col) = table.column_index_of(col_name.trim()) else {
+        return tags;
+    };
+    for ri in 0..table.rows.len() {
+        let cell = get_cell(table, ri, col).unwrap_or("");
+        for tag in cell.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let rows = tags.entry(tag.to_string()).or_default();
+            if rows.last() != Some(&ri) {
+                rows.push(ri);
+            }
+        }
+    }
+    tags
+}
+
+/// `| Key | Value |` 形式の2列のテーブルを、1列目の値から2列目の値へのマップにする
+///
+/// 列数が2でないテーブルと、同じキーが複数ある場合はエラーにする。
+pub fn table_to_key_value_map(table: &MarkdownTable) -> Result<HashMap<String, String>, String> {
+    if table.headers.len() != 2 {
+        return Err(format!(
+            "キーと値の2列のテーブルではありません（{} 列）",
+            table.headers.len()
+        ));
+    }
+    let mut map = HashMap::new();
+    for ri in 0..table.rows.len() {
+        let key = get_cell(table, ri, 0).unwrap_or("");
+        let value = get_cell(table, ri, 1).unwrap_or("");
+        if map.insert(key.to_string(), value.to_string()).is_some() {
+            return Err(format!("キーが重複しています: {}（{} 行目）", key, ri + 1));
+        }
+    }
+    Ok(map)
+}
+
+/// キーと値のマップから `| key_header | value_header |` の2列のテーブルを作る（`table_to_key_value_map` の逆）
+///
+/// 行はマップの挿入順に並べる。前後に空白のある値はそのまま残るよう印を付ける。
+/// 行番号は 0 のままなので、ドキュメントへの挿入は `insert_table_at_line` で行う。
+pub fn table_from_key_value_map(
+    map: &IndexMap<String, String>,
+    key_header: &str,
+    value_header: &str,
+) -> MarkdownTable {
+    let mut verbatim = Vec::new();
+    let rows = map
+        .iter()
+        .enumerate()
+        .map(|(ri, (key, value))| {
+            for (ci, cell) in [key, value].into_iter().enumerate() {
+                if !cell.is_empty() && cell.trim() != cell {
+                    verbatim.push((ri, ci));
+                }
+            }
+            vec![key.clone(), value.clone()]
+        })
+        .collect();
+    MarkdownTable {
+        heading: None,
+        headers: vec![
+            key_header.trim().to_string(),
+            value_header.trim().to_string(),
+        ],
+        alignments: vec!["none".to_string(); 2],
+        rows,
+        start_line: 0,
+        end_line: 0,
+        has_summary_row: false,
+        verbatim,
+        cell_annotations: Vec::new(),
+    }
+}
+
+/// 条件に合うデータセルの `(row, col)` を行優先の順で返す（ヘッダーは対象外）
+pub fn find_cells(table: &MarkdownTable, matches: impl Fn(&str) -> bool) -> Vec<(usize, usize)> {
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .flat_map(|(ri, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, cell)| matches(cell))
+                .map(move |(ci, _)| (ri, ci))
+        })
+        .collect()
+}
+
+/// セルを `f` の結果で置き換え、内容が変わったセル数を返す
+///
+/// `include_headers` が false の場合はデータ行のみを対象にする。置き換えた値にパイプや改行を
+/// 含むセルが1つでもあれば、何も変更せずにエラーを返す。
+pub fn replace_cells(
+    table: &mut MarkdownTable,
+    include_headers: bool,
+    f: impl Fn(&str) -> Cow<'_, str>,
+) -> Result<usize, String> {
+    let headers = table.headers.iter().filter(|_| include_headers);
+    let mut replaced = HashMap::new();
+    for (i, cell) in headers.chain(table.rows.iter().flatten()).enumerate() {
+        if let Cow::Owned(value) = f(cell) {
+            if value != *cell {
+                check_cell_text(&value, "置換後の値")?;
+                replaced.insert(i, value);
+            }
+        }
+    }
+    let changed = replaced.len();
+    let headers = table.headers.iter_mut().filter(|_| include_headers);
+    for (i, cell) in headers.chain(table.rows.iter_mut().flatten()).enumerate() {
+        if let Some(value) = replaced.remove(&i) {
+            *cell = value;
+        }
+    }
+    Ok(changed)
+}
+
+/// ヘッダーとすべてのセルの前後の空白を取り除き、変更したセル数を返す
+///
+/// パース時には空白を除去するが、プログラムから変更したセルや CSV から取り込んだ値には残りうる。
+pub fn trim_table_cells(table: &mut MarkdownTable) -> usize {
+    let mut changed = 0;
+    for cell in table
+        .headers
+        .iter_mut()
+        .chain(table.rows.iter_mut().flatten())
+    {
+        let trimmed = cell.trim();
+        if trimmed.len() != cell.len() {
+            *cell = trimmed.to_string();
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// `max_len` 文字より長いデータセルを、先頭の文字と `ellipsis` で `max_len` 文字に縮める
+///
+/// 文字数は Unicode スカラー値で数える（バイト数ではない）。ちょうど `max_len` 文字のセルは
+/// 変えない。`ellipsis` 自体が `max_len` 文字より長い場合は `ellipsis` を切り詰めて使う。
+/// 変更したセル数を返す。`max_len` が 0（すべてのセルが空になる）ならエラー。
+pub fn truncate_long_cells(
+    table: &mut MarkdownTable,
+    max_len: usize,
+    ellipsis: &str,
+) -> Result<usize, String> {
+    if max_len == 0 {
+        return Err("最大文字数は1以上を指定してください".to_string());
+    }
+    check_cell_text(ellipsis, "省略記号")?;
+    let ellipsis: String = ellipsis.chars().take(max_len).collect();
+    let keep = max_len - ellipsis.chars().count();
+    let mut changed = 0;
+    for cell in table.rows.iter_mut().flatten() {
+        if cell.chars().nth(max_len).is_none() {
+            continue;
+        }
+        let head: String = cell.chars().take(keep).collect();
+        *cell = head + &ellipsis;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Collate` でもコードポイント順に比べる（テストでは照合器を使わない）
+    fn plain_collate(_: Option<&str>) -> Result<Comparator, String> {
+        Ok(Box::new(|a, b| a.cmp(b)))
+    }
+
+    #[test]
+    fn test_parse_simple_table() {
+        let md = "# Test\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n| 3 | 4 |\n";
+        let doc = parse_markdown(md);
+        assert_eq!(doc.tables.len(), 1);
+        assert_eq!(doc.tables[0].headers, vec!["A", "B"]);
+        assert_eq!(doc.tables[0].rows.len(), 2);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let md = "# Heading\n\n| Name | Age |\n| --- | --- |\n| Alice | 30 |\n| Bob | 25 |\n";
+        let doc = parse_markdown(md);
+        let rebuilt = rebuild_document(&doc.lines, &doc.tables);
+        // パースし直して同じテーブルが取れることを確認
+        let doc2 = parse_markdown(&rebuilt);
+        assert_eq!(doc2.tables[0].headers, doc.tables[0].headers);
+        assert_eq!(doc2.tables[0].rows, doc.tables[0].rows);
+    }
+
+    #[test]
+    fn test_repair_broken_table() {
+        let md = "| A |  | C |\n| --- | --- |\n| 1 |\n| 1 | 2 | 3 | 4 |\n";
+        let doc = parse_markdown(md);
+        let mut table = doc.tables[0].clone();
+        let report = repair_table(&doc.lines, &mut table, true);
+        assert_eq!(
+            report.fixes,
+            vec![
+                RepairFix::WidenedColumns { added: 1 },
+                RepairFix::PaddedRow { row: 0, added: 3 },
+                RepairFix::FilledHeader {
+                    col: 1,
+                    name: "Column 2".to_string()
+                },
+                RepairFix::FilledHeader {
+                    col: 3,
+                    name: "Column 4".to_string()
+                },
+                RepairFix::AlignedAlignments { from: 2, to: 4 },
+            ]
+        );
+        assert_eq!(table.headers, vec!["A", "Column 2", "C", "Column 4"]);
+        assert_eq!(table.rows[1], vec!["1", "2", "3", "4"]);
+        assert_eq!(table.alignments.len(), 4);
+    }
+
+    #[test]
+    fn test_count_tables_matches_parse() {
+        let md =
+            "# A\n\n| A | B |\n| --- | --- |\n| 1 | 2 |\n\ntext\n\n| C |\n|---|\n| x |\n| --- |\n";
+        assert_eq!(count_tables(md), parse_markdown(md).tables.len());
+        assert_eq!(count_tables(md), 2);
+    }
+
+    #[test]
+    fn test_serialize_default_format_unchanged() {
+        let md = "| A | B | C | D |\n|---|:--|--:|:-:|\n| 1 | 2 | 3 | 4 |\n";
+        let doc = parse_markdown(md);
+        assert_eq!(
+            serialize_table(&doc.tables[0]),
+            "| A   | B   | C   | D   |\n| ----|:----| ---:|:---:|\n| 1   | 2   | 3   | 4   |\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_compact() {
+        let md = "|  Name   |Age|\n|:-----|------:|\n|   Alice | 30 |\n";
+        let doc = parse_markdown(md);
+        let opts = SerializeOptions {
+            style: TableStyle::Compact,
+            ..SerializeOptions::default()
+        };
+        assert_eq!(
+            serialize_table_with(&doc.tables[0], &opts),
+            "| Name | Age |\n| :-- | --: |\n| Alice | 30 |\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_empty_tables() {
+        let md = "# A\n| X |\n| --- |\n\n| Y |\n| --- |\n| 1 |\n\n| Z |\n| --- |\n";
+        let mut doc = parse_markdown(md);
+        assert_eq!(doc.tables.len(), 3);
+        assert_eq!(remove_empty_tables(&mut doc), 2);
+        assert_eq!(doc.tables.len(), 1);
+        assert_eq!(doc.tables[0].headers, vec!["Y"]);
+        assert_eq!(doc.tables[0].start_line, 2);
+        assert_eq!(doc.lines[2], "| Y |");
+        assert_eq!(parse_markdown(&doc.lines.join("\n")).tables.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_table_at_line() {
+        let md = "# A\n| X |\n| --- |\n| 1 |\nintro\n## B\n| Y |\n| --- |\n| 2 |";
+        let mut doc = parse_markdown(md);
+        let new_table = MarkdownTable {
+            heading: None,
+            headers: vec!["New".to_string(), "Col".to_string()],
+            alignments: vec!["right".to_string()],
+            rows: vec![vec!["a".to_string(), "b".to_string()]],
+            start_line: 0,
+            end_line: 0,
+            has_summary_row: false,
+            verbatim: Vec::new(),
+            cell_annotations: Vec::new(),
+        };
+        assert!(insert_table_at_line(&mut doc, 2, new_table.clone()).is_err());
+        assert!(insert_table_at_line(&mut doc, 10, new_table.clone()).is_err());
+
+        insert_table_at_line(&mut doc, 5, new_table).unwrap();
+        let headers: Vec<&str> = doc.tables.iter().map(|t| t.headers[0].as_str()).collect();
+        assert_eq!(headers, vec!["X", "New", "Y"]);
+        assert_eq!(doc.lines[5], "");
+        assert_eq!(doc.lines[9], "");
+        assert_eq!(doc.lines[10], "## B");
+
+        let reparsed = parse_markdown(&doc.lines.join("\n"));
+        assert_eq!(reparsed.tables.len(), 3);
+        for (a, b) in reparsed.tables.iter().zip(&doc.tables) {
+            assert_eq!(a.heading, b.heading);
+            assert_eq!(a.headers, b.headers);
+            assert_eq!(a.alignments, b.alignments);
+            assert_eq!(a.rows, b.rows);
+            assert_eq!((a.start_line, a.end_line), (b.start_line, b.end_line));
+        }
+        assert_eq!(reparsed.tables[1].heading.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn test_remove_empty_rows() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | 2 |\n|  |  |\n| 3 |  |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(remove_empty_rows(&mut table), 1);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.end_line, 3);
+
+        let md = "| A | B |\n| --- | --- |\n|  |  |\n| 3 | x <!-- n --> |\n";
+        let mut table = parse_markdown_annotated(md).tables.remove(0);
+        assert_eq!(remove_empty_rows(&mut table), 1);
+        assert_eq!(table.annotation(0, 1), Some("n"));
+    }
+
+    #[test]
+    fn test_append_summary_row() {
+        let md = "| Item | Qty | Price |\n| --- | --- | --- |\n| a | 2 | 1.5 |\n| b | 3 | 1,000 |\n| c | x | |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        append_summary_row(
+            &mut table,
+            &[(1, Aggregation::Sum), (2, Aggregation::Mean)],
+            0,
+            "Total",
+        );
+        assert!(table.has_summary_row);
+        assert_eq!(table.rows.last().unwrap(), &vec!["Total", "5", "500.75"]);
+        assert_eq!(table.end_line, 5);
+
+        // 再集計しても集計行自体は対象にならない
+        append_summary_row(&mut table, &[(1, Aggregation::Count)], 0, "Count");
+        assert_eq!(table.rows.len(), 4);
+        assert_eq!(table.rows.last().unwrap(), &vec!["Count", "3", ""]);
+
+        let opts = SerializeOptions {
+            bold_summary: true,
+            ..Default::default()
+        };
+        let out = serialize_table_with(&table, &opts);
+        assert!(out.ends_with("| **Count** | **3** |       |\n"));
+    }
+
+    #[test]
+    fn test_pivot_table_sum_by_category() {
+        let md = "| Category | Item | Amount |\n| --- | --- | ---: |\n| Food | Apple | 120 |\n| Tools | Saw | 1,500 |\n| Food | Bread | 80.5 |\n| Food | Gift | n/a |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let pivot = pivot_table(&table, 0, 2, Aggregation::Sum).unwrap();
+        assert_eq!(pivot.headers, vec!["Category", "Amount"]);
+        assert_eq!(
+            pivot.rows,
+            vec![vec!["Food", "200.5"], vec!["Tools", "1500"]]
+        );
+        let counts = pivot_table(&table, 0, 2, Aggregation::Count).unwrap();
+        assert_eq!(counts.rows[0], vec!["Food", "3"]);
+        assert!(pivot_table(&table, 0, 3, Aggregation::Sum).is_err());
+    }
+
+    #[test]
+    fn test_reorder_columns_by_names() {
+        let md = "| A | B | C | D |\n| --- | :-: | --- | --: |\n| 1 | 2 | 3 | 4 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        reorder_columns_by_names(&mut table, &["D", "B"]).unwrap();
+        assert_eq!(table.headers, vec!["D", "B", "A", "C"]);
+        assert_eq!(table.alignments, vec!["right", "center", "none", "none"]);
+        assert_eq!(table.rows[0], vec!["4", "2", "1", "3"]);
+
+        assert!(reorder_columns_by_names(&mut table, &["Z"]).is_err());
+        assert!(reorder_columns_by_names(&mut table, &["A", "A"]).is_err());
+        assert_eq!(table.headers, vec!["D", "B", "A", "C"]);
+    }
+
+    #[test]
+    fn test_tables_with_heading() {
+        let md = "## 2024-01 売上\n| A |\n| --- |\n| 1 |\n## メモ\n| B |\n| --- |\n## 2024-02 売上\n| C |\n| --- |\n";
+        let doc = parse_markdown(md);
+        let exact: Vec<usize> = doc.tables_with_heading("メモ").map(|(i, _)| i).collect();
+        assert_eq!(exact, vec![1]);
+        assert_eq!(doc.tables_with_heading("メ").count(), 0);
+
+        let monthly: Vec<&str> = doc
+            .tables_matching_heading_pattern(r"^\d{4}-\d{2} 売上$")
+            .unwrap()
+            .map(|(_, t)| t.headers[0].as_str())
+            .collect();
+        assert_eq!(monthly, vec!["A", "C"]);
+        assert!(doc.tables_matching_heading_pattern("(").is_err());
+    }
+
+    #[test]
+    fn test_resolve_footnotes_in_cell() {
+        let md =
+            "| Fruit |\n| --- |\n| Apple[^a] [^missing] |\n\n[^a]: Red fruit\n    from trees\n";
+        let doc = parse_markdown(md);
+        assert_eq!(doc.tables[0].rows[0][0], "Apple[^a] [^missing]");
+        assert_eq!(doc.footnotes.len(), 1);
+        assert_eq!(
+            doc.resolve_footnotes_in_cell(&doc.tables[0].rows[0][0]),
+            "Apple (Red fruit from trees) [^missing]"
+        );
     }
 
     #[test]
-    fn test_roundtrip() {
-        let md = "# Heading\n\n| Name | Age |\n| --- | --- |\n| Alice | 30 |\n| Bob | 25 |\n";
+    fn test_select_columns() {
+        let md = "| A | B | C |\n| :-- | --- | --: |\n| 1 | 2 | 3 |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let projected = select_columns(&table, &["C", "A"]).unwrap();
+        assert_eq!(projected.headers, vec!["C", "A"]);
+        assert_eq!(projected.alignments, vec!["right", "left"]);
+        assert_eq!(projected.rows, vec![vec!["3", "1"]]);
+        assert!(select_columns(&table, &["A", "Z"]).is_err());
+    }
+
+    #[test]
+    fn test_table_ids_and_resolve() {
+        let md = "| A |\n| --- |\n\n## Tasks: Q1\n| B |\n| --- |\n\n| C |\n| --- |\n\n## 予定 表\n| D |\n| --- |\n";
         let doc = parse_markdown(md);
-        let rebuilt = rebuild_document(&doc.lines, &doc.tables);
-        // パースし直して同じテーブルが取れることを確認
-        let doc2 = parse_markdown(&rebuilt);
-        assert_eq!(doc2.tables[0].headers, doc.tables[0].headers);
-        assert_eq!(doc2.tables[0].rows, doc.tables[0].rows);
+        assert_eq!(
+            table_ids(&doc.tables),
+            vec!["table", "tasks-q1", "tasks-q1-2", "予定-表"]
+        );
+        assert_eq!(resolve_table_ref(&doc.tables, "tasks-q1-2").unwrap(), 2);
+        assert_eq!(resolve_table_ref(&doc.tables, "3").unwrap(), 3);
+        assert!(resolve_table_ref(&doc.tables, "4").is_err());
+        assert!(resolve_table_ref(&doc.tables, "nope").is_err());
+
+        // 連番を付けた ID が別の見出しのスラッグと重ならない
+        let md = "# a\n| A |\n| --- |\n\n# a\n| A |\n| --- |\n\n# a 2\n| A |\n| --- |\n";
+        assert_eq!(
+            table_ids(&parse_markdown(md).tables),
+            vec!["a", "a-2", "a-2-2"]
+        );
+        // 数字だけの見出しのテーブルも ID で指定できる
+        let md = "| A |\n| --- |\n\n# 2\n| B |\n| --- |\n";
+        let doc = parse_markdown(md);
+        assert_eq!(table_ids(&doc.tables), vec!["table", "table-2"]);
+        assert_eq!(resolve_table_ref(&doc.tables, "table-2").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_apply_sparse_patch() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | 2 |\n\n| C |\n| --- |\n| 3 |\n";
+        let mut doc = parse_markdown(md);
+        let patch = HashMap::from([
+            ("0.0.1".to_string(), "x".to_string()),
+            ("1.0.0".to_string(), "y".to_string()),
+        ]);
+        apply_sparse_patch(&mut doc, patch).unwrap();
+        assert_eq!(doc.tables[0].rows[0], vec!["1", "x"]);
+        assert_eq!(doc.tables[1].rows[0], vec!["y"]);
+
+        let patch = HashMap::from([
+            ("0.0.0".to_string(), "changed".to_string()),
+            ("0.a.0".to_string(), "z".to_string()),
+        ]);
+        let err = apply_sparse_patch(&mut doc, patch).unwrap_err();
+        assert!(err.contains("0.a.0"));
+        assert_eq!(doc.tables[0].rows[0][0], "1");
+
+        for key in ["0.0", "0.0.2", "0.1.0", "2.0.0"] {
+            let patch = HashMap::from([(key.to_string(), String::new())]);
+            assert!(apply_sparse_patch(&mut doc, patch)
+                .unwrap_err()
+                .contains(key));
+        }
+
+        // パイプや改行を含む値はセルを壊すため、何も変更せずにエラーにする
+        for value in ["x|y", "x\ny"] {
+            let patch = HashMap::from([
+                ("0.0.0".to_string(), "changed".to_string()),
+                ("1.0.0".to_string(), value.to_string()),
+            ]);
+            assert!(validate_sparse_patch(&doc, &patch)
+                .unwrap_err()
+                .contains("1.0.0"));
+            assert!(apply_sparse_patch(&mut doc, patch)
+                .unwrap_err()
+                .contains("1.0.0"));
+            assert_eq!(doc.tables[0].rows[0][0], "1");
+            assert_eq!(doc.tables[1].rows[0], vec!["y"]);
+        }
+    }
+
+    #[test]
+    fn test_split_column() {
+        let md = "| ID | Name | Age |\n| --- | :-: | --- |\n| 1 | Taro Yamada | 30 |\n| 2 | Hanako |\n| 3 | Ana de Armas | 35 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        let unmatched = table
+            .split_column(1, " ", vec!["First".into(), "Last".into()])
+            .unwrap();
+        assert_eq!(unmatched, vec![1]);
+        assert_eq!(table.headers, vec!["ID", "First", "Last", "Age"]);
+        assert_eq!(table.alignments, vec!["none", "center", "center", "none"]);
+        assert_eq!(table.rows[0], vec!["1", "Taro", "Yamada", "30"]);
+        // 区切り文字を含まないセルは新しい列を空にし、余った分は最後の列にまとめる
+        assert_eq!(table.rows[1], vec!["2", "", "", ""]);
+        assert_eq!(table.rows[2], vec!["3", "Ana", "de Armas", "35"]);
+
+        assert!(table.split_column(9, " ", vec!["A".into()]).is_err());
+        assert!(table.split_column(0, "", vec!["A".into()]).is_err());
+        assert!(table.split_column(0, " ", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_split_column_by() {
+        let md = "| ID | Contact | Age |\n| --- | :-: | --- |\n| 1 | Taro (taro@example.com) | 30 |\n| 2 | Hanako | 25 |\n| 3 | |\n| 4 | Ana (ana@example.com) | 35 |\n";
+        let headers = || vec!["Name".to_string(), "Email".to_string()];
+        let regex = ColumnSplitter::Regex {
+            pattern: r"^(.*?)\s*\((.*)\)$".to_string(),
+        };
+
+        let mut table = parse_markdown(md).tables.remove(0);
+        let unmatched = split_column_by(&mut table, 1, &regex, headers(), true).unwrap();
+        assert_eq!(unmatched, vec![1]);
+        assert_eq!(table.headers, vec!["ID", "Contact", "Name", "Email", "Age"]);
+        assert_eq!(table.alignments[1..4], ["center", "center", "center"]);
+        assert_eq!(
+            table.rows[0],
+            vec![
+                "1",
+                "Taro (taro@example.com)",
+                "Taro",
+                "taro@example.com",
+                "30"
+            ]
+        );
+        // 一致しないセルは空にする（元の列は残す）
+        assert_eq!(table.rows[1], vec!["2", "Hanako", "", "", "25"]);
+        assert_eq!(table.rows[2], vec!["3", "", "", "", ""]);
+
+        // keep_original = false では元の列を削除する
+        let mut table = parse_markdown(md).tables.remove(0);
+        split_column_by(&mut table, 1, &regex, headers(), false).unwrap();
+        assert_eq!(table.headers, vec!["ID", "Name", "Email", "Age"]);
+        assert_eq!(table.rows[3], vec!["4", "Ana", "ana@example.com", "35"]);
+        assert!(table.rows.iter().all(|r| r.len() == 4));
+
+        // 区切り文字は max_splits 回まで分ける
+        let md = "| Path |\n| --- |\n| a/b/c |\n| a |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        let delimiter = ColumnSplitter::Delimiter {
+            delimiter: "/".to_string(),
+            max_splits: Some(1),
+        };
+        let cols = vec!["Dir".to_string(), "Rest".to_string(), "Extra".to_string()];
+        let unmatched = split_column_by(&mut table, 0, &delimiter, cols, false).unwrap();
+        assert_eq!(unmatched, vec![1]);
+        assert_eq!(table.rows[0], vec!["a", "b/c", ""]);
+
+        let wrong_groups = ColumnSplitter::Regex {
+            pattern: "(a)".to_string(),
+        };
+        assert!(split_column_by(&mut table, 0, &wrong_groups, headers(), true).is_err());
+        let bad = ColumnSplitter::Regex {
+            pattern: "(".to_string(),
+        };
+        assert!(split_column_by(&mut table, 0, &bad, headers(), true).is_err());
+    }
+
+    #[test]
+    fn test_cleanup_table() {
+        let md = "| Name |  | Note |  |\n| --- | --- | --- | --- |\n|  a  |  | x |  |\n|  |  |  |  |\n| b | memo |  |\n";
+        let table = || parse_markdown(md).tables.remove(0);
+        let opts = |rows, columns, trim| CleanupOptions {
+            remove_empty_rows: rows,
+            remove_empty_columns: columns,
+            trim_cells: trim,
+        };
+
+        let mut t = table();
+        let report = cleanup_table(&mut t, opts(true, false, false)).unwrap();
+        assert_eq!(report.removed_rows, vec![1]);
+        assert_eq!(t.rows.len(), 2);
+        assert_eq!(t.headers.len(), 4);
+
+        // ヘッダーが空でもセルに値がある列（1列目）は残す
+        let mut t = table();
+        let report = cleanup_table(&mut t, opts(false, true, false)).unwrap();
+        assert_eq!(report.removed_columns, vec![3]);
+        assert_eq!(t.headers, vec!["Name", "", "Note"]);
+        assert_eq!(t.rows[1], vec!["", "", ""]);
+
+        let mut t = table();
+        t.rows[0][0] = "  a  ".to_string();
+        let report = cleanup_table(&mut t, opts(false, false, true)).unwrap();
+        assert_eq!(report.trimmed_cells, 1);
+        assert_eq!(t.rows[0][0], "a");
+        assert!(report.removed_rows.is_empty() && report.removed_columns.is_empty());
+
+        let mut t = table();
+        let report = cleanup_table(&mut t, CleanupOptions::default()).unwrap();
+        assert_eq!(report, CleanupReport::default());
+        assert_eq!(t, table());
+
+        // すべての列が空でも先頭の1列は残す
+        let mut t = parse_markdown("|  |  |\n| --- | --- |\n|  |  |\n")
+            .tables
+            .remove(0);
+        let report = cleanup_table(&mut t, opts(true, true, true)).unwrap();
+        assert_eq!(report.removed_columns, vec![1]);
+        assert_eq!(t.headers.len(), 1);
+        assert!(t.rows.is_empty());
+    }
+
+    #[test]
+    fn test_join_columns() {
+        let md = "| ID | First | Age | Last |\n| --- | :-: | --: | --- |\n| 1 | Taro | 30 | Yamada |\n| 2 | Hanako | 25 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        table
+            .join_columns(vec![1, 3], " ", "Name".to_string())
+            .unwrap();
+        assert_eq!(table.headers, vec!["ID", "Name", "Age"]);
+        assert_eq!(table.alignments, vec!["none", "center", "right"]);
+        assert_eq!(table.rows[0], vec!["1", "Taro Yamada", "30"]);
+        // 空のセルは飛ばす
+        assert_eq!(table.rows[1], vec!["2", "Hanako", "25"]);
+
+        // 後ろの列を先に指定すると、その列の位置にまとめる
+        let mut table = parse_markdown(md).tables.remove(0);
+        table
+            .join_columns(vec![3, 1], ", ", "Name".to_string())
+            .unwrap();
+        assert_eq!(table.headers, vec!["ID", "Age", "Name"]);
+        assert_eq!(table.rows[0], vec!["1", "30", "Yamada, Taro"]);
+
+        assert!(table.join_columns(vec![0], " ", "X".into()).is_err());
+        assert!(table.join_columns(vec![0, 0], " ", "X".into()).is_err());
+        assert!(table.join_columns(vec![0, 9], " ", "X".into()).is_err());
+        assert!(table.join_columns(vec![0, 1], "|", "X".into()).is_err());
+    }
+
+    #[test]
+    fn test_summary_flag_on_table_without_rows() {
+        // 行をすべて削除したテーブルや、デシリアライズしたテーブルでも集計行の扱いで落ちない
+        let mut empty = parse_markdown("| A |\n| --- |\n").tables.remove(0);
+        empty.has_summary_row = true;
+        let report = deduplicate_rows(&mut empty.clone(), None, KeepRow::First, false).unwrap();
+        assert_eq!(report.removed_count, 0);
+        let split = split_table(&empty, &SplitMode::ByColumnValue(ColumnRef::Index(0))).unwrap();
+        assert!(split.parts.is_empty());
+
+        let source = parse_markdown("| A |\n| --- |\n| 1 |\n").tables.remove(0);
+        let mut target = empty.clone();
+        assert_eq!(append_table_rows(&mut target, &source, None).unwrap(), 1);
+        assert_eq!(append_table_rows(&mut target, &empty, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_deduplicate_rows() {
+        let md = "| Name | City | Note |\n| --- | --- | --- |\n| a | Tokyo | x |\n| b | Osaka | y |\n|  a  | Tokyo | x |\n| B | osaka | z |\n| c | Nagoya | w |\n| a | Tokyo | x |\n";
+        let table = || parse_markdown(md).tables.remove(0);
+
+        // 行全体を比べる（前後の空白は無視する）
+        let mut t = table();
+        let report = deduplicate_rows(&mut t, None, KeepRow::First, false).unwrap();
+        assert_eq!(report.removed_count, 2);
+        assert_eq!(
+            report
+                .removed_rows
+                .iter()
+                .map(|r| r.index)
+                .collect::<Vec<_>>(),
+            vec![2, 5]
+        );
+        assert_eq!(report.removed_rows[0].cells, vec!["a", "Tokyo", "x"]);
+        assert_eq!(t.rows.len(), 4);
+        assert_eq!(t.end_line, 5);
+
+        // キー列だけを比べ、大文字・小文字を区別しない
+        let mut t = table();
+        let keys = [ColumnRef::Name("Name".into()), ColumnRef::Index(1)];
+        let report = deduplicate_rows(&mut t, Some(&keys), KeepRow::First, true).unwrap();
+        assert_eq!(report.removed_count, 3);
+        assert_eq!(t.rows[1], vec!["b", "Osaka", "y"]);
+
+        // Last は最後に出てくる行を残す
+        let mut t = table();
+        let report = deduplicate_rows(&mut t, Some(&keys), KeepRow::Last, true).unwrap();
+        assert_eq!(
+            report
+                .removed_rows
+                .iter()
+                .map(|r| r.index)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        let names: Vec<&str> = t.rows.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(names, vec!["B", "c", "a"]);
+        assert_eq!(t.rows[0][2], "z");
+
+        assert!(deduplicate_rows(&mut table(), Some(&[]), KeepRow::First, false).is_err());
+        let missing = [ColumnRef::Name("Zip".into())];
+        assert!(deduplicate_rows(&mut table(), Some(&missing), KeepRow::First, false).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_deduplicate() {
+        let md = "| Name |\n| --- |\n| Tokyo Tower |\n| Tokyo Towr |\n| Tokyo Tower |\n| Osaka Castle |\n|  |\n|  |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        let pairs = fuzzy_deduplicate(&mut table, 0, 0.8);
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs.contains(&(0, 2, 1.0)));
+        assert!(pairs.iter().all(|&(a, b, _)| a != 3 && b != 3));
+        assert!(fuzzy_deduplicate(&mut table, 0, 0.0)
+            .iter()
+            .any(|&(a, b, s)| (a, b) == (0, 3) && s < 0.5));
+        assert_eq!(table.rows.len(), 6);
+    }
+
+    #[test]
+    fn test_normalize_case() {
+        let md = "| Name |\n| --- |\n| ÉCOLE NORMALE |\n| already lower |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(normalize_case(&mut table, 0, CaseMode::Lowercase), 1);
+        assert_eq!(table.rows[0][0], "école normale");
+
+        assert_eq!(
+            convert_case("éCOLE nORMALE", CaseMode::Capitalize),
+            "ÉCOLE nORMALE"
+        );
+        assert_eq!(convert_case("ßx", CaseMode::Capitalize), "SSx");
+        assert_eq!(
+            convert_case("jean-PAUL  sartre", CaseMode::TitleCase),
+            "Jean-Paul  Sartre"
+        );
+        assert_eq!(normalize_case(&mut table, 5, CaseMode::Uppercase), 0);
+    }
+
+    #[test]
+    fn test_verbatim_cells_keep_spaces() {
+        let md = "| Key | Value |\n| --- | --- |\n| b | ` x ` |\n| a | `code` |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        assert_eq!(table.rows[0][1], " x ");
+        assert_eq!(table.rows[1][1], "`code`");
+        assert_eq!(table.verbatim, vec![(0, 1)]);
+
+        let out = serialize_table(&table);
+        assert!(out.contains("` x `"));
+        let reparsed = parse_markdown(&out).tables.remove(0);
+        assert_eq!(reparsed.rows, table.rows);
+        assert_eq!(reparsed.verbatim, table.verbatim);
+
+        // 印の付いていないセルの空白はこれまでどおり失われる
+        let mut plain = table.clone();
+        plain.verbatim.clear();
+        let reparsed = parse_markdown(&serialize_table(&plain)).tables.remove(0);
+        assert_eq!(reparsed.rows[0][1], "x");
+
+        // 行を並べ替えても印はセルについていく
+        let mut sorted = table.clone();
+        sort_table_rows(
+            &mut sorted,
+            0,
+            SortDirection::Asc,
+            SortMode::Text,
+            &plain_collate,
+        )
+        .unwrap();
+        assert_eq!(sorted.verbatim, vec![(1, 1)]);
+        assert!(serialize_table(&sorted).contains("` x `"));
+    }
+
+    #[test]
+    fn test_quote_reserved_cells_roundtrip() {
+        let md = "| A |\n| --- |\n| x |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        table.rows.push(vec!["---".to_string()]);
+        table.rows.push(vec!["# tag".to_string()]);
+
+        // クォートしないと `---` の行がセパレーターとみなされ、テーブルが途切れる
+        let plain = parse_markdown(&serialize_table(&table));
+        assert_eq!(plain.tables[0].rows.len(), 1);
+
+        for quoting in [CellQuoting::Backticks, CellQuoting::Backslash] {
+            let opts = SerializeOptions {
+                quote_reserved: quoting,
+                ..Default::default()
+            };
+            let reparsed = parse_markdown(&serialize_table_with(&table, &opts))
+                .tables
+                .remove(0);
+            assert_eq!(reparsed.rows.len(), 3);
+            assert_eq!(reparsed.rows[0][0], "x");
+            if quoting == CellQuoting::Backslash {
+                assert_eq!(unquote_cell(&reparsed.rows[1][0]), "---");
+                assert_eq!(unquote_cell(&reparsed.rows[2][0]), "# tag");
+            } else {
+                assert_eq!(reparsed.rows[1][0], "`---`");
+            }
+        }
+        assert_eq!(quote_cell(" a ", CellQuoting::Backslash), "&#32;a&#32;");
+        assert_eq!(unquote_cell("&#32;a&#32;"), " a ");
+        assert_eq!(quote_cell("a`b", CellQuoting::Backticks), "a`b");
+        assert_eq!(quote_cell("-`b", CellQuoting::Backticks), "``-`b``");
+
+        // 前後に空白のあるセルはバッククォートで囲んでも元の値に戻る
+        table.rows.push(vec![" lead".to_string()]);
+        table.rows.push(vec!["trail  ".to_string()]);
+        let opts = SerializeOptions {
+            quote_reserved: CellQuoting::Backticks,
+            ..Default::default()
+        };
+        let reparsed = parse_markdown(&serialize_table_with(&table, &opts))
+            .tables
+            .remove(0);
+        assert_eq!(reparsed.rows[3][0], " lead");
+        assert_eq!(reparsed.rows[4][0], "trail  ");
+    }
+
+    #[test]
+    fn test_table_checksum() {
+        let a = parse_markdown("| A | B |\n| --- | :-: |\n| 1 | 2 |\n")
+            .tables
+            .remove(0);
+        let b = parse_markdown("# H\n\n|A|B|\n|---|:---:|\n|  1 |2|\n")
+            .tables
+            .remove(0);
+        assert_eq!(a.checksum(), b.checksum());
+        assert_eq!(
+            table_checksums(std::slice::from_ref(&a)),
+            vec![format!("{:016x}", a.checksum())]
+        );
+
+        let mut changed = a.clone();
+        changed.rows[0][1] = "3".to_string();
+        assert_ne!(a.checksum(), changed.checksum());
+        // セルの区切り位置が違えば別の値になる
+        let mut shifted = a.clone();
+        shifted.rows[0] = vec!["12".to_string(), String::new()];
+        assert_ne!(a.checksum(), shifted.checksum());
+    }
+
+    #[test]
+    fn test_dedupe_consecutive() {
+        let md =
+            "| A | B |\n| --- | --- |\n| x | 1 |\n| x | 1 |\n| x | 1 |\n| y | 2 |\n| x | 1 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(table.dedupe_consecutive(), 2);
+        assert_eq!(
+            table.rows,
+            vec![vec!["x", "1"], vec!["y", "2"], vec!["x", "1"]]
+        );
+        assert_eq!(table.dedupe_consecutive(), 0);
+
+        // 残った行の `verbatim` は位置を詰める
+        let md = "| A | B |\n| --- | --- |\n| x | 1 |\n| x | 1 |\n| y | ` 2 ` |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(table.verbatim, vec![(2, 1)]);
+        table.dedupe_consecutive();
+        assert_eq!(table.verbatim, vec![(1, 1)]);
+    }
+
+    #[test]
+    fn test_table_context() {
+        let md = "# T\n\nintro\n| A |\n| --- |\n| 1 |\nnext\n";
+        let doc = parse_markdown(md);
+        let ctx = table_context(&doc.lines, &doc.tables[0], 2, 2);
+        assert_eq!(ctx.before, vec!["", "intro"]);
+        assert_eq!(ctx.first_line, 1);
+        assert_eq!(ctx.source, "| A |\n| --- |\n| 1 |");
+        // ファイルの末尾で打ち切る
+        assert_eq!(ctx.after, vec!["next"]);
+
+        let ctx = table_context(&doc.lines, &doc.tables[0], 10, 0);
+        assert_eq!(ctx.before.len(), 3);
+        assert!(ctx.after.is_empty());
+    }
+
+    #[test]
+    fn test_column_index_of() {
+        let md = "| ID | Name |  | name | Name |\n| --- | --- | --- | --- | --- |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        assert_eq!(table.column_index_of("Name"), Some(1));
+        assert_eq!(table.column_index_of("name"), Some(3));
+        assert_eq!(table.column_index_of(""), Some(2));
+        assert_eq!(table.column_index_of("Missing"), None);
+        assert_eq!(table.column_index_of_ci("NAME"), Some(1));
+        assert_eq!(table.column_index_of_ci("id"), Some(0));
+        assert_eq!(table.column_index_of_ci("missing"), None);
+    }
+
+    #[test]
+    fn test_iter_rows_by_column_name() {
+        let md = "| Name | Qty |\n| --- | --- |\n| a | 1 |\n| b |\n| c | 3 |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let qty: Vec<&str> = table.iter_rows().filter_map(|r| r.get("Qty")).collect();
+        assert_eq!(qty, vec!["1", "", "3"]);
+        let row = table.iter_rows().nth(2).unwrap();
+        assert_eq!((row.index, row.get_by_index(0)), (2, Some("c")));
+        assert_eq!(row.get("Missing"), None);
+        assert_eq!(row.get_by_index(2), None);
+        assert_eq!(
+            serde_json::to_value(row).unwrap(),
+            serde_json::json!({ "Name": "c", "Qty": "3" })
+        );
+    }
+
+    #[test]
+    fn test_sort_table_rows() {
+        let md = "| Name | Amount |\n| --- | ---: |\n| item10 | $1,200 |\n| item2 | -5 |\n| | 3 |\n| Item1 | -$20 |\n| item2 | |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let col = |t: &MarkdownTable, c: usize| -> Vec<String> {
+            t.rows.iter().map(|r| r[c].clone()).collect()
+        };
+
+        let mut natural = table.clone();
+        sort_table_rows(
+            &mut natural,
+            0,
+            SortDirection::Asc,
+            SortMode::Natural,
+            &plain_collate,
+        )
+        .unwrap();
+        assert_eq!(
+            col(&natural, 0),
+            vec!["Item1", "item2", "item2", "item10", ""]
+        );
+        // 同じ値の行は元の順序を保つ
+        assert_eq!(col(&natural, 1)[1..3], ["-5", ""]);
+
+        let mut text = table.clone();
+        sort_table_rows(
+            &mut text,
+            0,
+            SortDirection::Asc,
+            SortMode::Text,
+            &plain_collate,
+        )
+        .unwrap();
+        assert_eq!(col(&text, 0), vec!["Item1", "item10", "item2", "item2", ""]);
+
+        let mut numeric = table.clone();
+        sort_table_rows(
+            &mut numeric,
+            1,
+            SortDirection::Asc,
+            SortMode::Numeric,
+            &plain_collate,
+        )
+        .unwrap();
+        assert_eq!(col(&numeric, 1), vec!["-$20", "-5", "3", "$1,200", ""]);
+        // 降順でも空のセルは末尾
+        sort_table_rows(
+            &mut numeric,
+            1,
+            SortDirection::Desc,
+            SortMode::Auto,
+            &plain_collate,
+        )
+        .unwrap();
+        assert_eq!(col(&numeric, 1), vec!["$1,200", "3", "-5", "-$20", ""]);
+
+        // Collate は渡された比べ方にロケールを渡して使う
+        let mut collated = table.clone();
+        let by_length = |locale: Option<&str>| -> Result<Comparator, String> {
+            assert_eq!(locale, Some("ja"));
+            Ok(Box::new(|a: &str, b: &str| a.len().cmp(&b.len())))
+        };
+        let mode = SortMode::Collate(Some("ja".to_string()));
+        sort_table_rows(&mut collated, 0, SortDirection::Asc, mode, &by_length).unwrap();
+        assert_eq!(
+            col(&collated, 0),
+            vec!["item2", "Item1", "item2", "item10", ""]
+        );
+        let failing = |_: Option<&str>| -> Result<Comparator, String> { Err("x".to_string()) };
+        let mode = SortMode::Collate(None);
+        assert!(sort_table_rows(&mut collated, 0, SortDirection::Asc, mode, &failing).is_err());
+
+        assert!(sort_table_rows(
+            &mut numeric,
+            2,
+            SortDirection::Asc,
+            SortMode::Text,
+            &plain_collate
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_markdown_lenient() {
+        let md = "# Broken\n| A | B |\n| 1 | 2 |\n\n# Mismatch\n| A | B |\n| --- |\n| 1 | 2 |\n\n# Ok\n| C |\n| --- |\n| 3 |\n\nnot | a table\n";
+        let (doc, errors) = parse_markdown_lenient(md);
+        assert_eq!(doc.tables.len(), 1);
+        assert_eq!(doc.tables[0].heading.as_deref(), Some("Ok"));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line, 1);
+        assert!(errors[0].message.contains("区切り行"));
+        assert_eq!(errors[1].line, 5);
+        assert!(errors[1].message.contains("列数"));
+
+        let (doc, errors) = parse_markdown_lenient("| A |\n| --- |\n| 1 |\n");
+        assert_eq!((doc.tables.len(), errors.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_parse_markdown_lenient_malformed_separator() {
+        let md = "# T\n| A | B | C | D |\n| :--:- | -:- | :-: | --: |\n| 1 | 2 | 3 | 4 |\n";
+        let (doc, errors) = parse_markdown_lenient(md);
+        // テーブルは読み込み、崩れた区切りセルを区切り行の行番号で知らせる
+        assert_eq!(doc.tables.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert!(errors[0].message.contains("1 列目 `:--:-`"));
+        assert!(errors[0].message.contains("2 列目 `-:-`"));
+        assert!(!errors[0].message.contains("3 列目"));
+
+        let (_, errors) = parse_markdown_lenient("| A | B |\n|:-|-:|\n|1|2|\n\n| X |\n| : |\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 5);
+    }
+
+    #[test]
+    fn test_sort_table_rows_multi() {
+        let md = "| Task | Priority | Due |\n| --- | --- | --- |\n| a | 2 | 2024-03-01 |\n| b | 1 | 2024-05-01 |\n| c | 2 | 2024-01-15 |\n| d | 1 | 2024-05-01 |\n| e | 2 | 2024-01-15 |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let key = |column: ColumnRef, order: SortDirection| SortKey {
+            column,
+            order,
+            mode: SortMode::Auto,
+        };
+        let tasks =
+            |t: &MarkdownTable| -> Vec<String> { t.rows.iter().map(|r| r[0].clone()).collect() };
+
+        let two = [
+            key(ColumnRef::Name("Priority".into()), SortDirection::Asc),
+            key(ColumnRef::Index(2), SortDirection::Asc),
+        ];
+        // プレビューではテーブルを変えずに順序だけ返す
+        assert_eq!(
+            sorted_row_order(&table, &two, &plain_collate).unwrap(),
+            vec![1, 3, 2, 4, 0]
+        );
+        let mut sorted = table.clone();
+        assert_eq!(
+            sort_table_rows_multi(&mut sorted, &two, &plain_collate).unwrap(),
+            vec![1, 3, 2, 4, 0]
+        );
+        assert_eq!(tasks(&sorted), vec!["b", "d", "c", "e", "a"]);
+
+        let three = [
+            key(ColumnRef::Name("Priority".into()), SortDirection::Desc),
+            key(ColumnRef::Name("Due".into()), SortDirection::Asc),
+            key(ColumnRef::Name("Task".into()), SortDirection::Desc),
+        ];
+        let mut sorted = table.clone();
+        sort_table_rows_multi(&mut sorted, &three, &plain_collate).unwrap();
+        assert_eq!(tasks(&sorted), vec!["e", "c", "a", "d", "b"]);
+
+        let err = sorted_row_order(
+            &table,
+            &[key(ColumnRef::Name("Owner".into()), SortDirection::Asc)],
+            &plain_collate,
+        )
+        .unwrap_err();
+        assert!(err.contains("Owner") && err.contains("Task, Priority, Due"));
+        assert!(sorted_row_order(
+            &table,
+            &[key(ColumnRef::Index(3), SortDirection::Asc)],
+            &plain_collate
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_rename_column() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | 2 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        rename_column(&mut table, 1, " 数量 ").unwrap();
+        assert_eq!(table.headers, vec!["A", "数量"]);
+        assert_eq!(table.rows[0], vec!["1", "2"]);
+
+        assert!(rename_column(&mut table, 2, "C")
+            .unwrap_err()
+            .contains("範囲外"));
+        assert!(rename_column(&mut table, 0, "a|b").is_err());
+        assert!(rename_column(&mut table, 0, "a\\|b").is_err());
+        assert_eq!(table.headers[0], "A");
+
+        // 現在の列名で指定した列を、他の列と同じ名前に変える
+        let col = ColumnRef::Name("A".to_string()).resolve(&table).unwrap();
+        let warnings = rename_column(&mut table, col, "数量").unwrap();
+        assert_eq!(table.headers, vec!["数量", "数量"]);
+        assert_eq!(
+            warnings,
+            vec![ColumnWarning::DuplicateHeader {
+                name: "数量".to_string(),
+                columns: vec![0, 1],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_insert_row_strict_types() {
+        let md =
+            "| Name | Qty | Due |\n| --- | ---: | --- |\n| a | 1 | 2024-01-31 |\n| b | 1,200 | |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(infer_column_type(&table, 1), ColumnType::Number);
+        assert_eq!(infer_column_type(&table, 2), ColumnType::Date);
+
+        let row = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect();
+        let at = insert_row(&mut table, 99, row(&["c", "3.5"]), true).unwrap();
+        assert_eq!(at, 2);
+        assert_eq!(table.rows[2], vec!["c", "3.5", ""]);
+        assert_eq!(table.end_line, 4);
+
+        let err = insert_row(&mut table, 0, row(&["d", "many"]), true).unwrap_err();
+        assert!(err.contains("Qty"));
+        assert!(insert_row(&mut table, 0, row(&["d", "1", "soon"]), true).is_err());
+        assert_eq!(table.rows.len(), 3);
+        // strict_types なしなら型は問わない
+        insert_row(&mut table, 0, row(&["d", "many"]), false).unwrap();
+        assert_eq!(table.rows[0][1], "many");
+    }
+
+    #[test]
+    fn test_insert_and_delete_rows() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | x |\n| 2 | y |\n| 3 | z |\n| 4 | w |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        let row = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(insert_row(&mut table, 0, row(&["0"]), false).unwrap(), 0);
+        let len = table.rows.len();
+        let at = insert_row(&mut table, len, row(&["5", "v", "extra"]), false).unwrap();
+        assert_eq!(at, 5);
+        assert_eq!(table.rows[0], vec!["0", ""]);
+        assert_eq!(table.rows[5], vec!["5", "v"]);
+        assert_eq!(table.end_line, 7);
+
+        assert!(delete_rows(&mut table, &[1, 6]).is_err());
+        assert_eq!(table.rows.len(), 6);
+        assert_eq!(delete_rows(&mut table, &[5, 1, 3, 3]).unwrap(), 3);
+        let first: Vec<&str> = table.rows.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(first, vec!["0", "2", "4"]);
+        assert_eq!(table.end_line, 4);
+        let reparsed = parse_markdown(&serialize_table(&table)).tables.remove(0);
+        assert_eq!(reparsed.end_line, table.end_line - table.start_line);
+    }
+
+    #[test]
+    fn test_insert_and_delete_column() {
+        let md = "| A | B | C |\n| --- | :-: | ---: |\n| 1 | 2 | 3 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        table.rows.push(vec!["x".to_string()]);
+        let separator_cells = |t: &MarkdownTable| {
+            let out = serialize_table(t);
+            let sep = out.lines().nth(1).unwrap().to_string();
+            sep.trim_matches('|').split('|').count()
+        };
+
+        insert_column(&mut table, 3, "D", "right", "0").unwrap();
+        assert_eq!(table.headers, vec!["A", "B", "C", "D"]);
+        assert_eq!(table.alignments, vec!["none", "center", "right", "right"]);
+        // 短い行は先に埋めてから追加する
+        assert_eq!(table.rows[1], vec!["x", "", "", "0"]);
+        assert_eq!(separator_cells(&table), 4);
+
+        insert_column(&mut table, 0, "No", "none", "").unwrap();
+        assert_eq!(table.rows[0], vec!["", "1", "2", "3", "0"]);
+        assert!(insert_column(&mut table, 9, "E", "none", "").is_err());
+        assert!(insert_column(&mut table, 0, "E", "middle", "").is_err());
+        assert!(insert_column(&mut table, 0, "E|F", "none", "").is_err());
+
+        delete_column(&mut table, 2).unwrap();
+        assert_eq!(table.headers, vec!["No", "A", "C", "D"]);
+        assert_eq!(table.alignments, vec!["none", "none", "right", "right"]);
+        assert_eq!(table.rows[0], vec!["", "1", "3", "0"]);
+        assert_eq!(separator_cells(&table), 4);
+        let reparsed = parse_markdown(&serialize_table(&table)).tables.remove(0);
+        assert_eq!(reparsed.rows, table.rows);
+
+        for _ in 0..3 {
+            delete_column(&mut table, 0).unwrap();
+        }
+        assert!(delete_column(&mut table, 0).is_err());
+        assert!(delete_column(&mut table, 5).is_err());
+    }
+
+    #[test]
+    fn test_document_equality_ignores_layout() {
+        let a = parse_markdown("# T\n\n|A|B|\n|---|:-:|\n|1|2|\n");
+        let b = parse_markdown("# T\nintro\n\n\n|  A | B  |\n| --- | :---: |\n|  1 |  2 |\n\n");
+        assert_ne!(a.lines, b.lines);
+        assert_ne!(a.tables[0].start_line, b.tables[0].start_line);
+        assert_eq!(a, b);
+        let reparsed = parse_markdown(&serialize_table(&a.tables[0]));
+        assert_eq!(reparsed, a);
+
+        let c = parse_markdown("| A | B |\n| --- | --- |\n| 1 | 2 |\n");
+        assert_ne!(a, c);
+
+        // 行番号以外のメタデータの違いは等しいとみなさない
+        let mut summary = a.clone();
+        summary.tables[0].has_summary_row = true;
+        assert_ne!(summary, a);
+        let mut bom = a.clone();
+        bom.has_bom = true;
+        assert_ne!(bom, a);
+        let mut footnote = a.clone();
+        footnote
+            .footnotes
+            .insert("1".to_string(), "note".to_string());
+        assert_ne!(footnote, a);
+    }
+
+    #[test]
+    fn test_move_and_reorder_columns() {
+        let md = "| A | B | C |\n| :-- | --- | --: |\n| 1 | 2 | 3 |\n| 4 |\n";
+        let table = parse_markdown(md).tables.remove(0);
+
+        let mut first_to_last = table.clone();
+        move_column(&mut first_to_last, 0, 2).unwrap();
+        assert_eq!(first_to_last.headers, vec!["B", "C", "A"]);
+        assert_eq!(first_to_last.alignments, vec!["none", "right", "left"]);
+        assert_eq!(
+            first_to_last.rows,
+            vec![vec!["2", "3", "1"], vec!["", "", "4"]]
+        );
+
+        let mut last_to_first = table.clone();
+        move_column(&mut last_to_first, 2, 0).unwrap();
+        assert_eq!(last_to_first.headers, vec!["C", "A", "B"]);
+        assert_eq!(last_to_first.rows[0], vec!["3", "1", "2"]);
+
+        let mut same = table.clone();
+        move_column(&mut same, 1, 1).unwrap();
+        assert_eq!(same, table);
+        assert!(move_column(&mut same, 0, 3).is_err());
+
+        let mut reordered = table.clone();
+        reorder_columns(&mut reordered, &[2, 0, 1]).unwrap();
+        assert_eq!(reordered, last_to_first);
+        for bad in [&[0, 1][..], &[0, 1, 1], &[0, 1, 3], &[0, 1, 2, 3]] {
+            assert!(reorder_columns(&mut reordered, bad).is_err());
+        }
+        assert_eq!(reordered, last_to_first);
+    }
+
+    #[test]
+    fn test_parse_markdown_borrowed_matches_owned() {
+        let md = "# Sales\n\n| Item | Qty |\n| :-- | --: |\n| apple | 3 |\n| ` x ` |\n\ntext [^1]\n\n[^1]: note\n## Other\n| A |\n| --- |\n| 1 | extra |\n";
+        let borrowed = parse_markdown_borrowed(md);
+        assert_eq!(borrowed.tables.len(), 2);
+        assert_eq!(borrowed.tables[0].heading, Some("Sales"));
+        assert_eq!(borrowed.tables[0].rows[1], vec![" x ", ""]);
+        assert_eq!(borrowed.tables[1].rows[0], vec!["1"]);
+
+        // 行・見出し・セルはすべて元のテキストを指している（コピーしていない）
+        let range = md.as_bytes().as_ptr_range();
+        let in_content = |s: &str| s.is_empty() || range.contains(&s.as_ptr());
+        assert!(borrowed.lines.iter().all(|l| in_content(l)));
+        for table in &borrowed.tables {
+            assert!(table.heading.is_some_and(in_content));
+            assert!(table.headers.iter().all(|c| in_content(c)));
+            assert!(table.rows.iter().flatten().all(|c| in_content(c)));
+        }
+
+        let owned = parse_markdown(md);
+        let converted = borrowed.into_owned();
+        assert_eq!(converted, owned);
+        assert_eq!(converted.lines, owned.lines);
+        assert_eq!(converted.footnotes, owned.footnotes);
+        for (a, b) in converted.tables.iter().zip(&owned.tables) {
+            assert_eq!(a.heading, b.heading);
+            assert_eq!((a.start_line, a.end_line), (b.start_line, b.end_line));
+            assert_eq!(a.verbatim, b.verbatim);
+        }
+    }
+
+    #[test]
+    fn test_cell_annotations_round_trip() {
+        let md = "| Item | Qty | Memo |\n| --- | --- | --- |\n| apple | 3 <!-- hl --> | |\n| pear | 5 | <!--  check later  --> |\n";
+        let mut doc = parse_markdown_annotated(md);
+        let table = &doc.tables[0];
+        assert_eq!(table.rows[0], vec!["apple", "3", ""]);
+        assert_eq!(table.rows[1], vec!["pear", "5", ""]);
+        assert_eq!(table.annotation(0, 1), Some("hl"));
+        assert_eq!(table.annotation(1, 2), Some("check later"));
+        assert_eq!(table.annotation(0, 0), None);
+
+        let serialized = serialize_table(table);
+        assert!(serialized.contains("| 3 <!-- hl --> |"));
+        assert!(serialized.contains("| <!-- check later --> |"));
+        let reparsed = parse_markdown_annotated(&serialized).tables.remove(0);
+        assert_eq!(reparsed.rows, table.rows);
+        assert_eq!(reparsed.cell_annotations, table.cell_annotations);
+
+        // 列を削除すると注釈の位置も付け替える
+        let table = &mut doc.tables[0];
+        delete_column(table, 0).unwrap();
+        assert_eq!(table.annotation(0, 0), Some("hl"));
+        assert_eq!(table.annotation(1, 1), Some("check later"));
+
+        // 通常のパースでは注釈を取り出さない
+        let plain = parse_markdown(md).tables.remove(0);
+        assert_eq!(plain.rows[0][1], "3 <!-- hl -->");
+        assert!(plain.cell_annotations.is_empty());
+    }
+
+    #[test]
+    fn test_get_and_set_cell() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | 2 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        table.rows.push(vec!["3".to_string()]);
+        assert_eq!(get_cell(&table, 0, 1), Some("2"));
+        // 列が足りない行はヘッダーの範囲内なら空
+        assert_eq!(get_cell(&table, 1, 1), Some(""));
+        assert_eq!(get_cell(&table, 1, 2), None);
+        assert_eq!(get_cell(&table, 2, 0), None);
+
+        set_cell(&mut table, 1, 1, "4".to_string()).unwrap();
+        assert_eq!(table.rows[1], vec!["3", "4"]);
+        assert!(set_cell(&mut table, 2, 0, "x".to_string()).is_err());
+        assert!(set_cell(&mut table, 0, 2, "x".to_string()).is_err());
+        assert!(set_cell(&mut table, 0, 0, "a|b".to_string()).is_err());
+        assert!(set_cell(&mut table, 0, 0, "a\r\nb".to_string()).is_err());
+        assert_eq!(table.rows[0], vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_split_table_by_column_value() {
+        let md = "# T\n\n| K | V |\n| --- | ---: |\n| a | 1 |\n| b | 2 |\n| a | 3 |\n\nend\n";
+        let doc = parse_markdown(md);
+        let parts = split_table_by_column_value(&doc.tables[0], 0);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].heading.as_deref(), Some("a"));
+        assert_eq!(parts[0].rows, vec![vec!["a", "1"], vec!["a", "3"]]);
+        assert_eq!(parts[1].heading.as_deref(), Some("b"));
+        assert_eq!(parts[1].alignments, doc.tables[0].alignments);
+        assert!(split_table_by_column_value(&doc.tables[0], 5).is_empty());
+
+        let text: Vec<String> = parts.iter().map(serialize_table).collect();
+        let rebuilt = replace_table_text(&doc.lines, &doc.tables[0], &text.join("\n"));
+        let reparsed = parse_markdown(&rebuilt);
+        assert_eq!(reparsed.tables.len(), 2);
+        assert_eq!(reparsed.tables[1].rows, vec![vec!["b", "2"]]);
+        assert!(rebuilt.ends_with("\nend"));
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        let md = "| Name | Tags |\n| --- | --- |\n| a | rust, cli |\n| b | rust |\n| c | |\n| d | cli ,web, cli |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        let tags = extract_tags(&table, "Tags");
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags["rust"], vec![0, 1]);
+        assert_eq!(tags["cli"], vec![0, 3]);
+        assert_eq!(tags["web"], vec![3]);
+        assert!(extract_tags(&table, "Missing").is_empty());
+    }
+
+    #[test]
+    fn test_table_to_key_value_map() {
+        let md =
+            "| Key | Value |\n| --- | --- |\n| host | localhost |\n| port | 8080 |\n| debug | |\n";
+        let map = table_to_key_value_map(&parse_markdown(md).tables[0]).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["host"], "localhost");
+        assert_eq!(map["port"], "8080");
+        assert_eq!(map["debug"], "");
+
+        let dup = "| Key | Value |\n| --- | --- |\n| a | 1 |\n| a | 2 |\n";
+        let err = table_to_key_value_map(&parse_markdown(dup).tables[0]).unwrap_err();
+        assert!(err.contains("a"));
+        let three = "| A | B | C |\n| --- | --- | --- |\n";
+        assert!(table_to_key_value_map(&parse_markdown(three).tables[0]).is_err());
+    }
+
+    #[test]
+    fn test_table_from_key_value_map_roundtrip() {
+        let mut map = IndexMap::new();
+        map.insert("port".to_string(), "8080".to_string());
+        map.insert("host".to_string(), "localhost".to_string());
+        map.insert("prefix".to_string(), " > ".to_string());
+        map.insert("debug".to_string(), String::new());
+        let table = table_from_key_value_map(&map, "Key", "Value");
+        assert_eq!(table.headers, vec!["Key", "Value"]);
+        assert_eq!(table.rows[0], vec!["port", "8080"]);
+
+        // 書き出して読み直しても同じ組が挿入順のまま得られる
+        let reparsed = parse_markdown(&serialize_table(&table)).tables.remove(0);
+        assert_eq!(reparsed, table);
+        let back = table_to_key_value_map(&reparsed).unwrap();
+        assert_eq!(back.len(), map.len());
+        for (key, value) in &map {
+            assert_eq!(&back[key], value);
+        }
+        let keys: Vec<&str> = reparsed.rows.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(keys, vec!["port", "host", "prefix", "debug"]);
+    }
+
+    #[test]
+    fn test_demote_and_promote_header() {
+        let md = "| 2024-01 | 120 |\n| --- | ---: |\n| 2024-02 | ` 80 ` |\n";
+        let original = parse_markdown(md).tables.remove(0);
+        let mut table = original.clone();
+        table
+            .demote_header(vec!["Month".to_string(), " Sales ".to_string()])
+            .unwrap();
+        assert_eq!(table.headers, vec!["Month", "Sales"]);
+        assert_eq!(
+            table.rows,
+            vec![vec!["2024-01", "120"], vec!["2024-02", " 80 "]]
+        );
+        assert_eq!(table.verbatim, vec![(1, 1)]);
+        assert_eq!(table.end_line, original.end_line + 1);
+
+        table.promote_row_to_header(0).unwrap();
+        assert_eq!(table, original);
+        assert_eq!(table.verbatim, original.verbatim);
+        assert_eq!(table.end_line, original.end_line);
+
+        table.demote_header(Vec::new()).unwrap();
+        assert_eq!(table.headers, vec!["Column 1", "Column 2"]);
+        assert!(table.demote_header(vec!["A".to_string()]).is_err());
+        assert!(table.promote_row_to_header(5).is_err());
+        table.has_summary_row = true;
+        assert!(table.promote_row_to_header(2).is_err());
+    }
+
+    #[test]
+    fn test_transpose_table() {
+        let md = "| Spec | Model A | Model B |\n| --- | :-: | --- |\n| Price | 1,200 | 980 |\n| Weight | ` 2kg ` |\n";
+        let original = parse_markdown(md).tables.remove(0);
+        let mut table = original.clone();
+        transpose_table(&mut table, true, true);
+        assert_eq!(table.headers, vec!["Spec", "Price", "Weight"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["Model A", "1,200", " 2kg "],
+                vec!["Model B", "980", ""]
+            ]
+        );
+        assert_eq!(table.alignments, vec!["none", "right", "none"]);
+        assert_eq!(table.verbatim, vec![(0, 2)]);
+        assert_eq!(table.end_line, table.start_line + 3);
+
+        // 2回入れ替えると内容は元に戻る（アライメントを除く）
+        transpose_table(&mut table, true, false);
+        assert_eq!(table.headers, original.headers);
+        assert_eq!(table.rows, original.rows);
+        assert_eq!(table.verbatim, original.verbatim);
+        assert_eq!(table.alignments, vec!["none"; 3]);
+
+        transpose_table(&mut table, false, false);
+        assert_eq!(table.headers, vec!["Col 1", "Col 2", "Col 3"]);
+        assert_eq!(table.rows[0], vec!["Spec", "Price", "Weight"]);
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.verbatim, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_truncate_long_cells() {
+        let md = "| Note |\n| --- |\n| 日本語のとても長い説明 |\n| 五文字です |\n| short |\n| 🍣🍣🍣🍣🍣🍣 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        assert_eq!(truncate_long_cells(&mut table, 5, "…"), Ok(2));
+        let notes: Vec<&str> = table.rows.iter().map(|r| r[0].as_str()).collect();
+        // ちょうど5文字のセルはそのまま残す
+        assert_eq!(notes, vec!["日本語の…", "五文字です", "short", "🍣🍣🍣🍣…"]);
+        assert!(table.rows.iter().all(|r| r[0].chars().count() <= 5));
+
+        assert_eq!(truncate_long_cells(&mut table, 3, "..."), Ok(4));
+        assert_eq!(table.rows[2][0], "...");
+        assert_eq!(truncate_long_cells(&mut table, 2, "...."), Ok(4));
+        assert_eq!(table.rows[0][0], "..");
+
+        // 0 文字ではすべてのセルが空になるためエラーにする
+        assert!(truncate_long_cells(&mut table, 0, "…").is_err());
+        assert!(truncate_long_cells(&mut table, 1, "|").is_err());
+        assert_eq!(table.rows[0][0], "..");
+    }
+
+    #[test]
+    fn test_merge_tables() {
+        let md = "## Week 1\n\n| Task | Hours |\n| --- | --: |\n| a | 2 |\n\n## Week 2\n\n| Hours | Task |\n| --- | --- |\n| 3 | ` b ` |\n\nend\n";
+        let doc = parse_markdown(md);
+        let (content, merged) = merge_tables(&doc, 1, 0, None).unwrap();
+        assert_eq!(merged.rows, vec![vec!["a", "2"], vec![" b ", "3"]]);
+        assert_eq!(merged.verbatim, vec![(1, 0)]);
+        assert_eq!(
+            content,
+            "## Week 1\n\n| Task  | Hours |\n| ------| -----:|\n| a     | 2     |\n| ` b ` | 3     |\n\n## Week 2\n\nend"
+        );
+
+        // 列名が異なる場合は対応表で読み替え、片方にしかない列は空のセルにする
+        let md = "| Name | Qty |\n| --- | --- |\n| x | 1 |\n| Total | 1 |\n\n| Item | Note |\n| --- | --- |\n| y | new |\n";
+        let mut doc = parse_markdown(md);
+        doc.tables[0].has_summary_row = true;
+        let err = merge_tables(&doc, 1, 0, None).unwrap_err();
+        assert_eq!(
+            err,
+            MergeError::UnmatchedColumns {
+                source_only: vec!["Item".to_string(), "Note".to_string()],
+                target_only: vec!["Name".to_string(), "Qty".to_string()],
+            }
+        );
+        let mapping = vec![("Item".to_string(), "Name".to_string())];
+        let (content, merged) = merge_tables(&doc, 1, 0, Some(&mapping)).unwrap();
+        assert_eq!(merged.headers, vec!["Name", "Qty", "Note"]);
+        assert_eq!(
+            merged.rows,
+            vec![
+                vec!["x", "1", ""],
+                vec!["y", "", "new"],
+                vec!["Total", "1", ""],
+            ]
+        );
+        assert_eq!(parse_markdown(&content).tables.len(), 1);
+
+        let bad = vec![("Missing".to_string(), "Name".to_string())];
+        assert!(matches!(
+            merge_tables(&doc, 1, 0, Some(&bad)),
+            Err(MergeError::Invalid { .. })
+        ));
+        assert!(merge_tables(&doc, 0, 0, None).is_err());
+    }
+
+    #[test]
+    fn test_column_widths_use_display_width() {
+        let md = "| 名前 | A | Note |\n| --- | --- | --- |\n| 東京都 | 1 | café |\n| x | 22 | |\n";
+        let table = parse_markdown(md).tables.remove(0);
+        // 全角文字は2桁、最小幅は 3
+        assert_eq!(column_widths(&table), vec![6, 3, 4]);
+        let out = serialize_table(&table);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "| 名前   | A   | Note |");
+        assert_eq!(lines[2], "| 東京都 | 1   | café |");
+        assert!(lines
+            .iter()
+            .all(|l| display_width(l) == display_width(lines[0])));
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_split_table_by_row_ranges() {
+        let md = "## Log\n\n| N | Note |\n| --- | --- |\n| 1 | a |\n| 2 | ` b ` |\n| 3 | c |\n| 4 | d |\n| 5 | e |\n| Total | |\n\nafter\n";
+        let mut doc = parse_markdown(md);
+        doc.tables[0].has_summary_row = true;
+        let table = &doc.tables[0];
+        let split = split_table(table, &SplitMode::ByRowRanges(vec![1..3, 4..5])).unwrap();
+        let rest = split.remaining.as_ref().unwrap();
+        assert_eq!(
+            rest.rows,
+            vec![vec!["1", "a"], vec!["4", "d"], vec!["Total", ""]]
+        );
+        assert!(rest.has_summary_row);
+        assert_eq!(split.parts[0].heading.as_deref(), Some("行 2–3"));
+        assert_eq!(split.parts[0].rows, vec![vec!["2", " b "], vec!["3", "c"]]);
+        assert_eq!(split.parts[0].verbatim, vec![(0, 1)]);
+
+        let rebuilt = replace_table_text(&doc.lines, table, &render_table_split(&split, true));
+        let reparsed = parse_markdown(&rebuilt);
+        assert_eq!(reparsed.tables.len(), 3);
+        assert_eq!(reparsed.tables[1].heading.as_deref(), Some("行 2–3"));
+        assert_eq!(reparsed.tables[1].rows[0], vec!["2", " b "]);
+        assert_eq!(reparsed.tables[2].rows, vec![vec!["5", "e"]]);
+        assert!(rebuilt.ends_with("| 5   | e    |\n\nafter"));
+
+        let all = split_table(table, &SplitMode::ByRowRanges(vec![0..5])).unwrap();
+        assert!(all.remaining.is_none());
+        for bad in [vec![0..6], vec![2..2], vec![0..2, 1..3], vec![]] {
+            assert!(split_table(table, &SplitMode::ByRowRanges(bad)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_split_table_by_column_value_with_headings() {
+        let md = "| Task | Status |\n| --- | --- |\n| a | *Blocked* #1 |\n| b | Done |\n| c | |\n";
+        let doc = parse_markdown(md);
+        let mode = SplitMode::ByColumnValue(ColumnRef::Name("Status".to_string()));
+        let split = split_table(&doc.tables[0], &mode).unwrap();
+        assert!(split.remaining.is_none());
+        assert_eq!(split.parts.len(), 3);
+        let text = render_table_split(&split, true);
+        assert!(text.starts_with("### \\*Blocked\\* \\#1\n\n| Task |"));
+        assert!(text.contains("### （空）\n"));
+        let reparsed = parse_markdown(&replace_table_text(&doc.lines, &doc.tables[0], &text));
+        let headings: Vec<&str> = reparsed
+            .tables
+            .iter()
+            .map(|t| t.heading.as_deref().unwrap())
+            .collect();
+        assert_eq!(headings, vec!["\\*Blocked\\* \\#1", "Done", "（空）"]);
+        assert!(split_table(
+            &doc.tables[0],
+            &SplitMode::ByColumnValue(ColumnRef::Index(9))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_table_to_pandoc_md() {
+        let md = "## Sales 2024\n\n| Item | Qty |\n| --- | --: |\n| apple | 3 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        let out = table_to_pandoc_md(&table);
+        assert!(out.starts_with(": Sales 2024\n\n| Item"));
+        assert!(out.ends_with("| apple | 3   |\n"));
+
+        table.heading = None;
+        let out = table_to_pandoc_md(&table);
+        assert!(out.starts_with("| Item"));
+        assert!(!out.contains(": "));
+    }
+
+    #[test]
+    fn test_heading_path_for_table() {
+        let md = "| X |\n| --- |\n\n# Guide\n## Setup\n### Linux\n## Usage\n#### Note\n### Options\n\n| A |\n| --- |\n| 1 |\n";
+        let doc = parse_markdown(md);
+        let path = |level: u8, text: &str| (level, text.to_string());
+        assert_eq!(
+            heading_path_for_table(&doc, 1),
+            vec![path(1, "Guide"), path(2, "Usage"), path(3, "Options")]
+        );
+        assert!(heading_path_for_table(&doc, 0).is_empty());
+        assert!(heading_path_for_table(&doc, 5).is_empty());
+
+        let md = "# Guide\n#draft\n~~~\n# comment\n~~~\n\n| A |\n| --- |\n";
+        assert_eq!(
+            heading_path_for_table(&parse_markdown(md), 0),
+            vec![path(1, "Guide")]
+        );
+        // パーサーと走査でも `#draft` は見出しにしない
+        let md = "# Guide\n#draft\n| A |\n| --- |\n";
+        assert_eq!(
+            parse_markdown(md).tables[0].heading.as_deref(),
+            Some("Guide")
+        );
+        assert_eq!(outline(md).headings, vec!["Guide"]);
+        assert_eq!(outline(md).table_headings, vec![Some("Guide".to_string())]);
+    }
+
+    #[test]
+    fn test_split_document_at_heading() {
+        let md = "intro\n\n## A\n| X |\n| --- |\n| 1 |\n\n### A-1\ntext\n## B\nb\n## C\n| Y |\n| --- |\n| 2 |\n";
+        let parts = split_document_at_heading(&parse_markdown(md), 2);
+        let headings: Vec<&str> = parts.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(headings, vec!["", "A", "B", "C"]);
+        assert_eq!(parts[0].1.lines, vec!["intro"]);
+        assert_eq!(parts[1].1.lines.len(), 7);
+        assert_eq!(parts[1].1.tables[0].heading.as_deref(), Some("A"));
+        assert_eq!(parts[2].1.lines, vec!["## B", "b"]);
+        assert_eq!(parts[3].1.tables[0].rows, vec![vec!["2"]]);
+
+        // `#tag` の行やコードフェンス内の `#` は見出しにしない
+        let md = "## A\n#tag\n```sh\n## not a heading\n```\n##\n";
+        let parts = split_document_at_heading(&parse_markdown(md), 2);
+        let headings: Vec<&str> = parts.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(headings, vec!["A", ""]);
+        assert_eq!(parts[0].1.lines.len(), 5);
+    }
+
+    #[test]
+    fn test_reindex_table_lines() {
+        let md = "# A\n| X |\n| --- |\n| 1 |\n\n| Y |\n| --- |\n| 2 |\n";
+        let mut doc = parse_markdown(md);
+        let (end0, start1) = (doc.tables[0].end_line, doc.tables[1].start_line);
+        doc.lines.insert(end0 + 1, "| 3 |".to_string());
+        reindex_table_lines(&mut doc);
+        assert_eq!(doc.tables[0].end_line, end0 + 1);
+        assert_eq!(doc.tables[1].start_line, start1 + 1);
+    }
+
+    #[test]
+    fn test_trim_table_cells() {
+        let md = "| A | B |\n| --- | --- |\n| 1 | 2 |\n";
+        let mut table = parse_markdown(md).tables.remove(0);
+        table.headers[1] = " B\t".to_string();
+        table.rows.push(vec!["  x ".to_string(), "y".to_string()]);
+        assert_eq!(trim_table_cells(&mut table), 2);
+        assert_eq!(table.headers, vec!["A", "B"]);
+        assert_eq!(table.rows[1], vec!["x", "y"]);
+        assert_eq!(trim_table_cells(&mut table), 0);
     }
 }
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 保持する履歴の最大件数
+const MAX_RECENT_FILES: usize = 50;
+
+/// 最近開いたファイルの履歴1件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub path: String,
+    /// 最後に開いた日時（Unix エポックからのミリ秒）
+    pub last_opened: u64,
+    /// 取得時点でファイルが存在しないか
+    #[serde(default)]
+    pub missing: bool,
+}
+
+/// 最近開いたファイルの履歴を保持する管理ステート（アプリデータディレクトリに JSON で永続化）
+#[derive(Debug)]
+pub struct RecentFiles {
+    store_path: PathBuf,
+    entries: Mutex<Vec<RecentEntry>>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+impl RecentFiles {
+    /// 保存先の JSON を読み込む。存在しない・壊れている場合は空の履歴で始める
+    pub fn load(store_path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&store_path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        RecentFiles {
+            store_path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    fn persist(&self, entries: &[RecentEntry]) -> Result<(), String> {
+        if let Some(dir) = self.store_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+        fs::write(&self.store_path, json).map_err(|e| e.to_string())
+    }
+
+    /// ファイルを開いたことを記録する（既存の履歴は先頭に移動する）
+    pub fn add(&self, path: &str) -> Result<(), String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.retain(|e| e.path != path);
+        entries.insert(
+            0,
+            RecentEntry {
+                path: path.to_string(),
+                last_opened: now_ms(),
+                missing: false,
+            },
+        );
+        entries.truncate(MAX_RECENT_FILES);
+        self.persist(&entries)
+    }
+
+    /// 新しい順に最大 `limit` 件返す。存在しなくなったファイルは `missing` を立てて残す
+    pub fn get(&self, limit: usize) -> Vec<RecentEntry> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        entries
+            .iter()
+            .take(limit)
+            .map(|e| RecentEntry {
+                missing: !Path::new(&e.path).exists(),
+                ..e.clone()
+            })
+            .collect()
+    }
+
+    /// 履歴から1件削除する
+    pub fn remove(&self, path: &str) -> Result<(), String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.retain(|e| e.path != path);
+        self.persist(&entries)
+    }
+
+    /// 履歴をすべて消去する
+    pub fn clear(&self) -> Result<(), String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.clear();
+        self.persist(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_order_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("data").join("recent_files.json");
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        fs::write(&a, "").unwrap();
+        fs::write(&b, "").unwrap();
+        let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+
+        let recent = RecentFiles::load(store.clone());
+        recent.add(&a).unwrap();
+        recent.add(&b).unwrap();
+        recent.add(&a).unwrap();
+
+        let reloaded = RecentFiles::load(store);
+        let paths: Vec<_> = reloaded.get(10).into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, vec![a.to_string(), b.to_string()]);
+        assert_eq!(reloaded.get(1).len(), 1);
+
+        reloaded.clear().unwrap();
+        assert!(reloaded.get(10).is_empty());
+    }
+
+    #[test]
+    fn test_missing_file_is_flagged_not_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gone.md");
+        fs::write(&path, "").unwrap();
+        let recent = RecentFiles::load(dir.path().join("recent_files.json"));
+        recent.add(&path.to_string_lossy()).unwrap();
+        assert!(!recent.get(10)[0].missing);
+
+        fs::remove_file(&path).unwrap();
+        let entries = recent.get(10);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].missing);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::file_io::{document_from_bytes, sha256_hex};
+use crate::markdown_parser::{apply_sparse_patch, ParsedDocument};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// ジャーナルの1行（受け付けたセル編集1件）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    path: String,
+    /// 編集を受け付けた時点のディスク上のファイルの SHA-256
+    base_sha256: String,
+    /// `"table.row.col"`
+    cell: String,
+    value: String,
+}
+
+/// 復元できる未保存の編集
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecoveryCandidate {
+    pub path: String,
+    /// ジャーナルに残っている編集の件数
+    pub edits: usize,
+    /// 編集後にファイルが変更（または削除）されていて、そのままでは適用できない
+    pub stale: bool,
+}
+
+/// 未保存のセル編集をドキュメントごとに JSON Lines で書き留めるクラッシュ復旧用ジャーナル
+///
+/// 保存に成功したら `clear` で消す。起動時に残っているものが復旧候補になる。
+#[derive(Debug, Clone)]
+pub struct RecoveryJournal {
+    dir: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+fn read_entries(journal: &Path) -> io::Result<Vec<JournalEntry>> {
+    let text = fs::read_to_string(journal)?;
+    // 書き込み途中で落ちた最終行などは読み飛ばす
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// ジャーナルを記録したときから対象ファイルが変わっていないか
+fn is_stale(entries: &[JournalEntry], current_sha256: Option<&str>) -> bool {
+    entries
+        .iter()
+        .any(|e| Some(e.base_sha256.as_str()) != current_sha256)
+}
+
+impl RecoveryJournal {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn journal_path(&self, path: &Path) -> PathBuf {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let key = sha256_hex(path.to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.jsonl", &key[..16]))
+    }
+
+    /// 受け付けたセル編集をジャーナルに追記する
+    pub fn append(&self, path: &Path, edits: &HashMap<String, String>) -> Result<(), String> {
+        if edits.is_empty() {
+            return Ok(());
+        }
+        let base_sha256 = sha256_hex(&fs::read(path).map_err(|e| e.to_string())?);
+        let mut lines = String::new();
+        for (cell, value) in edits {
+            let entry = JournalEntry {
+                path: path.to_string_lossy().to_string(),
+                base_sha256: base_sha256.clone(),
+                cell: cell.clone(),
+                value: value.clone(),
+            };
+            lines.push_str(&serde_json::to_string(&entry).map_err(|e| e.to_string())?);
+            lines.push('\n');
+        }
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal_path(path))
+            .map_err(|e| e.to_string())?;
+        file.write_all(lines.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| e.to_string())
+    }
+
+    /// 保存に成功したファイルのジャーナルを消す
+    pub fn clear(&self, path: &Path) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        match fs::remove_file(self.journal_path(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// 残っているジャーナルを一覧する（古くなったものも `stale` として含める）
+    pub fn candidates(&self) -> Vec<RecoveryCandidate> {
+        let Ok(_guard) = self.lock.lock() else {
+            return Vec::new();
+        };
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut candidates: Vec<RecoveryCandidate> = dir
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "jsonl"))
+            .filter_map(|e| {
+                let entries = read_entries(&e.path()).ok()?;
+                let path = entries.first()?.path.clone();
+                let current = fs::read(&path).ok().map(|b| sha256_hex(&b));
+                Some(RecoveryCandidate {
+                    stale: is_stale(&entries, current.as_deref()),
+                    edits: entries.len(),
+                    path,
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.path.cmp(&b.path));
+        candidates
+    }
+
+    /// ジャーナルの編集を記録順に再生したドキュメントを返す（ファイルには書き込まない）
+    ///
+    /// 記録後にファイルが変更されている場合は適用せずエラーにする。
+    pub fn recover(&self, path: &Path) -> Result<ParsedDocument, String> {
+        let entries = {
+            let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+            read_entries(&self.journal_path(path))
+                .map_err(|_| format!("復旧できる編集がありません: {}", path.display()))?
+        };
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        if is_stale(&entries, Some(&sha256_hex(&bytes))) {
+            return Err(format!(
+                "編集の記録後にファイルが変更されているため復旧できません: {}",
+                path.display()
+            ));
+        }
+        let mut doc = document_from_bytes(&bytes);
+        // 同じセルへの編集は後の記録を優先する
+        let patch: HashMap<String, String> =
+            entries.into_iter().map(|e| (e.cell, e.value)).collect();
+        apply_sparse_patch(&mut doc, patch)?;
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edits(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_journal_replay_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A | B |\n| --- | --- |\n| 1 | 2 |\n").unwrap();
+        let journal = RecoveryJournal::new(dir.path().join("recovery"));
+        assert!(journal.candidates().is_empty());
+
+        journal.append(&path, &edits(&[("0.0.0", "x")])).unwrap();
+        journal
+            .append(&path, &edits(&[("0.0.0", "y"), ("0.0.1", "z")]))
+            .unwrap();
+        let candidates = journal.candidates();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].edits, 3);
+        assert!(!candidates[0].stale);
+
+        let doc = journal.recover(&path).unwrap();
+        assert_eq!(doc.tables[0].rows[0], vec!["y", "z"]);
+        // 復旧してもファイル自体は変わらない
+        assert!(fs::read_to_string(&path).unwrap().contains("| 1 | 2 |"));
+
+        journal.clear(&path).unwrap();
+        assert!(journal.candidates().is_empty());
+        assert!(journal.recover(&path).is_err());
+    }
+
+    #[test]
+    fn test_stale_journal_is_not_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.md");
+        fs::write(&path, "| A |\n| --- |\n| 1 |\n").unwrap();
+        let journal = RecoveryJournal::new(dir.path().join("recovery"));
+        journal.append(&path, &edits(&[("0.0.0", "x")])).unwrap();
+
+        fs::write(&path, "| A |\n| --- |\n| changed |\n").unwrap();
+        let candidates = journal.candidates();
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].stale);
+        assert!(journal.recover(&path).is_err());
+    }
+}
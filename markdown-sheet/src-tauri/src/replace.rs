@@ -0,0 +1,586 @@
+use crate::content_search::{build_matcher, table_row_at, CellLocation, SearchOptions};
+use crate::file_io::{decode_bytes, document_from_bytes, encode_text, sha256_hex, write_atomic};
+use crate::inventory::FileError;
+use crate::markdown_parser::{
+    check_cell_text, fenced_lines, rebuild_document, set_cell, table_ids, MarkdownTable,
+    ParsedDocument,
+};
+use crate::workspace;
+use regex::{NoExpand, Regex};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 置換候補1件（テーブル内ならセル単位、それ以外は行単位）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplaceMatch {
+    /// 計画内で一意な ID（`apply_replace_plan` で選択に使う）
+    pub id: String,
+    /// 行番号（0 始まり）
+    pub line: usize,
+    /// テーブル内の置換ならそのセル
+    pub cell: Option<CellLocation>,
+    /// 置換前のセル・行の値
+    pub before: String,
+    /// 置換後のセル・行の値
+    pub after: String,
+}
+
+/// ファイルごとの置換候補
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileReplacePlan {
+    pub path: String,
+    /// 計画作成時のファイルの SHA-256（適用時に変更を検出する）
+    pub sha256: String,
+    pub matches: Vec<ReplaceMatch>,
+}
+
+/// 書き込み前の置換計画
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplacePlan {
+    pub plan_id: String,
+    pub files: Vec<FileReplacePlan>,
+    /// 置換候補の総数
+    pub total: usize,
+}
+
+/// ファイルごとの適用結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileReplaceResult {
+    pub path: String,
+    /// 適用した置換の件数
+    pub applied: usize,
+    /// 置換後の値がセルを壊すため適用しなかった候補
+    pub skipped: Vec<SkippedMatch>,
+    /// 失敗した場合の理由（他のファイルの適用は続ける）
+    pub error: Option<String>,
+}
+
+/// 適用しなかった置換候補とその理由
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedMatch {
+    pub id: String,
+    pub reason: String,
+}
+
+/// セル単位の一括置換の1件（`search_replace_in_directory` の結果）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplacePreview {
+    pub file_path: String,
+    pub table_index: usize,
+    /// データ行の番号（ヘッダーは対象外）
+    pub row_index: usize,
+    pub col_index: usize,
+    /// 置換前のセルの値
+    pub original: String,
+    /// 置換後のセルの値
+    pub replacement: String,
+}
+
+/// フォルダ一括置換の結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DirectoryReplaceResult {
+    /// 置換した（`dry_run` では置換する）セル
+    pub replaced: Vec<ReplacePreview>,
+    /// 置換後の値にパイプや改行を含むため置換しなかったセル
+    pub skipped: Vec<ReplacePreview>,
+    /// 書き換えたファイル（`dry_run` では空）
+    pub changed_files: Vec<String>,
+    /// 読み込み・書き込みに失敗したファイル（他のファイルの置換は続ける）
+    pub errors: Vec<FileError>,
+}
+
+/// 一致箇所をすべて置き換えた値（一致しなければ None）
+///
+/// 正規表現検索では `$1` や `${name}` でキャプチャを参照できる。
+fn replace_text(re: &Regex, text: &str, replace: &str, expand: bool) -> Option<String> {
+    if !re.is_match(text) {
+        return None;
+    }
+    let replaced = if expand {
+        re.replace_all(text, replace)
+    } else {
+        re.replace_all(text, NoExpand(replace))
+    };
+    (replaced != text).then(|| replaced.into_owned())
+}
+
+/// 1つのファイル分の置換候補を作る
+fn plan_file(
+    path: &Path,
+    file_no: usize,
+    re: &Regex,
+    replace: &str,
+    options: &SearchOptions,
+) -> Option<FileReplacePlan> {
+    let bytes = fs::read(path).ok()?;
+    let doc = document_from_bytes(&bytes);
+    let ids = table_ids(&doc.tables);
+    let fenced = fenced_lines(&doc.lines);
+    let mut matches = Vec::new();
+    let mut push = |line: usize, cell: Option<CellLocation>, before: &str| {
+        if let Some(after) = replace_text(re, before, replace, options.regex) {
+            matches.push(ReplaceMatch {
+                id: format!("{}-{}", file_no, matches.len()),
+                line,
+                cell,
+                before: before.to_string(),
+                after,
+            });
+        }
+    };
+    for (line_no, line) in doc.lines.iter().enumerate() {
+        match table_row_at(&doc.tables, line_no) {
+            Some((ti, row)) => {
+                let Some(row) = row else {
+                    continue;
+                };
+                if options.tables_only && fenced[doc.tables[ti].start_line] {
+                    continue;
+                }
+                let table = &doc.tables[ti];
+                let cells = match row {
+                    None => &table.headers,
+                    Some(r) => &table.rows[r],
+                };
+                for (col, cell) in cells.iter().enumerate() {
+                    let location = CellLocation {
+                        table_index: ti,
+                        table_id: ids[ti].clone(),
+                        row,
+                        col,
+                    };
+                    push(line_no, Some(location), cell);
+                }
+            }
+            None if options.tables_only => {}
+            None => push(line_no, None, line),
+        }
+    }
+    (!matches.is_empty()).then(|| FileReplacePlan {
+        path: path.to_string_lossy().to_string(),
+        sha256: sha256_hex(&bytes),
+        matches,
+    })
+}
+
+/// 選択された置換を1つのファイルに適用し、適用した件数と適用しなかった候補を返す
+///
+/// 計画の作成後にファイルが変更されていたら何もせずエラーにする。置換後の値にパイプや改行を
+/// 含むセルは書き込まずに読み飛ばす。書き直すのは置換したセルを含むテーブルと置換した行だけで、
+/// それ以外は元のテキストのまま残す。
+fn apply_file(
+    plan: &FileReplacePlan,
+    selected: &HashSet<String>,
+) -> Result<(usize, Vec<SkippedMatch>), String> {
+    let path = Path::new(&plan.path);
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if sha256_hex(&bytes) != plan.sha256 {
+        return Err(format!(
+            "置換の計画後にファイルが変更されています: {}",
+            plan.path
+        ));
+    }
+    let (content, _, _) = decode_bytes(&bytes);
+    let mut doc = document_from_bytes(&bytes);
+    let mut touched: Vec<usize> = Vec::new();
+    let mut applied = 0;
+    let mut skipped = Vec::new();
+    for m in plan.matches.iter().filter(|m| selected.contains(&m.id)) {
+        match &m.cell {
+            Some(cell) => {
+                if let Err(reason) = check_cell_text(&m.after, "置換後の値") {
+                    skipped.push(SkippedMatch {
+                        id: m.id.clone(),
+                        reason,
+                    });
+                    continue;
+                }
+                let table = doc
+                    .tables
+                    .get_mut(cell.table_index)
+                    .ok_or_else(|| format!("テーブルが見つかりません: {}", cell.table_index))?;
+                match cell.row {
+                    Some(row) => set_cell(table, row, cell.col, m.after.clone())?,
+                    None => {
+                        let header = table
+                            .headers
+                            .get_mut(cell.col)
+                            .ok_or_else(|| format!("列番号が範囲外です: {}", cell.col))?;
+                        *header = m.after.clone();
+                    }
+                }
+                touched.push(cell.table_index);
+            }
+            None => {
+                let line = doc
+                    .lines
+                    .get_mut(m.line)
+                    .ok_or_else(|| format!("行番号が範囲外です: {}", m.line))?;
+                *line = m.after.clone();
+            }
+        }
+        applied += 1;
+    }
+    if applied > 0 {
+        write_document(path, &content, &doc, touched)?;
+    }
+    Ok((applied, skipped))
+}
+
+/// 変更したテーブルと行を一時ファイル経由でファイルに書き戻す
+///
+/// `touched` に含まれないテーブルは元のテキストのまま残し、元の末尾の改行と文字コードを保つ。
+fn write_document(
+    path: &Path,
+    original: &str,
+    doc: &ParsedDocument,
+    mut touched: Vec<usize>,
+) -> Result<(), String> {
+    touched.sort_unstable();
+    touched.dedup();
+    let tables: Vec<MarkdownTable> = touched.iter().map(|&ti| doc.tables[ti].clone()).collect();
+    let mut text = rebuild_document(&doc.lines, &tables);
+    if original.ends_with('\n') && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    let bytes = encode_text(&text, &doc.encoding, doc.has_bom)?;
+    write_atomic(path, &bytes).map_err(|e| e.to_string())
+}
+
+/// フォルダ以下の .md ファイルのテーブルのデータセルを一括置換する
+///
+/// `find` は大文字・小文字を区別して探し、`use_regex` のときは正規表現として扱って置換文字列で
+/// `$1` などのキャプチャを参照できる。正規表現はすべてのファイルで使い回す。除外パターンに
+/// 一致するファイル、ヘッダー、コードフェンス内のテーブルは対象外。置換後の値にパイプや改行を
+/// 含むセルは置換せずに `skipped` で返す。`dry_run` なら書き込まずに置換候補だけを返し、そうで
+/// なければファイルごとに一時ファイル経由でまとめて書き込む。読み書きに失敗したファイルは
+/// `errors` に入れ、残りのファイルの置換は続ける。
+pub fn search_replace_in_directory(
+    dir: &Path,
+    find: &str,
+    replace: &str,
+    use_regex: bool,
+    dry_run: bool,
+) -> Result<DirectoryReplaceResult, String> {
+    if !dir.is_dir() {
+        return Err("ディレクトリが存在しません".to_string());
+    }
+    let options = SearchOptions {
+        case_sensitive: true,
+        regex: use_regex,
+        ..SearchOptions::default()
+    };
+    let re = build_matcher(find, &options)?;
+    let mut result = DirectoryReplaceResult {
+        replaced: Vec::new(),
+        skipped: Vec::new(),
+        changed_files: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (path, _) in workspace::markdown_files(dir) {
+        let file_path = path.to_string_lossy().to_string();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                result.errors.push(FileError {
+                    path: file_path,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let (content, _, _) = decode_bytes(&bytes);
+        let mut doc = document_from_bytes(&bytes);
+        let fenced = fenced_lines(&doc.lines);
+        let mut previews = Vec::new();
+        let mut touched = Vec::new();
+        for (ti, table) in doc.tables.iter_mut().enumerate() {
+            if fenced[table.start_line] {
+                continue;
+            }
+            for (ri, row) in table.rows.iter_mut().enumerate() {
+                for (ci, cell) in row.iter_mut().enumerate() {
+                    let Some(replacement) = replace_text(&re, cell, replace, use_regex) else {
+                        continue;
+                    };
+                    let preview = ReplacePreview {
+                        file_path: file_path.clone(),
+                        table_index: ti,
+                        row_index: ri,
+                        col_index: ci,
+                        original: cell.clone(),
+                        replacement,
+                    };
+                    if check_cell_text(&preview.replacement, "置換後の値").is_err() {
+                        result.skipped.push(preview);
+                        continue;
+                    }
+                    cell.clone_from(&preview.replacement);
+                    previews.push(preview);
+                    touched.push(ti);
+                }
+            }
+        }
+        if !dry_run && !touched.is_empty() {
+            if let Err(e) = write_document(&path, &content, &doc, touched) {
+                result.errors.push(FileError {
+                    path: file_path,
+                    error: e,
+                });
+                continue;
+            }
+            result.changed_files.push(file_path);
+        }
+        result.replaced.extend(previews);
+    }
+    Ok(result)
+}
+
+/// 作成済みの置換計画を適用まで保持する管理ステート
+#[derive(Debug, Default)]
+pub struct ReplacePlans(Mutex<HashMap<String, ReplacePlan>>);
+
+impl ReplacePlans {
+    /// フォルダ以下の .md ファイルから置換候補を集め、書き込まずに計画として返す
+    ///
+    /// 検索条件は全文検索と同じ。`tables_only` のときはコードフェンス内のテーブルも対象外。
+    pub fn plan(
+        &self,
+        dir: &Path,
+        find: &str,
+        replace: &str,
+        options: &SearchOptions,
+    ) -> Result<ReplacePlan, String> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let re = build_matcher(find, options)?;
+        let mut files: Vec<FileReplacePlan> = workspace::markdown_files(dir)
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (path, _))| plan_file(path, i, &re, replace, options))
+            .collect();
+        if let Some(max) = options.max_results {
+            let mut remaining = max;
+            files.retain_mut(|f| {
+                f.matches.truncate(remaining);
+                remaining -= f.matches.len();
+                !f.matches.is_empty()
+            });
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let plan = ReplacePlan {
+            plan_id: format!("{:x}-{}", now, COUNTER.fetch_add(1, Ordering::Relaxed)),
+            total: files.iter().map(|f| f.matches.len()).sum(),
+            files,
+        };
+        let mut plans = self.0.lock().map_err(|e| e.to_string())?;
+        plans.insert(plan.plan_id.clone(), plan.clone());
+        Ok(plan)
+    }
+
+    /// 計画のうち選択された置換だけを適用する（計画は破棄する）
+    ///
+    /// ファイルごとに一時ファイル経由で書き込み、1つのファイルの失敗で残りを止めない。
+    pub fn apply(
+        &self,
+        plan_id: &str,
+        selected_match_ids: &[String],
+    ) -> Result<Vec<FileReplaceResult>, String> {
+        let plan = self
+            .0
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(plan_id)
+            .ok_or_else(|| format!("置換の計画が見つかりません: {}", plan_id))?;
+        let selected: HashSet<String> = selected_match_ids.iter().cloned().collect();
+        Ok(plan
+            .files
+            .iter()
+            .filter(|f| f.matches.iter().any(|m| selected.contains(&m.id)))
+            .map(|f| match apply_file(f, &selected) {
+                Ok((applied, skipped)) => FileReplaceResult {
+                    path: f.path.clone(),
+                    applied,
+                    skipped,
+                    error: None,
+                },
+                Err(e) => FileReplaceResult {
+                    path: f.path.clone(),
+                    applied: 0,
+                    skipped: Vec::new(),
+                    error: Some(e),
+                },
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_capture_replace_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("a.md"),
+            "Widget-2 is new.\n\n| Name | Qty |\n| --- | --- |\n| Widget-1 | 3 |\n| Gadget | 1 |\n\n```\n| Widget-9 |\n| --- |\n```\n",
+        )
+        .unwrap();
+        fs::write(root.join("b.md"), "no match\n").unwrap();
+
+        let plans = ReplacePlans::default();
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        let plan = plans
+            .plan(root, r"Widget-(\d)", "Gizmo ${1}", &options)
+            .unwrap();
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.total, 3);
+        let first = &plan.files[0].matches[0];
+        assert_eq!((first.line, first.cell.is_none()), (0, true));
+        assert_eq!(first.after, "Gizmo 2 is new.");
+        let cell = &plan.files[0].matches[1];
+        assert_eq!(cell.after, "Gizmo 1");
+        assert_eq!(
+            cell.cell.as_ref().map(|c| (c.row, c.col)),
+            Some((Some(0), 0))
+        );
+
+        // テーブルのみのときはコードフェンス内のテーブルを除く
+        let options = SearchOptions {
+            tables_only: true,
+            ..options
+        };
+        let plan = plans
+            .plan(root, r"Widget-(\d)", "Gizmo ${1}", &options)
+            .unwrap();
+        assert_eq!(plan.total, 1);
+        // 検索が正規表現でなければ `$1` はそのまま入る
+        let plan = plans
+            .plan(root, "Gadget", "$1", &SearchOptions::default())
+            .unwrap();
+        assert_eq!(plan.files[0].matches[0].after, "$1");
+    }
+
+    #[test]
+    fn test_partial_apply_and_per_file_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let a = "# A\n\nfoo in text\n\n| K | V |\n| --- | --- |\n| foo | 1 |\n| x   | 2 |\n";
+        fs::write(root.join("a.md"), a).unwrap();
+        fs::write(root.join("b.md"), "foo\n").unwrap();
+
+        let plans = ReplacePlans::default();
+        let plan = plans
+            .plan(root, "foo", "bar", &SearchOptions::default())
+            .unwrap();
+        assert_eq!(plan.total, 3);
+        // b.md は計画後に変更されたので適用できない
+        fs::write(root.join("b.md"), "foo changed\n").unwrap();
+        let keep = vec![
+            plan.files[0].matches[1].id.clone(),
+            plan.files[1].matches[0].id.clone(),
+        ];
+        let results = plans.apply(&plan.plan_id, &keep).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].applied, &results[0].error), (1, &None));
+        assert!(results[1].error.is_some());
+
+        let text = fs::read_to_string(root.join("a.md")).unwrap();
+        // 選ばなかった行はそのまま、置換したテーブルだけ整形し直す
+        assert!(text.contains("foo in text"));
+        assert!(text.contains("| bar | 1   |"));
+        assert!(text.ends_with("| x   | 2   |\n"));
+        assert_eq!(
+            fs::read_to_string(root.join("b.md")).unwrap(),
+            "foo changed\n"
+        );
+        // 適用した計画は破棄される
+        assert!(plans.apply(&plan.plan_id, &keep).is_err());
+    }
+
+    #[test]
+    fn test_apply_skips_replacements_that_break_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let a = "foo\n\n| K | V |\n| --- | --- |\n| foo | 1 |\n";
+        fs::write(root.join("a.md"), a).unwrap();
+
+        let plans = ReplacePlans::default();
+        let plan = plans
+            .plan(root, "foo", "x|y", &SearchOptions::default())
+            .unwrap();
+        let all: Vec<String> = plan.files[0].matches.iter().map(|m| m.id.clone()).collect();
+        let results = plans.apply(&plan.plan_id, &all).unwrap();
+        // 本文の行は置換し、パイプを含むことになるセルは読み飛ばして知らせる
+        assert_eq!(results[0].applied, 1);
+        assert_eq!(results[0].skipped.len(), 1);
+        assert_eq!(results[0].skipped[0].id, plan.files[0].matches[1].id);
+        assert!(results[0].skipped[0].reason.contains("パイプ"));
+        let text = fs::read_to_string(root.join("a.md")).unwrap();
+        assert!(text.starts_with("x|y\n"));
+        assert!(text.contains("| foo | 1 |"));
+    }
+
+    #[test]
+    fn test_search_replace_in_directory_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let a = "v1.2 in text\n\n| Ver | Note |\n| --- | --- |\n| v1.2 | keep V1.2 |\n| v2.0 | v1.25 |\n\n```\n| v1.2 |\n| --- |\n| v1.2 |\n```\n";
+        fs::write(root.join("a.md"), a).unwrap();
+        fs::write(root.join("b.md"), "| A |\n| --- |\n| x |\n").unwrap();
+
+        let previews = search_replace_in_directory(root, r"v1\.(\d+)", "v9.$1", true, true)
+            .unwrap()
+            .replaced;
+        let cells: Vec<(usize, usize, &str, &str)> = previews
+            .iter()
+            .map(|p| {
+                (
+                    p.row_index,
+                    p.col_index,
+                    p.original.as_str(),
+                    p.replacement.as_str(),
+                )
+            })
+            .collect();
+        // 大文字・小文字を区別し、本文とコードフェンス内のテーブルは対象外
+        assert_eq!(
+            cells,
+            vec![(0, 0, "v1.2", "v9.2"), (1, 1, "v1.25", "v9.25")]
+        );
+        assert!(previews[0].file_path.ends_with("a.md"));
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), a);
+
+        let applied = search_replace_in_directory(root, "v1.2", "v1.3", false, false).unwrap();
+        assert_eq!(applied.replaced.len(), 2);
+        assert_eq!(applied.changed_files.len(), 1);
+        assert!(applied.changed_files[0].ends_with("a.md"));
+        let text = fs::read_to_string(root.join("a.md")).unwrap();
+        assert!(text.starts_with("v1.2 in text\n"));
+        assert!(text.contains("| v1.3 | keep V1.2 |"));
+        assert!(text.contains("| v1.35 "));
+        assert!(text.ends_with("| v1.2 |\n| --- |\n| v1.2 |\n```\n"));
+        assert!(search_replace_in_directory(root, "(", "x", true, true).is_err());
+
+        // パイプや改行になる置換はセルを壊すため行わずに知らせる
+        let broken = search_replace_in_directory(root, "x", "a\nb", false, false).unwrap();
+        assert!(broken.replaced.is_empty() && broken.changed_files.is_empty());
+        assert_eq!(broken.skipped.len(), 1);
+        assert!(broken.skipped[0].file_path.ends_with("b.md"));
+        assert_eq!(
+            fs::read_to_string(root.join("b.md")).unwrap(),
+            "| A |\n| --- |\n| x |\n"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::markdown_parser::{
+    get_cell, infer_column_type, natural_cmp, parse_amount, ColumnRef, ColumnType, MarkdownTable,
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// 行の絞り込み条件（条件1つ、または and / or の組み合わせ）
+///
+/// JSON では `{"and": [...]}`・`{"or": [...]}`・`{"column": "Status", "op": "ne", "value": "Done"}`
+/// の形で書く。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum RowFilter {
+    And { and: Vec<RowFilter> },
+    Or { or: Vec<RowFilter> },
+    Clause(FilterClause),
+}
+
+/// 列1つに対する条件
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FilterClause {
+    pub column: ColumnRef,
+    pub op: FilterOp,
+    /// 比べる値（`empty` / `not_empty` では使わない）
+    #[serde(default)]
+    pub value: String,
+}
+
+/// 条件の演算子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    /// 部分一致（大文字・小文字は区別しない）
+    Contains,
+    Regex,
+    Gt,
+    Lt,
+    Empty,
+    NotEmpty,
+}
+
+/// 列と比べ方を解決済みの条件
+enum Compiled {
+    And(Vec<Compiled>),
+    Or(Vec<Compiled>),
+    Clause {
+        col: usize,
+        ty: ColumnType,
+        op: FilterOp,
+        value: String,
+        regex: Option<Regex>,
+    },
+}
+
+/// 条件に合うデータ行の番号を返す（集計行は対象外）
+///
+/// 数値の列は数値として（`1,000` と `1000` は等しい）、日付の列は日付として比べる。
+/// 存在しない列・正しくない正規表現・比べられない値はエラーにし、どの条件かを
+/// `and[0].or[1]` のような位置で示す。
+pub fn filter_rows(table: &MarkdownTable, filter: &RowFilter) -> Result<Vec<usize>, String> {
+    let compiled = compile(filter, table, "条件")?;
+    let data_rows = table
+        .rows
+        .len()
+        .saturating_sub(usize::from(table.has_summary_row));
+    Ok((0..data_rows)
+        .filter(|&ri| matches(&compiled, table, ri))
+        .collect())
+}
+
+fn compile(filter: &RowFilter, table: &MarkdownTable, path: &str) -> Result<Compiled, String> {
+    let children = |filters: &[RowFilter], key: &str| -> Result<Vec<Compiled>, String> {
+        filters
+            .iter()
+            .enumerate()
+            .map(|(i, f)| compile(f, table, &format!("{}.{}[{}]", path, key, i)))
+            .collect()
+    };
+    let clause = match filter {
+        RowFilter::And { and } => return children(and, "and").map(Compiled::And),
+        RowFilter::Or { or } => return children(or, "or").map(Compiled::Or),
+        RowFilter::Clause(clause) => clause,
+    };
+    let fail = |message: String| format!("{}: {}", path, message);
+    let col = clause.column.resolve(table).map_err(fail)?;
+    let ty = infer_column_type(table, col);
+    let value = clause.value.trim().to_string();
+    let regex = match clause.op {
+        FilterOp::Regex => Some(
+            Regex::new(&clause.value)
+                .map_err(|e| fail(format!("正規表現が正しくありません: {}", e)))?,
+        ),
+        FilterOp::Gt | FilterOp::Lt if !ty.accepts(&value) || value.is_empty() => {
+            return Err(fail(format!(
+                "列の型（{:?}）と比べられない値です: {}",
+                ty, value
+            )));
+        }
+        _ => None,
+    };
+    Ok(Compiled::Clause {
+        col,
+        ty,
+        op: clause.op,
+        value,
+        regex,
+    })
+}
+
+/// 列の型に合わせてセルと値が等しいかを判定する（数値の列以外は文字列として完全一致）
+fn equals(cell: &str, value: &str, ty: ColumnType) -> bool {
+    match (ty, parse_amount(cell), parse_amount(value)) {
+        (ColumnType::Number, Some(a), Some(b)) => a == b,
+        _ => cell == value,
+    }
+}
+
+/// 列の型に合わせてセルと値の大小を比べる
+fn compare(cell: &str, value: &str, ty: ColumnType) -> Ordering {
+    match (ty, parse_amount(cell), parse_amount(value)) {
+        (ColumnType::Number, Some(a), Some(b)) => a.total_cmp(&b),
+        (ColumnType::Number | ColumnType::Date, _, _) => cell.cmp(value),
+        _ => natural_cmp(cell, value),
+    }
+}
+
+fn matches(filter: &Compiled, table: &MarkdownTable, ri: usize) -> bool {
+    match filter {
+        Compiled::And(all) => all.iter().all(|f| matches(f, table, ri)),
+        Compiled::Or(any) => any.iter().any(|f| matches(f, table, ri)),
+        Compiled::Clause {
+            col,
+            ty,
+            op,
+            value,
+            regex,
+        } => {
+            let cell = get_cell(table, ri, *col).unwrap_or("").trim();
+            match op {
+                FilterOp::Empty => cell.is_empty(),
+                FilterOp::NotEmpty => !cell.is_empty(),
+                FilterOp::Eq => equals(cell, value, *ty),
+                FilterOp::Ne => !equals(cell, value, *ty),
+                FilterOp::Contains => cell.to_lowercase().contains(&value.to_lowercase()),
+                FilterOp::Regex => regex.as_ref().is_some_and(|re| re.is_match(cell)),
+                FilterOp::Gt => !cell.is_empty() && compare(cell, value, *ty).is_gt(),
+                FilterOp::Lt => !cell.is_empty() && compare(cell, value, *ty).is_lt(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::parse_markdown;
+
+    fn sample() -> MarkdownTable {
+        let md = "| Task | Status | Owner | Cost | Due |\n| --- | --- | --- | --- | --- |\n| a | Done | 田中 | 1,200 | 2024-03-01 |\n| b | Open | 田中太郎 | 300 | 2024-05-01 |\n| c | open | 鈴木 | 45 | |\n| d | Review | | 1000 | 2024-01-15 |\n";
+        parse_markdown(md).tables.remove(0)
+    }
+
+    fn run(json: serde_json::Value) -> Result<Vec<usize>, String> {
+        filter_rows(&sample(), &serde_json::from_value(json).unwrap())
+    }
+
+    #[test]
+    fn test_filter_rows_operators() {
+        use serde_json::json;
+        let clause = |column: &str, op: &str, value: &str| {
+            run(json!({ "column": column, "op": op, "value": value })).unwrap()
+        };
+        assert_eq!(clause("Status", "eq", "Open"), vec![1]);
+        assert_eq!(clause("Status", "ne", "Done"), vec![1, 2, 3]);
+        assert_eq!(clause("Owner", "contains", "田中"), vec![0, 1]);
+        assert_eq!(clause("Status", "regex", "(?i)^open$"), vec![1, 2]);
+        // 数値の列は数値として比べる
+        assert_eq!(clause("Cost", "gt", "999"), vec![0, 3]);
+        assert_eq!(clause("Cost", "lt", "1000"), vec![1, 2]);
+        assert_eq!(clause("Cost", "eq", "1000.0"), vec![3]);
+        // 日付の列は日付として比べ、空のセルは大小比較に含めない
+        assert_eq!(clause("Due", "lt", "2024-04-01"), vec![0, 3]);
+        assert_eq!(clause("Due", "gt", "2024-04-01"), vec![1]);
+        assert_eq!(run(json!({ "column": 2, "op": "empty" })).unwrap(), vec![3]);
+        assert_eq!(
+            run(json!({ "column": "Due", "op": "not_empty" })).unwrap(),
+            vec![0, 1, 3]
+        );
+    }
+
+    #[test]
+    fn test_filter_rows_nested_and_errors() {
+        use serde_json::json;
+        let nested = json!({ "and": [
+            { "column": "Status", "op": "ne", "value": "Done" },
+            { "or": [
+                { "column": "Owner", "op": "contains", "value": "田中" },
+                { "column": "Cost", "op": "gt", "value": "500" },
+            ] },
+        ] });
+        assert_eq!(run(nested).unwrap(), vec![1, 3]);
+
+        let err = run(json!({ "or": [
+            { "column": "Status", "op": "empty" },
+            { "column": "Status", "op": "regex", "value": "(" },
+        ] }))
+        .unwrap_err();
+        assert!(err.starts_with("条件.or[1]") && err.contains("正規表現"));
+        let err =
+            run(json!({ "and": [{ "column": "Owner2", "op": "eq", "value": "x" }] })).unwrap_err();
+        assert!(err.starts_with("条件.and[0]") && err.contains("Owner2"));
+        assert!(run(json!({ "column": "Cost", "op": "gt", "value": "many" })).is_err());
+    }
+}
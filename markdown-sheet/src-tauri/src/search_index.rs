@@ -0,0 +1,390 @@
+use crate::file_io::{mtime_ms, read_document, write_atomic};
+use crate::workspace::{self, SETTINGS_DIR};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 索引作成の進捗を通知するイベント名
+pub const INDEX_PROGRESS_EVENT: &str = "search-index-progress";
+/// 索引のファイル形式のバージョン（変わったら読み込まずに作り直す）
+const INDEX_VERSION: u32 = 1;
+
+/// 索引作成の進捗
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexProgress {
+    pub root: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// 索引で絞り込んだファイルと、照合する行番号
+pub type FileCandidates = (PathBuf, HashSet<usize>);
+
+/// 索引の状態
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexStatus {
+    pub root: String,
+    /// 索引が作成済みか
+    pub built: bool,
+    /// 索引に含まれるファイル数
+    pub documents: usize,
+    /// 索引作成後に追加・変更・削除されたファイル数
+    pub stale: usize,
+}
+
+/// 索引に登録したファイル
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    /// ルートからの相対パス（`/` 区切り）
+    rel: String,
+    mtime_ms: i64,
+    size: u64,
+    /// テーブルの `(start_line, end_line)`
+    tables: Vec<(usize, usize)>,
+}
+
+/// ルート1つ分の転置索引（小文字化した3文字組 → `(ファイル ID, 行番号)`）
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexData {
+    version: u32,
+    next_id: u32,
+    files: HashMap<u32, IndexedFile>,
+    postings: HashMap<String, Vec<(u32, u32)>>,
+}
+
+/// 前回の索引作成から変わったファイル
+struct Changes {
+    /// 追加・変更されたファイル `(絶対パス, 相対パス, 更新日時, サイズ)`
+    updated: Vec<(PathBuf, String, i64, u64)>,
+    /// 削除・変更されたファイルの ID
+    removed: HashSet<u32>,
+}
+
+/// テキストに含まれる3文字組（小文字化して重複を除く）
+fn trigrams(text: &str) -> HashSet<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn index_path(root: &Path) -> PathBuf {
+    root.join(SETTINGS_DIR).join("index").join("index.json")
+}
+
+fn load(root: &Path) -> Option<IndexData> {
+    let data: IndexData = serde_json::from_slice(&fs::read(index_path(root)).ok()?).ok()?;
+    (data.version == INDEX_VERSION).then_some(data)
+}
+
+fn save(root: &Path, data: &IndexData) -> Result<(), String> {
+    let path = index_path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let bytes = serde_json::to_vec(data).map_err(|e| e.to_string())?;
+    write_atomic(&path, &bytes).map_err(|e| e.to_string())
+}
+
+impl IndexData {
+    fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// ディスク上のファイルと更新日時・サイズを突き合わせる
+    fn changes(&self, root: &Path) -> Changes {
+        let mut known: HashMap<&str, (u32, &IndexedFile)> = self
+            .files
+            .iter()
+            .map(|(id, f)| (f.rel.as_str(), (*id, f)))
+            .collect();
+        let mut changes = Changes {
+            updated: Vec::new(),
+            removed: HashSet::new(),
+        };
+        for (path, rel) in workspace::markdown_files(root) {
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            let (mtime, size) = (mtime_ms(&meta), meta.len());
+            match known.remove(rel.as_str()) {
+                Some((_, f)) if f.mtime_ms == mtime && f.size == size => {}
+                Some((id, _)) => {
+                    changes.removed.insert(id);
+                    changes.updated.push((path, rel, mtime, size));
+                }
+                None => changes.updated.push((path, rel, mtime, size)),
+            }
+        }
+        changes.removed.extend(known.values().map(|(id, _)| *id));
+        changes
+    }
+
+    fn remove_files(&mut self, ids: &HashSet<u32>) {
+        if ids.is_empty() {
+            return;
+        }
+        self.files.retain(|id, _| !ids.contains(id));
+        self.postings.retain(|_, list| {
+            list.retain(|(id, _)| !ids.contains(id));
+            !list.is_empty()
+        });
+    }
+
+    /// ファイルを読み込んで行ごとの3文字組を登録する（読めないファイルは飛ばす）
+    fn add_file(&mut self, path: &Path, rel: String, mtime_ms: i64, size: u64) {
+        let Ok(doc) = read_document(path) else {
+            return;
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        for (line_no, line) in doc.lines.iter().enumerate() {
+            for gram in trigrams(line) {
+                self.postings
+                    .entry(gram)
+                    .or_default()
+                    .push((id, line_no as u32));
+            }
+        }
+        let tables = doc
+            .tables
+            .iter()
+            .map(|t| (t.start_line, t.end_line))
+            .collect();
+        self.files.insert(
+            id,
+            IndexedFile {
+                rel,
+                mtime_ms,
+                size,
+                tables,
+            },
+        );
+    }
+
+    /// 変更のあったファイルだけを索引し直す
+    fn apply(&mut self, changes: Changes) -> usize {
+        self.remove_files(&changes.removed);
+        let count = changes.updated.len();
+        for (path, rel, mtime, size) in changes.updated {
+            self.add_file(&path, rel, mtime, size);
+        }
+        count
+    }
+}
+
+/// ワークスペースのルートごとの全文検索索引（`.markdown-sheet/index` に保存する管理ステート）
+///
+/// 行ごとに3文字組の転置索引を持ち、候補の行を絞り込んでから実際のファイルで照合する。
+/// ファイルの変更は更新日時とサイズで検出し、変わったファイルだけを索引し直す。
+#[derive(Debug, Default)]
+pub struct SearchIndex(Mutex<HashMap<PathBuf, IndexData>>);
+
+impl SearchIndex {
+    /// ルート以下のファイルをすべて索引し直して保存する
+    pub fn build(
+        &self,
+        root: &Path,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<IndexStatus, String> {
+        let files = workspace::markdown_files(root);
+        let mut data = IndexData::new();
+        let total = files.len();
+        on_progress(0, total);
+        for (i, (path, rel)) in files.into_iter().enumerate() {
+            if let Ok(meta) = fs::metadata(&path) {
+                data.add_file(&path, rel, mtime_ms(&meta), meta.len());
+            }
+            on_progress(i + 1, total);
+        }
+        save(root, &data)?;
+        let status = IndexStatus {
+            root: root.to_string_lossy().to_string(),
+            built: true,
+            documents: data.files.len(),
+            stale: 0,
+        };
+        let mut cache = self.0.lock().map_err(|e| e.to_string())?;
+        cache.insert(root.to_path_buf(), data);
+        Ok(status)
+    }
+
+    /// 変更のあったファイルだけを索引し直し、索引し直したファイル数を返す
+    ///
+    /// 索引が作成されていなければ何もしない。ディレクトリの監視から呼ぶことも想定している。
+    pub fn refresh(&self, root: &Path) -> Result<usize, String> {
+        let mut cache = self.0.lock().map_err(|e| e.to_string())?;
+        let Some(data) = Self::loaded(&mut cache, root) else {
+            return Ok(0);
+        };
+        let changes = data.changes(root);
+        if changes.updated.is_empty() && changes.removed.is_empty() {
+            return Ok(0);
+        }
+        let count = data.apply(changes);
+        save(root, data)?;
+        Ok(count)
+    }
+
+    /// 索引の状態を返す
+    pub fn status(&self, root: &Path) -> Result<IndexStatus, String> {
+        let mut cache = self.0.lock().map_err(|e| e.to_string())?;
+        let mut status = IndexStatus {
+            root: root.to_string_lossy().to_string(),
+            built: false,
+            documents: 0,
+            stale: 0,
+        };
+        if let Some(data) = Self::loaded(&mut cache, root) {
+            let changes = data.changes(root);
+            let stale: HashSet<&str> = changes
+                .updated
+                .iter()
+                .map(|(_, rel, _, _)| rel.as_str())
+                .chain(
+                    changes
+                        .removed
+                        .iter()
+                        .filter_map(|id| data.files.get(id).map(|f| f.rel.as_str())),
+                )
+                .collect();
+            status.built = true;
+            status.documents = data.files.len();
+            status.stale = stale.len();
+        }
+        Ok(status)
+    }
+
+    /// 索引から、クエリを含みうる行をファイルごとに返す（ファイルはツリーと同じ順）
+    ///
+    /// 索引がない場合や、クエリが短すぎて絞り込めない場合は None（全件を走査する）。
+    /// 照合の前に変更のあったファイルを索引し直す。
+    pub fn candidates(
+        &self,
+        root: &Path,
+        query: &str,
+        tables_only: bool,
+    ) -> Result<Option<Vec<FileCandidates>>, String> {
+        let grams = trigrams(query);
+        if grams.is_empty() {
+            return Ok(None);
+        }
+        self.refresh(root)?;
+        let mut cache = self.0.lock().map_err(|e| e.to_string())?;
+        let Some(data) = Self::loaded(&mut cache, root) else {
+            return Ok(None);
+        };
+        let mut lists = Vec::with_capacity(grams.len());
+        for gram in &grams {
+            match data.postings.get(gram) {
+                Some(list) => lists.push(list),
+                None => return Ok(Some(Vec::new())),
+            }
+        }
+        lists.sort_by_key(|list| list.len());
+        let mut hits: HashSet<(u32, u32)> = lists[0].iter().copied().collect();
+        for list in &lists[1..] {
+            let other: HashSet<&(u32, u32)> = list.iter().collect();
+            hits.retain(|hit| other.contains(hit));
+        }
+
+        let mut per_file: HashMap<u32, HashSet<usize>> = HashMap::new();
+        for (id, line) in hits {
+            let line = line as usize;
+            let in_table = data.files[&id]
+                .tables
+                .iter()
+                .any(|&(start, end)| start <= line && line <= end);
+            if tables_only && !in_table {
+                continue;
+            }
+            per_file.entry(id).or_default().insert(line);
+        }
+        let mut result: Vec<(&str, HashSet<usize>)> = per_file
+            .into_iter()
+            .map(|(id, lines)| (data.files[&id].rel.as_str(), lines))
+            .collect();
+        // ディレクトリを先にたどる `markdown_files` と同じく、パスの要素ごとに比べる
+        result.sort_by(|a, b| a.0.split('/').cmp(b.0.split('/')));
+        Ok(Some(
+            result
+                .into_iter()
+                .map(|(rel, lines)| (root.join(rel), lines))
+                .collect(),
+        ))
+    }
+
+    /// キャッシュになければディスクから読み込む
+    fn loaded<'a>(
+        cache: &'a mut HashMap<PathBuf, IndexData>,
+        root: &Path,
+    ) -> Option<&'a mut IndexData> {
+        if !cache.contains_key(root) {
+            cache.insert(root.to_path_buf(), load(root)?);
+        }
+        cache.get_mut(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_query_and_incremental_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("billing")).unwrap();
+        fs::write(
+            root.join("billing/2024.md"),
+            "# Invoices\n\n| No | Note |\n| --- | --- |\n| INV-117 | 未払い |\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes.md"), "nothing here\n").unwrap();
+
+        let index = SearchIndex::default();
+        assert!(index.candidates(root, "inv-117", false).unwrap().is_none());
+        assert!(!index.status(root).unwrap().built);
+
+        let mut progress = Vec::new();
+        let status = index
+            .build(root, |done, total| progress.push((done, total)))
+            .unwrap();
+        assert_eq!(status.documents, 2);
+        assert_eq!(progress.last(), Some(&(2, 2)));
+        assert!(index_path(root).exists());
+
+        let found = index.candidates(root, "inv-117", true).unwrap().unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].0.ends_with("billing/2024.md"));
+        assert_eq!(found[0].1, HashSet::from([4]));
+        // 日本語も3文字組で引ける
+        assert!(
+            index
+                .candidates(root, "未払い", false)
+                .unwrap()
+                .unwrap()
+                .len()
+                == 1
+        );
+        // 2文字以下は絞り込めない
+        assert!(index.candidates(root, "未払", false).unwrap().is_none());
+
+        // 1ファイルだけ変えると、そのファイルだけが索引し直される
+        fs::write(root.join("notes.md"), "see\ninv-117 too\n").unwrap();
+        let stale = SearchIndex::default();
+        assert_eq!(stale.status(root).unwrap().stale, 1);
+        let found = index.candidates(root, "INV-117", false).unwrap().unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found[1].0.ends_with("notes.md"));
+        assert_eq!(found[1].1, HashSet::from([1]));
+        assert_eq!(index.status(root).unwrap().stale, 0);
+
+        fs::remove_file(root.join("billing/2024.md")).unwrap();
+        assert_eq!(index.refresh(root).unwrap(), 0);
+        assert_eq!(index.status(root).unwrap().documents, 1);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::file_io::{read_snapshot, DocumentSnapshot};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// フロントエンドの準備後に OS から渡されたファイルを通知するイベント名
+pub const OPEN_FILE_EVENT: &str = "open-file-request";
+
+/// ウィンドウにドロップされた Markdown ファイルを通知するイベント名
+pub const FILES_DROPPED_EVENT: &str = "files-dropped";
+/// ウィンドウにドロップされたフォルダを通知するイベント名（ワークスペースとして開く）
+pub const FOLDER_DROPPED_EVENT: &str = "folder-dropped";
+/// 開けないファイルがドロップされたことを通知するイベント名
+pub const UNSUPPORTED_DROP_EVENT: &str = "unsupported-drop";
+
+/// Markdown として開ける拡張子（.md / .markdown）か
+pub fn is_markdown_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"))
+}
+
+/// 開く対象のパスを正規化して検証する（存在する Markdown ファイルのみ通す）
+pub fn validate_open_path(path: &Path) -> Option<String> {
+    let abs = fs::canonicalize(path).ok()?;
+    (abs.is_file() && is_markdown_path(&abs)).then(|| abs.to_string_lossy().to_string())
+}
+
+/// ドロップで開けるファイルか（Markdown に加えてプレーンテキストの .txt も開く）
+fn is_droppable_text(path: &Path) -> bool {
+    is_markdown_path(path)
+        || path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("txt"))
+}
+
+/// ドロップされたパスの振り分け結果
+#[derive(Debug, Default)]
+pub struct DropOutcome {
+    /// 読み込んだ Markdown・テキストファイル
+    pub documents: Vec<DocumentSnapshot>,
+    /// ドロップされたフォルダ（中身は走査しない）
+    pub folders: Vec<String>,
+    /// Markdown 以外のファイルや読み込めなかったファイル
+    pub unsupported: Vec<String>,
+}
+
+/// ドロップされたパスを Markdown・テキストファイル、フォルダ、それ以外に振り分け、ファイルは読み込む
+pub fn classify_drop(paths: &[PathBuf]) -> DropOutcome {
+    let mut outcome = DropOutcome::default();
+    for path in paths {
+        if path.is_dir() {
+            let abs = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            outcome.folders.push(abs.to_string_lossy().to_string());
+            continue;
+        }
+        let snapshot = fs::canonicalize(path)
+            .ok()
+            .filter(|abs| abs.is_file() && is_droppable_text(abs))
+            .and_then(|abs| read_snapshot(&abs).ok());
+        match snapshot {
+            Some(snapshot) => outcome.documents.push(snapshot),
+            None => outcome.unsupported.push(path.to_string_lossy().to_string()),
+        }
+    }
+    outcome
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    files: Vec<String>,
+    /// フロントエンドが `take_startup_files` を呼んだか
+    ready: bool,
+}
+
+/// 起動時のコマンドライン引数や OS のファイル関連付けで渡されたファイルを、
+/// フロントエンドの準備ができるまで保持する管理ステート
+#[derive(Debug, Default)]
+pub struct StartupFiles(Mutex<Pending>);
+
+impl StartupFiles {
+    /// コマンドライン引数（先頭はプログラム名）から作成する。オプションや Markdown 以外は無視する
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let state = StartupFiles::default();
+        state.receive(
+            args.into_iter()
+                .skip(1)
+                .filter(|a| !a.starts_with('-'))
+                .map(PathBuf::from),
+        );
+        state
+    }
+
+    /// 保持しているファイルを取り出す。以降に届いたファイルはイベントで通知する
+    pub fn take(&self) -> Vec<String> {
+        let Ok(mut pending) = self.0.lock() else {
+            return Vec::new();
+        };
+        pending.ready = true;
+        std::mem::take(&mut pending.files)
+    }
+
+    /// ファイルを受け取る
+    ///
+    /// フロントエンドの準備前なら保持して空を返し、準備後なら通知すべきパスを返す。
+    pub fn receive(&self, paths: impl IntoIterator<Item = PathBuf>) -> Vec<String> {
+        let Ok(mut pending) = self.0.lock() else {
+            return Vec::new();
+        };
+        let mut valid = Vec::new();
+        for path in paths {
+            if let Some(path) = validate_open_path(&path) {
+                if !valid.contains(&path) && !pending.files.contains(&path) {
+                    valid.push(path);
+                }
+            }
+        }
+        if pending.ready {
+            valid
+        } else {
+            pending.files.extend(valid);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_files_handoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.MARKDOWN");
+        let txt = dir.path().join("c.txt");
+        for p in [&a, &b, &txt] {
+            fs::write(p, "").unwrap();
+        }
+        let s = |p: &Path| p.to_string_lossy().to_string();
+        let canon = |p: &Path| s(&fs::canonicalize(p).unwrap());
+
+        let state = StartupFiles::from_args(vec![
+            "markdown-sheet".to_string(),
+            "--flag".to_string(),
+            s(&a),
+            s(&txt),
+            s(&dir.path().join("missing.md")),
+            s(&a),
+        ]);
+        // 準備前に届いたファイルは保持される
+        assert!(state.receive(vec![b.clone()]).is_empty());
+        assert_eq!(state.take(), vec![canon(&a), canon(&b)]);
+        assert!(state.take().is_empty());
+
+        // 準備後に届いたファイルは通知対象として返る
+        assert_eq!(state.receive(vec![a.clone(), txt]), vec![canon(&a)]);
+        assert!(state.take().is_empty());
+    }
+
+    #[test]
+    fn test_classify_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let md = dir.path().join("a.md");
+        let png = dir.path().join("b.png");
+        let txt = dir.path().join("c.TXT");
+        fs::write(&md, "| A |\n| --- |\n| 1 |\n").unwrap();
+        fs::write(&png, "").unwrap();
+        fs::write(&txt, "memo").unwrap();
+        fs::create_dir(dir.path().join("notes")).unwrap();
+
+        let outcome = classify_drop(&[md, png.clone(), txt, dir.path().join("notes")]);
+        assert_eq!(outcome.documents.len(), 2);
+        assert_eq!(outcome.documents[1].document.lines, vec!["memo"]);
+        assert_eq!(
+            outcome.documents[0].document.tables[0].rows,
+            vec![vec!["1"]]
+        );
+        assert_eq!(outcome.folders.len(), 1);
+        assert!(outcome.folders[0].ends_with("notes"));
+        assert_eq!(outcome.unsupported, vec![png.to_string_lossy().to_string()]);
+    }
+}
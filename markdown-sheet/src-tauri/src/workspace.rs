@@ -0,0 +1,746 @@
+use crate::file_io::write_atomic;
+use crate::markdown_parser::{ColumnRef, SerializeOptions, SortKey};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// ワークスペース内の設定ディレクトリ名
+pub const SETTINGS_DIR: &str = ".markdown-sheet";
+/// ワークスペース設定ファイル名
+pub const SETTINGS_FILE: &str = "settings.json";
+/// 壊れた設定ファイルを上書きする前に退避するファイル名
+pub const SETTINGS_BACKUP_FILE: &str = "settings.json.bak";
+
+/// 現在の設定スキーマのバージョン
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// ワークスペース設定（`<root>/.markdown-sheet/settings.json`）
+///
+/// フォルダと一緒に持ち運べるよう、パスはワークスペースルートからの相対パス（`/` 区切り）で保存する。
+/// 欠けているキーは既定値で補う。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceSettings {
+    /// 設定スキーマのバージョン（バージョン導入前のファイルは 0 とみなす）
+    pub schema_version: u32,
+    /// ピン留めしたファイル（表示順）
+    pub pinned: Vec<String>,
+    /// テーブル整形時の既定オプション
+    pub serialize: SerializeOptions,
+    /// ファイルツリーや検索から除外するパスの glob パターン
+    pub ignore: Vec<String>,
+    /// テーブルごとの表示状態（ファイルの相対パス → テーブルの安定 ID → 表示状態）
+    pub table_views: BTreeMap<String, BTreeMap<String, TableView>>,
+}
+
+/// テーブルの表示状態（列は列名で覚える）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableView {
+    /// ピン留めした並べ替えキー
+    pub sort: Vec<SortKey>,
+    /// 列名ごとの列幅（ピクセル）
+    pub column_widths: BTreeMap<String, u32>,
+}
+
+impl Default for WorkspaceSettings {
+    fn default() -> Self {
+        WorkspaceSettings {
+            schema_version: SCHEMA_VERSION,
+            pinned: Vec::new(),
+            serialize: SerializeOptions::default(),
+            ignore: Vec::new(),
+            table_views: BTreeMap::new(),
+        }
+    }
+}
+
+/// 読み込んだ設定と、読み込み時の警告（設定ファイルが壊れていた場合など）
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedSettings {
+    pub settings: WorkspaceSettings,
+    pub warning: Option<String>,
+    /// 設定ファイルの状態（保存してよいかの判断に使う）
+    #[serde(skip)]
+    file: SettingsFile,
+}
+
+/// 読み込んだ設定ファイルの状態
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum SettingsFile {
+    /// 存在しない、または読み込めた
+    #[default]
+    Usable,
+    /// JSON として読めない（上書きする前に退避する）
+    Corrupt,
+    /// 読み込めない・新しいバージョンのファイル（上書きしない）
+    Protected,
+}
+
+/// ワークスペースとして登録されたルートフォルダの一覧（管理ステート）
+///
+/// 先頭のルートを主ワークスペースとして扱う。ルートの ID は正規化した絶対パス。
+#[derive(Debug, Default)]
+pub struct Workspace(Mutex<Vec<PathBuf>>);
+
+fn path_strings(roots: &[PathBuf]) -> Vec<String> {
+    roots
+        .iter()
+        .map(|r| r.to_string_lossy().to_string())
+        .collect()
+}
+
+impl Workspace {
+    /// ルートを追加する。重複や入れ子（一方が他方の中にある）はエラー
+    pub fn add_root(&self, root: &Path) -> Result<Vec<String>, String> {
+        if !root.is_dir() {
+            return Err("ディレクトリが存在しません".to_string());
+        }
+        let root = absolute_path(root);
+        let mut roots = self.0.lock().map_err(|e| e.to_string())?;
+        for existing in roots.iter() {
+            if *existing == root {
+                return Err(format!("既に登録されているルートです: {}", root.display()));
+            }
+            if root.starts_with(existing) || existing.starts_with(&root) {
+                return Err(format!(
+                    "ルートを入れ子にすることはできません: {} と {}",
+                    root.display(),
+                    existing.display()
+                ));
+            }
+        }
+        roots.push(root);
+        Ok(path_strings(&roots))
+    }
+
+    /// ルートの登録を解除する
+    pub fn remove_root(&self, root: &Path) -> Result<Vec<String>, String> {
+        let root = absolute_path(root);
+        let mut roots = self.0.lock().map_err(|e| e.to_string())?;
+        let before = roots.len();
+        roots.retain(|r| *r != root);
+        if roots.len() == before {
+            return Err(format!("登録されていないルートです: {}", root.display()));
+        }
+        Ok(path_strings(&roots))
+    }
+
+    /// 登録済みのルート一覧
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.0.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// 主ワークスペース（先頭のルート）を取得する（未設定ならエラー）
+    pub fn root(&self) -> Result<PathBuf, String> {
+        self.roots()
+            .into_iter()
+            .next()
+            .ok_or_else(|| "ワークスペースが開かれていません".to_string())
+    }
+
+    /// ルート ID を解決する。`None` なら主ワークスペース、指定時は登録済みのルートである必要がある
+    pub fn resolve_root(&self, root_id: Option<&str>) -> Result<PathBuf, String> {
+        let Some(id) = root_id else {
+            return self.root();
+        };
+        let target = absolute_path(Path::new(id));
+        self.roots()
+            .into_iter()
+            .find(|r| *r == target)
+            .ok_or_else(|| format!("登録されていないルートです: {}", id))
+    }
+
+    /// パスを含むルートを返す。どのルートにも含まれないパスはエラー
+    pub fn root_for(&self, path: &str) -> Result<PathBuf, String> {
+        let abs = absolute_path(Path::new(path));
+        if abs
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+        {
+            return Err(format!("ワークスペース外のパスです: {}", path));
+        }
+        let roots = self.roots();
+        if roots.is_empty() {
+            return Err("ワークスペースが開かれていません".to_string());
+        }
+        roots
+            .into_iter()
+            .find(|r| abs.starts_with(r))
+            .ok_or_else(|| format!("ワークスペース外のパスです: {}", path))
+    }
+}
+
+/// 設定の読み込み〜書き込みをプロセス内で直列化するためのロック
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// 設定ファイルのパス
+pub fn settings_path(root: &Path) -> PathBuf {
+    root.join(SETTINGS_DIR).join(SETTINGS_FILE)
+}
+
+/// 古いバージョンの設定を現在のスキーマに移行する
+fn migrate(mut settings: WorkspaceSettings) -> WorkspaceSettings {
+    // v0 → v1: バージョン番号の導入のみ（追加されたキーは serde の既定値で補われる）
+    if settings.schema_version < SCHEMA_VERSION {
+        settings.schema_version = SCHEMA_VERSION;
+    }
+    settings
+}
+
+/// 設定を読み込み、読み込みに問題があれば警告を添えて返す
+///
+/// ファイルが存在しない場合は既定値。壊れている場合も既定値で続行し、警告を返す。
+pub fn load_settings_checked(root: &Path) -> LoadedSettings {
+    let path = settings_path(root);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return LoadedSettings {
+                settings: WorkspaceSettings::default(),
+                warning: None,
+                file: SettingsFile::Usable,
+            }
+        }
+        Err(e) => {
+            return LoadedSettings {
+                settings: WorkspaceSettings::default(),
+                warning: Some(format!(
+                    "設定ファイルを読み込めません（既定値を使用します）: {}",
+                    e
+                )),
+                file: SettingsFile::Protected,
+            }
+        }
+    };
+    // バージョン番号のないファイルは v0 として扱う
+    let parsed = serde_json::from_str::<serde_json::Value>(&text).and_then(|mut value| {
+        if let Some(obj) = value.as_object_mut() {
+            obj.entry("schema_version").or_insert(0.into());
+        }
+        serde_json::from_value::<WorkspaceSettings>(value)
+    });
+    match parsed {
+        Ok(settings) if settings.schema_version > SCHEMA_VERSION => LoadedSettings {
+            warning: Some(format!(
+                "新しいバージョンの設定ファイルです（v{}）。未対応の項目は無視し、設定は保存しません",
+                settings.schema_version
+            )),
+            settings: WorkspaceSettings {
+                schema_version: SCHEMA_VERSION,
+                ..settings
+            },
+            file: SettingsFile::Protected,
+        },
+        Ok(settings) => {
+            // 不正な除外パターンは無視されるので、設定画面で直せるよう知らせる
+            let (_, pattern_warnings) = IgnoreRules::compile(&settings.ignore);
+            LoadedSettings {
+                settings: migrate(settings),
+                warning: (!pattern_warnings.is_empty()).then(|| pattern_warnings.join("\n")),
+                file: SettingsFile::Usable,
+            }
+        }
+        Err(e) => LoadedSettings {
+            settings: WorkspaceSettings::default(),
+            warning: Some(format!(
+                "設定ファイルが壊れています（既定値を使用します。保存時に {} に退避します）: {}",
+                SETTINGS_BACKUP_FILE, e
+            )),
+            file: SettingsFile::Corrupt,
+        },
+    }
+}
+
+/// 設定を読み込む（存在しない・壊れている場合は既定値。警告は `load_settings_checked` で得る）
+pub fn load_settings(root: &Path) -> WorkspaceSettings {
+    load_settings_checked(root).settings
+}
+
+/// 設定を保存する（一時ファイル経由で書き込み、壊れた JSON を残さない）
+pub fn save_settings(root: &Path, settings: &WorkspaceSettings) -> Result<(), String> {
+    let path = settings_path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    write_atomic(&path, json.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// 設定を読み込んで変更し、保存する（プロセス内では直列化され、複数ウィンドウ間は後勝ち）
+fn modify_settings<T>(
+    root: &Path,
+    f: impl FnOnce(&mut WorkspaceSettings) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = SETTINGS_LOCK.lock().map_err(|e| e.to_string())?;
+    let loaded = load_settings_checked(root);
+    if loaded.file == SettingsFile::Protected {
+        return Err(format!(
+            "設定ファイルを上書きできません: {}",
+            loaded.warning.unwrap_or_default()
+        ));
+    }
+    let mut settings = loaded.settings;
+    let result = f(&mut settings)?;
+    if loaded.file == SettingsFile::Corrupt {
+        let path = settings_path(root);
+        fs::copy(&path, path.with_file_name(SETTINGS_BACKUP_FILE))
+            .map_err(|e| format!("壊れた設定ファイルを退避できません: {}", e))?;
+    }
+    save_settings(root, &settings)?;
+    Ok(result)
+}
+
+/// JSON Merge Patch（RFC 7396）を適用する。`null` はキーを既定値に戻す
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (target.as_object_mut(), patch.as_object()) {
+        (Some(target), Some(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else {
+                    merge_patch(
+                        target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                        value,
+                    );
+                }
+            }
+        }
+        _ => *target = patch.clone(),
+    }
+}
+
+/// 部分的な設定オブジェクトを現在の設定にマージして保存する
+pub fn update_settings(
+    root: &Path,
+    patch: &serde_json::Value,
+) -> Result<WorkspaceSettings, String> {
+    if !patch.is_object() {
+        return Err("設定の更新内容はオブジェクトで指定してください".to_string());
+    }
+    modify_settings(root, |settings| {
+        let mut value = serde_json::to_value(&*settings).map_err(|e| e.to_string())?;
+        merge_patch(&mut value, patch);
+        let mut updated: WorkspaceSettings = serde_json::from_value(value)
+            .map_err(|e| format!("設定の更新内容が不正です: {}", e))?;
+        updated.schema_version = SCHEMA_VERSION;
+        *settings = updated;
+        Ok(settings.clone())
+    })
+}
+
+/// パスを絶対パスに正規化する
+///
+/// 存在しないパスは親ディレクトリを正規化して結合する。
+pub fn absolute_path(path: &Path) -> PathBuf {
+    if let Ok(abs) = fs::canonicalize(path) {
+        return abs;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|p| p.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// ワークスペース内のパスをルートからの相対パス（`/` 区切り）に変換する
+///
+/// ワークスペース外のパスはエラーにする。
+pub fn relative_to_root(root: &Path, path: &str) -> Result<String, String> {
+    let root = absolute_path(root);
+    let abs = absolute_path(&root.join(path));
+    if abs
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(format!("ワークスペース外のパスです: {}", path));
+    }
+    let rel = abs
+        .strip_prefix(&root)
+        .map_err(|_| format!("ワークスペース外のパスです: {}", path))?;
+    Ok(rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// 設定の `ignore`（glob パターン）による除外判定
+#[derive(Debug, Clone)]
+pub struct IgnoreRules(GlobSet);
+
+impl IgnoreRules {
+    /// パターンから組み立てる（不正なパターンは無視する）
+    pub fn new(patterns: &[String]) -> Self {
+        Self::compile(patterns).0
+    }
+
+    /// パターンから組み立て、無視した不正なパターンの警告も返す
+    pub fn compile(patterns: &[String]) -> (Self, Vec<String>) {
+        let mut builder = GlobSetBuilder::new();
+        let mut warnings = Vec::new();
+        for pattern in patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => warnings.push(format!("除外パターンが不正です: {}: {}", pattern, e)),
+            }
+        }
+        let set = builder.build().unwrap_or_else(|_| GlobSet::empty());
+        (IgnoreRules(set), warnings)
+    }
+
+    /// ワークスペース設定の除外パターンを読み込む
+    pub fn load(root: &Path) -> Self {
+        Self::new(&load_settings(root).ignore)
+    }
+
+    /// ルートからの相対パス（`/` 区切り）が除外対象か（除外されたフォルダの中身も対象）
+    pub fn is_ignored(&self, rel: &str) -> bool {
+        let mut prefix = rel;
+        loop {
+            if self.0.is_match(prefix) {
+                return true;
+            }
+            match prefix.rfind('/') {
+                Some(i) => prefix = &prefix[..i],
+                None => return false,
+            }
+        }
+    }
+}
+
+/// ワークスペース以下の .md ファイルを `(絶対パス, ルートからの相対パス)` で列挙する
+///
+/// ファイルツリーと同じく隠しファイルと深すぎる階層は除外し、除外パターンに一致するものも含めない。
+pub fn markdown_files(root: &Path) -> Vec<(PathBuf, String)> {
+    fn walk(
+        root: &Path,
+        dir: &Path,
+        depth: u32,
+        rules: &IgnoreRules,
+        out: &mut Vec<(PathBuf, String)>,
+    ) {
+        if depth > 5 {
+            return;
+        }
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut items: Vec<_> = read_dir.filter_map(|e| e.ok()).collect();
+        items.sort_by_key(|e| e.file_name());
+        for entry in items {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(rel) = path.strip_prefix(root) else {
+                continue;
+            };
+            let rel = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if rules.is_ignored(&rel) {
+                continue;
+            }
+            if path.is_dir() {
+                walk(root, &path, depth + 1, rules, out);
+            } else if rel.ends_with(".md") {
+                out.push((path, rel));
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(root, root, 0, &IgnoreRules::load(root), &mut out);
+    out
+}
+
+/// ピン留めしたファイルを絶対パスで返す
+pub fn pinned_files(root: &Path) -> Vec<String> {
+    load_settings(root)
+        .pinned
+        .iter()
+        .map(|rel| root.join(rel).to_string_lossy().to_string())
+        .collect()
+}
+
+/// ファイルをピン留めする（重複は無視し、末尾に追加）
+pub fn pin(root: &Path, path: &str) -> Result<Vec<String>, String> {
+    let rel = relative_to_root(root, path)?;
+    modify_settings(root, |settings| {
+        if !settings.pinned.contains(&rel) {
+            settings.pinned.push(rel);
+        }
+        Ok(())
+    })?;
+    Ok(pinned_files(root))
+}
+
+/// ピン留めを外す
+pub fn unpin(root: &Path, path: &str) -> Result<Vec<String>, String> {
+    let rel = relative_to_root(root, path)?;
+    modify_settings(root, |settings| {
+        settings.pinned.retain(|p| p != &rel);
+        Ok(())
+    })?;
+    Ok(pinned_files(root))
+}
+
+/// ピン留めの順序を並べ替える（現在のピン留めと同じ集合である必要がある）
+pub fn reorder_pins(root: &Path, paths: &[String]) -> Result<Vec<String>, String> {
+    let order = paths
+        .iter()
+        .map(|p| relative_to_root(root, p))
+        .collect::<Result<Vec<_>, _>>()?;
+    modify_settings(root, |settings| {
+        let mut current = settings.pinned.clone();
+        let mut requested = order.clone();
+        current.sort();
+        requested.sort();
+        requested.dedup();
+        if current != requested || requested.len() != order.len() {
+            return Err("並べ替え対象がピン留めされたファイルと一致しません".to_string());
+        }
+        settings.pinned = order;
+        Ok(())
+    })?;
+    Ok(pinned_files(root))
+}
+
+/// 列名の変更に合わせて、テーブルの表示状態に保存した列名を書き換える
+///
+/// `table_id` はテーブルの安定 ID。書き換えた場合は true。表示状態が保存されていなければ
+/// 設定ファイルには触れない。
+pub fn rename_column_references(
+    root: &Path,
+    path: &str,
+    table_id: &str,
+    old_name: &str,
+    new_name: &str,
+) -> Result<bool, String> {
+    let rel = relative_to_root(root, path)?;
+    let (old_name, new_name) = (old_name.trim(), new_name.trim());
+    if old_name == new_name
+        || !load_settings(root)
+            .table_views
+            .get(&rel)
+            .is_some_and(|views| views.contains_key(table_id))
+    {
+        return Ok(false);
+    }
+    modify_settings(root, |settings| {
+        let Some(view) = settings
+            .table_views
+            .get_mut(&rel)
+            .and_then(|views| views.get_mut(table_id))
+        else {
+            return Ok(false);
+        };
+        let mut changed = false;
+        for key in &mut view.sort {
+            if matches!(&key.column, ColumnRef::Name(name) if name.trim() == old_name) {
+                key.column = ColumnRef::Name(new_name.to_string());
+                changed = true;
+            }
+        }
+        if let Some(width) = view.column_widths.remove(old_name) {
+            view.column_widths.insert(new_name.to_string(), width);
+            changed = true;
+        }
+        Ok(changed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_parser::TableStyle;
+
+    fn setup() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("a.md"), "").unwrap();
+        fs::write(root.join("sub/b.md"), "").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_pin_persistence_format() {
+        let (_dir, root) = setup();
+        pin(&root, &root.join("a.md").to_string_lossy()).unwrap();
+        pin(&root, &root.join("sub/b.md").to_string_lossy()).unwrap();
+        pin(&root, &root.join("a.md").to_string_lossy()).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(settings_path(&root)).unwrap()).unwrap();
+        assert_eq!(json["pinned"], serde_json::json!(["a.md", "sub/b.md"]));
+
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("x.md"), "").unwrap();
+        assert!(pin(&root, &outside.path().join("x.md").to_string_lossy()).is_err());
+        // `..` でルートの外に出るパスも拒否する
+        assert!(pin(&root, &root.join("sub/../../x.md").to_string_lossy()).is_err());
+        assert!(pin(&root, "../x.md").is_err());
+    }
+
+    #[test]
+    fn test_rename_column_references() {
+        let (_dir, root) = setup();
+        let a = root.join("a.md").to_string_lossy().to_string();
+        // 表示状態がなければ設定ファイルを作らない
+        assert!(!rename_column_references(&root, &a, "tasks", "Status", "State").unwrap());
+        assert!(!settings_path(&root).exists());
+
+        update_settings(
+            &root,
+            &serde_json::json!({ "table_views": { "a.md": { "tasks": {
+                "sort": [{ "column": "Status" }, { "column": 0 }, { "column": "Due", "order": "desc" }],
+                "column_widths": { "Status": 120, "Due": 80 },
+            } } } }),
+        )
+        .unwrap();
+        assert!(rename_column_references(&root, &a, "tasks", "Status", "State").unwrap());
+        let view = &load_settings(&root).table_views["a.md"]["tasks"];
+        let columns: Vec<&ColumnRef> = view.sort.iter().map(|k| &k.column).collect();
+        assert_eq!(
+            columns,
+            vec![
+                &ColumnRef::Name("State".to_string()),
+                &ColumnRef::Index(0),
+                &ColumnRef::Name("Due".to_string()),
+            ]
+        );
+        assert_eq!(view.column_widths.get("State"), Some(&120));
+        assert!(!view.column_widths.contains_key("Status"));
+        assert!(!rename_column_references(&root, &a, "other", "Due", "Deadline").unwrap());
+    }
+
+    #[test]
+    fn test_reorder_pins() {
+        let (_dir, root) = setup();
+        let a = root.join("a.md").to_string_lossy().to_string();
+        let b = root.join("sub/b.md").to_string_lossy().to_string();
+        pin(&root, &a).unwrap();
+        pin(&root, &b).unwrap();
+
+        assert_eq!(
+            reorder_pins(&root, &[b.clone(), a.clone()]).unwrap(),
+            vec![b.clone(), a.clone()]
+        );
+        assert!(reorder_pins(&root, std::slice::from_ref(&a)).is_err());
+        assert!(reorder_pins(&root, &[a.clone(), a.clone()]).is_err());
+        assert_eq!(unpin(&root, &b).unwrap(), vec![a]);
+    }
+
+    #[test]
+    fn test_settings_migration_and_corruption() {
+        let (_dir, root) = setup();
+        let missing = load_settings_checked(&root);
+        assert_eq!(missing.settings, WorkspaceSettings::default());
+        assert!(missing.warning.is_none());
+
+        // バージョン導入前の形式（pinned のみ）
+        fs::create_dir_all(root.join(SETTINGS_DIR)).unwrap();
+        fs::write(settings_path(&root), r#"{"pinned":["a.md"]}"#).unwrap();
+        let old = load_settings_checked(&root);
+        assert!(old.warning.is_none());
+        assert_eq!(old.settings.schema_version, SCHEMA_VERSION);
+        assert_eq!(old.settings.pinned, vec!["a.md"]);
+        assert_eq!(old.settings.serialize, SerializeOptions::default());
+
+        fs::write(settings_path(&root), "{\"pinned\": [").unwrap();
+        let corrupt = load_settings_checked(&root);
+        assert!(corrupt.warning.is_some());
+        assert_eq!(corrupt.settings, WorkspaceSettings::default());
+        // 壊れたファイルは退避してから保存する
+        pin(&root, &root.join("a.md").to_string_lossy()).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(SETTINGS_DIR).join(SETTINGS_BACKUP_FILE)).unwrap(),
+            "{\"pinned\": ["
+        );
+        assert_eq!(load_settings(&root).pinned, vec!["a.md"]);
+
+        // 新しいバージョンのファイルは上書きしない
+        let newer = format!(r#"{{"schema_version":{},"pinned":[]}}"#, SCHEMA_VERSION + 1);
+        fs::write(settings_path(&root), &newer).unwrap();
+        assert!(load_settings_checked(&root).warning.is_some());
+        assert!(pin(&root, &root.join("a.md").to_string_lossy()).is_err());
+        assert_eq!(fs::read_to_string(settings_path(&root)).unwrap(), newer);
+    }
+
+    #[test]
+    fn test_update_settings_merges_patch() {
+        let (_dir, root) = setup();
+        pin(&root, &root.join("a.md").to_string_lossy()).unwrap();
+        let updated = update_settings(
+            &root,
+            &serde_json::json!({ "serialize": { "style": "compact" }, "ignore": ["drafts/**"] }),
+        )
+        .unwrap();
+        assert_eq!(updated.pinned, vec!["a.md"]);
+        assert_eq!(updated.serialize.style, TableStyle::Compact);
+        assert_eq!(updated.serialize.min_width, 3);
+        assert_eq!(load_settings(&root), updated);
+
+        assert!(update_settings(&root, &serde_json::json!({ "ignore": 1 })).is_err());
+        assert_eq!(load_settings(&root), updated);
+        let entries: Vec<_> = fs::read_dir(root.join(SETTINGS_DIR)).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        assert!(load_settings_checked(&root).warning.is_none());
+
+        // 不正な除外パターンは無視し、読み込み時の警告で知らせる
+        update_settings(
+            &root,
+            &serde_json::json!({ "ignore": ["drafts/**", "a[b"] }),
+        )
+        .unwrap();
+        let loaded = load_settings_checked(&root);
+        assert!(loaded.warning.unwrap().contains("a[b"));
+        assert!(IgnoreRules::load(&root).is_ignored("drafts/x.md"));
+    }
+
+    #[test]
+    fn test_sandbox_across_multiple_roots() {
+        let (_d1, personal) = setup();
+        let (_d2, work) = setup();
+        let outside = tempfile::tempdir().unwrap();
+        let ws = Workspace::default();
+        ws.add_root(&personal).unwrap();
+        ws.add_root(&work).unwrap();
+
+        let a = personal.join("a.md").to_string_lossy().to_string();
+        let b = work.join("sub/b.md").to_string_lossy().to_string();
+        assert_eq!(ws.root_for(&a).unwrap(), personal);
+        assert_eq!(ws.root_for(&b).unwrap(), work);
+        assert!(ws
+            .root_for(&outside.path().join("x.md").to_string_lossy())
+            .is_err());
+        assert!(ws
+            .root_for(&personal.join("../escape.md").to_string_lossy())
+            .is_err());
+
+        // 重複・入れ子は拒否
+        assert!(ws.add_root(&personal).is_err());
+        assert!(ws.add_root(&work.join("sub")).is_err());
+        assert!(ws.add_root(personal.parent().unwrap()).is_err());
+
+        // ピン留めはルートごとの設定に保存される
+        pin(&ws.root_for(&a).unwrap(), &a).unwrap();
+        pin(&ws.root_for(&b).unwrap(), &b).unwrap();
+        assert_eq!(load_settings(&personal).pinned, vec!["a.md"]);
+        assert_eq!(load_settings(&work).pinned, vec!["sub/b.md"]);
+
+        ws.remove_root(&personal).unwrap();
+        assert!(ws.root_for(&a).is_err());
+        assert_eq!(ws.root().unwrap(), work);
+    }
+}
@@ -13,7 +13,17 @@ import TabBar from "./components/TabBar";
 import Toolbar from "./components/Toolbar";
 import { callAI } from "./lib/callAI";
 import { makeHeadingId } from "./lib/headingId";
-import type { AiSettings, FileEntry, ParsedDocument, RecentFile, Tab } from "./types";
+import type {
+  AiSettings,
+  DeepLinkPayload,
+  DocumentSnapshot,
+  FileEntry,
+  ParseError,
+  RecentFile,
+  RejectedDeepLink,
+  Tab,
+  TakenDeepLinks,
+} from "./types";
 
 // ========== AI & Template Constants ==========
 
@@ -192,6 +202,14 @@ import html2pdf from "html2pdf.js";
 
 type Theme = "light" | "dark";
 
+const IMAGE_EXTENSIONS = [".png", ".jpg", ".jpeg", ".gif", ".bmp", ".svg", ".webp"];
+
+/** エディタに画像として挿入できるファイルか */
+function isImagePath(filePath: string): boolean {
+  const ext = filePath.toLowerCase().replace(/^.*(\.[^.]+)$/, "$1");
+  return IMAGE_EXTENSIONS.includes(ext);
+}
+
 function makeInitialTab(): Tab {
   return {
     id: crypto.randomUUID(),
@@ -469,7 +487,7 @@ function App() {
   // ====== File Loading ======
 
   const loadFile = useCallback(
-    async (filePath: string) => {
+    async (filePath: string, preloaded?: DocumentSnapshot) => {
       // すでに開いているタブがあればそこに切り替える
       const existing = tabsRef.current.find((t) => t.filePath === filePath);
       if (existing) {
@@ -480,8 +498,9 @@ function App() {
       try {
         let text: string;
         try {
-          const doc: ParsedDocument = await invoke("read_markdown_file", { filePath });
-          text = doc.lines.join("\n");
+          const snapshot: DocumentSnapshot =
+            preloaded ?? (await invoke("read_markdown_file", { filePath }));
+          text = snapshot.document.lines.join("\n");
         } catch {
           text = await readTextFile(filePath);
         }
@@ -538,6 +557,19 @@ function App() {
         }
 
         addRecentFile(filePath);
+
+        // 通常の読み込みでは読み飛ばされる壊れたテーブルを知らせる
+        invoke<ParseError[]>("validate_markdown_file", { filePath })
+          .then((errors) => {
+            if (errors.length === 0) return;
+            const first = errors[0];
+            showToast(
+              `${first.line + 1} 行目: ${first.message}` +
+                (errors.length > 1 ? `（ほか ${errors.length - 1} 件）` : ""),
+              true
+            );
+          })
+          .catch(() => { /* 検査できなくても読み込みは成功扱い */ });
       } catch (e) {
         console.error("ファイル読み込みエラー:", e);
         showToast("ファイル読み込みに失敗しました", true);
@@ -566,6 +598,21 @@ function App() {
   }, [autoSave, activeFile]);
 
   // --- Folder open ---
+  const openFolder = useCallback(async (dirPath: string) => {
+    try {
+      const entries: FileEntry[] = await invoke("get_file_tree", {
+        dirPath,
+        includeTableCounts: false,
+      });
+      setFileTree(entries);
+      // フォルダを開くまで保持していたディープリンクを開く
+      await takeDeepLinksRef.current();
+    } catch (e) {
+      console.error("フォルダ読み込みエラー:", e);
+      showToast(`フォルダを開けませんでした: ${e}`, true);
+    }
+  }, []);
+
   const handleOpenFolder = useCallback(async () => {
     let selected: string | null = null;
     try {
@@ -576,15 +623,8 @@ function App() {
       return;
     }
     if (!selected) return;
-    try {
-      const entries: FileEntry[] = await invoke("get_file_tree", {
-        dirPath: selected,
-      });
-      setFileTree(entries);
-    } catch (e) {
-      console.error("フォルダ読み込みエラー:", e);
-    }
-  }, []);
+    await openFolder(selected);
+  }, [openFolder]);
 
   // --- File open ---
   const handleOpenFile = useCallback(async () => {
@@ -1286,16 +1326,68 @@ function App() {
   const handleContentChangeRef = useRef(handleContentChange);
   handleContentChangeRef.current = handleContentChange;
 
-  // 起動時にコマンドライン引数で渡されたファイルを開く（.md ファイル関連付け用）
+  // ディープリンクのファイルを開き、指定されたテーブルまでスクロールする
+  const openDeepLinkRef = useRef<(link: DeepLinkPayload) => Promise<void>>(async () => {});
+  openDeepLinkRef.current = async (link: DeepLinkPayload) => {
+    await loadFile(link.path);
+    if (link.warning) showToast(link.warning, true);
+    if (link.table_index === null) return;
+    // プレビューの描画を待ってからテーブルを探す
+    for (let attempt = 0; attempt < 20; attempt++) {
+      const table = previewRef.current?.querySelector(
+        `table[data-table-index="${link.table_index}"]`
+      );
+      if (table) {
+        table.scrollIntoView({ behavior: "smooth", block: "start" });
+        return;
+      }
+      await new Promise((resolve) => setTimeout(resolve, 50));
+    }
+  };
+
+  // 保持されているディープリンクを受け取って開く（フォルダが開かれるまではバックエンドが保持し続ける）
+  const takeDeepLinksRef = useRef<() => Promise<void>>(async () => {});
+  takeDeepLinksRef.current = async () => {
+    const deepLinks: TakenDeepLinks = await invoke("take_deep_links");
+    for (const link of deepLinks.links) await openDeepLinkRef.current(link);
+    for (const rejected of deepLinks.rejected) {
+      showToast(`リンクを開けませんでした: ${rejected.message}`, true);
+    }
+    if (deepLinks.pending > 0) {
+      showToast("リンクのファイルを含むフォルダを開くと、リンク先を開きます");
+    }
+  };
+
+  // 起動時にコマンドライン引数・OS の関連付けで渡されたファイルを開く（.md ファイル関連付け用）
+  // 起動後に渡されたファイルは open-file-request イベントで届く
+  // markdown-sheet://open?path=...&table=... のディープリンクは、準備前・フォルダを開く前に届いたものを
+  // take_deep_links で受け取り、以降は open-deep-link / deep-link-rejected イベントで届く
   useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    let unlistenDeepLink: (() => void) | undefined;
+    let unlistenRejected: (() => void) | undefined;
     (async () => {
       try {
-        const initialFile: string | null = await invoke("get_initial_file");
-        if (initialFile) {
-          loadFileRef.current(initialFile);
-        }
-      } catch { /* no initial file */ }
+        const { listen } = await import("@tauri-apps/api/event");
+        unlisten = await listen<string[]>("open-file-request", (event) => {
+          for (const path of event.payload) loadFileRef.current(path);
+        });
+        unlistenDeepLink = await listen<DeepLinkPayload>("open-deep-link", (event) =>
+          openDeepLinkRef.current(event.payload),
+        );
+        unlistenRejected = await listen<RejectedDeepLink>("deep-link-rejected", (event) =>
+          showToast(`リンクを開けませんでした: ${event.payload.message}`, true),
+        );
+        const startupFiles: string[] = await invoke("take_startup_files");
+        for (const path of startupFiles) loadFileRef.current(path);
+        await takeDeepLinksRef.current();
+      } catch { /* no startup files */ }
     })();
+    return () => {
+      unlisten?.();
+      unlistenDeepLink?.();
+      unlistenRejected?.();
+    };
   }, []);
 
   useEffect(() => {
@@ -1309,16 +1401,9 @@ function App() {
           const paths = event.payload.paths;
           if (!paths || paths.length === 0) return;
 
-          const mdExtensions = [".md", ".markdown", ".txt"];
-          const imageExtensions = [".png", ".jpg", ".jpeg", ".gif", ".bmp", ".svg", ".webp"];
-
           for (const filePath of paths) {
-            const ext = filePath.toLowerCase().replace(/^.*(\.[^.]+)$/, "$1");
-
-            if (mdExtensions.includes(ext)) {
-              // Markdown ファイルは新タブで開く
-              await loadFileRef.current(filePath);
-            } else if (imageExtensions.includes(ext)) {
+            // Markdown・テキストファイルとフォルダはバックエンドが files-dropped / folder-dropped で通知する
+            if (isImagePath(filePath)) {
               // 画像ファイルはエディタにマークダウン画像構文を挿入
               const textarea = editorRef.current;
               if (!textarea) continue;
@@ -1347,6 +1432,40 @@ function App() {
     return () => { unlisten?.(); };
   }, []);
 
+  // ドロップされた Markdown（読み込み済み）は新タブで開き、フォルダはワークスペースとして開く
+  const openFolderRef = useRef(openFolder);
+  openFolderRef.current = openFolder;
+  useEffect(() => {
+    const unlisteners: (() => void)[] = [];
+    (async () => {
+      try {
+        const { listen } = await import("@tauri-apps/api/event");
+        unlisteners.push(
+          await listen<DocumentSnapshot[]>("files-dropped", async (event) => {
+            for (const snapshot of event.payload) {
+              await loadFileRef.current(snapshot.path, snapshot);
+            }
+          }),
+          await listen<string>("folder-dropped", (event) => {
+            openFolderRef.current(event.payload);
+          }),
+          await listen<string[]>("unsupported-drop", (event) => {
+            // 画像はエディタへの挿入として別途処理する
+            const names = event.payload
+              .filter((p) => !isImagePath(p))
+              .map((p) => p.split(/[\\/]/).pop());
+            if (names.length > 0) {
+              showToast(`開けないファイルです: ${names.join(", ")}`, true);
+            }
+          }),
+        );
+      } catch (e) {
+        console.error("Failed to register drop listeners:", e);
+      }
+    })();
+    return () => unlisteners.forEach((u) => u());
+  }, []);
+
   // --- Keyboard shortcuts ---
   useEffect(() => {
     const handleKeyDown = (e: globalThis.KeyboardEvent) => {
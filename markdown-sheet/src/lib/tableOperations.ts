@@ -1,5 +1,32 @@
 import type { MarkdownTable } from "../types";
 
+/** 行・列を動かした後に verbatim と cell_annotations の位置を付け替える（map が null の位置は外す） */
+function remapCellMarks(
+  table: MarkdownTable,
+  map: (row: number, col: number) => [number, number] | null
+): void {
+  if (table.verbatim) {
+    table.verbatim = table.verbatim
+      .map(([r, c]) => map(r, c))
+      .filter((pos): pos is [number, number] => pos !== null)
+      .sort((a, b) => a[0] - b[0] || a[1] - b[1]);
+  }
+  if (table.cell_annotations) {
+    const moved: (string | null)[][] = [];
+    table.cell_annotations.forEach((row, r) =>
+      row.forEach((note, c) => {
+        const pos = note === null ? null : map(r, c);
+        if (pos === null) return;
+        const [nr, nc] = pos;
+        while (moved.length <= nr) moved.push([]);
+        while (moved[nr].length <= nc) moved[nr].push(null);
+        moved[nr][nc] = note;
+      })
+    );
+    table.cell_annotations = moved;
+  }
+}
+
 /** 行を追加する（指定行の上 or 下） */
 export function addRowToTable(
   table: MarkdownTable,
@@ -10,6 +37,7 @@ export function addRowToTable(
   const newRow = new Array(next.headers.length).fill("");
   const insertAt = position === "above" ? afterRow : afterRow + 1;
   next.rows.splice(insertAt, 0, newRow);
+  remapCellMarks(next, (r, c) => [r >= insertAt ? r + 1 : r, c]);
   next.end_line += 1;
   return next;
 }
@@ -22,6 +50,9 @@ export function deleteRowFromTable(
   if (table.rows.length <= 1) return table;
   const next = structuredClone(table);
   next.rows.splice(row, 1);
+  remapCellMarks(next, (r, c) =>
+    r === row ? null : [r > row ? r - 1 : r, c]
+  );
   next.end_line -= 1;
   return next;
 }
@@ -39,6 +70,7 @@ export function addColumnToTable(
   for (const row of next.rows) {
     row.splice(insertAt, 0, "");
   }
+  remapCellMarks(next, (r, c) => [r, c >= insertAt ? c + 1 : c]);
   return next;
 }
 
@@ -54,6 +86,9 @@ export function deleteColumnFromTable(
   for (const row of next.rows) {
     row.splice(col, 1);
   }
+  remapCellMarks(next, (r, c) =>
+    c === col ? null : [r, c > col ? c - 1 : c]
+  );
   return next;
 }
 
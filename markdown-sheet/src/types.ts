@@ -6,12 +6,61 @@ export interface MarkdownTable {
   rows: string[][];
   start_line: number;
   end_line: number;
+  has_summary_row?: boolean;
+  /** 前後の空白を保持するセルの位置 [行番号, 列番号] */
+  verbatim?: [number, number][];
+  /** セル末尾の `<!-- 注釈 -->`（rows と同じ並び。注釈を取り出したときだけ入る） */
+  cell_annotations?: (string | null)[][];
 }
 
 /** Markdown ドキュメント全体のパース結果 */
 export interface ParsedDocument {
   lines: string[];
   tables: MarkdownTable[];
+  encoding: string;
+  has_bom: boolean;
+  footnotes: Record<string, string>;
+}
+
+/** validate_markdown_file が返すテーブルの問題 */
+export interface ParseError {
+  /** 問題のある行（0 始まり） */
+  line: number;
+  message: string;
+}
+
+/** open-deep-link イベント・take_deep_links で届く、検証済みのディープリンク */
+export interface DeepLinkPayload {
+  path: string;
+  /** 開くテーブルの番号（指定がない・見つからない場合は null） */
+  table_index: number | null;
+  /** 指定されたテーブルが見つからなかった理由 */
+  warning: string | null;
+}
+
+/** 開けなかったディープリンク */
+export interface RejectedDeepLink {
+  url: string;
+  message: string;
+}
+
+/** take_deep_links の戻り値 */
+export interface TakenDeepLinks {
+  links: DeepLinkPayload[];
+  rejected: RejectedDeepLink[];
+  /** フォルダが開かれていないため、まだ保持しているリンクの数 */
+  pending: number;
+}
+
+/** read_markdown_file の戻り値（バージョントークン付き） */
+export interface DocumentSnapshot {
+  path: string;
+  document: ParsedDocument;
+  mtime_ms: number;
+  sha256: string;
+  read_only: boolean;
+  /** 各テーブルの内容のチェックサム（16進文字列） */
+  table_checksums: string[];
 }
 
 /** ファイルツリーのエントリ */
@@ -20,6 +69,10 @@ export interface FileEntry {
   path: string;
   is_dir: boolean;
   children: FileEntry[] | null;
+  size_bytes: number | null;
+  modified_at: number | null;
+  table_count: number | null;
+  pinned: boolean;
 }
 
 /** 最近開いたファイルのエントリ */